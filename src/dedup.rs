@@ -0,0 +1,278 @@
+//! Finding files with identical contents
+//!
+//! Files are first bucketed by size, which only needs metadata, and only files sharing
+//! a size are read and hashed. Files sharing a hash are then compared byte for byte, so a
+//! hash collision can never make different files look like duplicates.
+
+use crate::util::{
+    ensure_dir_root, hashfile, hashitems, iteritems, iteritems_sync, read_chunk,
+    same_file_identity, FtIterItemState, READ_CHUNK,
+};
+use anyhow::{Context, Result};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs::Metadata;
+use std::hash::Hash;
+use std::path::{Path, PathBuf};
+
+/// Groups `items` by key, keeping only the groups with more than one item
+fn collisions<T, K: Hash + Eq>(items: impl IntoIterator<Item = (T, K)>) -> Vec<Vec<T>> {
+    let mut groups: HashMap<K, Vec<T>> = HashMap::new();
+    for (item, key) in items {
+        groups.entry(key).or_default().push(item);
+    }
+
+    groups.into_values().filter(|g| g.len() > 1).collect()
+}
+
+/// Drops the files that are hardlinks to a file already in `files`, keeping the first path
+/// in sorted order for each
+fn distinct_files(mut files: Vec<(PathBuf, Metadata)>) -> Result<Vec<PathBuf>> {
+    files.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut distinct: Vec<(PathBuf, Metadata)> = vec![];
+    'files: for (file, meta) in files {
+        for (seen, seen_meta) in &distinct {
+            if same_file_identity(seen, seen_meta, &file, &meta)? {
+                continue 'files;
+            }
+        }
+        distinct.push((file, meta));
+    }
+
+    Ok(distinct.into_iter().map(|(file, _)| file).collect())
+}
+
+/// Checks two files have the same contents, reading both in chunks
+fn same_contents(a: &Path, b: &Path) -> Result<bool> {
+    let open = |path: &Path| {
+        std::fs::File::open(path).with_context(|| format!("unable to read file {}", path.display()))
+    };
+    let (mut a_file, mut b_file) = (open(a)?, open(b)?);
+    let (mut a_buf, mut b_buf) = (vec![0; READ_CHUNK], vec![0; READ_CHUNK]);
+
+    loop {
+        let a_read = read_chunk(&mut a_file, &mut a_buf)
+            .with_context(|| format!("unable to read file {}", a.display()))?;
+        let b_read = read_chunk(&mut b_file, &mut b_buf)
+            .with_context(|| format!("unable to read file {}", b.display()))?;
+
+        if a_buf[..a_read] != b_buf[..b_read] {
+            return Ok(false);
+        }
+        if a_read == 0 {
+            return Ok(true);
+        }
+    }
+}
+
+/// Splits files sharing a digest into the groups whose contents are actually identical,
+/// keeping only the groups with more than one file
+fn identical_groups(files: Vec<PathBuf>) -> Result<Vec<Vec<PathBuf>>> {
+    let mut groups: Vec<Vec<PathBuf>> = vec![];
+    'files: for file in files {
+        for group in groups.iter_mut() {
+            if same_contents(&group[0], &file)? {
+                group.push(file);
+                continue 'files;
+            }
+        }
+        groups.push(vec![file]);
+    }
+
+    Ok(groups.into_iter().filter(|g| g.len() > 1).collect())
+}
+
+/// Sorts each group and the groups themselves so results are stable
+fn sorted(mut groups: Vec<Vec<PathBuf>>) -> Vec<Vec<PathBuf>> {
+    for group in groups.iter_mut() {
+        group.sort();
+    }
+    groups.sort();
+    groups
+}
+
+/// Finds groups of files with identical contents in a directory including ALL subdirectories.
+///
+/// Each group contains at least two files, files without a duplicate are left out.
+/// Files are compared by size first and only files of the same size are read and hashed,
+/// files with the same hash are then compared byte for byte before being grouped. Files are
+/// read in chunks so large files are never held in memory. Groups and the files in them are sorted.
+///
+/// Symlinks are skipped, and hardlinks to the same file are only listed once under their first
+/// path in sorted order, so a group never holds two names for the same file.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::find_duplicates`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+/// * One of the files can't be read
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::find_duplicates;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     for group in find_duplicates("some/photos").await? {
+///         println!("{} copies of {}", group.len(), group[0].display());
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn find_duplicates<P: AsRef<Path> + Send>(path: P) -> Result<Vec<Vec<PathBuf>>> {
+    ensure_dir_root(&path)?;
+
+    let mut sized = vec![];
+    for file in iteritems(path, FtIterItemState::RFile, None).await? {
+        let meta = tokio::fs::symlink_metadata(&file)
+            .await
+            .with_context(|| format!("unable to read metadata for {}", file.display()))?;
+        if meta.is_file() {
+            let size = meta.len();
+            sized.push(((file, meta), size));
+        }
+    }
+
+    let mut groups = vec![];
+    for same_size in collisions(sized) {
+        let same_size = tokio::task::spawn_blocking(move || distinct_files(same_size))
+            .await
+            .context("file comparison task failed")??;
+        if same_size.len() < 2 {
+            continue;
+        }
+
+        let hashed = hashitems(same_size, DefaultHasher::new).await?;
+        for same_hash in collisions(hashed) {
+            let identical = tokio::task::spawn_blocking(move || identical_groups(same_hash))
+                .await
+                .context("file comparison task failed")??;
+            groups.extend(identical);
+        }
+    }
+
+    Ok(sorted(groups))
+}
+
+/// Sync version of [`find_duplicates`], exposed as [`crate::sync::find_duplicates`]
+pub(crate) fn find_duplicates_sync<P: AsRef<Path>>(path: P) -> Result<Vec<Vec<PathBuf>>> {
+    ensure_dir_root(&path)?;
+
+    let mut sized = vec![];
+    for file in iteritems_sync(path, FtIterItemState::RFile, None)? {
+        let meta = std::fs::symlink_metadata(&file)
+            .with_context(|| format!("unable to read metadata for {}", file.display()))?;
+        if meta.is_file() {
+            let size = meta.len();
+            sized.push(((file, meta), size));
+        }
+    }
+
+    let mut groups = vec![];
+    for same_size in collisions(sized) {
+        let same_size = distinct_files(same_size)?;
+        if same_size.len() < 2 {
+            continue;
+        }
+
+        let mut hashed = vec![];
+        for file in same_size {
+            let digest = hashfile(&file, DefaultHasher::new())?;
+            hashed.push((file, digest));
+        }
+        for same_hash in collisions(hashed) {
+            groups.extend(identical_groups(same_hash)?);
+        }
+    }
+
+    Ok(sorted(groups))
+}
+
+#[cfg(test)]
+mod dedup_tests {
+    use super::*;
+    use crate::util::TempPath;
+
+    #[tokio::test]
+    async fn groups_identical_files() -> Result<()> {
+        let root = TempPath::new("dedup_groups_identical").await?;
+        root.multi_folder(vec!["copies"]).await?;
+        std::fs::write(root.path.join("original.txt"), "duplicate me")?;
+        std::fs::write(root.path.join("copies/copy.txt"), "duplicate me")?;
+        std::fs::write(root.path.join("unique.txt"), "only one of these")?;
+
+        // Same size but different contents so only the hashing step can split them
+        std::fs::write(root.path.join("left.txt"), "aaaa")?;
+        std::fs::write(root.path.join("right.txt"), "bbbb")?;
+
+        let expected = vec![vec![
+            root.path.join("copies/copy.txt"),
+            root.path.join("original.txt"),
+        ]];
+        assert_eq!(find_duplicates(&root.path).await?, expected);
+        assert_eq!(find_duplicates_sync(&root.path)?, expected);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn hash_collisions_are_not_duplicates() -> Result<()> {
+        let root = TempPath::new("dedup_hash_collisions").await?;
+        let long = "x".repeat(READ_CHUNK + 7);
+        let files = [
+            ("a.txt", long.clone()),
+            ("b.txt", long.clone()),
+            // Only differs after the first chunk
+            ("c.txt", format!("{}y", &long[..long.len() - 1])),
+            ("d.txt", "short".to_string()),
+        ];
+        for (name, contents) in &files {
+            std::fs::write(root.path.join(name), contents)?;
+        }
+        let path = |name: &str| root.path.join(name);
+
+        // As if all four had collided on the same hash
+        let groups = identical_groups(files.iter().map(|(name, _)| path(name)).collect())?;
+        assert_eq!(groups, vec![vec![path("a.txt"), path("b.txt")]]);
+
+        assert!(same_contents(&path("a.txt"), &path("b.txt"))?);
+        assert!(!same_contents(&path("a.txt"), &path("c.txt"))?);
+        assert!(!same_contents(&path("a.txt"), &path("d.txt"))?);
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn links_are_not_duplicates() -> Result<()> {
+        let root = TempPath::new("dedup_links").await?;
+        let original = root.path.join("original.txt");
+        std::fs::write(&original, "linked contents")?;
+        std::os::unix::fs::symlink(&original, root.path.join("symlink.txt"))?;
+        std::fs::hard_link(&original, root.path.join("hardlink.txt"))?;
+
+        // Only links to the one file, so nothing is duplicated
+        assert!(find_duplicates(&root.path).await?.is_empty());
+        assert!(find_duplicates_sync(&root.path)?.is_empty());
+
+        // A real copy is grouped with a single name for the hardlinked file
+        std::fs::write(root.path.join("copy.txt"), "linked contents")?;
+        let expected = vec![vec![
+            root.path.join("copy.txt"),
+            root.path.join("hardlink.txt"),
+        ]];
+        assert_eq!(find_duplicates(&root.path).await?, expected);
+        assert_eq!(find_duplicates_sync(&root.path)?, expected);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,59 @@
+//! Errors carrying the path that caused them
+//!
+//! Functions in the crate return [`anyhow::Result`], the path checks they do up front fail with
+//! an [`FtError`] inside it so the offending path can be recovered with
+//! [`anyhow::Error::downcast_ref`].
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use filetools::{list_files, FtError};
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     if let Err(err) = list_files("some/folder").await {
+//!         match err.downcast_ref::<FtError>() {
+//!             Some(FtError::NotFound(path)) => println!("{} is missing", path.display()),
+//!             _ => println!("listing failed: {err}"),
+//!         }
+//!     }
+//! }
+//! ```
+
+use std::fmt;
+use std::path::PathBuf;
+
+/// Path errors returned by the functions in the crate, wrapped in an [`anyhow::Error`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FtError {
+    /// The path does not exist
+    NotFound(PathBuf),
+
+    /// The path exists but is a file where a directory was needed
+    NotADirectory(PathBuf),
+}
+
+impl FtError {
+    /// The path that caused the error
+    pub fn path(&self) -> &PathBuf {
+        match self {
+            Self::NotFound(path) | Self::NotADirectory(path) => path,
+        }
+    }
+}
+
+impl fmt::Display for FtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotFound(path) => write!(f, "path does not exist: {}", path.display()),
+            Self::NotADirectory(path) => write!(
+                f,
+                "path should be a directory, not a file: {}",
+                path.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for FtError {}
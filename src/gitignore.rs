@@ -0,0 +1,122 @@
+//! Listing files while honouring `.gitignore` rules
+//!
+//! Requires the `ignore` feature.
+
+use crate::util::ensure_dir_root;
+use anyhow::{Context, Result};
+use ignore::WalkBuilder;
+use std::path::{Path, PathBuf};
+
+/// Walks `path` with the `ignore` crate's walker, which reads ignore files as it goes
+fn walk_ignored(path: &Path) -> Result<Vec<PathBuf>> {
+    let walker = WalkBuilder::new(path)
+        // Hidden files are listed like everywhere else in the crate, only `.git` itself is skipped
+        .hidden(false)
+        .filter_entry(|entry| entry.file_name() != ".git")
+        // Honour `.gitignore` files even if the directory isn't a git repository
+        .require_git(false)
+        .follow_links(true)
+        .build();
+
+    let mut files = vec![];
+    for entry in walker {
+        let entry = entry.context("unable to read directory")?;
+        if entry.file_type().is_some_and(|t| t.is_file()) {
+            files.push(entry.into_path());
+        }
+    }
+
+    Ok(files)
+}
+
+/// Lists all files in a directory including ALL subdirectories, skipping anything ignored
+/// by `.gitignore`, `.ignore` or the global git excludes.
+///
+/// A drop-in alternative to [`crate::list_nested_files`] for source trees, so `target/`,
+/// `node_modules/` and the like are never walked into. Ignore files are honoured even outside
+/// of a git repository. Hidden files are still listed but the `.git` directory is skipped.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_nested_files_gitignore`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+/// * A directory could not be read
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::list_nested_files_gitignore;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     // Every source file in the project without anything from `target/`
+///     let sources = list_nested_files_gitignore("my/rust/project").await?;
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list_nested_files_gitignore<P: AsRef<Path> + Send>(path: P) -> Result<Vec<PathBuf>> {
+    ensure_dir_root(&path)?;
+
+    // The walker is blocking so keep it off the async threads
+    let path = path.as_ref().to_path_buf();
+    tokio::task::spawn_blocking(move || walk_ignored(&path))
+        .await
+        .context("gitignore listing task failed")?
+}
+
+/// Sync version of [`list_nested_files_gitignore`], exposed as
+/// [`crate::sync::list_nested_files_gitignore`]
+pub(crate) fn list_nested_files_gitignore_sync<P: AsRef<Path>>(path: P) -> Result<Vec<PathBuf>> {
+    ensure_dir_root(&path)?;
+
+    walk_ignored(path.as_ref())
+}
+
+#[cfg(test)]
+mod gitignore_tests {
+    use super::*;
+    use crate::util::TempPath;
+
+    #[tokio::test]
+    async fn skips_ignored_files() -> Result<()> {
+        let root = TempPath::new("gitignore_skips_ignored").await?;
+        root.multi_folder(vec!["src", "target/debug", "node_modules/pkg", ".git"])
+            .await?;
+        root.multi_file(vec![
+            "src/main.rs",
+            "src/scratch.tmp",
+            "target/debug/app",
+            "node_modules/pkg/index.js",
+            ".git/HEAD",
+            ".env.example",
+        ])
+        .await?;
+        std::fs::write(root.path.join(".gitignore"), "target/\n*.tmp\n")?;
+        std::fs::write(root.path.join(".ignore"), "node_modules/\n")?;
+
+        let mut files = list_nested_files_gitignore(&root.path).await?;
+        files.sort();
+        let expected: Vec<PathBuf> = [".env.example", ".gitignore", ".ignore", "src/main.rs"]
+            .iter()
+            .map(|f| root.path.join(f))
+            .collect();
+        assert_eq!(files, expected);
+
+        let mut files = list_nested_files_gitignore_sync(&root.path)?;
+        files.sort();
+        assert_eq!(files, expected);
+
+        assert!(list_nested_files_gitignore(root.path.join("src/main.rs"))
+            .await
+            .is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,430 @@
+//! Glob style searching where the pattern contains both the root and the wildcards
+//!
+//! Requires the `globset` feature.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use filetools::glob;
+//!
+//! #[tokio::main]
+//! async fn main() -> anyhow::Result<()> {
+//!     // All compressed logs anywhere under `/var/log`
+//!     let logs = glob("/var/log/**/*.gz").await?;
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use crate::util::{
+    ensure_dir_root, iteritems, iteritems_sync, walk, walk_sync, FtIterItemState, FtVisitor,
+};
+use anyhow::{Context, Result};
+use globset::{GlobBuilder, GlobMatcher, GlobSet};
+use std::ops::ControlFlow;
+use std::path::{Component, Path, PathBuf};
+
+/// Characters that mark a path component as containing a wildcard
+const WILDCARDS: &[char] = &['*', '?', '[', '{'];
+
+/// A glob pattern split into its literal root and the wildcard remainder
+pub(crate) struct GlobPlan {
+    /// Literal path to start the traversal from
+    pub root: PathBuf,
+
+    /// Matcher for paths relative to `root`, `None` if the pattern had no wildcards
+    pub matcher: Option<GlobMatcher>,
+
+    /// Whether the remainder can match beyond the immediate children of `root`
+    pub recursive: bool,
+}
+
+impl GlobPlan {
+    /// Splits the literal prefix from the first wildcard component of `pattern`
+    pub fn new(pattern: &str) -> Result<Self> {
+        let mut root = PathBuf::new();
+        let mut rest = vec![];
+
+        for component in Path::new(pattern).components() {
+            match component {
+                Component::Normal(c) if !rest.is_empty() || is_wildcard(c.to_str()) => {
+                    rest.push(c.to_string_lossy().into_owned());
+                }
+                _ if !rest.is_empty() => {
+                    anyhow::bail!("unsupported component after wildcard in `{pattern}`")
+                }
+                c => root.push(c),
+            }
+        }
+
+        if rest.is_empty() {
+            return Ok(Self {
+                root,
+                matcher: None,
+                recursive: false,
+            });
+        }
+
+        let remainder = rest.join("/");
+        let matcher = GlobBuilder::new(&remainder)
+            .literal_separator(true)
+            .build()
+            .context("invalid glob pattern")?
+            .compile_matcher();
+
+        if root.as_os_str().is_empty() {
+            root.push(".");
+        }
+
+        Ok(Self {
+            root,
+            matcher: Some(matcher),
+            recursive: rest.len() > 1 || remainder.contains("**"),
+        })
+    }
+
+    /// Iteration state needed to find every file and directory the pattern could match
+    pub fn iterstate(&self) -> FtIterItemState {
+        if self.recursive {
+            FtIterItemState::RBoth
+        } else {
+            FtIterItemState::Both
+        }
+    }
+
+    /// Keeps only the candidates whose path relative to the root matches the pattern
+    pub fn finish(&self, candidates: Vec<PathBuf>) -> Vec<PathBuf> {
+        let mut matches: Vec<PathBuf> = match &self.matcher {
+            Some(matcher) => candidates
+                .into_iter()
+                .filter(|c| {
+                    c.strip_prefix(&self.root)
+                        .map(|rel| matcher.is_match(rel))
+                        .unwrap_or(false)
+                })
+                .collect(),
+            None => candidates,
+        };
+
+        matches.sort();
+        matches
+    }
+}
+
+fn is_wildcard(component: Option<&str>) -> bool {
+    component.is_some_and(|c| c.contains(WILDCARDS))
+}
+
+/// Finds all files and directories matching a glob pattern.
+///
+/// The literal part of the pattern before the first wildcard is used as the root of the
+/// search, the remainder is matched against paths relative to that root. `*` and `?` never
+/// match a path separator, `**` matches across any number of directories and character
+/// classes like `[abc]` are supported. The results are sorted.
+///
+/// If the pattern has no wildcards, the path itself is returned if it exists.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::glob`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The wildcard part of the pattern is not a valid glob
+/// * The literal root of the pattern exists but cannot be read
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::glob;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     // Rust files directly inside `src`
+///     let sources = glob("src/*.rs").await?;
+///
+///     // Compressed logs anywhere under `/var/log`
+///     let logs = glob("/var/log/**/*.gz").await?;
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn glob(pattern: &str) -> Result<Vec<PathBuf>> {
+    let plan = GlobPlan::new(pattern)?;
+    if plan.matcher.is_none() {
+        let found = plan.root.exists().then(|| plan.root.clone());
+        return Ok(found.into_iter().collect());
+    }
+
+    if !plan.root.is_dir() {
+        return Ok(vec![]);
+    }
+
+    let candidates = iteritems(&plan.root, plan.iterstate(), None).await?;
+    Ok(plan.finish(candidates))
+}
+
+/// Sync version of [`glob`], exposed as [`crate::sync::glob`]
+pub(crate) fn glob_sync(pattern: &str) -> Result<Vec<PathBuf>> {
+    let plan = GlobPlan::new(pattern)?;
+    if plan.matcher.is_none() {
+        let found = plan.root.exists().then(|| plan.root.clone());
+        return Ok(found.into_iter().collect());
+    }
+
+    if !plan.root.is_dir() {
+        return Ok(vec![]);
+    }
+
+    let candidates = iteritems_sync(&plan.root, plan.iterstate(), None)?;
+    Ok(plan.finish(candidates))
+}
+
+/// Visitor that collects the files whose path relative to `root` matches `set`
+struct GlobSetItems<'a> {
+    root: &'a Path,
+    set: &'a GlobSet,
+    files: Vec<PathBuf>,
+}
+
+impl<'a> GlobSetItems<'a> {
+    fn new(root: &'a Path, set: &'a GlobSet) -> Self {
+        Self {
+            root,
+            set,
+            files: vec![],
+        }
+    }
+}
+
+impl FtVisitor for GlobSetItems<'_> {
+    fn visit(&mut self, item: PathBuf) -> ControlFlow<()> {
+        if item
+            .strip_prefix(self.root)
+            .is_ok_and(|rel| self.set.is_match(rel))
+        {
+            self.files.push(item);
+        }
+
+        ControlFlow::Continue(())
+    }
+}
+
+/// Lists files in a folder (including ALL subdirectories) matching any glob in a precompiled `GlobSet`.
+///
+/// Each file's path relative to `path` is matched against the set, so a set built once can be
+/// reused across many directories without compiling the patterns again. How `*` treats path
+/// separators is down to how the globs in the set were built.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_nested_files_globset`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::list_nested_files_globset;
+/// use globset::{Glob, GlobSetBuilder};
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let mut builder = GlobSetBuilder::new();
+///     builder.add(Glob::new("**/*.rs")?);
+///     builder.add(Glob::new("**/Cargo.toml")?);
+///     let set = builder.build()?;
+///
+///     for project in ["crates/one", "crates/two"] {
+///         let files = list_nested_files_globset(project, &set).await?;
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list_nested_files_globset(
+    path: impl AsRef<Path> + Send,
+    set: &GlobSet,
+) -> Result<Vec<PathBuf>> {
+    let path = path.as_ref();
+    ensure_dir_root(path)?;
+
+    let mut visitor = GlobSetItems::new(path, set);
+    walk(path, FtIterItemState::RFile, None, &mut visitor).await?;
+
+    Ok(visitor.files)
+}
+
+/// Sync version of [`list_nested_files_globset`], exposed as [`crate::sync::list_nested_files_globset`]
+pub(crate) fn list_nested_files_globset_sync(
+    path: impl AsRef<Path>,
+    set: &GlobSet,
+) -> Result<Vec<PathBuf>> {
+    let path = path.as_ref();
+    ensure_dir_root(path)?;
+
+    let mut visitor = GlobSetItems::new(path, set);
+    walk_sync(path, FtIterItemState::RFile, None, &mut visitor)?;
+
+    Ok(visitor.files)
+}
+
+#[cfg(test)]
+mod glob_tests {
+    use super::*;
+    use crate::util::TempPath;
+
+    async fn fixture(name: &str) -> Result<TempPath> {
+        let root = TempPath::new(name).await?;
+        root.multi_folder(vec!["logs/2023", "logs/2024", "src"])
+            .await?;
+        root.multi_file(vec![
+            "logs/a.gz",
+            "logs/2023/b.gz",
+            "logs/2024/c.gz",
+            "logs/2024/d.txt",
+            "src/main.rs",
+            "src/lib.rs",
+            "src/mod.c",
+        ])
+        .await?;
+
+        Ok(root)
+    }
+
+    fn pattern(root: &TempPath, rest: &str) -> String {
+        format!("{}/{}", root.path.display(), rest)
+    }
+
+    #[tokio::test]
+    async fn single_star_stays_in_one_directory() -> Result<()> {
+        let root = fixture("glob_single_star").await?;
+
+        let result = glob(&pattern(&root, "src/*.rs")).await?;
+        assert_eq!(
+            result,
+            vec![root.path.join("src/lib.rs"), root.path.join("src/main.rs")]
+        );
+
+        let result = glob(&pattern(&root, "logs/*.gz")).await?;
+        assert_eq!(result, vec![root.path.join("logs/a.gz")]);
+        assert_eq!(glob_sync(&pattern(&root, "logs/*.gz"))?, result);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn double_star_matches_across_directories() -> Result<()> {
+        let root = fixture("glob_double_star").await?;
+
+        let result = glob(&pattern(&root, "logs/**/*.gz")).await?;
+        assert_eq!(
+            result,
+            vec![
+                root.path.join("logs/2023/b.gz"),
+                root.path.join("logs/2024/c.gz"),
+                root.path.join("logs/a.gz"),
+            ]
+        );
+
+        // Directories are matched too
+        let result = glob(&pattern(&root, "logs/20*")).await?;
+        assert_eq!(
+            result,
+            vec![root.path.join("logs/2023"), root.path.join("logs/2024")]
+        );
+        assert_eq!(glob_sync(&pattern(&root, "logs/20*"))?, result);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn character_classes() -> Result<()> {
+        let root = fixture("glob_char_class").await?;
+
+        let result = glob(&pattern(&root, "logs/202[3]/*")).await?;
+        assert_eq!(result, vec![root.path.join("logs/2023/b.gz")]);
+
+        let result = glob(&pattern(&root, "src/*.[rc]*")).await?;
+        assert_eq!(result.len(), 3);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn literal_and_relative_patterns() -> Result<()> {
+        let root = fixture("glob_literal").await?;
+
+        let literal = pattern(&root, "src/main.rs");
+        assert_eq!(glob(&literal).await?, vec![root.path.join("src/main.rs")]);
+        assert!(glob(&pattern(&root, "src/missing.rs")).await?.is_empty());
+        assert!(glob(&pattern(&root, "missing/*.rs")).await?.is_empty());
+
+        // Relative to the crate root the tests run from
+        let result = glob("src/*.rs").await?;
+        assert!(result.contains(&PathBuf::from("src/glob.rs")));
+        assert!(result.iter().all(|p| p.extension().unwrap() == "rs"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn splits_root_from_wildcards() -> Result<()> {
+        let plan = GlobPlan::new("/var/log/**/*.gz")?;
+        assert_eq!(plan.root, PathBuf::from("/var/log"));
+        assert!(plan.recursive);
+
+        let plan = GlobPlan::new("*.rs")?;
+        assert_eq!(plan.root, PathBuf::from("."));
+        assert!(!plan.recursive);
+
+        assert!(GlobPlan::new("src/[*.rs").is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn globset_listing_matches_any_glob() -> Result<()> {
+        use globset::{Glob, GlobSetBuilder};
+
+        let root = fixture("glob_globset").await?;
+        let mut builder = GlobSetBuilder::new();
+        builder.add(Glob::new("src/*.rs")?);
+        builder.add(Glob::new("**/*.gz")?);
+        let set = builder.build()?;
+
+        let mut result = list_nested_files_globset(&root.path, &set).await?;
+        result.sort();
+        assert_eq!(
+            result,
+            vec![
+                root.path.join("logs/2023/b.gz"),
+                root.path.join("logs/2024/c.gz"),
+                root.path.join("logs/a.gz"),
+                root.path.join("src/lib.rs"),
+                root.path.join("src/main.rs"),
+            ]
+        );
+
+        let mut sync_result = list_nested_files_globset_sync(&root.path, &set)?;
+        sync_result.sort();
+        assert_eq!(sync_result, result);
+
+        // Matched relative to the listing root
+        let logs = list_nested_files_globset(root.path.join("logs"), &set).await?;
+        assert_eq!(logs.len(), 3);
+
+        assert!(list_nested_files_globset(root.path.join("missing"), &set)
+            .await
+            .is_err());
+
+        Ok(())
+    }
+}
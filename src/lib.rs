@@ -13,6 +13,54 @@
 //! The operations in this crate are designed for async/await, however sync variations
 //! of the operations exist in the [`crate::sync`] module.
 //!
+//! ## Symlinks
+//!
+//! Symlinks are followed when listing, both for the root path and for anything found
+//! beneath it. Results are always built by joining entries onto the path that was given,
+//! so listing through a symlinked root returns paths under the symlink rather than its target.
+//! Use [`list_nested_files_canonical`] / [`list_nested_directories_canonical`] if you want
+//! the root resolved to its real location first.
+//!
+//! ## Results
+//!
+//! Every entry is visited exactly once per listing and filters are applied as a single
+//! yes / no check, so results never contain duplicates, even when a filter matches an entry
+//! in several places (e.g. `FtFilter::Raw("log")` against `logs/log.txt`). The order follows
+//! the order the OS returns directory entries in, sort the results if a stable order matters.
+//!
+//! ## Errors
+//!
+//! Functions return an [`anyhow::Result`]. When the given path is missing or isn't a directory
+//! the error is an [`FtError`] holding that path, recover it with `downcast_ref::<FtError>()`.
+//!
+//! ## Runtime
+//!
+//! The async functions use `tokio::fs`, which hands the blocking filesystem calls to the
+//! blocking pool of the runtime they're awaited on, so they need to run inside a tokio runtime.
+//! Most of them run entirely in the calling task, these spawn extra tasks on the current
+//! runtime:
+//!
+//! * [`list_nested_files_concurrent`] / [`list_nested_directories_concurrent`], use the
+//!   `_on` variants to spawn on a given `Handle` instead
+//! * [`list_nested_files_with_hash`] and [`find_duplicates`] while reading files
+//! * [`list_nested_files_multi`] and the other `_multi` listings, one task per root
+//!
+//! [`list_nested_files_blocking`] / [`list_nested_directories_blocking`] and the functions
+//! backed by blocking libraries, like the `ignore` and `trash` features, run on the blocking
+//! pool with `spawn_blocking`.
+//!
+//! tokio is a required dependency, other runtimes like `async-std` are not supported.
+//! Use the [`crate::sync`] functions from those, e.g. inside their own `spawn_blocking`.
+//!
+//! ## Feature flags
+//!
+//! * `globset` - glob pattern searching with [`glob`] and [`list_nested_files_globset`]
+//! * `serde` - `Serialize` / `Deserialize` for [`FtFilter`] so filters can live in config files
+//! * `notify` - watching directories for changes with [`watch_directory`]
+//! * `ignore` - listing files while honouring `.gitignore` with [`list_nested_files_gitignore`]
+//! * `trash` - sending files to the system trash with [`move_to_trash`]
+//! * `sha2` / `blake3` - content addressed names with [`naming::content_addressed_name`]
+//!
 //! # Example
 //!
 //! ```rust,no_run
@@ -34,16 +82,57 @@
 //! }
 //! ```
 
+mod dedup;
+mod error;
+#[cfg(feature = "ignore")]
+mod gitignore;
+#[cfg(feature = "globset")]
+mod glob;
 pub mod naming;
+pub mod prelude;
+#[cfg(feature = "trash")]
+mod recycle;
+mod render;
 pub mod sync;
+mod tree;
 pub(crate) mod util;
+pub mod vfs;
+#[cfg(feature = "notify")]
+mod watch;
+
+pub use dedup::find_duplicates;
+pub use error::FtError;
+#[cfg(feature = "ignore")]
+pub use gitignore::list_nested_files_gitignore;
+#[cfg(feature = "globset")]
+pub use glob::{glob, list_nested_files_globset};
+#[cfg(feature = "trash")]
+pub use recycle::{move_to_trash, remove_files_matching_to_trash};
+pub use render::format_tree;
+pub use tree::{build_tree, FtTreeNode};
+#[cfg(feature = "notify")]
+pub use watch::{watch_directory, FtWatchEvent, FtWatcher};
 
 use anyhow::{Context, Result};
 use regex::Regex;
+use std::borrow::Borrow;
+use std::cmp::Reverse;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeSet, HashMap};
+use std::ffi::OsStr;
+use std::ops::ControlFlow;
 use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
 use tokio::fs;
 
-use util::{iteritems, FtIterItemState};
+use util::{
+    brokenlinks, check_numeric_range, countitems, dirs_holding_files, ensure_dir_root, firstitem,
+    gitrepos, hashitems, iteritems, iteritems_into, multiitems, partition_plan, progressitems,
+    rename_plan, scanneditems, strip_root, tree_targets, walk, CancellableItems, EntryItems,
+    ExcludedItems, FtIterItemState, PartialItems, PrunedItems, StatsItems, TopItems,
+};
 
 /// Filter types for listing files / directories
 ///
@@ -60,20 +149,450 @@ use util::{iteritems, FtIterItemState};
 /// // Use the Path filter to match paths that contain `sub/path/to/math`
 /// let filter = FtFilter::Path(PathBuf::from("sub/path/to/match"));
 ///
+/// // Use a Name filter to match items whose own name contains "test",
+/// // ignoring the directories they are in
+/// let filter = FtFilter::Name("test".to_string());
+///
+/// // Wrap a filter in Canonical to match against the absolute path of each item,
+/// // with symlinks and `..` resolved
+/// let inner = FtFilter::Path(PathBuf::from("/home/user/data"));
+/// let filter = FtFilter::Canonical(Box::new(inner));
+///
+/// // Use the Executable filter to match executable files
+/// let filter = FtFilter::Executable;
+///
 /// // Use a Regex filter to match all files ending with `.rs`
 /// let re = Regex::new(r"(.*)\.rs").expect("unable to create regex");
 /// let filter = FtFilter::Regex(re);
+///
+/// // Use a Custom filter for anything else, e.g. names with an even number of bytes
+/// let filter = FtFilter::custom(|path| {
+///     path.file_name().is_some_and(|name| name.len() % 2 == 0)
+/// });
+/// ```
+///
+/// The `*_with_filter` functions take the filter either by value or by reference,
+/// so the same filter can be reused across calls without rebuilding it:
+///
+/// ```rust,no_run
+/// use filetools::{list_files_with_filter, list_nested_directories_with_filter, FtFilter};
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let filter = FtFilter::Raw("2024".to_string());
+///     let reports = list_files_with_filter("reports", &filter).await?;
+///     let archives = list_nested_directories_with_filter("archive", &filter).await?;
+///
+///     // Or clone it if it needs to be owned elsewhere
+///     let owned = filter.clone();
+///
+///     Ok(())
+/// }
 /// ```
-#[derive(Debug)]
+///
+/// Two filters are equal if they are the same variant with the same pattern,
+/// regex filters are compared by their pattern string and custom filters are only equal
+/// to clones of themselves.
+///
+/// ## Serde
+///
+/// With the `serde` feature enabled, filters are (de)serialized as a tagged object with
+/// the pattern as a string, e.g. `{ "type": "regex", "pattern": "(.*)\\.rs" }`.
+/// Regex patterns are compiled when deserialized so an invalid pattern is a deserialization error.
+/// Custom filters can't be serialized.
+#[derive(Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "type", content = "pattern", rename_all = "snake_case")
+)]
 pub enum FtFilter {
     /// Filter based on a raw String pattern
+    ///
+    /// On Windows `/` and `\` are treated as the same separator, see [`path_contains_str`]
     Raw(String),
 
     /// Filter based on a PathBuf pattern
+    ///
+    /// On Windows `/` and `\` are treated as the same separator so `files/test` also matches
+    /// paths listed with `\`. Elsewhere `\` is part of a file name, see [`path_contains_str`]
     Path(PathBuf),
 
+    /// Filter based on a raw String pattern matched against the item name only
+    /// (the final path component), not the rest of the path
+    Name(String),
+
+    /// Filter based on a raw String pattern matched against the name of the item's
+    /// immediate parent directory only, grandparents and above are ignored
+    ParentName(String),
+
+    /// Applies the inner filter to the canonicalized path of each item rather than the
+    /// path built from the listing root, so absolute path filters work however the root was given.
+    ///
+    /// Canonicalizing touches the filesystem for every item so this is slower than the
+    /// other filters. Items that can't be canonicalized never match.
+    Canonical(Box<FtFilter>),
+
+    /// Filter for executable files.
+    ///
+    /// On Unix this matches files with any execute permission bit set, on Windows it matches
+    /// `.exe`, `.bat` and `.cmd` files. Directories never match.
+    Executable,
+
+    /// Filter for zero byte files, like the leftovers of failed downloads.
+    ///
+    /// Directories never match, even empty ones.
+    Empty,
+
+    /// Filter for files with any content, the opposite of [`FtFilter::Empty`] for files.
+    ///
+    /// Directories never match.
+    NonEmpty,
+
     /// Filter based on a regex pattern
+    #[cfg_attr(feature = "serde", serde(with = "util::serde_regex"))]
     Regex(Regex),
+
+    /// Filter based on a regex pattern matched against the item name only (the final path
+    /// component), so anchors like `^test` and `\.rs$` apply to the name rather than the path
+    #[cfg_attr(feature = "serde", serde(with = "util::serde_regex"))]
+    RegexName(Regex),
+
+    /// Filter for items at least this many levels below the listing root, inclusive.
+    ///
+    /// The direct children of the root are at depth 1, so `MinDepth(3)` matches `a/b/file.txt`
+    /// but not `a/file.txt`. Recursive listings still go through the shallower directories.
+    MinDepth(usize),
+
+    /// Filter for items at most this many levels below the listing root, inclusive.
+    ///
+    /// The direct children of the root are at depth 1, so `MaxDepth(2)` matches `a/file.txt`
+    /// but not `a/b/file.txt`. Deeper directories are still walked, use [`build_tree`] to stop
+    /// the traversal itself at a depth.
+    MaxDepth(usize),
+
+    /// Filter based on a user supplied predicate, called with the path of each item.
+    ///
+    /// See [`FtFilter::custom`] to build one from a closure.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    Custom(Arc<dyn Fn(&Path) -> bool + Send + Sync>),
+}
+
+impl FtFilter {
+    /// Creates an [`FtFilter::Custom`] filter from a closure
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use filetools::FtFilter;
+    ///
+    /// // Only items whose name is all lowercase
+    /// let filter = FtFilter::custom(|path| {
+    ///     path.file_name()
+    ///         .is_some_and(|name| name.to_string_lossy().chars().all(|c| !c.is_uppercase()))
+    /// });
+    /// ```
+    pub fn custom(predicate: impl Fn(&Path) -> bool + Send + Sync + 'static) -> Self {
+        Self::Custom(Arc::new(predicate))
+    }
+}
+
+impl std::fmt::Debug for FtFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Raw(raw) => f.debug_tuple("Raw").field(raw).finish(),
+            Self::Path(path) => f.debug_tuple("Path").field(path).finish(),
+            Self::Name(name) => f.debug_tuple("Name").field(name).finish(),
+            Self::ParentName(name) => f.debug_tuple("ParentName").field(name).finish(),
+            Self::Canonical(inner) => f.debug_tuple("Canonical").field(inner).finish(),
+            Self::Executable => f.write_str("Executable"),
+            Self::Empty => f.write_str("Empty"),
+            Self::NonEmpty => f.write_str("NonEmpty"),
+            Self::Regex(re) => f.debug_tuple("Regex").field(re).finish(),
+            Self::RegexName(re) => f.debug_tuple("RegexName").field(re).finish(),
+            Self::MinDepth(depth) => f.debug_tuple("MinDepth").field(depth).finish(),
+            Self::MaxDepth(depth) => f.debug_tuple("MaxDepth").field(depth).finish(),
+            Self::Custom(_) => f.write_str("Custom(..)"),
+        }
+    }
+}
+
+impl PartialEq for FtFilter {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Raw(a), Self::Raw(b)) => a == b,
+            (Self::Path(a), Self::Path(b)) => a == b,
+            (Self::Name(a), Self::Name(b)) => a == b,
+            (Self::ParentName(a), Self::ParentName(b)) => a == b,
+            (Self::Canonical(a), Self::Canonical(b)) => a == b,
+            (Self::Executable, Self::Executable) => true,
+            (Self::Empty, Self::Empty) => true,
+            (Self::NonEmpty, Self::NonEmpty) => true,
+            (Self::Regex(a), Self::Regex(b)) => a.as_str() == b.as_str(),
+            (Self::RegexName(a), Self::RegexName(b)) => a.as_str() == b.as_str(),
+            (Self::MinDepth(a), Self::MinDepth(b)) => a == b,
+            (Self::MaxDepth(a), Self::MaxDepth(b)) => a == b,
+            (Self::Custom(a), Self::Custom(b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for FtFilter {}
+
+/// The kind of items to look for with [`list_items`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum FtIterTarget {
+    /// Only list files
+    Files,
+
+    /// Only list directories
+    Dirs,
+
+    /// List both files and directories
+    Both,
+}
+
+/// An item found by the combined listing functions such as [`list_nested_entries`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum DirEntryKind {
+    /// A file
+    File(PathBuf),
+
+    /// A directory
+    Dir(PathBuf),
+}
+
+impl DirEntryKind {
+    /// The path of the item
+    pub fn path(&self) -> &Path {
+        match self {
+            Self::File(path) | Self::Dir(path) => path,
+        }
+    }
+
+    /// Consumes the item, returning its path
+    pub fn into_path(self) -> PathBuf {
+        match self {
+            Self::File(path) | Self::Dir(path) => path,
+        }
+    }
+
+    /// Checks if the item is a file
+    pub fn is_file(&self) -> bool {
+        matches!(self, Self::File(_))
+    }
+
+    /// Checks if the item is a directory
+    pub fn is_dir(&self) -> bool {
+        matches!(self, Self::Dir(_))
+    }
+}
+
+/// Paths listed by the `*_partial` functions along with each path that couldn't be read
+/// and the error it produced
+pub type FtPartialListing = (Vec<PathBuf>, Vec<(PathBuf, std::io::Error)>);
+
+/// Options for [`copy_directory_with_options`]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct FtCopyOptions {
+    /// Give each copy the same modification / access times and permissions as the original.
+    ///
+    /// File contents are always copied along with their permissions, this also carries over
+    /// the times and applies the same to directories. On Windows only the file times and the
+    /// read-only flag are preserved. Symlinks recreated at the destination keep their own.
+    pub preserve_metadata: bool,
+
+    /// Copy what symlinks point to instead of recreating the links themselves.
+    ///
+    /// By default a symlink is recreated with the same, possibly relative, target so links
+    /// inside the tree keep working in the copy. Dangling symlinks are skipped when this is set,
+    /// and a symlink pointing back at a directory it is inside is an error.
+    pub dereference_symlinks: bool,
+}
+
+/// Incremental digest of a file's contents, see [`list_nested_files_with_hash`]
+///
+/// Files are read in fixed size chunks and fed to [`FtHasher::update`] in order, so large
+/// files are never held in memory at once. Hashers from crates like `sha2` can implement it
+/// directly. [`DefaultHasher`] implements it for quick in-process checks, its output is not
+/// stable across Rust releases so don't store it.
+pub trait FtHasher: Send + 'static {
+    /// Feeds the next chunk of the file contents
+    fn update(&mut self, chunk: &[u8]);
+
+    /// Consumes the hasher, returning the digest of everything fed to it
+    fn finish(self) -> String;
+}
+
+impl FtHasher for DefaultHasher {
+    fn update(&mut self, chunk: &[u8]) {
+        std::hash::Hasher::write(self, chunk);
+    }
+
+    fn finish(self) -> String {
+        format!("{:016x}", std::hash::Hasher::finish(&self))
+    }
+}
+
+/// Totals of what a copy did, returned by [`copy_directory_with_report`] and
+/// [`copy_files_matching_with_report`]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct FtCopyReport {
+    /// Number of files copied. Symlinks recreated as links are not counted
+    pub files: usize,
+
+    /// Total bytes written across all the copied files
+    pub bytes: u64,
+}
+
+impl FtCopyReport {
+    /// Counts one more copied file of `bytes` bytes
+    pub(crate) fn add(&mut self, bytes: u64) {
+        self.files += 1;
+        self.bytes += bytes;
+    }
+}
+
+/// Default maximum number of directories [`create_numeric_directories`] and
+/// [`create_numeric_directories_ext`] will create in one call.
+///
+/// Use [`create_numeric_directories_with_limit`] for a different limit.
+pub const MAX_NUMERIC_DIRECTORIES: usize = 100_000;
+
+/// Zero padding of the bucket directories made by [`partition_files_into_directories`]
+pub(crate) const PARTITION_FILL: usize = 4;
+
+/// How [`partition_files_into_directories`] decides which bucket a file goes in
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum FtPartition {
+    /// Files are sorted by name and dealt out one per bucket in turn, giving buckets
+    /// that differ in size by at most one file
+    RoundRobin,
+
+    /// Files go in the bucket picked by a hash of their name, so the same file name
+    /// always lands in the same bucket no matter what else is in the directory
+    Hash,
+}
+
+/// A single page of files from [`list_files_paged`] or a [`FtPager`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FtPage {
+    /// Files in this page
+    pub items: Vec<PathBuf>,
+
+    /// Total number of files across all pages
+    pub total: usize,
+
+    /// Whether there are more files after this page
+    pub has_more: bool,
+}
+
+/// Files matching a filter along with how many files were checked against it, from
+/// [`list_files_with_filter_scanned`] / [`list_nested_files_with_filter_scanned`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FtFilterResult {
+    /// Files that matched the filter
+    pub matched: Vec<PathBuf>,
+
+    /// Number of files checked against the filter, matching or not
+    pub total_scanned: usize,
+}
+
+impl FtFilterResult {
+    /// Whether there were no files to check at all, as opposed to none of them matching
+    pub fn nothing_scanned(&self) -> bool {
+        self.total_scanned == 0
+    }
+}
+
+/// How much work a listing did, from [`list_nested_files_with_stats`]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct FtTraversalStats {
+    /// Number of directories opened, including the root
+    pub dirs_visited: usize,
+
+    /// Number of entries read from those directories, files and directories alike
+    pub entries_seen: usize,
+
+    /// Number of items returned
+    pub matched: usize,
+
+    /// How long the listing took
+    pub elapsed: Duration,
+}
+
+/// Files listed once up front and handed out a page at a time.
+///
+/// Created with [`list_files_pager`] (or [`crate::sync::list_files_pager`]). The files are
+/// sorted so pages are stable no matter what order the directory was read in.
+#[derive(Debug, Clone, Default)]
+pub struct FtPager {
+    items: Vec<PathBuf>,
+}
+
+impl FtPager {
+    pub(crate) fn new(mut items: Vec<PathBuf>) -> Self {
+        items.sort();
+        Self { items }
+    }
+
+    /// Returns at most `limit` files starting at `offset`.
+    ///
+    /// An `offset` past the end returns an empty page.
+    pub fn page(&self, offset: usize, limit: usize) -> FtPage {
+        let total = self.items.len();
+        let start = offset.min(total);
+        let end = start.saturating_add(limit).min(total);
+
+        FtPage {
+            items: self.items[start..end].to_vec(),
+            total,
+            has_more: end < total,
+        }
+    }
+
+    /// Total number of files available
+    pub fn total(&self) -> usize {
+        self.items.len()
+    }
+}
+
+/// A file or directory along with the details a file manager would show,
+/// from [`list_entries_detailed`] / [`list_nested_entries_detailed`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FtFileInfo {
+    /// Path of the item, built by joining names onto the listing root
+    pub path: PathBuf,
+
+    /// Name of the item, the final component of `path`
+    pub name: String,
+
+    /// Whether the item is a directory
+    pub is_dir: bool,
+
+    /// Size in bytes as reported by the filesystem
+    pub len: u64,
+
+    /// Last modification time, `None` if the platform doesn't record it
+    pub modified: Option<std::time::SystemTime>,
+}
+
+impl FtFileInfo {
+    pub(crate) fn new(path: PathBuf, meta: &std::fs::Metadata) -> Self {
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        Self {
+            name,
+            is_dir: meta.is_dir(),
+            len: meta.len(),
+            modified: meta.modified().ok(),
+            path,
+        }
+    }
 }
 
 /// Checks if a given pattern is considered a subdirectory of the given path
@@ -101,9 +620,53 @@ pub fn is_subdir(path: impl AsRef<Path>, dir: impl AsRef<Path>) -> bool {
     false
 }
 
+/// Checks if `path` is located somewhere below the `ancestor` directory
+///
+/// Both paths are lexically normalized first (see [`normalize_path`]) and then compared
+/// component by component, so only a genuine ancestor matches, not a directory that
+/// happens to share a name with one of the components. A path is not a descendant of itself.
+///
+/// The filesystem is never touched and symlinks are not resolved, so relative and absolute
+/// paths never match each other.
+///
+/// # Example
+///
+/// ```rust
+/// use filetools::is_descendant_of;
+///
+/// assert!(is_descendant_of("/srv/data/logs/app.log", "/srv/data"));
+/// assert!(is_descendant_of("/srv/data/../data/logs", "/srv/./data"));
+///
+/// // `data` appears in the path but `/srv/data` is not an ancestor
+/// assert!(!is_descendant_of("/home/data/logs", "/srv/data"));
+/// ```
+pub fn is_descendant_of(path: impl AsRef<Path>, ancestor: impl AsRef<Path>) -> bool {
+    let path = normalize_path(path);
+    let ancestor = normalize_path(ancestor);
+
+    if path == ancestor {
+        return false;
+    }
+
+    // A normalized `.` is the root of every relative path that doesn't escape it
+    if ancestor == Path::new(".") {
+        return path.is_relative()
+            && path != Path::new(".")
+            && !path.starts_with(Component::ParentDir);
+    }
+
+    path.starts_with(&ancestor)
+}
+
 /// Determines if a path contains a given pattern
 ///
-/// Converts both the path and the pattern to a string and performs simple matching
+/// Compares the raw bytes of the path and the pattern, so paths that aren't valid UTF-8
+/// are matched like any other. Separators are compared as written so `a/b` won't match
+/// a path written as `a\b`, see [`path_contains_str`] for a more forgiving check.
+///
+/// The pattern is matched as a raw substring so it can start or end part way through a
+/// component (`a/b/cd` contains `b/c`), see [`path_contains_components`] to only match
+/// whole components.
 ///
 /// # Example
 ///
@@ -117,882 +680,8507 @@ pub fn is_subdir(path: impl AsRef<Path>, dir: impl AsRef<Path>) -> bool {
 /// let result = path_contains(path, pattern);
 /// ```
 pub fn path_contains(path: impl AsRef<Path>, pattern: impl AsRef<Path> /* maybe */) -> bool {
-    if let Some(p) = path.as_ref().to_str() {
-        if let Some(pat) = pattern.as_ref().to_str() {
-            return p.contains(pat);
-        }
-    }
-
-    false
+    util::os_contains(path.as_ref().as_os_str(), pattern.as_ref().as_os_str())
 }
 
-/// Creates a directory at the given path.
+/// Determines if a path contains a given substring
 ///
-/// If the directory already exists, nothing is done
-///
-/// ## Sync
+/// A plain string check on the path with no path semantics, unlike [`path_contains`]:
 ///
-/// For the `sync` version, see [`crate::sync::ensure_directory`]
+/// * On Windows `/` and `\` are treated as the same separator, so `"a/b"` matches `a\b` and
+///   the other way around. Elsewhere `\` is a valid file name character and only matches itself
+/// * Paths that aren't valid UTF-8 are compared byte for byte like any other
 ///
 /// # Example
 ///
-/// ```rust,no_run
-/// use filetools::ensure_directory;
+/// ```rust
+/// use filetools::{path_contains, path_contains_str};
 ///
-/// #[tokio::main]
-/// async fn main() -> anyhow::Result<()> {
-///     let target_path = "directory/to/create";
-///     ensure_directory(target_path).await?;
+/// assert!(path_contains_str("projects/app/src/main.rs", "app/src"));
 ///
-///     Ok(())
+/// // Windows paths match whichever separator they were written with
+/// #[cfg(windows)]
+/// {
+///     let path = r"C:\Users\me\projects\app\src\main.rs";
+///     assert!(path_contains_str(path, "app/src"));
+///     assert!(!path_contains(path, "app/src"));
 /// }
 /// ```
-pub async fn ensure_directory(dir: impl AsRef<Path>) -> Result<()> {
-    if !dir.as_ref().exists() {
-        fs::create_dir_all(dir)
-            .await
-            .context("unable to create directory")?;
+pub fn path_contains_str(path: impl AsRef<Path>, substring: &str) -> bool {
+    util::os_contains_any_separator(path.as_ref().as_os_str(), OsStr::new(substring))
+}
+
+/// Determines if a path contains a pattern made of whole components
+///
+/// Unlike [`path_contains`], the pattern has to line up with the separators of the path: it
+/// matches if its components appear one after another in the path's components. Repeated
+/// separators and `.` components in the middle of either path are ignored, like
+/// [`Path::components`]. An empty pattern always matches.
+///
+/// # Example
+///
+/// ```rust
+/// use filetools::{path_contains, path_contains_components};
+///
+/// assert!(path_contains_components("a/b/c", "b/c"));
+///
+/// // `b/c` runs into `cd` so it isn't a whole component match
+/// assert!(path_contains("a/b/cd", "b/c"));
+/// assert!(!path_contains_components("a/b/cd", "b/c"));
+/// ```
+pub fn path_contains_components(path: impl AsRef<Path>, pattern: impl AsRef<Path>) -> bool {
+    let pattern: Vec<Component> = pattern.as_ref().components().collect();
+    if pattern.is_empty() {
+        return true;
     }
 
-    Ok(())
+    let path: Vec<Component> = path.as_ref().components().collect();
+    path.windows(pattern.len()).any(|window| window == pattern)
 }
 
-/// Creates multiple directories inside the target path.
+/// Determines if a path matches an [`FtFilter`], without a directory walk
 ///
-/// ## Sync
+/// Useful to apply the same filters as the listing functions to paths collected some other way.
+/// As there is no listing root, [`FtFilter::MinDepth`] and [`FtFilter::MaxDepth`] count the
+/// components of the path itself, so pass paths relative to the root they came from.
 ///
-/// For the `sync` version, see [`crate::sync::create_multiple_directories`]
+/// The filters that look at the item itself ([`FtFilter::Canonical`], [`FtFilter::Executable`],
+/// [`FtFilter::Empty`] and [`FtFilter::NonEmpty`]) still touch the filesystem and never match
+/// paths that don't exist.
 ///
 /// # Example
 ///
-/// ```rust,no_run
-/// use filetools::create_multiple_directories;
-///
-/// #[tokio::main]
-/// async fn main() -> anyhow::Result<()> {
-///     let root = "dir/to/populate";
-///     let to_create = ["dir1", "dir2", "dir3"];
+/// ```rust
+/// use filetools::{filter_matches, FtFilter};
 ///
-///     // Will create:
-///     // `dir/to/populate/dir1`
-///     // `dir/to/populate/dir2`
-///     // `dir/to/populate/dir3`
-///     create_multiple_directories(root, &to_create);
+/// let filter = FtFilter::Name(".rs".to_string());
+/// assert!(filter_matches("app/src/main.rs", &filter));
+/// assert!(!filter_matches("app/README.md", &filter));
 ///
-///     Ok(())
-/// }
+/// // Depth filters count the components of the path
+/// assert!(filter_matches("app/src/main.rs", &FtFilter::MinDepth(3)));
 /// ```
-pub async fn create_multiple_directories(
-    path: impl AsRef<Path>,
-    directories: &[impl AsRef<Path>],
-) -> Result<()> {
-    for dir in directories {
-        let target = path.as_ref().join(dir);
-        ensure_directory(target).await?;
-    }
-
-    Ok(())
+pub fn filter_matches(path: impl AsRef<Path>, filter: &FtFilter) -> bool {
+    let depth = path.as_ref().components().count();
+    util::matches_filter_at(path, filter, depth)
 }
 
-/// Creates a range of numeric folders in the given path
+/// Lexically normalizes a path without touching the filesystem.
 ///
-/// Directories can be padded with X zeros using the `fill` parameter.
+/// Similar to Go's `filepath.Clean`:
 ///
-/// ## Sync
+/// * Duplicate separators and `.` components are removed
+/// * Each `..` removes the preceding normal component
+/// * A `..` that would escape the root of an absolute path is dropped
+/// * Leading `..` components of a relative path are kept
 ///
-/// For the `sync` version, see [`crate::sync::create_numeric_directories`]
+/// An empty result becomes `.`. Unlike [`std::fs::canonicalize`], the path doesn't need
+/// to exist and symlinks are not resolved.
 ///
 /// # Example
 ///
-/// ```rust,no_run
-/// use filetools::create_numeric_directories;
+/// ```rust
+/// use filetools::normalize_path;
+/// use std::path::PathBuf;
 ///
-/// #[tokio::main]
-/// async fn main() -> anyhow::Result<()> {
-///     let root = "some/root/path";
-///     
-///     // This will create the following directories:
-///     // "some/root/path/0000"
-///     // ...
-///     // "some/root/path/0099"
-///     create_numeric_directories(root, 0, 100, 4).await?;
-///     Ok(())
-/// }
+/// assert_eq!(normalize_path("a/./b/../c"), PathBuf::from("a/c"));
+/// assert_eq!(normalize_path("/../etc//hosts"), PathBuf::from("/etc/hosts"));
+/// assert_eq!(normalize_path("../a/../../b"), PathBuf::from("../../b"));
 /// ```
-pub async fn create_numeric_directories(
-    path: impl AsRef<Path>,
-    start: usize,
-    end: usize,
-    fill: usize,
-) -> Result<()> {
-    for i in start..end {
-        let name = path
-            .as_ref()
-            .join(naming::generate_n_digit_name(i, fill, ""));
-        ensure_directory(name)
-            .await
-            .context("creating numeric directories")?;
+pub fn normalize_path(path: impl AsRef<Path>) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.as_ref().components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                Some(Component::RootDir) | Some(Component::Prefix(_)) => {}
+                _ => normalized.push(".."),
+            },
+            c => normalized.push(c),
+        }
     }
 
-    Ok(())
+    if normalized.as_os_str().is_empty() {
+        normalized.push(".");
+    }
+
+    normalized
 }
 
-/// Lists all files in the given directory (not including subdirectories).
-///
-/// ## Sync
-///
-/// For the `sync` version, see [`crate::sync::list_files`]
-///
-/// # Errors
+/// Computes the relative path that leads from `from` to `to`, like Python's `os.path.relpath`.
 ///
-/// This function will return an error in the following situations:
+/// Both paths are lexically normalized with [`normalize_path`] first and `from` is treated as
+/// a directory. Paths without a common prefix are still related through the root or the
+/// current directory, so `a/b` to `c` is `../../c`. An empty result becomes `.`.
 ///
-/// * The path given is a file and not a directory
-/// * The given path does not exist
+/// Returns `None` when no relative path can be worked out without the filesystem:
 ///
+/// * One path is absolute and the other is relative
+/// * The paths are on different drives / prefixes on Windows
+/// * `from` climbs out of the directory that `to` is relative to, e.g. `../a` to `b`
 ///
 /// # Example
 ///
-/// ```rust,no_run
-/// use filetools::list_files;
-///
-/// #[tokio::main]
-/// async fn main() -> anyhow::Result<()> {
-///     let target_folder = "folder/containing/files";
+/// ```rust
+/// use filetools::relative_path;
+/// use std::path::PathBuf;
 ///
-///     // Will return a Vec containing all files in the folder
-///     let files = list_files(target_folder).await?;
-///     Ok(())
-/// }
+/// assert_eq!(
+///     relative_path("/srv/www/static", "/srv/data/logo.png"),
+///     Some(PathBuf::from("../../data/logo.png"))
+/// );
+/// assert_eq!(relative_path("docs", "docs/guide/intro.md"), Some(PathBuf::from("guide/intro.md")));
+/// assert_eq!(relative_path("/srv", "srv"), None);
 /// ```
-pub async fn list_files<P: AsRef<Path> + Send>(path: P) -> Result<Vec<PathBuf>> {
-    anyhow::ensure!(path.as_ref().exists(), "path does not exist");
-    anyhow::ensure!(
-        path.as_ref().is_dir(),
-        "path should be a directory, not a file"
-    );
+pub fn relative_path(from: impl AsRef<Path>, to: impl AsRef<Path>) -> Option<PathBuf> {
+    let from = normalize_path(from);
+    let to = normalize_path(to);
+    if from.is_absolute() != to.is_absolute() {
+        return None;
+    }
 
-    iteritems(path, FtIterItemState::File, None).await
+    let from: Vec<Component> = from
+        .components()
+        .filter(|c| *c != Component::CurDir)
+        .collect();
+    let to: Vec<Component> = to
+        .components()
+        .filter(|c| *c != Component::CurDir)
+        .collect();
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+
+    let mut relative = PathBuf::new();
+    for component in &from[common..] {
+        // Only named directories can be climbed back out of
+        match component {
+            Component::Normal(_) => relative.push(".."),
+            _ => return None,
+        }
+    }
+    relative.extend(&to[common..]);
+
+    if relative.as_os_str().is_empty() {
+        relative.push(".");
+    }
+
+    Some(relative)
 }
 
-/// Lists all files in a directory including ALL subdirectories
+/// Resolves a user supplied path by expanding a leading `~` and any environment variables.
 ///
-/// Use responsibly.
+/// * A leading `~` (on its own or followed by a separator) is replaced with the home directory
+/// * `$VAR` and `${VAR}` are replaced with the value of the environment variable `VAR`
+///
+/// Variables that are not set and a `~` when no home directory is known are left as-is.
+/// This is opt-in, none of the other functions in this crate resolve paths themselves.
+///
+/// # Example
+///
+/// ```rust
+/// use filetools::resolve_path;
+///
+/// // Expands to something like `/home/user/projects/data`
+/// let path = resolve_path("~/projects/$DATA_DIR");
+/// ```
+pub fn resolve_path(input: impl AsRef<str>) -> PathBuf {
+    util::resolve_path_with(input.as_ref(), &|name| std::env::var_os(name))
+}
+
+/// Creates a directory at the given path.
+///
+/// If the directory already exists, nothing is done. A file at the path is an error
+/// rather than being mistaken for the directory.
 ///
 /// ## Sync
 ///
-/// For the `sync` version, see [`crate::sync::list_nested_files`]
+/// For the `sync` version, see [`crate::sync::ensure_directory`]
 ///
 /// # Errors
 ///
 /// This function will return an error in the following situations:
 ///
-/// * The given path is a file and not a directory
-/// * The given path does not exist
+/// * The path already exists but is a file, not a directory
+/// * The directory could not be created
 ///
 /// # Example
 ///
 /// ```rust,no_run
-/// use filetools::list_nested_files;
+/// use filetools::ensure_directory;
 ///
 /// #[tokio::main]
 /// async fn main() -> anyhow::Result<()> {
-///     let target_folder = "directory/containing/nested/files";
+///     let target_path = "directory/to/create";
+///     ensure_directory(target_path).await?;
 ///
-///     // This will return a Vec of ALL files contained within the directory
-///     // (including in all subdirectories)
-///     let files = list_nested_files(target_folder).await?;
 ///     Ok(())
 /// }
 /// ```
-pub async fn list_nested_files<P: AsRef<Path> + Send>(path: P) -> Result<Vec<PathBuf>> {
-    anyhow::ensure!(path.as_ref().exists(), "path does not exist");
-    anyhow::ensure!(
-        path.as_ref().is_dir(),
-        "path should be a directory, not a file"
-    );
+pub async fn ensure_directory(dir: impl AsRef<Path>) -> Result<()> {
+    ensure_directory_reported(dir).await?;
 
-    iteritems(path, FtIterItemState::RFile, None).await
+    Ok(())
 }
 
-/// Lists files in a folder (not including subdirectories) matching a filter pattern.
+/// Creates a directory at the given path, reporting whether it was created.
 ///
-/// This pattern can be a `String`, `PathBuf`, or a [`regex::Regex`] pattern.
+/// Returns `true` if the directory was created and `false` if it already existed.
 ///
 /// ## Sync
 ///
-/// For the `sync` version, see [`crate::sync::list_files_with_filter`]
+/// For the `sync` version, see [`crate::sync::ensure_directory_reported`]
 ///
 /// # Errors
 ///
 /// This function will return an error in the following situations:
 ///
-/// * The given path is a file and not a directory
-/// * The given path does not exist
+/// * The path already exists but is a file, not a directory
+/// * The directory could not be created
 ///
 /// # Example
 ///
 /// ```rust,no_run
-/// use regex::Regex;
-/// use std::path::PathBuf;
-/// use filetools::{list_files_with_filter, FtFilter};
+/// use filetools::ensure_directory_reported;
 ///
 /// #[tokio::main]
 /// async fn main() -> anyhow::Result<()> {
-///     let root = "some/path/containing/files";
-///
-///     // List all files containing the phrase `log`
-///     let mut filter = FtFilter::Raw("log".to_string());
-///     let mut results = list_files_with_filter(&root, filter).await?;
-///
-///     // List all files containing the path segment `files/test`
-///     filter = FtFilter::Path(PathBuf::from("files/test"));
-///     results = list_files_with_filter(&root, filter).await?;
-///
-///     // List all files ending with `.rs`
-///     let re = Regex::new(r"(.*)\.rs").expect("unable to create regex");
-///     filter = FtFilter::Regex(re);
-///     results = list_files_with_filter(&root, filter).await?;
+///     let target_path = "directory/to/create";
+///     if ensure_directory_reported(target_path).await? {
+///         println!("created {target_path}");
+///     }
 ///
 ///     Ok(())
 /// }
 /// ```
-pub async fn list_files_with_filter<P: AsRef<Path> + Send>(
-    path: P,
-    pattern: FtFilter,
-) -> Result<Vec<PathBuf>> {
-    anyhow::ensure!(path.as_ref().exists(), "path does not exist");
-    anyhow::ensure!(
-        path.as_ref().is_dir(),
-        "path should be a directory, not a file"
-    );
+pub async fn ensure_directory_reported(dir: impl AsRef<Path>) -> Result<bool> {
+    if dir.as_ref().exists() {
+        anyhow::ensure!(
+            dir.as_ref().is_dir(),
+            FtError::NotADirectory(dir.as_ref().to_path_buf())
+        );
+        return Ok(false);
+    }
+
+    fs::create_dir_all(dir)
+        .await
+        .context("unable to create directory")?;
 
-    iteritems(path, FtIterItemState::File, Some(&pattern)).await
+    Ok(true)
 }
 
-/// Lists files in a folder (including ALL subdirectories) matching a filter pattern.
-///
-/// This pattern can be a `String`, `PathBuf`, or a [`regex::Regex`] pattern.
+/// Creates a directory at the given path with the given Unix permission bits.
 ///
-/// Use responsibly.
+/// Like [`ensure_directory`], missing parents are created and an existing directory is left
+/// as it is, permissions included. Parents get the default permissions, only the directory
+/// itself gets `mode`, which is still masked by the process umask like `mkdir(2)`, so bits
+/// the umask clears are not set.
 ///
 /// ## Sync
 ///
-/// For the `sync` version, see [`crate::sync::list_nested_files_with_filter`]
+/// For the `sync` version, see [`crate::sync::ensure_directory_with_mode`]
 ///
 /// # Errors
 ///
 /// This function will return an error in the following situations:
 ///
-/// * The given path is a file and not a directory
-/// * The given path does not exist
+/// * The path already exists but is a file, not a directory
+/// * The directory could not be created
 ///
 /// # Example
 ///
 /// ```rust,no_run
-/// use regex::Regex;
-/// use std::path::PathBuf;
-/// use filetools::{list_nested_files_with_filter, FtFilter};
+/// use filetools::ensure_directory_with_mode;
 ///
 /// #[tokio::main]
 /// async fn main() -> anyhow::Result<()> {
-///     let root = "some/path/containing/nested/folders/with/files";
-///
-///     // List all files containing the phrase `log`
-///     let mut filter = FtFilter::Raw("log".to_string());
-///     let mut results = list_nested_files_with_filter(&root, filter).await?;
-///
-///     // List all files containing the path segment `files/test`
-///     filter = FtFilter::Path(PathBuf::from("files/test"));
-///     results = list_nested_files_with_filter(&root, filter).await?;
-///
-///     // List all files ending with `.rs`
-///     let re = Regex::new(r"(.*)\.rs").expect("unable to create regex");
-///     filter = FtFilter::Regex(re);
-///     results = list_nested_files_with_filter(&root, filter).await?;
+///     // Only readable by the current user
+///     ensure_directory_with_mode("secrets/keys", 0o700).await?;
 ///
 ///     Ok(())
 /// }
 /// ```
-pub async fn list_nested_files_with_filter<P: AsRef<Path> + Send>(
-    path: P,
-    pattern: FtFilter,
-) -> Result<Vec<PathBuf>> {
-    anyhow::ensure!(path.as_ref().exists(), "path does not exist");
-    anyhow::ensure!(
-        path.as_ref().is_dir(),
-        "path should be a directory, not a file"
-    );
+#[cfg(unix)]
+pub async fn ensure_directory_with_mode(dir: impl AsRef<Path>, mode: u32) -> Result<()> {
+    if dir.as_ref().exists() {
+        anyhow::ensure!(
+            dir.as_ref().is_dir(),
+            FtError::NotADirectory(dir.as_ref().to_path_buf())
+        );
+        return Ok(());
+    }
+
+    let dir = dir.as_ref();
+    if let Some(parent) = dir.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)
+            .await
+            .context("unable to create directory")?;
+    }
 
-    iteritems(path, FtIterItemState::RFile, Some(&pattern)).await
+    match fs::DirBuilder::new().mode(mode).create(dir).await {
+        // Created in the meantime, left as it is like an existing directory
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists && dir.is_dir() => Ok(()),
+        created => created.context("unable to create directory"),
+    }
 }
 
-/// Lists all directories in the given directory (not including subdirectories).
+/// Creates an empty file at the given path, or updates its modification time if it
+/// already exists (like `touch`).
+///
+/// Any missing parent directories are created first.
+/// Returns `true` if the file was created and `false` if it already existed.
 ///
 /// ## Sync
 ///
-/// For the `sync` version, see [`crate::sync::list_directories`]
+/// For the `sync` version, see [`crate::sync::ensure_file`]
 ///
 /// # Errors
 ///
 /// This function will return an error in the following situations:
 ///
-/// * The given path is a file and not a directory
-/// * The given path does not exist
+/// * A missing parent directory could not be created
+/// * The file could not be created
+/// * The existing file could not be opened to update its modification time
 ///
 /// # Example
 ///
 /// ```rust,no_run
-/// use filetools::list_directories;
+/// use filetools::ensure_file;
 ///
 /// #[tokio::main]
 /// async fn main() -> anyhow::Result<()> {
-///     let target_folder = "directory/containing/other/directories";
+///     // Creates `logs/app` if needed, then `logs/app/output.log`
+///     let created = ensure_file("logs/app/output.log").await?;
 ///
-///     // Will return a Vec containing all directories in the folder
-///     let directories = list_directories(target_folder).await?;
 ///     Ok(())
 /// }
 /// ```
-pub async fn list_directories<P: AsRef<Path> + Send>(path: P) -> Result<Vec<PathBuf>> {
-    anyhow::ensure!(path.as_ref().exists(), "path does not exist");
-    anyhow::ensure!(
-        path.as_ref().is_dir(),
-        "path should be a directory, not a file"
-    );
+pub async fn ensure_file(path: impl AsRef<Path>) -> Result<bool> {
+    let path = path.as_ref().to_path_buf();
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        ensure_directory(parent).await?;
+    }
 
-    iteritems(path, FtIterItemState::Dir, None).await
+    tokio::task::spawn_blocking(move || util::touch(&path))
+        .await
+        .context("file touching task failed")?
 }
 
-/// Lists all directories in a directory including ALL subdirectories
-///
-/// Use responsibly.
+/// Creates multiple directories inside the target path.
 ///
 /// ## Sync
 ///
-/// For the `sync` version, see [`crate::sync::list_nested_directories`]
-///
-/// # Errors
-///
-/// This function will return an error in the following situations:
-///
-/// * The given path is a file and not a directory
-/// * The given path does not exist
+/// For the `sync` version, see [`crate::sync::create_multiple_directories`]
 ///
 /// # Example
 ///
 /// ```rust,no_run
-/// use filetools::list_nested_directories;
+/// use filetools::create_multiple_directories;
 ///
 /// #[tokio::main]
 /// async fn main() -> anyhow::Result<()> {
-///     let target_folder = "directory/containing/nested/files";
+///     let root = "dir/to/populate";
+///     let to_create = ["dir1", "dir2", "dir3"];
+///
+///     // Will create:
+///     // `dir/to/populate/dir1`
+///     // `dir/to/populate/dir2`
+///     // `dir/to/populate/dir3`
+///     create_multiple_directories(root, &to_create);
 ///
-///     // This will return a Vec of ALL directories contained within the directory
-///     // (including in all subdirectories)
-///     let directories = list_nested_directories(target_folder).await?;
 ///     Ok(())
 /// }
 /// ```
-pub async fn list_nested_directories<P: AsRef<Path> + Send>(path: P) -> Result<Vec<PathBuf>> {
-    anyhow::ensure!(path.as_ref().exists(), "path does not exist");
-    iteritems(path, FtIterItemState::RDir, None).await
+pub async fn create_multiple_directories(
+    path: impl AsRef<Path>,
+    directories: &[impl AsRef<Path>],
+) -> Result<()> {
+    for target in create_multiple_directories_dry_run(path, directories) {
+        ensure_directory(target).await?;
+    }
+
+    Ok(())
 }
 
-/// Lists directories in a given directory (not including subdirectories) matching a filter pattern.
+/// Creates multiple directories inside the target path, returning the ones that were
+/// newly created.
 ///
-/// This pattern can be a `String`, `PathBuf`, or a [`regex::Regex`] pattern.
+/// Directories that already existed are not included in the result.
 ///
 /// ## Sync
 ///
-/// For the `sync` version, see [`crate::sync::list_directories_with_filter`]
-///
-/// # Errors
-///
-/// This function will return an error in the following situations:
-///
-/// * The given path is a file and not a directory
-/// * The given path does not exist
+/// For the `sync` version, see [`crate::sync::create_multiple_directories_reported`]
 ///
 /// # Example
 ///
 /// ```rust,no_run
-/// use regex::Regex;
-/// use std::path::PathBuf;
-/// use filetools::{list_directories_with_filter, FtFilter};
+/// use filetools::create_multiple_directories_reported;
 ///
 /// #[tokio::main]
 /// async fn main() -> anyhow::Result<()> {
-///     let root = "some/path/containing/dirs";
-///
-///     // List all dirs containing the phrase `log`
-///     let mut filter = FtFilter::Raw("log".to_string());
-///     let mut results = list_directories_with_filter(&root, filter).await?;
-///
-///     // List all dirs containing the path segment `files/test`
-///     filter = FtFilter::Path(PathBuf::from("files/test"));
-///     results = list_directories_with_filter(&root, filter).await?;
+///     let root = "dir/to/populate";
+///     let to_create = ["dir1", "dir2", "dir3"];
 ///
-///     // List all dirs ending with `_test`
-///     let re = Regex::new(r"(.*)_test").expect("unable to create regex");
-///     filter = FtFilter::Regex(re);
-///     results = list_directories_with_filter(&root, filter).await?;
+///     // Only contains the directories that did not exist beforehand
+///     let created = create_multiple_directories_reported(root, &to_create).await?;
+///     for dir in created {
+///         println!("created {}", dir.display());
+///     }
 ///
 ///     Ok(())
 /// }
 /// ```
-pub async fn list_directories_with_filter<P: AsRef<Path> + Send>(
-    path: P,
-    filter: FtFilter,
+pub async fn create_multiple_directories_reported(
+    path: impl AsRef<Path>,
+    directories: &[impl AsRef<Path>],
 ) -> Result<Vec<PathBuf>> {
-    anyhow::ensure!(path.as_ref().exists(), "path does not exist");
-    anyhow::ensure!(
-        path.as_ref().is_dir(),
-        "path should be a directory, not a file"
-    );
+    let mut created = vec![];
+    for target in create_multiple_directories_dry_run(path, directories) {
+        if ensure_directory_reported(&target).await? {
+            created.push(target);
+        }
+    }
 
-    iteritems(path, FtIterItemState::Dir, Some(&filter)).await
+    Ok(created)
 }
 
-/// Lists directories in a given directory (including ALL subdirectories) matching a filter pattern.
-///
-/// This pattern can be a `String`, `PathBuf`, or a [`regex::Regex`] pattern.
+/// Creates a whole directory tree inside `root` from a list of relative paths.
 ///
-/// Use responsibly.
+/// Each entry is a path relative to `root` like `src/bin` or `docs/assets`, any missing
+/// intermediate directories are created along the way. Entries that already exist are left alone.
 ///
 /// ## Sync
 ///
-/// For the `sync` version, see [`crate::sync::list_nested_directories_with_filter`]
+/// For the `sync` version, see [`crate::sync::create_directory_tree`]
 ///
 /// # Errors
 ///
 /// This function will return an error in the following situations:
 ///
-/// * The given path is a file and not a directory
-/// * The given path does not exist
+/// * An entry is absolute or contains `..`, checked before anything is created
+/// * A directory could not be created
 ///
 /// # Example
 ///
 /// ```rust,no_run
-/// use regex::Regex;
-/// use std::path::PathBuf;
-/// use filetools::{list_nested_directories_with_filter, FtFilter};
+/// use filetools::create_directory_tree;
 ///
 /// #[tokio::main]
 /// async fn main() -> anyhow::Result<()> {
-///     let root = "some/path/containing/dirs";
-///
-///     // List all dirs containing the phrase `log`
-///     let mut filter = FtFilter::Raw("log".to_string());
-///     let mut results = list_nested_directories_with_filter(&root, filter).await?;
-///
-///     // List all dirs containing the path segment `files/test`
-///     filter = FtFilter::Path(PathBuf::from("files/test"));
-///     results = list_nested_directories_with_filter(&root, filter).await?;
-///
-///     // List all dirs ending with `_test`
-///     let re = Regex::new(r"(.*)_test").expect("unable to create regex");
-///     filter = FtFilter::Regex(re);
-///     results = list_nested_directories_with_filter(&root, filter).await?;
+///     // Creates `project/src`, `project/src/bin`, `project/tests`, `project/docs`
+///     // and `project/docs/assets`
+///     create_directory_tree("project", &["src/bin", "tests", "docs/assets"]).await?;
 ///
 ///     Ok(())
 /// }
 /// ```
-pub async fn list_nested_directories_with_filter<P: AsRef<Path> + Send>(
-    path: P,
-    filter: FtFilter,
-) -> Result<Vec<PathBuf>> {
-    anyhow::ensure!(path.as_ref().exists(), "path does not exist");
-    anyhow::ensure!(
-        path.as_ref().is_dir(),
-        "path should be a directory, not a file"
-    );
+pub async fn create_directory_tree(
+    root: impl AsRef<Path>,
+    spec: &[impl AsRef<Path>],
+) -> Result<()> {
+    for target in tree_targets(root.as_ref(), spec)? {
+        ensure_directory(target).await?;
+    }
 
-    iteritems(path, FtIterItemState::RDir, Some(&filter)).await
+    Ok(())
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use anyhow::{Context, Result};
-    use std::path::PathBuf;
-    use util::TempPath;
-
-    // This is kind of redundant as it just wraps `tokio::fs::create_dir_all`
-    // but yay for test coverage i suppose
-    #[tokio::test]
-    async fn creates_a_directory() -> Result<()> {
-        let tmp = std::env::temp_dir();
-
-        // Creates a single directory
-        let single_path = tmp.join("create_dir");
+/// Creates a range of numeric folders in the given path
+///
+/// Directories can be padded with X zeros using the `fill` parameter.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::create_numeric_directories`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The range covers more than [`MAX_NUMERIC_DIRECTORIES`] directories, checked before
+///   anything is created. Use [`create_numeric_directories_with_limit`] to raise the limit
+/// * A directory could not be created
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::create_numeric_directories;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let root = "some/root/path";
+///     
+///     // This will create the following directories:
+///     // "some/root/path/0000"
+///     // ...
+///     // "some/root/path/0099"
+///     create_numeric_directories(root, 0, 100, 4).await?;
+///     Ok(())
+/// }
+/// ```
+pub async fn create_numeric_directories(
+    path: impl AsRef<Path>,
+    start: usize,
+    end: usize,
+    fill: usize,
+) -> Result<()> {
+    create_numeric_directories_with_limit(path, start, end, fill, MAX_NUMERIC_DIRECTORIES).await
+}
+
+/// Creates a range of numeric folders in the given path, refusing to create more than `limit`.
+///
+/// Identical to [`create_numeric_directories`] except for the limit, which guards against
+/// a mistyped range creating millions of directories.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::create_numeric_directories_with_limit`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The range covers more than `limit` directories, checked before anything is created
+/// * A directory could not be created
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::create_numeric_directories_with_limit;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     // Really do want a million directories
+///     create_numeric_directories_with_limit("frames", 0, 1_000_000, 7, 1_000_000).await?;
+///     Ok(())
+/// }
+/// ```
+pub async fn create_numeric_directories_with_limit(
+    path: impl AsRef<Path>,
+    start: usize,
+    end: usize,
+    fill: usize,
+    limit: usize,
+) -> Result<()> {
+    check_numeric_range(end.saturating_sub(start), limit)?;
+    for name in util::numeric_dirs(path.as_ref(), start, end, fill) {
+        ensure_directory(name)
+            .await
+            .context("creating numeric directories")?;
+    }
+
+    Ok(())
+}
+
+/// Returns the directories that [`create_numeric_directories`] would create without
+/// touching the filesystem.
+///
+/// Useful for implementing a `--dry-run` style option.
+///
+/// # Errors
+///
+/// This function will return an error if the range covers more than
+/// [`MAX_NUMERIC_DIRECTORIES`] directories, which [`create_numeric_directories`] would refuse
+///
+/// # Example
+///
+/// ```rust
+/// use filetools::create_numeric_directories_dry_run;
+/// use std::path::PathBuf;
+///
+/// let root = "some/root/path";
+///
+/// // Returns the paths "some/root/path/00" and "some/root/path/01"
+/// let planned = create_numeric_directories_dry_run(root, 0, 2, 2)?;
+/// assert_eq!(planned[0], PathBuf::from("some/root/path/00"));
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn create_numeric_directories_dry_run(
+    path: impl AsRef<Path>,
+    start: usize,
+    end: usize,
+    fill: usize,
+) -> Result<Vec<PathBuf>> {
+    check_numeric_range(end.saturating_sub(start), MAX_NUMERIC_DIRECTORIES)?;
+
+    Ok(util::numeric_dirs(path.as_ref(), start, end, fill))
+}
+
+/// Creates numeric folders in the given path with a custom step, prefix and extension.
+///
+/// Every `step`th number from `start` up to (but not including) `end` is padded with zeros
+/// to `fill` digits and placed between `prefix` and `ext`, so a step of 5 with the prefix
+/// `batch_` gives `batch_0000`, `batch_0005`, `batch_0010`, ...
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::create_numeric_directories_ext`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * `step` is zero
+/// * The range covers more than [`MAX_NUMERIC_DIRECTORIES`] directories
+/// * A directory could not be created
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::create_numeric_directories_ext;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     // Creates "batches/batch_0000", "batches/batch_0005" and "batches/batch_0010"
+///     create_numeric_directories_ext("batches", 0, 15, 5, 4, "batch_", "").await?;
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn create_numeric_directories_ext(
+    path: impl AsRef<Path>,
+    start: usize,
+    end: usize,
+    step: usize,
+    fill: usize,
+    prefix: &str,
+    ext: &str,
+) -> Result<()> {
+    for name in create_numeric_directories_ext_dry_run(path, start, end, step, fill, prefix, ext)? {
+        ensure_directory(name)
+            .await
+            .context("creating numeric directories")?;
+    }
+
+    Ok(())
+}
+
+/// Returns the directories that [`create_numeric_directories_ext`] would create without
+/// touching the filesystem.
+///
+/// Useful for implementing a `--dry-run` style option.
+///
+/// # Errors
+///
+/// This function will return an error in the same situations [`create_numeric_directories_ext`]
+/// would refuse to create anything:
+///
+/// * `step` is zero
+/// * The range covers more than [`MAX_NUMERIC_DIRECTORIES`] directories
+///
+/// # Example
+///
+/// ```rust
+/// use filetools::create_numeric_directories_ext_dry_run;
+/// use std::path::PathBuf;
+///
+/// let planned = create_numeric_directories_ext_dry_run("root", 0, 15, 5, 4, "batch_", "")?;
+/// assert_eq!(planned.len(), 3);
+/// assert_eq!(planned[1], PathBuf::from("root/batch_0005"));
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn create_numeric_directories_ext_dry_run(
+    path: impl AsRef<Path>,
+    start: usize,
+    end: usize,
+    step: usize,
+    fill: usize,
+    prefix: &str,
+    ext: &str,
+) -> Result<Vec<PathBuf>> {
+    anyhow::ensure!(step > 0, "step must be greater than zero");
+    check_numeric_range(
+        end.saturating_sub(start).div_ceil(step),
+        MAX_NUMERIC_DIRECTORIES,
+    )?;
+
+    Ok((start..end)
+        .step_by(step)
+        .map(|i| {
+            path.as_ref().join(naming::generate_prefixed_n_digit_name(
+                prefix, "", i, fill, ext,
+            ))
+        })
+        .collect())
+}
+
+/// Returns the directories that [`create_multiple_directories`] would create without
+/// touching the filesystem.
+///
+/// Useful for implementing a `--dry-run` style option.
+///
+/// # Example
+///
+/// ```rust
+/// use filetools::create_multiple_directories_dry_run;
+/// use std::path::PathBuf;
+///
+/// let root = "dir/to/populate";
+/// let to_create = ["dir1", "dir2"];
+///
+/// // Returns the paths `dir/to/populate/dir1` and `dir/to/populate/dir2`
+/// let planned = create_multiple_directories_dry_run(root, &to_create);
+/// assert_eq!(planned[1], PathBuf::from("dir/to/populate/dir2"));
+/// ```
+pub fn create_multiple_directories_dry_run(
+    path: impl AsRef<Path>,
+    directories: &[impl AsRef<Path>],
+) -> Vec<PathBuf> {
+    directories
+        .iter()
+        .map(|dir| path.as_ref().join(dir))
+        .collect()
+}
+
+/// Atomically writes `contents` to the file at `path`.
+///
+/// The contents are written to a temporary sibling file which is synced to disk and then
+/// renamed over `path`, so readers only ever see the old file or the complete new one.
+/// On Unix the parent directory is synced afterwards so the rename survives a crash.
+/// If anything fails the temporary file is removed and the original is left untouched.
+///
+/// A replaced file keeps its permissions. If `path` is a symlink, the file it points to is
+/// replaced and the link is kept.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::write_atomic`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path has no file name
+/// * The given path is a symlink whose target can't be resolved
+/// * The temporary file could not be written or synced
+/// * The temporary file could not be renamed over the target
+/// * The parent directory could not be synced (Unix only)
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::write_atomic;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     write_atomic("config/settings.json", r#"{ "verbose": true }"#).await?;
+///     Ok(())
+/// }
+/// ```
+pub async fn write_atomic(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> Result<()> {
+    let path = path.as_ref().to_path_buf();
+    let contents = contents.as_ref().to_vec();
+
+    tokio::task::spawn_blocking(move || util::write_atomic(&path, &contents))
+        .await
+        .context("atomic write task failed")?
+}
+
+/// Lists all files in the given directory (not including subdirectories).
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_files`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The path given is a file and not a directory
+/// * The given path does not exist
+///
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::list_files;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let target_folder = "folder/containing/files";
+///
+///     // Will return a Vec containing all files in the folder
+///     let files = list_files(target_folder).await?;
+///     Ok(())
+/// }
+/// ```
+pub async fn list_files<P: AsRef<Path> + Send>(path: P) -> Result<Vec<PathBuf>> {
+    ensure_dir_root(&path)?;
+
+    iteritems(path, FtIterItemState::File, None).await
+}
+
+/// Lists files in a folder (not including subdirectories), optionally matching a filter
+/// pattern, and maps each one with `f` as it is found.
+///
+/// Saves a second pass over the listing when only something derived from each file is needed,
+/// like its size or stem. `f` is a plain closure even in the `async` version.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_files_map`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::{list_files_map, FtFilter};
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     // Just the names of the PDFs
+///     let filter = FtFilter::Name(".pdf".to_string());
+///     let stems = list_files_map("documents", Some(filter), |file| {
+///         file.file_stem().map(|s| s.to_string_lossy().into_owned())
+///     })
+///     .await?;
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list_files_map<P, T, F>(path: P, filter: Option<FtFilter>, mut f: F) -> Result<Vec<T>>
+where
+    P: AsRef<Path> + Send,
+    T: Send,
+    F: FnMut(&Path) -> T + Send,
+{
+    ensure_dir_root(&path)?;
+
+    let mut mapped = vec![];
+    walk(
+        path,
+        FtIterItemState::File,
+        filter.as_ref(),
+        &mut |item: PathBuf| {
+            mapped.push(f(&item));
+            ControlFlow::Continue(())
+        },
+    )
+    .await?;
+
+    Ok(mapped)
+}
+
+/// Lists all files in a directory including ALL subdirectories
+///
+/// Use responsibly.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_nested_files`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::list_nested_files;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let target_folder = "directory/containing/nested/files";
+///
+///     // This will return a Vec of ALL files contained within the directory
+///     // (including in all subdirectories)
+///     let files = list_nested_files(target_folder).await?;
+///     Ok(())
+/// }
+/// ```
+pub async fn list_nested_files<P: AsRef<Path> + Send>(path: P) -> Result<Vec<PathBuf>> {
+    ensure_dir_root(&path)?;
+
+    iteritems(path, FtIterItemState::RFile, None).await
+}
+
+/// Lists files in a folder (not including subdirectories) matching a filter pattern.
+///
+/// This pattern can be a `String`, `PathBuf`, or a [`regex::Regex`] pattern.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_files_with_filter`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use regex::Regex;
+/// use std::path::PathBuf;
+/// use filetools::{list_files_with_filter, FtFilter};
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let root = "some/path/containing/files";
+///
+///     // List all files containing the phrase `log`
+///     let mut filter = FtFilter::Raw("log".to_string());
+///     let mut results = list_files_with_filter(&root, filter).await?;
+///
+///     // List all files containing the path segment `files/test`
+///     filter = FtFilter::Path(PathBuf::from("files/test"));
+///     results = list_files_with_filter(&root, filter).await?;
+///
+///     // List all files ending with `.rs`
+///     let re = Regex::new(r"(.*)\.rs").expect("unable to create regex");
+///     filter = FtFilter::Regex(re);
+///     results = list_files_with_filter(&root, filter).await?;
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list_files_with_filter<P: AsRef<Path> + Send>(
+    path: P,
+    pattern: impl Borrow<FtFilter> + Send,
+) -> Result<Vec<PathBuf>> {
+    ensure_dir_root(&path)?;
+
+    iteritems(path, FtIterItemState::File, Some(pattern.borrow())).await
+}
+
+/// Lists files in a folder (including ALL subdirectories) matching a filter pattern.
+///
+/// This pattern can be a `String`, `PathBuf`, or a [`regex::Regex`] pattern.
+///
+/// Use responsibly.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_nested_files_with_filter`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use regex::Regex;
+/// use std::path::PathBuf;
+/// use filetools::{list_nested_files_with_filter, FtFilter};
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let root = "some/path/containing/nested/folders/with/files";
+///
+///     // List all files containing the phrase `log`
+///     let mut filter = FtFilter::Raw("log".to_string());
+///     let mut results = list_nested_files_with_filter(&root, filter).await?;
+///
+///     // List all files containing the path segment `files/test`
+///     filter = FtFilter::Path(PathBuf::from("files/test"));
+///     results = list_nested_files_with_filter(&root, filter).await?;
+///
+///     // List all files ending with `.rs`
+///     let re = Regex::new(r"(.*)\.rs").expect("unable to create regex");
+///     filter = FtFilter::Regex(re);
+///     results = list_nested_files_with_filter(&root, filter).await?;
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list_nested_files_with_filter<P: AsRef<Path> + Send>(
+    path: P,
+    pattern: impl Borrow<FtFilter> + Send,
+) -> Result<Vec<PathBuf>> {
+    ensure_dir_root(&path)?;
+
+    iteritems(path, FtIterItemState::RFile, Some(pattern.borrow())).await
+}
+
+/// Lists all files in a folder (including ALL subdirectories) as a sorted set, optionally
+/// matching a filter pattern.
+///
+/// The `BTreeSet` orders the paths lexicographically by component and holds each one once,
+/// handy for deterministic output and diffing one listing against another.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_nested_files_set`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::{list_nested_files_set, FtFilter};
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let before = list_nested_files_set("my/project", None).await?;
+///     // ...
+///     let after = list_nested_files_set("my/project", None).await?;
+///     let added: Vec<_> = after.difference(&before).collect();
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list_nested_files_set<P: AsRef<Path> + Send>(
+    path: P,
+    filter: Option<FtFilter>,
+) -> Result<BTreeSet<PathBuf>> {
+    ensure_dir_root(&path)?;
+
+    let items = iteritems(path, FtIterItemState::RFile, filter.as_ref()).await?;
+    Ok(items.into_iter().collect())
+}
+
+/// Lists all directories in a folder (including ALL subdirectories) as a sorted set, optionally
+/// matching a filter pattern.
+///
+/// The `BTreeSet` orders the paths lexicographically by component and holds each one once,
+/// handy for deterministic output and diffing one listing against another.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_nested_directories_set`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::{list_nested_directories_set, FtFilter};
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let before = list_nested_directories_set("my/project", None).await?;
+///     // ...
+///     let after = list_nested_directories_set("my/project", None).await?;
+///     let added: Vec<_> = after.difference(&before).collect();
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list_nested_directories_set<P: AsRef<Path> + Send>(
+    path: P,
+    filter: Option<FtFilter>,
+) -> Result<BTreeSet<PathBuf>> {
+    ensure_dir_root(&path)?;
+
+    let items = iteritems(path, FtIterItemState::RDir, filter.as_ref()).await?;
+    Ok(items.into_iter().collect())
+}
+
+/// Lists all files in several folders (including ALL subdirectories).
+///
+/// The roots are walked at the same time and the results are joined in the order the roots
+/// were given. If `dedup` is set, paths found under more than one root (e.g. when one root is
+/// nested in another) are only kept the first time they are seen. Paths are compared as
+/// listed, so roots should be given in the same form (e.g. both relative or both absolute).
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_nested_files_multi`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * Any of the given paths is a file and not a directory
+/// * Any of the given paths does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::{list_nested_files_multi};
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let roots = ["/etc/nginx", "/etc/nginx/sites-enabled", "/opt/nginx"];
+///     let files = list_nested_files_multi(&roots, true).await?;
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list_nested_files_multi<P: AsRef<Path> + Send + Sync>(
+    roots: &[P],
+    dedup: bool,
+) -> Result<Vec<PathBuf>> {
+    multiitems(roots, FtIterItemState::RFile, None, dedup).await
+}
+
+/// Lists all files in several folders (including ALL subdirectories) matching a filter pattern.
+///
+/// The roots are walked at the same time and the results are joined in the order the roots
+/// were given. If `dedup` is set, paths found under more than one root (e.g. when one root is
+/// nested in another) are only kept the first time they are seen. Paths are compared as
+/// listed, so roots should be given in the same form (e.g. both relative or both absolute).
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_nested_files_multi_with_filter`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * Any of the given paths is a file and not a directory
+/// * Any of the given paths does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::{list_nested_files_multi_with_filter, FtFilter};
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let roots = ["/etc/nginx", "/opt/nginx"];
+///     let filter = FtFilter::Raw("conf".to_string());
+///     let files = list_nested_files_multi_with_filter(&roots, filter, true).await?;
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list_nested_files_multi_with_filter<P: AsRef<Path> + Send + Sync>(
+    roots: &[P],
+    filter: impl Borrow<FtFilter>,
+    dedup: bool,
+) -> Result<Vec<PathBuf>> {
+    multiitems(roots, FtIterItemState::RFile, Some(filter.borrow()), dedup).await
+}
+
+/// Lists all directories in several folders (including ALL subdirectories).
+///
+/// The roots are walked at the same time and the results are joined in the order the roots
+/// were given. If `dedup` is set, paths found under more than one root (e.g. when one root is
+/// nested in another) are only kept the first time they are seen. Paths are compared as
+/// listed, so roots should be given in the same form (e.g. both relative or both absolute).
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_nested_directories_multi`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * Any of the given paths is a file and not a directory
+/// * Any of the given paths does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::{list_nested_directories_multi};
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let roots = ["/etc/nginx", "/etc/nginx/sites-enabled", "/opt/nginx"];
+///     let directories = list_nested_directories_multi(&roots, true).await?;
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list_nested_directories_multi<P: AsRef<Path> + Send + Sync>(
+    roots: &[P],
+    dedup: bool,
+) -> Result<Vec<PathBuf>> {
+    multiitems(roots, FtIterItemState::RDir, None, dedup).await
+}
+
+/// Lists all directories in several folders (including ALL subdirectories) matching a filter pattern.
+///
+/// The roots are walked at the same time and the results are joined in the order the roots
+/// were given. If `dedup` is set, paths found under more than one root (e.g. when one root is
+/// nested in another) are only kept the first time they are seen. Paths are compared as
+/// listed, so roots should be given in the same form (e.g. both relative or both absolute).
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_nested_directories_multi_with_filter`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * Any of the given paths is a file and not a directory
+/// * Any of the given paths does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::{list_nested_directories_multi_with_filter, FtFilter};
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let roots = ["/etc/nginx", "/opt/nginx"];
+///     let filter = FtFilter::Raw("conf".to_string());
+///     let directories = list_nested_directories_multi_with_filter(&roots, filter, true).await?;
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list_nested_directories_multi_with_filter<P: AsRef<Path> + Send + Sync>(
+    roots: &[P],
+    filter: impl Borrow<FtFilter>,
+    dedup: bool,
+) -> Result<Vec<PathBuf>> {
+    multiitems(roots, FtIterItemState::RDir, Some(filter.borrow()), dedup).await
+}
+
+/// Lists all directories in the given directory (not including subdirectories).
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_directories`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::list_directories;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let target_folder = "directory/containing/other/directories";
+///
+///     // Will return a Vec containing all directories in the folder
+///     let directories = list_directories(target_folder).await?;
+///     Ok(())
+/// }
+/// ```
+pub async fn list_directories<P: AsRef<Path> + Send>(path: P) -> Result<Vec<PathBuf>> {
+    ensure_dir_root(&path)?;
+
+    iteritems(path, FtIterItemState::Dir, None).await
+}
+
+/// Lists all directories in a directory including ALL subdirectories
+///
+/// Use responsibly.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_nested_directories`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::list_nested_directories;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let target_folder = "directory/containing/nested/files";
+///
+///     // This will return a Vec of ALL directories contained within the directory
+///     // (including in all subdirectories)
+///     let directories = list_nested_directories(target_folder).await?;
+///     Ok(())
+/// }
+/// ```
+pub async fn list_nested_directories<P: AsRef<Path> + Send>(path: P) -> Result<Vec<PathBuf>> {
+    ensure_dir_root(&path)?;
+    iteritems(path, FtIterItemState::RDir, None).await
+}
+
+/// Lists files in a folder (not including subdirectories) into a caller provided buffer.
+///
+/// Same as [`list_files`] except `items` is cleared and filled with the results instead of a new
+/// `Vec` being returned. If the listing fails `items` holds whatever was found before the error.
+///
+/// Reusing the buffer across calls keeps its capacity, so repeated scans in a hot loop
+/// don't allocate a new `Vec` every time.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_files_into`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::list_files_into;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let mut files = Vec::new();
+///     for dir in ["data/one", "data/two"] {
+///         list_files_into(dir, &mut files).await?;
+///         println!("{} has {} files", dir, files.len());
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list_files_into<P: AsRef<Path> + Send>(
+    path: P,
+    items: &mut Vec<PathBuf>,
+) -> Result<()> {
+    ensure_dir_root(&path)?;
+
+    iteritems_into(path, FtIterItemState::File, None, items).await
+}
+
+/// Lists files in a folder (including ALL subdirectories) into a caller provided buffer.
+///
+/// Same as [`list_nested_files`] except `items` is cleared and filled with the results instead of a new
+/// `Vec` being returned. If the listing fails `items` holds whatever was found before the error.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_nested_files_into`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::list_nested_files_into;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let mut files = Vec::new();
+///     for dir in ["data/one", "data/two"] {
+///         list_nested_files_into(dir, &mut files).await?;
+///         println!("{} has {} files", dir, files.len());
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list_nested_files_into<P: AsRef<Path> + Send>(
+    path: P,
+    items: &mut Vec<PathBuf>,
+) -> Result<()> {
+    ensure_dir_root(&path)?;
+
+    iteritems_into(path, FtIterItemState::RFile, None, items).await
+}
+
+/// Lists directories in a folder (not including subdirectories) into a caller provided buffer.
+///
+/// Same as [`list_directories`] except `items` is cleared and filled with the results instead of a new
+/// `Vec` being returned. If the listing fails `items` holds whatever was found before the error.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_directories_into`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::list_directories_into;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let mut directories = Vec::new();
+///     for dir in ["data/one", "data/two"] {
+///         list_directories_into(dir, &mut directories).await?;
+///         println!("{} has {} directories", dir, directories.len());
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list_directories_into<P: AsRef<Path> + Send>(
+    path: P,
+    items: &mut Vec<PathBuf>,
+) -> Result<()> {
+    ensure_dir_root(&path)?;
+
+    iteritems_into(path, FtIterItemState::Dir, None, items).await
+}
+
+/// Lists directories in a folder (including ALL subdirectories) into a caller provided buffer.
+///
+/// Same as [`list_nested_directories`] except `items` is cleared and filled with the results instead of a new
+/// `Vec` being returned. If the listing fails `items` holds whatever was found before the error.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_nested_directories_into`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::list_nested_directories_into;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let mut directories = Vec::new();
+///     for dir in ["data/one", "data/two"] {
+///         list_nested_directories_into(dir, &mut directories).await?;
+///         println!("{} has {} directories", dir, directories.len());
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list_nested_directories_into<P: AsRef<Path> + Send>(
+    path: P,
+    items: &mut Vec<PathBuf>,
+) -> Result<()> {
+    ensure_dir_root(&path)?;
+
+    iteritems_into(path, FtIterItemState::RDir, None, items).await
+}
+
+/// Lists directories in a given directory (not including subdirectories) matching a filter pattern.
+///
+/// This pattern can be a `String`, `PathBuf`, or a [`regex::Regex`] pattern.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_directories_with_filter`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use regex::Regex;
+/// use std::path::PathBuf;
+/// use filetools::{list_directories_with_filter, FtFilter};
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let root = "some/path/containing/dirs";
+///
+///     // List all dirs containing the phrase `log`
+///     let mut filter = FtFilter::Raw("log".to_string());
+///     let mut results = list_directories_with_filter(&root, filter).await?;
+///
+///     // List all dirs containing the path segment `files/test`
+///     filter = FtFilter::Path(PathBuf::from("files/test"));
+///     results = list_directories_with_filter(&root, filter).await?;
+///
+///     // List all dirs ending with `_test`
+///     let re = Regex::new(r"(.*)_test").expect("unable to create regex");
+///     filter = FtFilter::Regex(re);
+///     results = list_directories_with_filter(&root, filter).await?;
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list_directories_with_filter<P: AsRef<Path> + Send>(
+    path: P,
+    filter: impl Borrow<FtFilter> + Send,
+) -> Result<Vec<PathBuf>> {
+    ensure_dir_root(&path)?;
+
+    iteritems(path, FtIterItemState::Dir, Some(filter.borrow())).await
+}
+
+/// Lists directories in a given directory (including ALL subdirectories) matching a filter pattern.
+///
+/// This pattern can be a `String`, `PathBuf`, or a [`regex::Regex`] pattern.
+///
+/// Use responsibly.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_nested_directories_with_filter`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use regex::Regex;
+/// use std::path::PathBuf;
+/// use filetools::{list_nested_directories_with_filter, FtFilter};
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let root = "some/path/containing/dirs";
+///
+///     // List all dirs containing the phrase `log`
+///     let mut filter = FtFilter::Raw("log".to_string());
+///     let mut results = list_nested_directories_with_filter(&root, filter).await?;
+///
+///     // List all dirs containing the path segment `files/test`
+///     filter = FtFilter::Path(PathBuf::from("files/test"));
+///     results = list_nested_directories_with_filter(&root, filter).await?;
+///
+///     // List all dirs ending with `_test`
+///     let re = Regex::new(r"(.*)_test").expect("unable to create regex");
+///     filter = FtFilter::Regex(re);
+///     results = list_nested_directories_with_filter(&root, filter).await?;
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list_nested_directories_with_filter<P: AsRef<Path> + Send>(
+    path: P,
+    filter: impl Borrow<FtFilter> + Send,
+) -> Result<Vec<PathBuf>> {
+    ensure_dir_root(&path)?;
+
+    iteritems(path, FtIterItemState::RDir, Some(filter.borrow())).await
+}
+
+/// Counts the files in a directory matching an optional filter without collecting them.
+///
+/// If `recursive` is `true`, files in ALL subdirectories are counted too.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::count_files`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::{count_files, FtFilter};
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let root = "some/path/containing/nested/files";
+///
+///     // Count every log file in the tree
+///     let filter = FtFilter::Raw(".log".to_string());
+///     let total = count_files(root, true, Some(filter)).await?;
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn count_files<P: AsRef<Path> + Send>(
+    path: P,
+    recursive: bool,
+    filter: Option<FtFilter>,
+) -> Result<usize> {
+    ensure_dir_root(&path)?;
+
+    let iterstate = if recursive {
+        FtIterItemState::RFile
+    } else {
+        FtIterItemState::File
+    };
+
+    countitems(path, iterstate, filter.as_ref()).await
+}
+
+/// Counts the directories in a directory matching an optional filter without collecting them.
+///
+/// If `recursive` is `true`, directories in ALL subdirectories are counted too.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::count_directories`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::count_directories;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let root = "some/path/containing/dirs";
+///
+///     // Count every directory in the tree
+///     let total = count_directories(root, true, None).await?;
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn count_directories<P: AsRef<Path> + Send>(
+    path: P,
+    recursive: bool,
+    filter: Option<FtFilter>,
+) -> Result<usize> {
+    ensure_dir_root(&path)?;
+
+    let iterstate = if recursive {
+        FtIterItemState::RDir
+    } else {
+        FtIterItemState::Dir
+    };
+
+    countitems(path, iterstate, filter.as_ref()).await
+}
+
+/// Finds the first file in a directory (including ALL subdirectories) matching a filter pattern.
+///
+/// Traversal stops as soon as a match is found, so this is much cheaper than listing
+/// everything when you only need to know if a match exists.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::find_first_file`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::{find_first_file, FtFilter};
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let root = "some/path/containing/nested/files";
+///
+///     // Check if there are any log files in the tree
+///     let filter = FtFilter::Raw(".log".to_string());
+///     if let Some(log) = find_first_file(root, filter).await? {
+///         println!("found {}", log.display());
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn find_first_file<P: AsRef<Path> + Send>(
+    path: P,
+    filter: impl Borrow<FtFilter> + Send,
+) -> Result<Option<PathBuf>> {
+    ensure_dir_root(&path)?;
+
+    firstitem(path, FtIterItemState::RFile, Some(filter.borrow())).await
+}
+
+/// Lists files in a directory (including ALL subdirectories), relative to the given path.
+///
+/// Identical to [`crate::list_nested_files`] except the `path` prefix is stripped from each result.
+/// Filters are still applied to the full path.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_nested_files_relative`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::list_nested_files_relative;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let root = "some/path/containing/nested/files";
+///
+///     // Paths like `sub/item` instead of `some/path/containing/nested/files/sub/item`
+///     let relative = list_nested_files_relative(root).await?;
+///     Ok(())
+/// }
+/// ```
+pub async fn list_nested_files_relative<P: AsRef<Path> + Send>(path: P) -> Result<Vec<PathBuf>> {
+    ensure_dir_root(&path)?;
+
+    let items = iteritems(path.as_ref(), FtIterItemState::RFile, None).await?;
+    Ok(strip_root(path, items))
+}
+
+/// Lists files in a directory (including ALL subdirectories) matching a filter pattern, relative to the given path.
+///
+/// Identical to [`crate::list_nested_files_with_filter`] except the `path` prefix is stripped from each result.
+/// Filters are still applied to the full path.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_nested_files_with_filter_relative`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::{list_nested_files_with_filter_relative, FtFilter};
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let root = "some/path/containing/nested/files";
+///
+///     // Paths like `sub/item` instead of `some/path/containing/nested/files/sub/item`
+///     let relative = list_nested_files_with_filter_relative(root, FtFilter::Raw("log".to_string())).await?;
+///     Ok(())
+/// }
+/// ```
+pub async fn list_nested_files_with_filter_relative<P: AsRef<Path> + Send>(
+    path: P,
+    filter: impl Borrow<FtFilter> + Send,
+) -> Result<Vec<PathBuf>> {
+    ensure_dir_root(&path)?;
+
+    let items = iteritems(path.as_ref(), FtIterItemState::RFile, Some(filter.borrow())).await?;
+    Ok(strip_root(path, items))
+}
+
+/// Lists directories in a directory (including ALL subdirectories), relative to the given path.
+///
+/// Identical to [`crate::list_nested_directories`] except the `path` prefix is stripped from each result.
+/// Filters are still applied to the full path.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_nested_directories_relative`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::list_nested_directories_relative;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let root = "some/path/containing/nested/directories";
+///
+///     // Paths like `sub/item` instead of `some/path/containing/nested/directories/sub/item`
+///     let relative = list_nested_directories_relative(root).await?;
+///     Ok(())
+/// }
+/// ```
+pub async fn list_nested_directories_relative<P: AsRef<Path> + Send>(
+    path: P,
+) -> Result<Vec<PathBuf>> {
+    ensure_dir_root(&path)?;
+
+    let items = iteritems(path.as_ref(), FtIterItemState::RDir, None).await?;
+    Ok(strip_root(path, items))
+}
+
+/// Lists directories in a directory (including ALL subdirectories) matching a filter pattern, relative to the given path.
+///
+/// Identical to [`crate::list_nested_directories_with_filter`] except the `path` prefix is stripped from each result.
+/// Filters are still applied to the full path.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_nested_directories_with_filter_relative`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::{list_nested_directories_with_filter_relative, FtFilter};
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let root = "some/path/containing/nested/directories";
+///
+///     // Paths like `sub/item` instead of `some/path/containing/nested/directories/sub/item`
+///     let relative = list_nested_directories_with_filter_relative(root, FtFilter::Raw("log".to_string())).await?;
+///     Ok(())
+/// }
+/// ```
+pub async fn list_nested_directories_with_filter_relative<P: AsRef<Path> + Send>(
+    path: P,
+    filter: impl Borrow<FtFilter> + Send,
+) -> Result<Vec<PathBuf>> {
+    ensure_dir_root(&path)?;
+
+    let items = iteritems(path.as_ref(), FtIterItemState::RDir, Some(filter.borrow())).await?;
+    Ok(strip_root(path, items))
+}
+
+/// Lists all files in a directory including ALL subdirectories, carrying on past any
+/// directories or entries that can't be read.
+///
+/// Returns the files that could be listed along with each path that failed and its error,
+/// e.g. subdirectories the current user doesn't have permission to read.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_nested_files_partial`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::list_nested_files_partial;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let (files, errors) = list_nested_files_partial("/home").await?;
+///     for (path, err) in errors {
+///         eprintln!("skipped {}: {err}", path.display());
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list_nested_files_partial<P: AsRef<Path> + Send>(path: P) -> Result<FtPartialListing> {
+    ensure_dir_root(&path)?;
+
+    let mut partial = PartialItems::default();
+    walk(path, FtIterItemState::RFile, None, &mut partial).await?;
+
+    Ok((partial.items, partial.errors))
+}
+
+/// Lists all directories in a directory including ALL subdirectories, carrying on past any
+/// directories or entries that can't be read.
+///
+/// Returns the directories that could be listed along with each path that failed and its
+/// error. Unreadable directories are still listed themselves, only their contents are missing.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_nested_directories_partial`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::list_nested_directories_partial;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let (dirs, errors) = list_nested_directories_partial("/home").await?;
+///     Ok(())
+/// }
+/// ```
+pub async fn list_nested_directories_partial<P: AsRef<Path> + Send>(
+    path: P,
+) -> Result<FtPartialListing> {
+    ensure_dir_root(&path)?;
+
+    let mut partial = PartialItems::default();
+    walk(path, FtIterItemState::RDir, None, &mut partial).await?;
+
+    Ok((partial.items, partial.errors))
+}
+
+/// Lists all files in a directory including ALL subdirectories, resolving the root first.
+///
+/// The root is canonicalized before traversal so if it is (or passes through) a symlink,
+/// the results are returned under the real target path instead of the symlink.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_nested_files_canonical`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::list_nested_files_canonical;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     // If `current` links to `releases/v2`, returns paths under `releases/v2`
+///     let files = list_nested_files_canonical("current").await?;
+///     Ok(())
+/// }
+/// ```
+pub async fn list_nested_files_canonical<P: AsRef<Path> + Send>(path: P) -> Result<Vec<PathBuf>> {
+    ensure_dir_root(&path)?;
+
+    let root = fs::canonicalize(path)
+        .await
+        .context("unable to resolve root path")?;
+    iteritems(root, FtIterItemState::RFile, None).await
+}
+
+/// Lists all directories in a directory including ALL subdirectories, resolving the root first.
+///
+/// The root is canonicalized before traversal so if it is (or passes through) a symlink,
+/// the results are returned under the real target path instead of the symlink.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_nested_directories_canonical`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::list_nested_directories_canonical;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     // If `current` links to `releases/v2`, returns paths under `releases/v2`
+///     let directories = list_nested_directories_canonical("current").await?;
+///     Ok(())
+/// }
+/// ```
+pub async fn list_nested_directories_canonical<P: AsRef<Path> + Send>(
+    path: P,
+) -> Result<Vec<PathBuf>> {
+    ensure_dir_root(&path)?;
+
+    let root = fs::canonicalize(path)
+        .await
+        .context("unable to resolve root path")?;
+    iteritems(root, FtIterItemState::RDir, None).await
+}
+
+/// Lists all files in the given directory (not including subdirectories), taking a concrete `&Path`.
+///
+/// Identical to [`list_files`] but without generics, so the returned future is easy to
+/// box as a `dyn Future` for dynamic dispatch.
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::list_files_path;
+/// use std::future::Future;
+/// use std::path::{Path, PathBuf};
+/// use std::pin::Pin;
+///
+/// type Listing<'a> = Pin<Box<dyn Future<Output = anyhow::Result<Vec<PathBuf>>> + Send + 'a>>;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let root = Path::new("some/path");
+///     let listing: Listing = Box::pin(list_files_path(root));
+///     let items = listing.await?;
+///     Ok(())
+/// }
+/// ```
+pub async fn list_files_path(path: &Path) -> Result<Vec<PathBuf>> {
+    list_files(path).await
+}
+
+/// Lists all files in a directory including ALL subdirectories, taking a concrete `&Path`.
+///
+/// Identical to [`list_nested_files`] but without generics, so the returned future is easy to
+/// box as a `dyn Future` for dynamic dispatch.
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::list_nested_files_path;
+/// use std::future::Future;
+/// use std::path::{Path, PathBuf};
+/// use std::pin::Pin;
+///
+/// type Listing<'a> = Pin<Box<dyn Future<Output = anyhow::Result<Vec<PathBuf>>> + Send + 'a>>;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let root = Path::new("some/path");
+///     let listing: Listing = Box::pin(list_nested_files_path(root));
+///     let items = listing.await?;
+///     Ok(())
+/// }
+/// ```
+pub async fn list_nested_files_path(path: &Path) -> Result<Vec<PathBuf>> {
+    list_nested_files(path).await
+}
+
+/// Lists all directories in the given directory (not including subdirectories), taking a concrete `&Path`.
+///
+/// Identical to [`list_directories`] but without generics, so the returned future is easy to
+/// box as a `dyn Future` for dynamic dispatch.
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::list_directories_path;
+/// use std::future::Future;
+/// use std::path::{Path, PathBuf};
+/// use std::pin::Pin;
+///
+/// type Listing<'a> = Pin<Box<dyn Future<Output = anyhow::Result<Vec<PathBuf>>> + Send + 'a>>;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let root = Path::new("some/path");
+///     let listing: Listing = Box::pin(list_directories_path(root));
+///     let items = listing.await?;
+///     Ok(())
+/// }
+/// ```
+pub async fn list_directories_path(path: &Path) -> Result<Vec<PathBuf>> {
+    list_directories(path).await
+}
+
+/// Lists all directories in a directory including ALL subdirectories, taking a concrete `&Path`.
+///
+/// Identical to [`list_nested_directories`] but without generics, so the returned future is easy to
+/// box as a `dyn Future` for dynamic dispatch.
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::list_nested_directories_path;
+/// use std::future::Future;
+/// use std::path::{Path, PathBuf};
+/// use std::pin::Pin;
+///
+/// type Listing<'a> = Pin<Box<dyn Future<Output = anyhow::Result<Vec<PathBuf>>> + Send + 'a>>;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let root = Path::new("some/path");
+///     let listing: Listing = Box::pin(list_nested_directories_path(root));
+///     let items = listing.await?;
+///     Ok(())
+/// }
+/// ```
+pub async fn list_nested_directories_path(path: &Path) -> Result<Vec<PathBuf>> {
+    list_nested_directories(path).await
+}
+
+/// Lists the items in a directory, optionally recursing into ALL subdirectories and
+/// applying a filter pattern.
+///
+/// This is the general form of the other listing functions. `target` picks whether files,
+/// directories or both are returned, and with [`FtIterTarget::Both`] everything is gathered
+/// in a single traversal. Directories are listed before their contents.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_items`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::{list_items, FtFilter, FtIterTarget};
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let root = "some/path/containing/nested/items";
+///
+///     // Every file and directory in the tree, in one pass
+///     let everything = list_items(root, FtIterTarget::Both, true, None).await?;
+///
+///     // Files and directories directly inside the root containing `test`
+///     let filter = FtFilter::Raw("test".to_string());
+///     let tests = list_items(root, FtIterTarget::Both, false, Some(filter)).await?;
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list_items<P: AsRef<Path> + Send>(
+    path: P,
+    target: FtIterTarget,
+    recursive: bool,
+    filter: Option<FtFilter>,
+) -> Result<Vec<PathBuf>> {
+    ensure_dir_root(&path)?;
+
+    let iterstate = FtIterItemState::new(target, recursive);
+    iteritems(path, iterstate, filter.as_ref()).await
+}
+
+/// Lists all files and directories in a directory including ALL subdirectories
+/// in a single traversal.
+///
+/// Each item is tagged as a [`DirEntryKind::File`] or [`DirEntryKind::Dir`] so there is no need
+/// to walk the tree twice or check the item type again. Directories are listed before their contents.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_nested_entries`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::{list_nested_entries, DirEntryKind};
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     for entry in list_nested_entries("some/project").await? {
+///         match entry {
+///             DirEntryKind::File(file) => println!("file: {}", file.display()),
+///             DirEntryKind::Dir(dir) => println!("dir:  {}", dir.display()),
+///         }
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list_nested_entries<P: AsRef<Path> + Send>(path: P) -> Result<Vec<DirEntryKind>> {
+    ensure_dir_root(&path)?;
+
+    let mut entries = EntryItems::default();
+    walk(path, FtIterItemState::RBoth, None, &mut entries).await?;
+
+    Ok(entries.entries)
+}
+
+/// Lists the directories (including ALL subdirectories) that directly contain at least one file.
+///
+/// Directories that only hold other directories, or nothing at all, are left out, as is
+/// `path` itself. The directories are found in a single traversal, like [`list_nested_entries`].
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_directories_containing_files`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::list_directories_containing_files;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     // Album folders, skipping the artist folders that only hold albums
+///     let albums = list_directories_containing_files("/home/user/Music").await?;
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list_directories_containing_files<P: AsRef<Path> + Send>(
+    path: P,
+) -> Result<Vec<PathBuf>> {
+    let entries = list_nested_entries(path).await?;
+    Ok(dirs_holding_files(entries))
+}
+
+/// Lists the git repositories below a directory, including ALL subdirectories.
+///
+/// A directory is a repository if it directly contains a `.git` entry, either a directory or
+/// the `.git` file of a submodule or worktree. Once a repository is found its contents are not
+/// searched, so repositories nested inside it are not listed. `path` itself is never listed
+/// but is always searched, so a monorepo root still has its nested repositories found.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_git_repositories`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+/// * A directory could not be read
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::list_git_repositories;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     for repo in list_git_repositories("/home/user/code").await? {
+///         println!("{}", repo.display());
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list_git_repositories(path: impl AsRef<Path> + Send) -> Result<Vec<PathBuf>> {
+    let path = path.as_ref();
+    ensure_dir_root(path)?;
+
+    gitrepos(path).await
+}
+
+/// Lists the broken symlinks below a directory, including ALL subdirectories.
+///
+/// A symlink is broken when its target can't be resolved, usually because it was moved or
+/// deleted. Unlike the listing functions, symlinks are not followed: symlinked directories
+/// aren't searched, which also keeps link cycles from trapping the walk.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_broken_symlinks`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+/// * A directory could not be read
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::list_broken_symlinks;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     for link in list_broken_symlinks("/home/user/.config").await? {
+///         println!("dangling: {}", link.display());
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list_broken_symlinks(path: impl AsRef<Path> + Send) -> Result<Vec<PathBuf>> {
+    let path = path.as_ref();
+    ensure_dir_root(path)?;
+
+    brokenlinks(path).await
+}
+
+/// Lists all files and directories in a directory including ALL subdirectories matching a filter pattern
+/// in a single traversal.
+///
+/// Each item is tagged as a [`DirEntryKind::File`] or [`DirEntryKind::Dir`] so there is no need
+/// to walk the tree twice or check the item type again. Directories are listed before their contents.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_nested_entries_with_filter`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::{list_nested_entries_with_filter, DirEntryKind, FtFilter};
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     for entry in list_nested_entries_with_filter("some/project", FtFilter::Raw("src".to_string())).await? {
+///         match entry {
+///             DirEntryKind::File(file) => println!("file: {}", file.display()),
+///             DirEntryKind::Dir(dir) => println!("dir:  {}", dir.display()),
+///         }
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list_nested_entries_with_filter<P: AsRef<Path> + Send>(
+    path: P,
+    filter: impl Borrow<FtFilter> + Send,
+) -> Result<Vec<DirEntryKind>> {
+    ensure_dir_root(&path)?;
+
+    let mut entries = EntryItems::default();
+    walk(
+        path,
+        FtIterItemState::RBoth,
+        Some(filter.borrow()),
+        &mut entries,
+    )
+    .await?;
+
+    Ok(entries.entries)
+}
+
+/// Lists files in a directory once, returning a [`FtPager`] to page through them.
+///
+/// The directory is only walked when this is called, requesting pages afterwards is cheap.
+/// Set `recursive` to include files in ALL subdirectories.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_files_pager`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::list_files_pager;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let pager = list_files_pager("some/big/folder", true).await?;
+///
+///     // Pages of 50 files at a time
+///     let mut offset = 0;
+///     loop {
+///         let page = pager.page(offset, 50);
+///         println!("{} of {}", offset + page.items.len(), page.total);
+///         if !page.has_more {
+///             break;
+///         }
+///
+///         offset += 50;
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list_files_pager<P: AsRef<Path> + Send>(path: P, recursive: bool) -> Result<FtPager> {
+    let items = list_items(path, FtIterTarget::Files, recursive, None).await?;
+    Ok(FtPager::new(items))
+}
+
+/// Lists a single page of at most `limit` files in a directory, starting at `offset`.
+///
+/// Files are sorted before paging. This walks the directory on every call, use
+/// [`list_files_pager`] if more than one page is needed.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_files_paged`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::list_files_paged;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     // The third page of 20 files directly inside the folder
+///     let page = list_files_paged("some/folder", false, 40, 20).await?;
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list_files_paged<P: AsRef<Path> + Send>(
+    path: P,
+    recursive: bool,
+    offset: usize,
+    limit: usize,
+) -> Result<FtPage> {
+    Ok(list_files_pager(path, recursive).await?.page(offset, limit))
+}
+
+/// Lists all files in a directory including ALL subdirectories, calling `progress` with
+/// each one as soon as it is found.
+///
+/// Useful for showing progress during long traversals. `progress` is called once for
+/// every item in the returned `Vec`, in the same order.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_nested_files_with_progress`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::list_nested_files_with_progress;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let mut found = 0;
+///     let files = list_nested_files_with_progress("some/huge/folder", |item| {
+///         found += 1;
+///         println!("{found}: {}", item.display());
+///     })
+///     .await?;
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list_nested_files_with_progress<P: AsRef<Path> + Send>(
+    path: P,
+    progress: impl FnMut(&Path) + Send,
+) -> Result<Vec<PathBuf>> {
+    ensure_dir_root(&path)?;
+
+    progressitems(path, FtIterItemState::RFile, None, progress).await
+}
+
+/// Lists all directories in a directory including ALL subdirectories, calling `progress` with
+/// each one as soon as it is found.
+///
+/// Useful for showing progress during long traversals. `progress` is called once for
+/// every item in the returned `Vec`, in the same order.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_nested_directories_with_progress`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::list_nested_directories_with_progress;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let mut found = 0;
+///     let directories = list_nested_directories_with_progress("some/huge/folder", |item| {
+///         found += 1;
+///         println!("{found}: {}", item.display());
+///     })
+///     .await?;
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list_nested_directories_with_progress<P: AsRef<Path> + Send>(
+    path: P,
+    progress: impl FnMut(&Path) + Send,
+) -> Result<Vec<PathBuf>> {
+    ensure_dir_root(&path)?;
+
+    progressitems(path, FtIterItemState::RDir, None, progress).await
+}
+
+/// Lists all files in a directory including ALL subdirectories, stopping early once
+/// `cancel` is set.
+///
+/// The flag is checked before every directory read and every entry, so setting it stops
+/// the traversal promptly even on deep trees. Whatever was found is returned along with whether
+/// the traversal was cancelled before it finished, a listing that wasn't cancelled is complete.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_nested_files_cancellable`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::list_nested_files_cancellable;
+/// use std::sync::atomic::{AtomicBool, Ordering};
+/// use std::sync::Arc;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let cancel = Arc::new(AtomicBool::new(false));
+///
+///     let flag = Arc::clone(&cancel);
+///     let listing = tokio::spawn(async move { list_nested_files_cancellable("/", &flag).await });
+///
+///     // The user navigated away
+///     cancel.store(true, Ordering::Relaxed);
+///     let (found, cancelled) = listing.await??;
+///     if cancelled {
+///         println!("stopped after {} items", found.len());
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list_nested_files_cancellable<P: AsRef<Path> + Send>(
+    path: P,
+    cancel: &AtomicBool,
+) -> Result<(Vec<PathBuf>, bool)> {
+    ensure_dir_root(&path)?;
+
+    let mut visitor = CancellableItems::new(cancel);
+    walk(path, FtIterItemState::RFile, None, &mut visitor).await?;
+
+    Ok(visitor.finish())
+}
+
+/// Lists all directories in a directory including ALL subdirectories, stopping early once
+/// `cancel` is set.
+///
+/// The flag is checked before every directory read and every entry, so setting it stops
+/// the traversal promptly even on deep trees. Whatever was found is returned along with whether
+/// the traversal was cancelled before it finished, a listing that wasn't cancelled is complete.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_nested_directories_cancellable`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::list_nested_directories_cancellable;
+/// use std::sync::atomic::{AtomicBool, Ordering};
+/// use std::sync::Arc;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let cancel = Arc::new(AtomicBool::new(false));
+///
+///     let flag = Arc::clone(&cancel);
+///     let listing = tokio::spawn(async move { list_nested_directories_cancellable("/", &flag).await });
+///
+///     // The user navigated away
+///     cancel.store(true, Ordering::Relaxed);
+///     let (found, cancelled) = listing.await??;
+///     if cancelled {
+///         println!("stopped after {} items", found.len());
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list_nested_directories_cancellable<P: AsRef<Path> + Send>(
+    path: P,
+    cancel: &AtomicBool,
+) -> Result<(Vec<PathBuf>, bool)> {
+    ensure_dir_root(&path)?;
+
+    let mut visitor = CancellableItems::new(cancel);
+    walk(path, FtIterItemState::RDir, None, &mut visitor).await?;
+
+    Ok(visitor.finish())
+}
+
+/// Lists all files in a directory including ALL subdirectories along with a digest of
+/// each file's contents.
+///
+/// `new_hasher` makes an [`FtHasher`] for each file, so any hashing crate can be plugged in.
+/// Files are read in chunks on the blocking pool, a few at a time.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_nested_files_with_hash`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+/// * One of the files can't be read
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::{list_nested_files_with_hash, FtHasher};
+///
+/// // FNV-1a, any digest crate works the same way
+/// struct Fnv(u64);
+///
+/// impl FtHasher for Fnv {
+///     fn update(&mut self, chunk: &[u8]) {
+///         for byte in chunk {
+///             self.0 = (self.0 ^ *byte as u64).wrapping_mul(0x100000001b3);
+///         }
+///     }
+///
+///     fn finish(self) -> String {
+///         format!("{:016x}", self.0)
+///     }
+/// }
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let hashed = list_nested_files_with_hash("some/folder", || Fnv(0xcbf29ce484222325)).await?;
+///
+///     for (file, digest) in hashed {
+///         println!("{digest}  {}", file.display());
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list_nested_files_with_hash<P, F, H>(
+    path: P,
+    new_hasher: F,
+) -> Result<Vec<(PathBuf, String)>>
+where
+    P: AsRef<Path> + Send,
+    F: Fn() -> H + Send + Sync + 'static,
+    H: FtHasher,
+{
+    ensure_dir_root(&path)?;
+
+    let files = iteritems(path, FtIterItemState::RFile, None).await?;
+    hashitems(files, new_hasher).await
+}
+
+/// Recreates the directory structure of `src` inside `dst` without copying any file contents.
+///
+/// Every directory under `src` is created at the same relative location under `dst`.
+/// If `placeholders` is set, an empty file is also created for every file in `src`.
+/// `dst` is created if it doesn't exist. Anything already in `dst` is left untouched, a
+/// placeholder is not created where a file already exists.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::clone_directory_structure`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * `src` is a file and not a directory
+/// * `src` does not exist
+/// * A directory or placeholder file could not be created
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::clone_directory_structure;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     // Just the folders of a real project as a test fixture
+///     clone_directory_structure("real/project", "fixtures/skeleton", false).await?;
+///
+///     // Folders plus an empty file for every file
+///     clone_directory_structure("real/project", "fixtures/empty_files", true).await?;
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn clone_directory_structure(
+    src: impl AsRef<Path> + Send,
+    dst: impl AsRef<Path> + Send,
+    placeholders: bool,
+) -> Result<()> {
+    let (src, dst) = (src.as_ref(), dst.as_ref());
+    let dirs = list_nested_directories_relative(src).await?;
+    ensure_directory(dst).await?;
+    for dir in dirs {
+        ensure_directory(dst.join(dir)).await?;
+    }
+
+    if placeholders {
+        for file in list_nested_files_relative(src).await? {
+            let created = fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(dst.join(file))
+                .await;
+
+            match created {
+                Err(e) if e.kind() != std::io::ErrorKind::AlreadyExists => {
+                    return Err(e).context("unable to create placeholder file");
+                }
+                _ => {}
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Renames files in a directory based on a closure.
+///
+/// `rename` is called with each file's path and returns its new name, which is joined onto
+/// the file's directory (so an absolute path moves it elsewhere). Files it returns `None` for
+/// are left alone. Set `recursive` to include files in ALL subdirectories and pass a `filter`
+/// to only rename matching files.
+///
+/// Every new name is worked out before anything is renamed, so a collision leaves all files
+/// untouched. Renames that swap or chain names between files are handled. If a rename fails
+/// part way through, the files already renamed are put back where they were.
+/// Returns the old and new path of each renamed file.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::rename_files`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+/// * Two files would be renamed to the same path
+/// * A file would be renamed over an existing file that isn't being renamed itself
+/// * A file could not be renamed
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::{naming::generate_n_digit_name, rename_files};
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     // Lowercase every file name
+///     rename_files("photos", true, None, |file| {
+///         let name = file.file_name()?.to_str()?;
+///         Some(name.to_lowercase().into())
+///     })
+///     .await?;
+///
+///     // Renumber to `0001.jpg`, `0002.jpg`, ...
+///     let mut n = 0;
+///     rename_files("photos", false, None, |_| {
+///         n += 1;
+///         Some(generate_n_digit_name(n, 4, "jpg"))
+///     })
+///     .await?;
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn rename_files<P: AsRef<Path> + Send>(
+    path: P,
+    recursive: bool,
+    filter: Option<FtFilter>,
+    rename: impl FnMut(&Path) -> Option<PathBuf> + Send,
+) -> Result<Vec<(PathBuf, PathBuf)>> {
+    let files = list_items(path, FtIterTarget::Files, recursive, filter).await?;
+    let plan = rename_plan(files, rename)?;
+    util::apply_rename_plan(&plan).await?;
+
+    Ok(plan.moves)
+}
+
+/// Moves all files directly inside `src` that match a filter into the `dst` directory.
+///
+/// `dst` is created if it doesn't exist. If a file with the same name is already in `dst`
+/// the moved file gets a numbered name like `photo (1).jpg` instead of overwriting it.
+/// Files are moved across filesystems by copying and removing them.
+/// Returns the new path of each moved file.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::move_files_matching`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * `src` is a file and not a directory
+/// * `src` does not exist
+/// * `dst` could not be created
+/// * A file could not be moved
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::{move_files_matching, FtFilter};
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     // Sort the downloads folder
+///     let images = FtFilter::Raw(".jpg".to_string());
+///     move_files_matching("Downloads", "Downloads/images", images).await?;
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn move_files_matching(
+    src: impl AsRef<Path> + Send,
+    dst: impl AsRef<Path> + Send,
+    filter: impl Borrow<FtFilter> + Send,
+) -> Result<Vec<PathBuf>> {
+    let files = list_files_with_filter(src, filter).await?;
+    ensure_directory(&dst).await?;
+
+    let mut moved = vec![];
+    for file in files {
+        let Some(name) = file.file_name() else {
+            continue;
+        };
+
+        let target = util::free_path(dst.as_ref(), Path::new(name));
+        util::move_file(&file, &target).await?;
+        moved.push(target);
+    }
+
+    Ok(moved)
+}
+
+/// Splits the files directly inside a directory into `buckets` numbered subdirectories
+/// (`0000`, `0001`, ...).
+///
+/// The subdirectories are created with [`create_numeric_directories`] and each file is moved
+/// into one of them as decided by `strategy`, see [`FtPartition`].
+/// Returns the new paths of the files in each bucket, in bucket order.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::partition_files_into_directories`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * `buckets` is zero
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+/// * A bucket directory could not be created or a file could not be moved
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::{partition_files_into_directories, FtPartition};
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     // Shard a dataset into `dataset/0000` to `dataset/0007`
+///     let shards = partition_files_into_directories("dataset", 8, FtPartition::Hash).await?;
+///     for (idx, shard) in shards.iter().enumerate() {
+///         println!("shard {idx}: {} files", shard.len());
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn partition_files_into_directories(
+    path: impl AsRef<Path> + Send,
+    buckets: usize,
+    strategy: FtPartition,
+) -> Result<Vec<Vec<PathBuf>>> {
+    anyhow::ensure!(buckets > 0, "at least one bucket is needed");
+    let path = path.as_ref();
+    let files = list_files(path).await?;
+
+    create_numeric_directories(path, 0, buckets, PARTITION_FILL).await?;
+    let dirs = util::numeric_dirs(path, 0, buckets, PARTITION_FILL);
+
+    let mut partitioned = vec![vec![]; buckets];
+    for (file, bucket) in partition_plan(files, buckets, strategy) {
+        let Some(name) = file.file_name() else {
+            continue;
+        };
+
+        let target = util::free_path(&dirs[bucket], Path::new(name));
+        util::move_file(&file, &target).await?;
+        partitioned[bucket].push(target);
+    }
+
+    Ok(partitioned)
+}
+
+/// Checks if a directory is empty.
+///
+/// Stops at the first entry found rather than listing the whole directory.
+/// Hidden files count as entries.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::is_directory_empty`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+/// * The directory can't be read
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::is_directory_empty;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     if is_directory_empty("old/builds").await? {
+///         tokio::fs::remove_dir("old/builds").await?;
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn is_directory_empty<P: AsRef<Path> + Send>(path: P) -> Result<bool> {
+    ensure_dir_root(&path)?;
+
+    let mut entries = fs::read_dir(path.as_ref())
+        .await
+        .context("unable to read directory")?;
+    let first = entries
+        .next_entry()
+        .await
+        .context("unable to read directory entry")?;
+
+    Ok(first.is_none())
+}
+
+/// Determines if two paths refer to the same underlying file.
+///
+/// Symlinks are followed and hardlinks count as the same file, so this is a better check
+/// than comparing the paths before copying or moving a file over itself.
+/// On Unix the device and inode numbers are compared, on Windows the volume serial number
+/// and file index.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::same_file`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * Either path does not exist
+/// * The metadata of either path can't be read
+/// * Either item can't be opened to read its file id (not on Unix)
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::same_file;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     if !same_file("report.pdf", "backup/report.pdf").await? {
+///         tokio::fs::copy("report.pdf", "backup/report.pdf").await?;
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn same_file(a: impl AsRef<Path> + Send, b: impl AsRef<Path> + Send) -> Result<bool> {
+    let (a, b) = (a.as_ref(), b.as_ref());
+    let a_meta = fs::metadata(a).await.context("unable to read metadata")?;
+    let b_meta = fs::metadata(b).await.context("unable to read metadata")?;
+
+    util::same_file_identity(a, &a_meta, b, &b_meta)
+}
+
+/// Lists all files in the given directory, including ALL subdirectories if `recursive` is set.
+///
+/// Same as calling [`list_files`] or [`list_nested_files`] depending on the flag.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_files_rec`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::list_files_rec;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let recursive = std::env::args().any(|arg| arg == "-r");
+///     let files = list_files_rec("some/folder", recursive).await?;
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list_files_rec<P: AsRef<Path> + Send>(
+    path: P,
+    recursive: bool,
+) -> Result<Vec<PathBuf>> {
+    list_items(path, FtIterTarget::Files, recursive, None).await
+}
+
+/// Lists all directories in the given directory, including ALL subdirectories if `recursive` is set.
+///
+/// Same as calling [`list_directories`] or [`list_nested_directories`] depending on the flag.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_directories_rec`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::list_directories_rec;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let recursive = std::env::args().any(|arg| arg == "-r");
+///     let directories = list_directories_rec("some/folder", recursive).await?;
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list_directories_rec<P: AsRef<Path> + Send>(
+    path: P,
+    recursive: bool,
+) -> Result<Vec<PathBuf>> {
+    list_items(path, FtIterTarget::Dirs, recursive, None).await
+}
+
+/// Recursively copies the contents of the `src` directory into `dst`.
+///
+/// `dst` and any missing directories inside it are created, existing files are overwritten.
+/// Symlinks are recreated as symlinks pointing at the same target, see
+/// [`FtCopyOptions::dereference_symlinks`] to copy what they point to instead.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::copy_directory`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * `src` is a file and not a directory
+/// * `src` does not exist
+/// * `dst` is `src` or inside it, also checked through symlinks
+/// * A file, directory or symlink could not be copied
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::copy_directory;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     copy_directory("my/project", "backups/project").await?;
+///     Ok(())
+/// }
+/// ```
+pub async fn copy_directory(
+    src: impl AsRef<Path> + Send,
+    dst: impl AsRef<Path> + Send,
+) -> Result<()> {
+    copy_directory_with_options(src, dst, FtCopyOptions::default()).await
+}
+
+/// Recursively copies the contents of the `src` directory into `dst` with the given options.
+///
+/// Same as [`copy_directory`] but lets [`FtCopyOptions`] pick what else to carry over.
+/// Directory metadata is applied last, deepest first, as copying files into a directory
+/// changes its modification time.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::copy_directory_with_options`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * `src` is a file and not a directory
+/// * `src` does not exist
+/// * `dst` is `src` or inside it, also checked through symlinks
+/// * A file, directory or symlink could not be copied
+/// * `dereference_symlinks` is set and a symlink points back at a directory it is inside
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::{copy_directory_with_options, FtCopyOptions};
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     // A faithful backup keeping modification times and permissions
+///     let options = FtCopyOptions {
+///         preserve_metadata: true,
+///         ..Default::default()
+///     };
+///     copy_directory_with_options("my/project", "backups/project", options).await?;
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn copy_directory_with_options(
+    src: impl AsRef<Path> + Send,
+    dst: impl AsRef<Path> + Send,
+    options: FtCopyOptions,
+) -> Result<()> {
+    copy_directory_with_report(src, dst, options).await?;
+
+    Ok(())
+}
+
+/// Recursively copies the contents of the `src` directory into `dst` with the given options,
+/// reporting how many files and bytes were copied.
+///
+/// Same as [`copy_directory_with_options`], the totals come from the copies themselves so
+/// no extra pass over the tree is needed.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::copy_directory_with_report`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * `src` is a file and not a directory
+/// * `src` does not exist
+/// * `dst` is `src` or inside it, also checked through symlinks
+/// * A file, directory or symlink could not be copied
+/// * `dereference_symlinks` is set and a symlink points back at a directory it is inside
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::{copy_directory_with_report, FtCopyOptions};
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let report =
+///         copy_directory_with_report("my/project", "backups/project", FtCopyOptions::default())
+///             .await?;
+///     println!("copied {} files ({} bytes)", report.files, report.bytes);
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn copy_directory_with_report(
+    src: impl AsRef<Path> + Send,
+    dst: impl AsRef<Path> + Send,
+    options: FtCopyOptions,
+) -> Result<FtCopyReport> {
+    let (src, dst) = (src.as_ref(), dst.as_ref());
+    ensure_dir_root(src)?;
+    anyhow::ensure!(
+        !util::copies_into_itself(src, dst)?,
+        "can't copy a directory into itself"
+    );
+
+    ensure_directory(dst).await?;
+    let mut dirs = vec![(src.to_path_buf(), dst.to_path_buf())];
+    let mut report = FtCopyReport::default();
+    util::copy_tree(src, dst, options, &mut dirs, &mut report, &mut vec![]).await?;
+
+    if options.preserve_metadata {
+        tokio::task::spawn_blocking(move || {
+            dirs.iter()
+                .rev()
+                .try_for_each(|(dir, target)| util::copy_metadata(dir, target))
+        })
+        .await
+        .context("copying task failed")??;
+    }
+
+    Ok(report)
+}
+
+/// Copies the files under `src` (including ALL subdirectories) matching a filter pattern
+/// into `dst`, keeping their directory structure relative to `src`.
+///
+/// The filter is applied the same way as [`list_nested_files_with_filter`]. Only the directories
+/// needed to hold the copied files are created under `dst`. Returns the paths of the copies.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::copy_files_matching`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * `src` is a file and not a directory
+/// * `src` does not exist
+/// * A file could not be copied or its directory could not be created
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::{copy_files_matching, FtFilter};
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     // Back up just the config files, e.g. `/etc/nginx/sites/a.conf` to `backups/nginx/sites/a.conf`
+///     let filter = FtFilter::Name(".conf".to_string());
+///     let copied = copy_files_matching("/etc/nginx", "backups/nginx", filter).await?;
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn copy_files_matching(
+    src: impl AsRef<Path> + Send,
+    dst: impl AsRef<Path> + Send,
+    pattern: impl Borrow<FtFilter> + Send,
+) -> Result<Vec<PathBuf>> {
+    let (copied, _) = copy_files_matching_with_report(src, dst, pattern).await?;
+
+    Ok(copied)
+}
+
+/// Copies the files under `src` (including ALL subdirectories) matching a filter pattern
+/// into `dst`, reporting how many files and bytes were copied.
+///
+/// Same as [`copy_files_matching`], returning the paths of the copies along with the totals.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::copy_files_matching_with_report`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * `src` is a file and not a directory
+/// * `src` does not exist
+/// * A file could not be copied or its directory could not be created
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::{copy_files_matching_with_report, FtFilter};
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let filter = FtFilter::Name(".conf".to_string());
+///     let (copied, report) =
+///         copy_files_matching_with_report("/etc/nginx", "backups/nginx", filter).await?;
+///     println!("backed up {} bytes", report.bytes);
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn copy_files_matching_with_report(
+    src: impl AsRef<Path> + Send,
+    dst: impl AsRef<Path> + Send,
+    pattern: impl Borrow<FtFilter> + Send,
+) -> Result<(Vec<PathBuf>, FtCopyReport)> {
+    let (src, dst) = (src.as_ref(), dst.as_ref());
+    let files = list_nested_files_with_filter(src, pattern).await?;
+
+    let mut copied = Vec::with_capacity(files.len());
+    let mut report = FtCopyReport::default();
+    for relative in strip_root(src, files) {
+        let target = dst.join(&relative);
+        if let Some(parent) = target.parent() {
+            ensure_directory(parent).await?;
+        }
+
+        let bytes = fs::copy(src.join(&relative), &target)
+            .await
+            .with_context(|| format!("unable to copy {}", src.join(&relative).display()))?;
+        report.add(bytes);
+        copied.push(target);
+    }
+
+    Ok((copied, report))
+}
+
+/// Removes every file under `path` that `predicate` approves, then prunes the directories
+/// that removal left empty.
+///
+/// A guarded alternative to a blunt recursive delete: only files the predicate returns `true`
+/// for are removed and a directory is only removed when it held one of those files and is now
+/// empty. `path` itself and directories that were already empty are kept. Returns the removed
+/// files.
+///
+/// Symlinked directories are not followed, so nothing outside of `path` is ever removed.
+/// A symlink to a file is handed to `predicate` like a file and removing it only removes the link.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::remove_directory_where`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+/// * A file or emptied directory could not be removed
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::remove_directory_where;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     // Clear out temporary files along with any directories holding nothing else
+///     let removed = remove_directory_where("build", |file| {
+///         file.extension().is_some_and(|ext| ext == "tmp")
+///     })
+///     .await?;
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn remove_directory_where(
+    path: impl AsRef<Path> + Send,
+    mut predicate: impl FnMut(&Path) -> bool + Send,
+) -> Result<Vec<PathBuf>> {
+    let path = path.as_ref();
+    ensure_dir_root(path)?;
+
+    let mut removed = vec![];
+    for file in util::physicalfiles(path).await? {
+        if predicate(&file) {
+            fs::remove_file(&file)
+                .await
+                .with_context(|| format!("unable to remove {}", file.display()))?;
+            removed.push(file);
+        }
+    }
+
+    for dir in util::emptied_dirs(path, &removed) {
+        if is_directory_empty(&dir).await? {
+            fs::remove_dir(&dir)
+                .await
+                .with_context(|| format!("unable to remove {}", dir.display()))?;
+        }
+    }
+
+    Ok(removed)
+}
+
+/// Lists all files in a directory including ALL subdirectories, reading up to
+/// `max_concurrency` directories at the same time.
+///
+/// Reading directories in parallel speeds up listings on slow or networked filesystems,
+/// `max_concurrency` keeps the number of in-flight directory reads bounded so the server
+/// isn't overwhelmed. The results are the same as [`list_nested_files`] but their order
+/// depends on which reads finish first, sort them if a stable order matters.
+/// The reads are spawned on the current runtime, see [`list_nested_files_concurrent_on`]
+/// to pick another one.
+///
+/// ## Sync
+///
+/// The sync functions read one directory at a time, see [`crate::sync::list_nested_files`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+/// * `max_concurrency` is 0
+/// * A directory could not be read
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::list_nested_files_concurrent;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     // Go easy on the NFS server
+///     let files = list_nested_files_concurrent("/mnt/shared", 4).await?;
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list_nested_files_concurrent(
+    path: impl AsRef<Path> + Send,
+    max_concurrency: usize,
+) -> Result<Vec<PathBuf>> {
+    list_nested_files_concurrent_on(&tokio::runtime::Handle::current(), path, max_concurrency).await
+}
+
+/// Same as [`list_nested_files_concurrent`] but the directory reads are spawned on the runtime
+/// behind `handle` instead of the one the caller is running on.
+///
+/// Useful when the caller is on a runtime that shouldn't take the work, like a current thread
+/// runtime or code inside `block_in_place`.
+///
+/// ## Sync
+///
+/// The sync functions read one directory at a time, see [`crate::sync::list_nested_files`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+/// * `max_concurrency` is 0
+/// * A directory could not be read
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::list_nested_files_concurrent_on;
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() -> anyhow::Result<()> {
+///     // A dedicated pool for filesystem work
+///     let io = tokio::runtime::Runtime::new()?;
+///     let files = list_nested_files_concurrent_on(io.handle(), "/mnt/shared", 8).await?;
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list_nested_files_concurrent_on(
+    handle: &tokio::runtime::Handle,
+    path: impl AsRef<Path> + Send,
+    max_concurrency: usize,
+) -> Result<Vec<PathBuf>> {
+    let path = path.as_ref();
+    ensure_dir_root(path)?;
+
+    util::concurrentitems(path, FtIterItemState::RFile, max_concurrency, handle).await
+}
+
+/// Lists all directories in a directory including ALL subdirectories, reading up to
+/// `max_concurrency` directories at the same time.
+///
+/// Reading directories in parallel speeds up listings on slow or networked filesystems,
+/// `max_concurrency` keeps the number of in-flight directory reads bounded so the server
+/// isn't overwhelmed. The results are the same as [`list_nested_directories`] but their order
+/// depends on which reads finish first, sort them if a stable order matters.
+/// The reads are spawned on the current runtime, see [`list_nested_directories_concurrent_on`]
+/// to pick another one.
+///
+/// ## Sync
+///
+/// The sync functions read one directory at a time, see [`crate::sync::list_nested_directories`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+/// * `max_concurrency` is 0
+/// * A directory could not be read
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::list_nested_directories_concurrent;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     // Go easy on the NFS server
+///     let dirs = list_nested_directories_concurrent("/mnt/shared", 4).await?;
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list_nested_directories_concurrent(
+    path: impl AsRef<Path> + Send,
+    max_concurrency: usize,
+) -> Result<Vec<PathBuf>> {
+    list_nested_directories_concurrent_on(&tokio::runtime::Handle::current(), path, max_concurrency)
+        .await
+}
+
+/// Same as [`list_nested_directories_concurrent`] but the directory reads are spawned on the runtime
+/// behind `handle` instead of the one the caller is running on.
+///
+/// Useful when the caller is on a runtime that shouldn't take the work, like a current thread
+/// runtime or code inside `block_in_place`.
+///
+/// ## Sync
+///
+/// The sync functions read one directory at a time, see [`crate::sync::list_nested_directories`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+/// * `max_concurrency` is 0
+/// * A directory could not be read
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::list_nested_directories_concurrent_on;
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() -> anyhow::Result<()> {
+///     // A dedicated pool for filesystem work
+///     let io = tokio::runtime::Runtime::new()?;
+///     let dirs = list_nested_directories_concurrent_on(io.handle(), "/mnt/shared", 8).await?;
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list_nested_directories_concurrent_on(
+    handle: &tokio::runtime::Handle,
+    path: impl AsRef<Path> + Send,
+    max_concurrency: usize,
+) -> Result<Vec<PathBuf>> {
+    let path = path.as_ref();
+    ensure_dir_root(path)?;
+
+    util::concurrentitems(path, FtIterItemState::RDir, max_concurrency, handle).await
+}
+
+/// Lists all files and directories in a directory with their name, type, size
+/// and modification time.
+///
+/// The details come from a single stat per item during the traversal so callers don't need
+/// to stat every path again. Symlinks are followed and report what they point to.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_entries_detailed`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+/// * A directory could not be read
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::list_entries_detailed;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     for info in list_entries_detailed("Downloads").await? {
+///         println!("{}\t{} bytes", info.name, info.len);
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list_entries_detailed(path: impl AsRef<Path> + Send) -> Result<Vec<FtFileInfo>> {
+    let path = path.as_ref();
+    ensure_dir_root(path)?;
+
+    let mut items = vec![];
+    util::detaileditems(path, false, &mut items).await?;
+
+    Ok(items)
+}
+
+/// Lists all files and directories in a directory including ALL subdirectories with their name, type, size
+/// and modification time.
+///
+/// The details come from a single stat per item during the traversal so callers don't need
+/// to stat every path again. Symlinks are followed and report what they point to.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_nested_entries_detailed`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+/// * A directory could not be read
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::list_nested_entries_detailed;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     for info in list_nested_entries_detailed("Downloads").await? {
+///         println!("{}\t{} bytes", info.name, info.len);
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list_nested_entries_detailed(
+    path: impl AsRef<Path> + Send,
+) -> Result<Vec<FtFileInfo>> {
+    let path = path.as_ref();
+    ensure_dir_root(path)?;
+
+    let mut items = vec![];
+    util::detaileditems(path, true, &mut items).await?;
+
+    Ok(items)
+}
+
+/// Lists all files in the given directory (not including subdirectories), returning an empty list if the
+/// directory doesn't exist.
+///
+/// Same as [`list_files`] for when a missing directory just means there's nothing to list.
+/// A file at the path is still an error.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_files_or_empty`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The path given is a file and not a directory
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::list_files_or_empty;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     // Nothing to do if the app never created the directory
+///     let logs = list_files_or_empty("/var/log/myapp").await?;
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list_files_or_empty<P: AsRef<Path> + Send>(path: P) -> Result<Vec<PathBuf>> {
+    if !path.as_ref().exists() {
+        return Ok(vec![]);
+    }
+
+    list_files(path).await
+}
+
+/// Lists all files in a directory including ALL subdirectories, returning an empty list if the
+/// directory doesn't exist.
+///
+/// Same as [`list_nested_files`] for when a missing directory just means there's nothing to list.
+/// A file at the path is still an error.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_nested_files_or_empty`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The path given is a file and not a directory
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::list_nested_files_or_empty;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     // Nothing to do if the app never created the directory
+///     let logs = list_nested_files_or_empty("/var/log/myapp").await?;
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list_nested_files_or_empty<P: AsRef<Path> + Send>(path: P) -> Result<Vec<PathBuf>> {
+    if !path.as_ref().exists() {
+        return Ok(vec![]);
+    }
+
+    list_nested_files(path).await
+}
+
+/// Lists all directories in the given directory (not including subdirectories), returning an empty list if the
+/// directory doesn't exist.
+///
+/// Same as [`list_directories`] for when a missing directory just means there's nothing to list.
+/// A file at the path is still an error.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_directories_or_empty`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The path given is a file and not a directory
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::list_directories_or_empty;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     // Nothing to do if the app never created the directory
+///     let plugins = list_directories_or_empty(".myapp/plugins").await?;
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list_directories_or_empty<P: AsRef<Path> + Send>(path: P) -> Result<Vec<PathBuf>> {
+    if !path.as_ref().exists() {
+        return Ok(vec![]);
+    }
+
+    list_directories(path).await
+}
+
+/// Lists all directories in a directory including ALL subdirectories, returning an empty list if the
+/// directory doesn't exist.
+///
+/// Same as [`list_nested_directories`] for when a missing directory just means there's nothing to list.
+/// A file at the path is still an error.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_nested_directories_or_empty`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The path given is a file and not a directory
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::list_nested_directories_or_empty;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     // Nothing to do if the app never created the directory
+///     let plugins = list_nested_directories_or_empty(".myapp/plugins").await?;
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list_nested_directories_or_empty<P: AsRef<Path> + Send>(
+    path: P,
+) -> Result<Vec<PathBuf>> {
+    if !path.as_ref().exists() {
+        return Ok(vec![]);
+    }
+
+    list_nested_directories(path).await
+}
+
+/// Groups the files in a directory by their lowercased extension, descending into
+/// subdirectories if `recursive` is set.
+///
+/// `photo.JPG` and `photo.jpg` both land under `"jpg"`, only the case is ignored so `.jpeg`
+/// files get their own `"jpeg"` group. Files without an extension are grouped under `""`.
+/// The files in each group are sorted.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::group_files_by_extension`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The path given is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::group_files_by_extension;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let groups = group_files_by_extension("Pictures", true).await?;
+///     let photos = groups.get("jpg").map_or(0, Vec::len);
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn group_files_by_extension(
+    path: impl AsRef<Path> + Send,
+    recursive: bool,
+) -> Result<HashMap<String, Vec<PathBuf>>> {
+    let files = list_files_rec(path.as_ref(), recursive).await?;
+
+    Ok(util::group_by_extension(files))
+}
+
+/// Finds the `n` largest files in a directory including ALL subdirectories.
+///
+/// Returns each file with its size in bytes, largest first. Only the `n` largest sizes seen
+/// so far are kept during the traversal, so memory doesn't grow with the size of the tree.
+/// Files of the same size are ordered by path, last first.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::largest_files`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The path given is a file and not a directory
+/// * The given path does not exist
+/// * A directory could not be read
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::largest_files;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     for (file, len) in largest_files("/home/user", 20).await? {
+///         println!("{len:>12} {}", file.display());
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn largest_files(path: impl AsRef<Path> + Send, n: usize) -> Result<Vec<(PathBuf, u64)>> {
+    let path = path.as_ref();
+    ensure_dir_root(path)?;
+
+    let mut visitor = TopItems::new(n, |meta| Some(meta.len()));
+    walk(path, FtIterItemState::RFile, None, &mut visitor).await?;
+
+    Ok(visitor.into_sorted())
+}
+
+/// Finds the `n` oldest files in a directory by modification time, descending into
+/// subdirectories if `recursive` is set.
+///
+/// Returns each file with its modification time, oldest first. Only the `n` oldest times seen
+/// so far are kept during the traversal. Files without a modification time, on platforms
+/// that don't record it, are left out.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::oldest_files`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The path given is a file and not a directory
+/// * The given path does not exist
+/// * A directory could not be read
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::oldest_files;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     // Rotate out the oldest logs
+///     for (log, _) in oldest_files("/var/log/myapp", 10, false).await? {
+///         tokio::fs::remove_file(log).await?;
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn oldest_files(
+    path: impl AsRef<Path> + Send,
+    n: usize,
+    recursive: bool,
+) -> Result<Vec<(PathBuf, SystemTime)>> {
+    let path = path.as_ref();
+    ensure_dir_root(path)?;
+
+    let iterstate = if recursive {
+        FtIterItemState::RFile
+    } else {
+        FtIterItemState::File
+    };
+
+    let mut visitor = TopItems::new(n, util::oldest_key);
+    walk(path, iterstate, None, &mut visitor).await?;
+
+    Ok(visitor
+        .into_sorted()
+        .into_iter()
+        .map(|(file, Reverse(modified))| (file, modified))
+        .collect())
+}
+
+/// Finds the `n` newest files in a directory by modification time, descending into
+/// subdirectories if `recursive` is set.
+///
+/// Returns each file with its modification time, newest first. Only the `n` newest times seen
+/// so far are kept during the traversal. Files without a modification time, on platforms
+/// that don't record it, are left out.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::newest_files`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The path given is a file and not a directory
+/// * The given path does not exist
+/// * A directory could not be read
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::newest_files;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     // Show what changed most recently
+///     for (file, modified) in newest_files("my/project", 10, true).await? {
+///         println!("{} {modified:?}", file.display());
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn newest_files(
+    path: impl AsRef<Path> + Send,
+    n: usize,
+    recursive: bool,
+) -> Result<Vec<(PathBuf, SystemTime)>> {
+    let path = path.as_ref();
+    ensure_dir_root(path)?;
+
+    let iterstate = if recursive {
+        FtIterItemState::RFile
+    } else {
+        FtIterItemState::File
+    };
+
+    let mut visitor = TopItems::new(n, util::modified_key);
+    walk(path, iterstate, None, &mut visitor).await?;
+
+    Ok(visitor.into_sorted())
+}
+
+/// Lists at most `limit` files in a directory, descending into subdirectories if `recursive`
+/// is set.
+///
+/// The traversal stops as soon as `limit` files have been found, so an accidental listing of
+/// `/` doesn't walk the whole filesystem. Which files make the cut depends on the order the OS
+/// returns directory entries in.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_files_limited`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The path given is a file and not a directory
+/// * The given path does not exist
+/// * A directory could not be read
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::{list_files_limited, FtFilter};
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     // A quick preview rather than the whole tree
+///     let filter = FtFilter::Raw(".rs".to_string());
+///     let preview = list_files_limited("/", true, Some(filter), 100).await?;
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list_files_limited(
+    path: impl AsRef<Path> + Send,
+    recursive: bool,
+    filter: Option<FtFilter>,
+    limit: usize,
+) -> Result<Vec<PathBuf>> {
+    let path = path.as_ref();
+    ensure_dir_root(path)?;
+
+    let iterstate = if recursive {
+        FtIterItemState::RFile
+    } else {
+        FtIterItemState::File
+    };
+
+    util::limiteditems(path, iterstate, filter.as_ref(), limit).await
+}
+
+/// Lists all files in a directory including ALL subdirectories by running
+/// [`crate::sync::list_nested_files`] on the blocking thread pool.
+///
+/// The whole walk happens on one blocking thread with plain `std::fs` calls instead of
+/// handing each call to the pool separately like [`list_nested_files`] does, which is often
+/// faster for large trees and never blocks the async worker threads.
+///
+/// ## Sync
+///
+/// This is the async bridge onto [`crate::sync::list_nested_files`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+/// * A directory could not be read
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::list_nested_files_blocking;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let files = list_nested_files_blocking("/usr/share").await?;
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list_nested_files_blocking(path: impl AsRef<Path> + Send) -> Result<Vec<PathBuf>> {
+    let path = path.as_ref().to_path_buf();
+    tokio::task::spawn_blocking(move || sync::list_nested_files(path))
+        .await
+        .context("blocking listing task failed")?
+}
+
+/// Lists all directories in a directory including ALL subdirectories by running
+/// [`crate::sync::list_nested_directories`] on the blocking thread pool.
+///
+/// The whole walk happens on one blocking thread with plain `std::fs` calls instead of
+/// handing each call to the pool separately like [`list_nested_directories`] does, which is often
+/// faster for large trees and never blocks the async worker threads.
+///
+/// ## Sync
+///
+/// This is the async bridge onto [`crate::sync::list_nested_directories`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+/// * A directory could not be read
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::list_nested_directories_blocking;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let dirs = list_nested_directories_blocking("/usr/share").await?;
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list_nested_directories_blocking(
+    path: impl AsRef<Path> + Send,
+) -> Result<Vec<PathBuf>> {
+    let path = path.as_ref().to_path_buf();
+    tokio::task::spawn_blocking(move || sync::list_nested_directories(path))
+        .await
+        .context("blocking listing task failed")?
+}
+
+/// Lists the files in the given directory (not including subdirectories) matching a filter, along
+/// with how many files were checked.
+///
+/// Same as [`list_files_with_filter`] but an empty result can be told apart: `total_scanned` is 0 when there
+/// were no files at all and more than 0 when there were files but none matched.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_files_with_filter_scanned`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::{list_files_with_filter_scanned, FtFilter};
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let filter = FtFilter::Raw(".log".to_string());
+///     let result = list_files_with_filter_scanned("my/app", filter).await?;
+///     if result.nothing_scanned() {
+///         println!("the directory is empty");
+///     } else if result.matched.is_empty() {
+///         println!("no logs among {} files", result.total_scanned);
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list_files_with_filter_scanned(
+    path: impl AsRef<Path> + Send,
+    pattern: impl Borrow<FtFilter> + Send,
+) -> Result<FtFilterResult> {
+    let path = path.as_ref();
+    ensure_dir_root(path)?;
+
+    scanneditems(path, FtIterItemState::File, pattern.borrow()).await
+}
+
+/// Lists the files in a directory including ALL subdirectories matching a filter, along
+/// with how many files were checked.
+///
+/// Same as [`list_nested_files_with_filter`] but an empty result can be told apart: `total_scanned` is 0 when there
+/// were no files at all and more than 0 when there were files but none matched.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_nested_files_with_filter_scanned`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::{list_nested_files_with_filter_scanned, FtFilter};
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let filter = FtFilter::Raw(".log".to_string());
+///     let result = list_nested_files_with_filter_scanned("my/app", filter).await?;
+///     if result.nothing_scanned() {
+///         println!("the directory is empty");
+///     } else if result.matched.is_empty() {
+///         println!("no logs among {} files", result.total_scanned);
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list_nested_files_with_filter_scanned(
+    path: impl AsRef<Path> + Send,
+    pattern: impl Borrow<FtFilter> + Send,
+) -> Result<FtFilterResult> {
+    let path = path.as_ref();
+    ensure_dir_root(path)?;
+
+    scanneditems(path, FtIterItemState::RFile, pattern.borrow()).await
+}
+
+/// Lists files in a folder (including ALL subdirectories) matching a filter pattern, along
+/// with statistics about the traversal.
+///
+/// Same as [`list_nested_files_with_filter`] but also reports how many directories were opened,
+/// how many entries were read from them and how long it took, handy for tuning filters
+/// or spotting pathological directories.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_nested_files_with_stats`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::{list_nested_files_with_stats, FtFilter};
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let filter = FtFilter::Raw(".log".to_string());
+///     let (logs, stats) = list_nested_files_with_stats("/var/log", filter).await?;
+///     println!(
+///         "found {} logs in {} directories ({} entries) in {:?}",
+///         stats.matched, stats.dirs_visited, stats.entries_seen, stats.elapsed
+///     );
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list_nested_files_with_stats(
+    path: impl AsRef<Path> + Send,
+    pattern: impl Borrow<FtFilter> + Send,
+) -> Result<(Vec<PathBuf>, FtTraversalStats)> {
+    let path = path.as_ref();
+    ensure_dir_root(path)?;
+
+    let start = Instant::now();
+    let mut visitor = StatsItems::default();
+    walk(
+        path,
+        FtIterItemState::RFile,
+        Some(pattern.borrow()),
+        &mut visitor,
+    )
+    .await?;
+    visitor.stats.elapsed = start.elapsed();
+
+    Ok((visitor.items, visitor.stats))
+}
+
+/// Lists all files in the given directory, including ALL subdirectories apart from those
+/// named in `exclude_dirs`.
+///
+/// Directories are matched on their name alone, e.g. `node_modules` or `.git`, at any depth.
+/// Excluded directories aren't read at all which is much cheaper than filtering the results
+/// afterwards for large trees like build outputs.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_nested_files_excluding`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::list_nested_files_excluding;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let sources = list_nested_files_excluding("my/project", &["node_modules", "target", ".git"]).await?;
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list_nested_files_excluding(
+    path: impl AsRef<Path> + Send,
+    exclude_dirs: &[impl AsRef<Path> + Sync],
+) -> Result<Vec<PathBuf>> {
+    let path = path.as_ref();
+    ensure_dir_root(path)?;
+
+    let mut visitor = ExcludedItems::new(exclude_dirs);
+    walk(path, FtIterItemState::RFile, None, &mut visitor).await?;
+
+    Ok(visitor.items)
+}
+
+/// Lists all directories in the given directory, including ALL subdirectories apart from those
+/// named in `exclude_dirs`.
+///
+/// Directories are matched on their name alone, e.g. `node_modules` or `.git`, at any depth.
+/// Excluded directories aren't read at all which is much cheaper than filtering the results
+/// afterwards for large trees like build outputs. The excluded directories are not listed either.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_nested_directories_excluding`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::list_nested_directories_excluding;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let sources = list_nested_directories_excluding("my/project", &["node_modules", "target", ".git"]).await?;
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list_nested_directories_excluding(
+    path: impl AsRef<Path> + Send,
+    exclude_dirs: &[impl AsRef<Path> + Sync],
+) -> Result<Vec<PathBuf>> {
+    let path = path.as_ref();
+    ensure_dir_root(path)?;
+
+    let mut visitor = ExcludedItems::new(exclude_dirs);
+    walk(path, FtIterItemState::RDir, None, &mut visitor).await?;
+
+    Ok(visitor.items)
+}
+
+/// Lists all files in the given directory, including the subdirectories `should_descend`
+/// approves, optionally matching a filter pattern.
+///
+/// `should_descend` is called with each directory found before it is read, returning `false`
+/// skips everything below it. The more general form of [`list_nested_files_excluding`] for
+/// pruning on anything known from the path, like its depth or an ignore file inside it.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::list_nested_files_prune`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::{list_nested_files_prune, FtFilter};
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     // Skip any directory marked as excluded from backups
+///     let files = list_nested_files_prune(
+///         "/home/user",
+///         |dir| !dir.join(".nobackup").exists(),
+///         None,
+///     )
+///     .await?;
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list_nested_files_prune<F>(
+    path: impl AsRef<Path> + Send,
+    should_descend: F,
+    filter: Option<FtFilter>,
+) -> Result<Vec<PathBuf>>
+where
+    F: FnMut(&Path) -> bool + Send,
+{
+    let path = path.as_ref();
+    ensure_dir_root(path)?;
+
+    let mut visitor = PrunedItems::new(should_descend);
+    walk(path, FtIterItemState::RFile, filter.as_ref(), &mut visitor).await?;
+
+    Ok(visitor.items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::{Context, Result};
+    use std::path::PathBuf;
+    use util::TempPath;
+
+    // This is kind of redundant as it just wraps `tokio::fs::create_dir_all`
+    // but yay for test coverage i suppose
+    #[tokio::test]
+    async fn creates_a_directory() -> Result<()> {
+        let tmp = std::env::temp_dir();
+
+        // Creates a single directory
+        let single_path = tmp.join("create_dir");
         ensure_directory(&single_path)
             .await
-            .context("create directory single")?;
+            .context("create directory single")?;
+
+        assert!(single_path.exists());
+
+        // Nested directories
+        let nested_path = tmp.join("create_dir/test/this/is/nested");
+        ensure_directory(&nested_path)
+            .await
+            .context("create directory nested")?;
+
+        assert!(nested_path.exists());
+
+        std::fs::remove_dir_all(single_path)?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reports_directory_creation() -> Result<()> {
+        let root = TempPath::new("ensure_directory_reported").await?;
+        let target = root.path.join("new/nested/dir");
+
+        assert!(ensure_directory_reported(&target).await?);
+        assert!(target.exists());
+        assert!(!ensure_directory_reported(&target).await?);
+
+        assert!(sync::ensure_directory_reported(root.path.join("sync"))?);
+        assert!(!sync::ensure_directory_reported(root.path.join("sync"))?);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reports_multiple_directory_creation() -> Result<()> {
+        let root = TempPath::new("create_multiple_reported").await?;
+        root.multi_folder(vec!["existing"]).await?;
+
+        let dirs = ["existing", "first", "second"];
+        let created = create_multiple_directories_reported(&root.path, &dirs).await?;
+        assert_eq!(created.len(), 2);
+        assert!(created.contains(&root.path.join("first")));
+        assert!(created.contains(&root.path.join("second")));
+
+        // Everything exists now so nothing new is made
+        assert!(create_multiple_directories_reported(&root.path, &dirs)
+            .await?
+            .is_empty());
+        assert!(sync::create_multiple_directories_reported(&root.path, &dirs)?.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn checks_if_a_directory_is_a_subdirectory() -> Result<()> {
+        let root = TempPath::new("is_subdir").await?;
+        let nested = root
+            .nest_folders(vec!["this", "is", "a", "nested", "tmp", "dir"])
+            .await?;
+        let mut result = is_subdir(&nested.path, "nested");
+
+        assert!(result);
+
+        result = is_subdir(&nested.path, "not_valid");
+
+        assert!(!result);
+        Ok(())
+    }
+
+    #[test]
+    fn checks_genuine_descendants() {
+        assert!(is_descendant_of("a/b/c", "a"));
+        assert!(is_descendant_of("a/b/c", "a/b"));
+        assert!(is_descendant_of("a/./b/../b/c", "a//b"));
+        assert!(is_descendant_of("/root/project/src", "/root"));
+        assert!(is_descendant_of("src/lib.rs", "."));
+
+        // `for` is a component in both but not an ancestor
+        assert!(is_subdir("x/for/b", "for"));
+        assert!(!is_descendant_of("x/for/b", "for"));
+        assert!(!is_descendant_of("a/forest/b", "a/for"));
+
+        assert!(!is_descendant_of("a/b", "a/b"));
+        assert!(!is_descendant_of("a", "a/b"));
+        assert!(!is_descendant_of("a/../b", "a"));
+        assert!(!is_descendant_of("/a/b", "a"));
+        assert!(!is_descendant_of("../a", "."));
+    }
+
+    #[test]
+    fn check_path_contains_subpath() {
+        // Basic str
+        let main = "I/am/a/path/hello/there";
+        assert!(path_contains(main, "a/path"));
+        assert!(!path_contains(main, "not"));
+
+        // Check it works for paths
+        let main = Path::new(main);
+        assert!(path_contains(main, Path::new("a/path")));
+        assert!(!path_contains(main, Path::new("not")));
+
+        // Pathbufs?
+        let main = PathBuf::from("I/am/a/path/hello/there");
+        assert!(path_contains(&main, PathBuf::from("a/path")));
+        assert!(!path_contains(main, PathBuf::from("not")));
+
+        // What about strings?
+        assert!(path_contains(
+            String::from("I/am/a/path/hello/there"),
+            String::from("a/path")
+        ));
+        assert!(!path_contains(
+            String::from("I/am/a/path/hello/there"),
+            String::from("not")
+        ));
+    }
+
+    #[test]
+    fn path_contains_str_ignores_separator_style() {
+        let windows = r"C:\Users\me\a\b\file.txt";
+        assert!(path_contains_str(windows, r"a\b"));
+        assert!(!path_contains(windows, "a/b"));
+
+        // `\` is only a separator on Windows, anywhere else it's part of the name
+        let unix = "/home/me/a/b/file.txt";
+        assert_eq!(path_contains_str(windows, "a/b"), cfg!(windows));
+        assert_eq!(path_contains_str(unix, r"a\b\file"), cfg!(windows));
+        assert!(!path_contains(unix, r"a\b\file"));
+
+        // Still a plain substring check otherwise
+        assert!(path_contains_str(unix, "me/a"));
+        assert!(path_contains_str(PathBuf::from(unix), "file.t"));
+        assert!(!path_contains_str(unix, "b/a"));
+    }
+
+    #[test]
+    fn path_filters_ignore_separator_style() {
+        let windows = r"C:\data\files\test\a.txt";
+        assert_eq!(
+            util::matches_filter_at(windows, &FtFilter::Path(PathBuf::from("files/test")), 1),
+            cfg!(windows)
+        );
+        assert_eq!(
+            util::matches_filter_at(windows, &FtFilter::Raw("files/test".to_string()), 1),
+            cfg!(windows)
+        );
+
+        let unix = "/data/files/test/a.txt";
+        assert_eq!(
+            util::matches_filter_at(unix, &FtFilter::Raw(r"files\test".to_string()), 1),
+            cfg!(windows)
+        );
+        assert!(util::matches_filter_at(
+            unix,
+            &FtFilter::Path(PathBuf::from("files/test")),
+            1
+        ));
+        assert!(!util::matches_filter_at(
+            windows,
+            &FtFilter::Path(PathBuf::from("test/files")),
+            1
+        ));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn path_filters_match_backslashes_literally_on_unix() -> Result<()> {
+        let root = TempPath::new("path_filters_backslash").await?;
+        root.multi_folder(vec!["a"]).await?;
+        root.multi_file(vec![r"a\b.txt", "a/b.txt"]).await?;
+
+        // `a\b.txt` is a single file name here, not a path
+        let filter = FtFilter::Raw(r"a\b".to_string());
+        assert_eq!(
+            list_files_with_filter(&root.path, &filter).await?,
+            vec![root.path.join(r"a\b.txt")]
+        );
+        assert_eq!(
+            sync::list_nested_files_with_filter(&root.path, &filter)?,
+            vec![root.path.join(r"a\b.txt")]
+        );
+
+        let filter = FtFilter::Path(PathBuf::from("a/b"));
+        assert_eq!(
+            list_nested_files_with_filter(&root.path, &filter).await?,
+            vec![root.path.join("a/b.txt")]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn normalizes_paths_lexically() {
+        let cases = [
+            ("a/./b/../c", "a/c"),
+            ("a//b///c/", "a/b/c"),
+            ("a/b/../../..", ".."),
+            ("../a/../../b", "../../b"),
+            ("a/..", "."),
+            ("", "."),
+            ("./", "."),
+            ("/a/b/../c", "/a/c"),
+            ("/../..", "/"),
+            ("//a/./b", "/a/b"),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(normalize_path(input), PathBuf::from(expected), "{input}");
+        }
+    }
+
+    #[test]
+    fn resolves_home_and_env_vars() {
+        use std::ffi::OsString;
+
+        // Variables come from a fixed set rather than the process environment other tests share
+        let home = PathBuf::from("/home/tester");
+        let lookup = |name: &str| match name {
+            "HOME" => Some(home.clone().into_os_string()),
+            "FILETOOLS_RESOLVE_TEST" => Some(OsString::from("custom")),
+            _ => None,
+        };
+        let resolve = |input: &str| util::resolve_path_with(input, &lookup);
+
+        assert_eq!(resolve("~"), home);
+        assert_eq!(resolve("~/projects"), home.join("projects"));
+        assert_eq!(
+            resolve("$HOME/data/$FILETOOLS_RESOLVE_TEST"),
+            home.join("data/custom")
+        );
+        assert_eq!(
+            resolve("/srv/${FILETOOLS_RESOLVE_TEST}_dir"),
+            PathBuf::from("/srv/custom_dir")
+        );
+
+        // Things that aren't expandable are left alone
+        assert_eq!(
+            resolve("/srv/$FILETOOLS_RESOLVE_UNSET/${unterminated"),
+            PathBuf::from("/srv/$FILETOOLS_RESOLVE_UNSET/${unterminated")
+        );
+        assert_eq!(resolve("~other/file"), PathBuf::from("~other/file"));
+        assert_eq!(resolve("costs $5"), PathBuf::from("costs $5"));
+
+        // No home directory known leaves `~` as it is, `USERPROFILE` is the fallback
+        let nothing = |_: &str| None;
+        assert_eq!(
+            util::resolve_path_with("~/a", &nothing),
+            PathBuf::from("~/a")
+        );
+        let profile = |name: &str| (name == "USERPROFILE").then(|| OsString::from("/profile"));
+        assert_eq!(
+            util::resolve_path_with("~", &profile),
+            PathBuf::from("/profile")
+        );
+
+        // The public function reads the real environment
+        assert_eq!(
+            resolve_path("/srv/$FILETOOLS_RESOLVE_UNSET"),
+            PathBuf::from("/srv/$FILETOOLS_RESOLVE_UNSET")
+        );
+    }
+
+    #[tokio::test]
+    async fn check_list_files_works() -> Result<()> {
+        let root = TempPath::new("lf_test").await?;
+        root.multi_file(vec!["first.rs", "second.c", "third.js", "fourth.rb"])
+            .await?;
+
+        let res = list_files(root.path.clone()).await?;
+        assert_eq!(res.len(), 4);
+
+        assert!(list_files("IDoNotExistAsADirectoryOrShouldntAtLeAst")
+            .await
+            .is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn check_list_nested_files_works() -> Result<()> {
+        let root = TempPath::new("lfr_test").await?;
+        let ffolder = root.new_folder("ffolder").await?;
+        let sfolder = root.new_folder("sfolder").await?;
+        let tfolder = root.new_folder("tfolder").await?;
+
+        root.new_file("initial.pdf").await?;
+        ffolder.new_file("first.rs").await?;
+        sfolder.multi_file(vec!["second.txt", "third.php"]).await?;
+        tfolder.new_file("fourth.cpp").await?;
+
+        let res = list_nested_files(&root.path).await?;
+        assert_eq!(res.len(), 5);
+
+        assert!(list_files("IDoNotExistAsADirectoryOrShouldntAtLeAst")
+            .await
+            .is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn check_list_directories_works() -> Result<()> {
+        let root = TempPath::new("lfolder_test").await?;
+        root.multi_folder(vec!["folder1", "folder2", "folder3", "folder4"])
+            .await?;
+
+        let res = list_directories(root.path.clone()).await?;
+        assert_eq!(res.len(), 4);
+
+        assert!(list_directories("non-existant_path").await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn check_list_nested_directories_works() -> Result<()> {
+        let root = TempPath::new("lfolderrec_test").await?;
+        root.multi_folder(vec!["folder1", "folder2"]).await?;
+
+        let f1 = TempPath::new(root.join("folder1")).await?;
+        f1.multi_folder(vec!["sub1", "sub2", "sub3"]).await?;
+
+        let s2 = TempPath::new(f1.join("sub2")).await?;
+        s2.multi_folder(vec!["deep1", "deep2"]).await?;
+
+        let res = list_nested_directories(root.path.clone()).await?;
+        assert_eq!(res.len(), 7);
+
+        assert!(list_nested_directories("not-a-valId_pathd").await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn numeric_directories() -> Result<()> {
+        let tmp = TempPath::new("numeric_directories").await?;
+        create_numeric_directories(&tmp.path, 0, 100, 4).await?;
+        let mut folders = list_directories(&tmp.path).await?;
+        folders.sort();
+        assert_eq!(folders.len(), 100);
+
+        for (i, folder) in folders.into_iter().enumerate() {
+            let test = &tmp.path.join(format!("{:0fill$}", i, fill = 4));
+            assert_eq!(&folder, test);
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn multiple_directory_creation() -> Result<()> {
+        let tmp = TempPath::new("create_multiple_dirs").await?;
+        let dirs = ["config", "src", "tests"];
+
+        create_multiple_directories(&tmp.path, &dirs).await?;
+        let folders = list_directories(&tmp.path).await?;
+        assert_eq!(folders.len(), 3);
+
+        for check in dirs {
+            let target = tmp.path.join(check);
+            assert!(folders.contains(&target));
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn dry_run_matches_created_directories() -> Result<()> {
+        let tmp = TempPath::new("dry_run_directories").await?;
+        let numeric = tmp.new_folder("numeric").await?;
+        let multiple = tmp.new_folder("multiple").await?;
+
+        let mut planned = create_numeric_directories_dry_run(&numeric.path, 5, 15, 3)?;
+        assert!(list_directories(&numeric.path).await?.is_empty());
+        create_numeric_directories(&numeric.path, 5, 15, 3).await?;
+        let mut created = list_directories(&numeric.path).await?;
+        planned.sort();
+        created.sort();
+        assert_eq!(planned, created);
+
+        let dirs = ["config", "src", "tests"];
+        let mut planned = create_multiple_directories_dry_run(&multiple.path, &dirs);
+        assert!(list_directories(&multiple.path).await?.is_empty());
+        create_multiple_directories(&multiple.path, &dirs).await?;
+        let mut created = list_directories(&multiple.path).await?;
+        planned.sort();
+        created.sort();
+        assert_eq!(planned, created);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn files_filter() -> Result<()> {
+        let root = TempPath::new("filter_files").await?;
+        root.multi_file(vec!["first.rs", "second.rs", "third.js", "fourth.rb"])
+            .await?;
+
+        // Raw string filter
+        let mut filter = FtFilter::Raw("fourth".to_string());
+        let mut result = list_files_with_filter(&root.path, filter).await?;
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0], root.path.join("fourth.rb"));
+
+        // PathBuf filter
+        filter = FtFilter::Path(PathBuf::from("third.js"));
+        result = list_files_with_filter(&root.path, filter).await?;
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0], root.path.join("third.js"));
+
+        // Regex filter
+        filter = FtFilter::Regex(Regex::new(r"(.*)\.rs").unwrap());
+        result = list_files_with_filter(&root.path, filter).await?;
+        assert_eq!(result.len(), 2);
+        assert!(result.contains(&root.path.join("first.rs")));
+        assert!(result.contains(&root.path.join("second.rs")));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn files_filter_is_empty() -> Result<()> {
+        let root = TempPath::new("filter_files_empty").await?;
+
+        // Raw string filter (normal + nested)
+        let mut filter = FtFilter::Raw("non-existant".to_string());
+        let mut result = list_files_with_filter(&root.path, &filter).await?;
+        assert!(result.is_empty());
+        result = list_nested_files_with_filter(&root.path, filter).await?;
+        assert!(result.is_empty());
+
+        // PathBuf Filter
+        filter = FtFilter::Path(PathBuf::from("another-missing"));
+        result = list_files_with_filter(&root.path, &filter).await?;
+        assert!(result.is_empty());
+        result = list_nested_files_with_filter(&root.path, filter).await?;
+        assert!(result.is_empty());
+
+        // Regex filter
+        filter = FtFilter::Regex(Regex::new(r"(.*)\.rs").unwrap());
+        result = list_files_with_filter(&root.path, &filter).await?;
+        assert!(result.is_empty());
+        result = list_nested_files_with_filter(&root.path, filter).await?;
+        assert!(result.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn list_items_error() -> Result<()> {
+        let root = TempPath::new("filter_files_error").await?;
+        let test = root.new_file("test.js").await?;
+
+        assert!(list_files(&test.path).await.is_err());
+        assert!(list_nested_files(&test.path).await.is_err());
+        assert!(
+            list_files_with_filter(&test.path, FtFilter::Raw("filter".to_string()))
+                .await
+                .is_err()
+        );
+        assert!(
+            list_nested_files_with_filter(&test.path, FtFilter::Raw("filter".to_string()))
+                .await
+                .is_err()
+        );
+        assert!(list_directories(&test.path).await.is_err());
+        assert!(list_nested_directories(&test.path).await.is_err());
+        assert!(
+            list_directories_with_filter(&test.path, FtFilter::Raw("filter".to_string()))
+                .await
+                .is_err()
+        );
+        assert!(list_nested_directories_with_filter(
+            &test.path,
+            FtFilter::Raw("filter".to_string())
+        )
+        .await
+        .is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn nested_files_filter() -> Result<()> {
+        let root = TempPath::new("nested_filter_files").await?;
+        let ffolder = root.new_folder("ffolder").await?;
+        let sfolder = root.new_folder("sfolder").await?;
+        let tfolder = root.new_folder("tfolder").await?;
+
+        root.new_file("initial.pdf").await?;
+        ffolder.new_file("first.rs").await?;
+        sfolder.multi_file(vec!["second.txt", "third.rs"]).await?;
+        tfolder.new_file("initial.cpp").await?;
+
+        let mut filter = FtFilter::Raw("initial".to_string());
+        let mut result = list_nested_files_with_filter(&root.path, filter).await?;
+        assert_eq!(result.len(), 2);
+        assert!(result.contains(&root.path.join("tfolder/initial.cpp")));
+        assert!(result.contains(&root.path.join("initial.pdf")));
+
+        filter = FtFilter::Path(PathBuf::from("second.txt"));
+        result = list_nested_files_with_filter(&root.path, filter).await?;
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0], root.path.join("sfolder/second.txt"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn directories_filter() -> Result<()> {
+        let root = TempPath::new("dir_filter").await?;
+        root.multi_folder(vec!["log_var", "store_var", "config", "etc"])
+            .await?;
+
+        // Raw string filter
+        let mut filter = FtFilter::Raw("config".to_string());
+        let mut result = list_directories_with_filter(&root.path, filter).await?;
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0], root.path.join("config"));
+
+        // PathBuf filter
+        filter = FtFilter::Path(PathBuf::from("etc"));
+        result = list_directories_with_filter(&root.path, filter).await?;
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0], root.path.join("etc"));
+
+        // Regex filter
+        filter = FtFilter::Regex(Regex::new(r"(.*)_var").unwrap());
+        result = list_directories_with_filter(&root.path, filter).await?;
+        assert_eq!(result.len(), 2);
+        assert!(result.contains(&root.path.join("log_var")));
+        assert!(result.contains(&root.path.join("store_var")));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn nested_directories_filter() -> Result<()> {
+        let root = TempPath::new("nested_dir_filter_test").await?;
+        root.multi_folder(vec!["folder1", "folder2"]).await?;
+
+        let f1 = TempPath::new(root.join("folder1")).await?;
+        f1.multi_folder(vec!["sub1", "sub_2", "sub3"]).await?;
+
+        let s2 = TempPath::new(f1.join("sub_2")).await?;
+        s2.multi_folder(vec!["deep_1", "deep2"]).await?;
+
+        // Raw filter
+        let mut filter = FtFilter::Raw("deep".to_string());
+        let mut result = list_nested_directories_with_filter(&root.path, filter).await?;
+        assert_eq!(result.len(), 2);
+        assert!(result.contains(&root.path.join("folder1/sub_2/deep_1")));
+        assert!(result.contains(&root.path.join("folder1/sub_2/deep2")));
+
+        // Path filter
+        filter = FtFilter::Path(PathBuf::from("folder1"));
+        result = list_nested_directories_with_filter(&root.path, filter).await?;
+        assert_eq!(result.len(), 6);
+
+        filter = FtFilter::Regex(Regex::new(r"(.*)_[0-9]{1}").unwrap());
+        result = list_nested_directories_with_filter(&root.path, filter).await?;
+        assert_eq!(result.len(), 3);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn list_dirs_empty() -> Result<()> {
+        let root = TempPath::new("list_dirs_empty").await?;
+
+        // Raw string filter (normal + nested)
+        let mut filter = FtFilter::Raw("non-existant".to_string());
+        let mut result = list_directories_with_filter(&root.path, &filter).await?;
+        assert!(result.is_empty());
+        result = list_nested_directories_with_filter(&root.path, filter).await?;
+        assert!(result.is_empty());
+
+        // PathBuf Filter
+        filter = FtFilter::Path(PathBuf::from("another-missing"));
+        result = list_directories_with_filter(&root.path, &filter).await?;
+        assert!(result.is_empty());
+        result = list_nested_directories_with_filter(&root.path, filter).await?;
+        assert!(result.is_empty());
+
+        // Regex filter
+        filter = FtFilter::Regex(Regex::new(r"(.*)\.rs").unwrap());
+        result = list_directories_with_filter(&root.path, &filter).await?;
+        assert!(result.is_empty());
+        result = list_nested_directories_with_filter(&root.path, filter).await?;
+        assert!(result.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn writes_files_atomically() -> Result<()> {
+        let root = TempPath::new("write_atomic").await?;
+        let target = root.path.join("data.txt");
+
+        write_atomic(&target, "first").await?;
+        assert_eq!(std::fs::read_to_string(&target)?, "first");
+        write_atomic(&target, "second").await?;
+        assert_eq!(std::fs::read_to_string(&target)?, "second");
+        sync::write_atomic(&target, "third")?;
+        assert_eq!(std::fs::read_to_string(&target)?, "third");
+
+        // No temporary files are left behind
+        assert_eq!(list_files(&root.path).await?, vec![target]);
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn atomic_writes_keep_permissions_and_symlinks() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = TempPath::new("write_atomic_metadata").await?;
+        let target = root.path.join("script.sh");
+        let link = root.path.join("link.sh");
+        std::fs::write(&target, "old")?;
+        std::fs::set_permissions(&target, std::fs::Permissions::from_mode(0o750))?;
+        std::os::unix::fs::symlink("script.sh", &link)?;
+
+        write_atomic(&target, "new").await?;
+        assert_eq!(
+            std::fs::metadata(&target)?.permissions().mode() & 0o777,
+            0o750
+        );
+
+        // Writing through the link replaces its target and keeps the link
+        sync::write_atomic(&link, "linked")?;
+        assert!(link.symlink_metadata()?.is_symlink());
+        assert_eq!(std::fs::read_to_string(&target)?, "linked");
+        assert_eq!(
+            std::fs::metadata(&target)?.permissions().mode() & 0o777,
+            0o750
+        );
+
+        write_atomic(&link, "again").await?;
+        assert!(link.symlink_metadata()?.is_symlink());
+        assert_eq!(std::fs::read_to_string(&target)?, "again");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn failed_atomic_write_leaves_original_untouched() -> Result<()> {
+        let root = TempPath::new("write_atomic_failure").await?;
+        let original = root.new_folder("original").await?;
+        original.multi_file(vec!["keep.txt"]).await?;
+
+        // Renaming a file over a non-empty directory fails after the temp file is written
+        assert!(write_atomic(&original.path, "new contents").await.is_err());
+        assert!(sync::write_atomic(&original.path, "new contents").is_err());
+
+        assert!(original.path.is_dir());
+        assert_eq!(
+            list_files(&original.path).await?,
+            vec![original.path.join("keep.txt")]
+        );
+        assert!(list_files(&root.path).await?.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn ensure_file_creates_nested_file() -> Result<()> {
+        let root = TempPath::new("ensure_file_nested").await?;
+        let target = root.path.join("not/yet/here/file.txt");
+
+        assert!(ensure_file(&target).await?);
+        assert!(target.is_file());
+        assert_eq!(std::fs::metadata(&target)?.len(), 0);
+
+        let sync_target = root.path.join("sync/also/file.txt");
+        assert!(sync::ensure_file(&sync_target)?);
+        assert!(sync_target.is_file());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn ensure_file_touches_existing_file() -> Result<()> {
+        let root = TempPath::new("ensure_file_touch").await?;
+        let target = root.path.join("existing.txt");
+        std::fs::write(&target, "contents")?;
+
+        let old = std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000);
+        std::fs::File::options()
+            .write(true)
+            .open(&target)?
+            .set_modified(old)?;
+
+        assert!(!ensure_file(&target).await?);
+        assert!(std::fs::metadata(&target)?.modified()? > old);
+        assert_eq!(std::fs::read_to_string(&target)?, "contents");
+
+        std::fs::File::options()
+            .write(true)
+            .open(&target)?
+            .set_modified(old)?;
+        assert!(!sync::ensure_file(&target)?);
+        assert!(std::fs::metadata(&target)?.modified()? > old);
+
+        // The owner can touch a read only file without write access
+        #[cfg(unix)]
+        {
+            let mut perms = std::fs::metadata(&target)?.permissions();
+            perms.set_readonly(true);
+            std::fs::set_permissions(&target, perms)?;
+            std::fs::File::open(&target)?.set_modified(old)?;
+
+            assert!(!ensure_file(&target).await?);
+            assert!(std::fs::metadata(&target)?.modified()? > old);
+            assert!(!sync::ensure_file(&target)?);
+            assert_eq!(std::fs::read_to_string(&target)?, "contents");
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn counts_match_listings() -> Result<()> {
+        let root = TempPath::new("count_items").await?;
+        let ffolder = root.new_folder("ffolder").await?;
+        let sfolder = root.new_folder("sfolder").await?;
+        root.multi_file(vec!["initial.rs", "initial.txt"]).await?;
+        ffolder.multi_file(vec!["first.rs", "second.rs"]).await?;
+        sfolder.multi_file(vec!["third.txt"]).await?;
+        sfolder.multi_folder(vec!["deep_rs", "deep_txt"]).await?;
+
+        let filter = || FtFilter::Raw(".rs".to_string());
+        assert_eq!(
+            count_files(&root.path, false, Some(filter())).await?,
+            list_files_with_filter(&root.path, filter()).await?.len()
+        );
+        assert_eq!(
+            count_files(&root.path, true, Some(filter())).await?,
+            list_nested_files_with_filter(&root.path, filter())
+                .await?
+                .len()
+        );
+        assert_eq!(
+            count_files(&root.path, true, None).await?,
+            list_nested_files(&root.path).await?.len()
+        );
+
+        let filter = || FtFilter::Raw("deep".to_string());
+        assert_eq!(
+            count_directories(&root.path, false, None).await?,
+            list_directories(&root.path).await?.len()
+        );
+        assert_eq!(
+            count_directories(&root.path, true, Some(filter())).await?,
+            list_nested_directories_with_filter(&root.path, filter())
+                .await?
+                .len()
+        );
+
+        assert_eq!(sync::count_files(&root.path, true, None)?, 5);
+        assert_eq!(sync::count_directories(&root.path, true, None)?, 4);
+        assert!(count_files("IDoNotExistAsADirectory", true, None)
+            .await
+            .is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn finds_first_matching_file() -> Result<()> {
+        let root = TempPath::new("find_first_file").await?;
+        for i in 0..20 {
+            let folder = format!("folder_{i}");
+            root.multi_folder(vec![&folder]).await?;
+
+            let mut files: Vec<_> = (0..20).map(|j| format!("{folder}/file_{j}.txt")).collect();
+            files.push(format!("{folder}/match_{i}.rs"));
+            root.multi_file(files).await?;
+        }
+
+        let filter = || FtFilter::Regex(Regex::new(r"match_(.*)\.rs").unwrap());
+        let all = list_nested_files_with_filter(&root.path, filter()).await?;
+        assert_eq!(all.len(), 20);
+
+        let first = find_first_file(&root.path, filter()).await?;
+        assert_eq!(first.as_ref(), all.first());
+        assert_eq!(sync::find_first_file(&root.path, filter())?, first);
+
+        let missing = FtFilter::Raw("not_here".to_string());
+        assert!(find_first_file(&root.path, &missing).await?.is_none());
+        assert!(sync::find_first_file(&root.path, &missing)?.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn lists_relative_paths() -> Result<()> {
+        let root = TempPath::new("list_relative").await?;
+        root.multi_folder(vec!["sub/deeper", "other"]).await?;
+        root.multi_file(vec!["top.txt", "sub/file.txt", "sub/deeper/file.log"])
+            .await?;
+
+        let mut expected: Vec<PathBuf> = list_nested_files(&root.path)
+            .await?
+            .into_iter()
+            .map(|p| p.strip_prefix(&root.path).unwrap().to_path_buf())
+            .collect();
+        expected.sort();
+
+        let mut relative = list_nested_files_relative(&root.path).await?;
+        relative.sort();
+        assert_eq!(relative, expected);
+        assert_eq!(
+            relative,
+            vec![
+                PathBuf::from("sub/deeper/file.log"),
+                PathBuf::from("sub/file.txt"),
+                PathBuf::from("top.txt"),
+            ]
+        );
+
+        // Works the same when the root is given with a trailing separator
+        let mut trailing = sync::list_nested_files_relative(format!("{}/", root.path.display()))?;
+        trailing.sort();
+        assert_eq!(trailing, expected);
+
+        let filter = FtFilter::Raw(".log".to_string());
+        let filtered = list_nested_files_with_filter_relative(&root.path, filter).await?;
+        assert_eq!(filtered, vec![PathBuf::from("sub/deeper/file.log")]);
+
+        let mut dirs = list_nested_directories_relative(&root.path).await?;
+        dirs.sort();
+        assert_eq!(
+            dirs,
+            vec![
+                PathBuf::from("other"),
+                PathBuf::from("sub"),
+                PathBuf::from("sub/deeper"),
+            ]
+        );
+
+        let filter = FtFilter::Raw("deeper".to_string());
+        let dirs = sync::list_nested_directories_with_filter_relative(&root.path, filter)?;
+        assert_eq!(dirs, vec![PathBuf::from("sub/deeper")]);
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    #[ignore = "permissions are not enforced for root, run with --ignored as an unprivileged user"]
+    async fn partial_listing_skips_unreadable_directories() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = TempPath::new("partial_listing").await?;
+        root.multi_folder(vec!["open/inner", "locked"]).await?;
+        root.multi_file(vec![
+            "top.txt",
+            "open/a.txt",
+            "open/inner/b.txt",
+            "locked/c.txt",
+        ])
+        .await?;
+
+        let locked = root.path.join("locked");
+        std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o000))?;
+        assert!(
+            std::fs::read_dir(&locked).is_err(),
+            "permissions aren't enforced for this user"
+        );
+
+        assert!(list_nested_files(&root.path).await.is_err());
+        assert!(sync::list_nested_files(&root.path).is_err());
+
+        let (mut files, errors) = list_nested_files_partial(&root.path).await?;
+        files.sort();
+        assert_eq!(
+            files,
+            vec![
+                root.path.join("open/a.txt"),
+                root.path.join("open/inner/b.txt"),
+                root.path.join("top.txt"),
+            ]
+        );
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].0, locked);
+        assert_eq!(errors[0].1.kind(), std::io::ErrorKind::PermissionDenied);
+
+        let (sync_files, sync_errors) = sync::list_nested_files_partial(&root.path)?;
+        assert_eq!(sync_files.len(), 3);
+        assert_eq!(sync_errors.len(), 1);
+
+        let (dirs, errors) = list_nested_directories_partial(&root.path).await?;
+        assert_eq!(dirs.len(), 3);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            sync::list_nested_directories_partial(&root.path)?.0.len(),
+            3
+        );
+
+        std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o755))?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn sync_listings_report_read_errors() -> Result<()> {
+        use util::{walk_sync_in, PartialItems};
+
+        let root = TempPath::new("sync_listings_report_read_errors").await?;
+        root.multi_folder(vec!["a", "b"]).await?;
+        root.multi_file(vec!["a/1.txt", "a/2.txt", "a/3.txt", "b/4.txt"])
+            .await?;
+
+        // Listing `a` fails after its first entry, as if it vanished mid read
+        let faulty = root.path.join("a");
+        let read_dir =
+            |dir: &Path| -> std::io::Result<std::vec::IntoIter<std::io::Result<PathBuf>>> {
+                let mut entries = std::fs::read_dir(dir)?
+                    .map(|entry| entry.map(|e| e.path()))
+                    .collect::<std::io::Result<Vec<_>>>()?;
+                entries.sort();
+
+                let mut entries: Vec<_> = entries.into_iter().map(Ok).collect();
+                if dir == faulty {
+                    entries.insert(1, Err(std::io::Error::other("entry went missing")));
+                }
+                Ok(entries.into_iter())
+            };
+
+        // The failed entry aborts instead of returning a truncated listing
+        let mut items = vec![];
+        let err = walk_sync_in(
+            &root.path,
+            FtIterItemState::RFile,
+            None,
+            &mut |item| {
+                items.push(item);
+                ControlFlow::Continue(())
+            },
+            &read_dir,
+        )
+        .unwrap_err();
+        assert!(format!("{err:#}").contains("entry went missing"));
+
+        // Carrying on skips the rest of the failing directory but not the others
+        let mut partial = PartialItems::default();
+        walk_sync_in(
+            &root.path,
+            FtIterItemState::RFile,
+            None,
+            &mut partial,
+            &read_dir,
+        )?;
+        assert_eq!(
+            partial.items,
+            vec![root.path.join("a/1.txt"), root.path.join("b/4.txt")]
+        );
+        assert_eq!(partial.errors.len(), 1);
+        assert_eq!(partial.errors[0].0, faulty);
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn regex_filter_handles_non_utf8_paths() -> Result<()> {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let root = TempPath::new("regex_non_utf8").await?;
+        let invalid = root.path.join(OsStr::from_bytes(b"bad_\xff\xfe.log"));
+        std::fs::File::create(&invalid)?;
+        root.multi_file(vec!["good.log", "other.txt"]).await?;
+
+        let filter = FtFilter::Regex(Regex::new(r"(.*)\.log$").unwrap());
+        let mut result = list_files_with_filter(&root.path, filter).await?;
+        result.sort();
+        assert_eq!(result, vec![invalid.clone(), root.path.join("good.log")]);
+
+        let filter = FtFilter::Regex(Regex::new(r"other").unwrap());
+        let result = sync::list_files_with_filter(&root.path, filter)?;
+        assert_eq!(result, vec![root.path.join("other.txt")]);
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn lists_through_symlinked_root() -> Result<()> {
+        let root = TempPath::new("symlinked_root").await?;
+        root.multi_folder(vec!["target/sub"]).await?;
+        root.multi_file(vec!["target/top.txt", "target/sub/nested.txt"])
+            .await?;
+
+        let target = root.path.join("target");
+        let link = root.path.join("link");
+        std::os::unix::fs::symlink(&target, &link)?;
+
+        // The symlinked root is followed but results stay under the link
+        let mut files = list_nested_files(&link).await?;
+        files.sort();
+        assert_eq!(
+            files,
+            vec![link.join("sub/nested.txt"), link.join("top.txt")]
+        );
+        assert_eq!(list_files(&link).await?, vec![link.join("top.txt")]);
+        assert_eq!(list_directories(&link).await?, vec![link.join("sub")]);
+        assert_eq!(sync::list_nested_files(&link)?.len(), 2);
+
+        // Resolving the root returns results under the real target instead
+        let real = std::fs::canonicalize(&target)?;
+        let mut files = list_nested_files_canonical(&link).await?;
+        files.sort();
+        assert_eq!(
+            files,
+            vec![real.join("sub/nested.txt"), real.join("top.txt")]
+        );
+        assert_eq!(
+            sync::list_nested_directories_canonical(&link)?,
+            vec![real.join("sub")]
+        );
+        assert_eq!(
+            list_nested_directories_canonical(&link).await?,
+            vec![real.join("sub")]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn sync_and_async_listings_match() -> Result<()> {
+        let root = TempPath::new("sync_async_parity").await?;
+        root.multi_folder(vec!["first/inner", "second"]).await?;
+        root.multi_file(vec![
+            "top.rs",
+            "first/a.rs",
+            "first/inner/b.rs",
+            "second/c.txt",
+        ])
+        .await?;
+
+        let sorted = |mut items: Vec<PathBuf>| {
+            items.sort();
+            items
+        };
+
+        assert_eq!(
+            sorted(sync::list_files(&root.path)?),
+            sorted(list_files(&root.path).await?)
+        );
+        assert_eq!(
+            sorted(sync::list_directories(&root.path)?),
+            sorted(list_directories(&root.path).await?)
+        );
+        assert_eq!(
+            sorted(sync::list_nested_files(&root.path)?),
+            sorted(list_nested_files(&root.path).await?)
+        );
+        assert_eq!(
+            sorted(sync::list_nested_directories(&root.path)?),
+            sorted(list_nested_directories(&root.path).await?)
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn path_listers_can_be_boxed() -> Result<()> {
+        use std::future::Future;
+        use std::pin::Pin;
+
+        type Listing<'a> = Pin<Box<dyn Future<Output = Result<Vec<PathBuf>>> + Send + 'a>>;
+
+        let root = TempPath::new("boxed_listers").await?;
+        root.multi_folder(vec!["sub"]).await?;
+        root.multi_file(vec!["top.txt", "sub/nested.txt"]).await?;
+
+        let listings: Vec<Listing> = vec![
+            Box::pin(list_files_path(&root.path)),
+            Box::pin(list_nested_files_path(&root.path)),
+            Box::pin(list_directories_path(&root.path)),
+            Box::pin(list_nested_directories_path(&root.path)),
+        ];
+
+        let mut counts = vec![];
+        for listing in listings {
+            counts.push(listing.await?.len());
+        }
+        assert_eq!(counts, vec![1, 2, 1, 1]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn list_items_matches_dedicated_listers() -> Result<()> {
+        let root = TempPath::new("list_items").await?;
+        root.multi_folder(vec!["first/inner", "second_test"])
+            .await?;
+        root.multi_file(vec!["top_test.rs", "first/a.rs", "first/inner/b_test.rs"])
+            .await?;
+
+        let sorted = |mut items: Vec<PathBuf>| {
+            items.sort();
+            items
+        };
+
+        assert_eq!(
+            sorted(list_items(&root.path, FtIterTarget::Files, false, None).await?),
+            sorted(list_files(&root.path).await?)
+        );
+        assert_eq!(
+            sorted(list_items(&root.path, FtIterTarget::Files, true, None).await?),
+            sorted(list_nested_files(&root.path).await?)
+        );
+        assert_eq!(
+            sorted(list_items(&root.path, FtIterTarget::Dirs, false, None).await?),
+            sorted(list_directories(&root.path).await?)
+        );
+        assert_eq!(
+            sorted(sync::list_items(
+                &root.path,
+                FtIterTarget::Dirs,
+                true,
+                None
+            )?),
+            sorted(list_nested_directories(&root.path).await?)
+        );
+
+        let both = list_items(&root.path, FtIterTarget::Both, false, None).await?;
+        assert_eq!(
+            sorted(both),
+            vec![
+                root.path.join("first"),
+                root.path.join("second_test"),
+                root.path.join("top_test.rs"),
+            ]
+        );
+
+        let filter = FtFilter::Raw("test".to_string());
+        let both = list_items(&root.path, FtIterTarget::Both, true, Some(filter)).await?;
+        assert_eq!(
+            sorted(both),
+            vec![
+                root.path.join("first/inner/b_test.rs"),
+                root.path.join("second_test"),
+                root.path.join("top_test.rs"),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn nested_entries_are_files_and_directories() -> Result<()> {
+        let root = TempPath::new("nested_entries").await?;
+        root.multi_folder(vec!["src/bin", "docs"]).await?;
+        root.multi_file(vec!["README.md", "src/lib.rs", "src/bin/main.rs"])
+            .await?;
+
+        let entries = list_nested_entries(&root.path).await?;
+        let mut files: Vec<_> = entries
+            .iter()
+            .filter(|e| e.is_file())
+            .map(|e| e.path().to_path_buf())
+            .collect();
+        let mut dirs: Vec<_> = entries
+            .iter()
+            .filter(|e| e.is_dir())
+            .map(|e| e.path().to_path_buf())
+            .collect();
+        files.sort();
+        dirs.sort();
+
+        let mut expected_files = list_nested_files(&root.path).await?;
+        let mut expected_dirs = list_nested_directories(&root.path).await?;
+        expected_files.sort();
+        expected_dirs.sort();
+        assert_eq!(files, expected_files);
+        assert_eq!(dirs, expected_dirs);
+        assert_eq!(entries.len(), expected_files.len() + expected_dirs.len());
+
+        let mut sync_entries = sync::list_nested_entries(&root.path)?;
+        let mut async_entries = entries;
+        sync_entries.sort();
+        async_entries.sort();
+        assert_eq!(sync_entries, async_entries);
+
+        let filter = || FtFilter::Raw("src".to_string());
+        let mut filtered = list_nested_entries_with_filter(&root.path, filter()).await?;
+        filtered.sort();
+        assert_eq!(
+            filtered,
+            vec![
+                DirEntryKind::File(root.path.join("src/bin/main.rs")),
+                DirEntryKind::File(root.path.join("src/lib.rs")),
+                DirEntryKind::Dir(root.path.join("src")),
+                DirEntryKind::Dir(root.path.join("src/bin")),
+            ]
+        );
+        assert_eq!(
+            sync::list_nested_entries_with_filter(&root.path, filter())?.len(),
+            4
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn regex_name_filter_anchors_to_name() -> Result<()> {
+        let root = TempPath::new("regex_name_filter").await?;
+        root.multi_folder(vec!["foo", "test_dir"]).await?;
+        root.multi_file(vec!["foo/test.rs", "foo/main.rs", "test_dir/mod.rs"])
+            .await?;
+
+        // The full path starts with the temp directory so `^test` never matches it
+        let anchored = || Regex::new(r"^test").unwrap();
+        let by_path =
+            list_nested_files_with_filter(&root.path, FtFilter::Regex(anchored())).await?;
+        assert!(by_path.is_empty());
+
+        let by_name = FtFilter::RegexName(anchored());
+        let result = list_nested_files_with_filter(&root.path, &by_name).await?;
+        assert_eq!(result, vec![root.path.join("foo/test.rs")]);
+        assert_eq!(
+            sync::list_nested_files_with_filter(&root.path, &by_name)?,
+            result
+        );
+
+        let dirs = list_nested_directories_with_filter(&root.path, by_name).await?;
+        assert_eq!(dirs, vec![root.path.join("test_dir")]);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn filter_serde_round_trip() -> Result<()> {
+        let raw: FtFilter = serde_json::from_str(r#"{ "type": "raw", "pattern": ".log" }"#)?;
+        assert!(matches!(&raw, FtFilter::Raw(p) if p == ".log"));
+        let json = serde_json::to_string(&raw)?;
+        assert_eq!(json, r#"{"type":"raw","pattern":".log"}"#);
+        assert!(matches!(serde_json::from_str(&json)?, FtFilter::Raw(p) if p == ".log"));
+
+        let path = FtFilter::Path(PathBuf::from("sub/dir"));
+        let json = serde_json::to_string(&path)?;
+        assert_eq!(json, r#"{"type":"path","pattern":"sub/dir"}"#);
+        assert!(
+            matches!(serde_json::from_str(&json)?, FtFilter::Path(p) if p == Path::new("sub/dir"))
+        );
+
+        let regex = FtFilter::Regex(Regex::new(r"(.*)\.rs$")?);
+        let json = serde_json::to_string(&regex)?;
+        assert_eq!(json, r#"{"type":"regex","pattern":"(.*)\\.rs$"}"#);
+        match serde_json::from_str(&json)? {
+            FtFilter::Regex(re) => {
+                assert_eq!(re.as_str(), r"(.*)\.rs$");
+                assert!(re.is_match("src/lib.rs"));
+            }
+            other => panic!("expected a regex filter, got {other:?}"),
+        }
+
+        let err =
+            serde_json::from_str::<FtFilter>(r#"{ "type": "regex", "pattern": "(unclosed" }"#)
+                .unwrap_err();
+        assert!(err.to_string().contains("regex parse error"));
+
+        let regex_name = FtFilter::RegexName(Regex::new(r"^test")?);
+        let json = serde_json::to_string(&regex_name)?;
+        assert_eq!(json, r#"{"type":"regex_name","pattern":"^test"}"#);
+        assert_eq!(serde_json::from_str::<FtFilter>(&json)?, regex_name);
+        assert!(serde_json::from_str::<FtFilter>(r#"{ "type": "glob", "pattern": "*" }"#).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn filter_clone_and_eq() {
+        let re = || Regex::new(r"(.*)\.rs").unwrap();
+        let filter = FtFilter::Regex(re());
+        assert_eq!(filter.clone(), filter);
+        assert_eq!(FtFilter::Regex(re()), filter);
+        assert_ne!(FtFilter::Regex(Regex::new(r".*\.rs").unwrap()), filter);
+
+        assert_eq!(
+            FtFilter::Raw("a".to_string()),
+            FtFilter::Raw("a".to_string())
+        );
+        assert_ne!(
+            FtFilter::Raw("a".to_string()),
+            FtFilter::Path(PathBuf::from("a"))
+        );
+    }
+
+    #[tokio::test]
+    async fn one_filter_many_roots() -> Result<()> {
+        let root = TempPath::new("one_filter_many_roots").await?;
+        root.multi_folder(vec!["a", "b/logs", "c"]).await?;
+        root.multi_file(vec![
+            "a/app.log",
+            "a/app.txt",
+            "b/logs/db.log",
+            "c/notes.md",
+        ])
+        .await?;
+
+        let filter = FtFilter::Raw(".log".to_string());
+        let roots = [
+            root.path.join("a"),
+            root.path.join("b"),
+            root.path.join("c"),
+        ];
+        let mut found = vec![];
+        for r in &roots {
+            found.extend(list_nested_files_with_filter(r, &filter).await?);
+            assert_eq!(
+                sync::list_nested_files_with_filter(r, &filter)?,
+                list_nested_files_with_filter(r, &filter).await?
+            );
+        }
+        found.sort();
+
+        assert_eq!(
+            found,
+            vec![root.path.join("a/app.log"), root.path.join("b/logs/db.log")]
+        );
+
+        // Still usable by value afterwards
+        assert_eq!(list_files_with_filter(&roots[0], filter).await?.len(), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn name_filter_ignores_ancestors() -> Result<()> {
+        let root = TempPath::new("name_filter_ignores_ancestors").await?;
+        root.multi_folder(vec!["test/reports", "other"]).await?;
+        root.multi_file(vec![
+            "test/report.txt",
+            "test/reports/summary.txt",
+            "other/test_data.txt",
+        ])
+        .await?;
+
+        // The pattern is only in an ancestor directory so nothing under `test` matches by name
+        let by_name = FtFilter::Name("test".to_string());
+        let result = list_nested_files_with_filter(&root.path, &by_name).await?;
+        assert_eq!(result, vec![root.path.join("other/test_data.txt")]);
+        assert_eq!(
+            sync::list_nested_files_with_filter(&root.path, &by_name)?,
+            result
+        );
+
+        // Whereas a raw filter matches anywhere in the path
+        let raw = FtFilter::Raw("test".to_string());
+        assert_eq!(
+            list_nested_files_with_filter(&root.path, raw).await?.len(),
+            3
+        );
+
+        // Directories are matched on their own name too
+        let dirs = FtFilter::Name("report".to_string());
+        let result = list_nested_directories_with_filter(&root.path, dirs).await?;
+        assert_eq!(result, vec![root.path.join("test/reports")]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn parent_name_filter_checks_immediate_parent() -> Result<()> {
+        let root = TempPath::new("parent_name_filter").await?;
+        root.multi_folder(vec!["photos/2023/edited", "photos/2024"])
+            .await?;
+        root.multi_file(vec![
+            "photos/2023/beach.jpg",
+            "photos/2023/hike.jpg",
+            "photos/2023/edited/beach.jpg",
+            "photos/2024/city.jpg",
+            "photos/2023.txt",
+        ])
+        .await?;
+
+        let filter = FtFilter::ParentName("2023".to_string());
+        let mut result = list_nested_files_with_filter(&root.path, &filter).await?;
+        result.sort();
+        assert_eq!(
+            result,
+            vec![
+                root.path.join("photos/2023/beach.jpg"),
+                root.path.join("photos/2023/hike.jpg"),
+            ]
+        );
+
+        let filter = FtFilter::ParentName("2024".to_string());
+        assert_eq!(
+            sync::list_nested_files_with_filter(&root.path, filter)?,
+            vec![root.path.join("photos/2024/city.jpg")]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn overlapping_matches_are_unique() -> Result<()> {
+        let root = TempPath::new("overlapping_matches_are_unique").await?;
+        root.multi_folder(vec!["log/logs/log"]).await?;
+        root.multi_file(vec!["log/log.log", "log/logs/log/log.log.txt"])
+            .await?;
+
+        // "log" matches every one of these paths several times over
+        let filter = FtFilter::Raw("log".to_string());
+        let mut files = list_nested_files_with_filter(&root.path, &filter).await?;
+        let mut dirs = list_nested_directories_with_filter(&root.path, &filter).await?;
+        let mut items = list_items(&root.path, FtIterTarget::Both, true, Some(filter)).await?;
+        for results in [&mut files, &mut dirs, &mut items] {
+            let total = results.len();
+            results.sort();
+            results.dedup();
+            assert_eq!(results.len(), total);
+        }
+
+        assert_eq!(files.len(), 2);
+        assert_eq!(dirs.len(), 3);
+        assert_eq!(items.len(), 5);
+
+        let re = FtFilter::Regex(Regex::new("log|txt")?);
+        assert_eq!(
+            sync::list_nested_files_with_filter(&root.path, re)?.len(),
+            2
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn paged_listing() -> Result<()> {
+        let root = TempPath::new("paged_listing").await?;
+        root.multi_folder(vec!["sub"]).await?;
+        root.multi_file(vec!["a.txt", "b.txt", "c.txt", "d.txt", "sub/e.txt"])
+            .await?;
+        let file = |name: &str| root.path.join(name);
+
+        let pager = list_files_pager(&root.path, false).await?;
+        assert_eq!(pager.total(), 4);
+
+        // Exact boundaries
+        let page = pager.page(0, 2);
+        assert_eq!(page.items, vec![file("a.txt"), file("b.txt")]);
+        assert_eq!(page.total, 4);
+        assert!(page.has_more);
+        let page = pager.page(2, 2);
+        assert_eq!(page.items, vec![file("c.txt"), file("d.txt")]);
+        assert!(!page.has_more);
+
+        // Limit larger than what is left
+        let page = pager.page(3, 10);
+        assert_eq!(page.items, vec![file("d.txt")]);
+        assert!(!page.has_more);
+        assert_eq!(pager.page(0, usize::MAX).items.len(), 4);
+
+        // Offset past the end
+        let page = pager.page(10, 2);
+        assert!(page.items.is_empty());
+        assert_eq!(page.total, 4);
+        assert!(!page.has_more);
+
+        // Recursive
+        let page = list_files_paged(&root.path, true, 4, 1).await?;
+        assert_eq!(page.items, vec![file("sub/e.txt")]);
+        assert_eq!(page.total, 5);
+        assert!(!page.has_more);
+        assert_eq!(sync::list_files_paged(&root.path, true, 4, 1)?, page);
+        assert_eq!(
+            sync::list_files_pager(&root.path, false)?.page(1, 1).items,
+            vec![file("b.txt")]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn progress_called_for_each_item() -> Result<()> {
+        let root = TempPath::new("progress_called_for_each_item").await?;
+        root.multi_folder(vec!["a/b/c", "d"]).await?;
+        root.multi_file(vec![
+            "1.txt",
+            "a/2.txt",
+            "a/b/3.txt",
+            "a/b/c/4.txt",
+            "d/5.txt",
+        ])
+        .await?;
+
+        let mut seen = vec![];
+        let files =
+            list_nested_files_with_progress(&root.path, |f| seen.push(f.to_path_buf())).await?;
+        assert_eq!(files.len(), 5);
+        assert_eq!(seen, files);
+
+        let mut calls = 0;
+        let dirs = list_nested_directories_with_progress(&root.path, |_| calls += 1).await?;
+        assert_eq!(dirs.len(), 4);
+        assert_eq!(calls, dirs.len());
+
+        let mut calls = 0;
+        let files = sync::list_nested_files_with_progress(&root.path, |_| calls += 1)?;
+        assert_eq!(calls, files.len());
+        let mut calls = 0;
+        let dirs = sync::list_nested_directories_with_progress(&root.path, |_| calls += 1)?;
+        assert_eq!(calls, dirs.len());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn cancelled_listing_stops_early() -> Result<()> {
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        let root = TempPath::new("cancelled_listing_stops_early").await?;
+        root.multi_folder(vec!["a/b/c/d/e", "f/g/h", "i/j"]).await?;
+        root.multi_file(vec!["a/1", "a/b/2", "a/b/c/3", "f/4", "f/g/5", "i/j/6"])
+            .await?;
+
+        // Already cancelled, nothing is walked
+        let cancel = AtomicBool::new(true);
+        assert_eq!(
+            list_nested_files_cancellable(&root.path, &cancel).await?,
+            (vec![], true)
+        );
+        assert_eq!(
+            sync::list_nested_directories_cancellable(&root.path, &cancel)?,
+            (vec![], true)
+        );
+
+        // Never cancelled, everything is walked
+        cancel.store(false, Ordering::Relaxed);
+        let (files, cancelled) = list_nested_files_cancellable(&root.path, &cancel).await?;
+        assert_eq!((files.len(), cancelled), (6, false));
+        let (dirs, cancelled) = sync::list_nested_directories_cancellable(&root.path, &cancel)?;
+        assert_eq!((dirs.len(), cancelled), (10, false));
+
+        // Cancelled mid-traversal, after letting the walk take a few steps
+        let wide = root.new_folder("wide").await?;
+        wide.multi_folder((0..50).map(|i| i.to_string()).collect())
+            .await?;
+        wide.multi_file((0..50).map(|i| format!("{i}/file.txt")).collect())
+            .await?;
+
+        let listing = list_nested_files_cancellable(&wide.path, &cancel);
+        tokio::pin!(listing);
+        for _ in 0..5 {
+            tokio::select! {
+                biased;
+                _ = &mut listing => panic!("listing finished before it was cancelled"),
+                _ = tokio::task::yield_now() => {}
+            }
+        }
+        cancel.store(true, Ordering::Relaxed);
+
+        let (files, cancelled) = listing.await?;
+        assert!(cancelled);
+        assert!(files.len() < 50);
+        assert!(files.iter().all(|f| f.starts_with(&wide.path)));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn hash_during_listing() -> Result<()> {
+        let root = TempPath::new("hash_during_listing").await?;
+        root.multi_folder(vec!["sub"]).await?;
+        std::fs::write(root.path.join("a.txt"), "same contents")?;
+        std::fs::write(root.path.join("sub/b.txt"), "same contents")?;
+        std::fs::write(root.path.join("c.txt"), "other contents")?;
+
+        let hashed = list_nested_files_with_hash(&root.path, DefaultHasher::new).await?;
+        assert_eq!(hashed.len(), 3);
+        let digest = |name: &str| {
+            hashed
+                .iter()
+                .find(|(p, _)| p == &root.path.join(name))
+                .map(|(_, d)| d.clone())
+                .unwrap()
+        };
+        assert_eq!(digest("a.txt"), digest("sub/b.txt"));
+        assert_ne!(digest("a.txt"), digest("c.txt"));
+
+        // Same order as the plain listing, and the same digests from the sync version
+        let files: Vec<_> = hashed.iter().map(|(p, _)| p.clone()).collect();
+        assert_eq!(files, list_nested_files(&root.path).await?);
+        let mut sync_hashed = sync::list_nested_files_with_hash(&root.path, DefaultHasher::new)?;
+        let mut async_hashed = hashed;
+        sync_hashed.sort();
+        async_hashed.sort();
+        assert_eq!(sync_hashed, async_hashed);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn directory_tree_from_spec() -> Result<()> {
+        let root = TempPath::new("directory_tree_from_spec").await?;
+        let spec = ["src/main", "src/lib", "tests", "docs/assets"];
+
+        create_directory_tree(&root.path, &spec).await?;
+        for dir in ["src", "src/main", "src/lib", "tests", "docs", "docs/assets"] {
+            assert!(root.path.join(dir).is_dir(), "{dir} should exist");
+        }
+        assert_eq!(list_nested_directories(&root.path).await?.len(), 6);
+
+        // Existing directories are fine
+        sync::create_directory_tree(&root.path, &["src/main/nested", "tests"])?;
+        assert!(root.path.join("src/main/nested").is_dir());
+
+        // Nothing is created if any entry escapes the root
+        let bad = ["fine", "../escaped"];
+        assert!(create_directory_tree(&root.path, &bad).await.is_err());
+        assert!(sync::create_directory_tree(&root.path, &["also_fine", "/abs"]).is_err());
+        assert!(!root.path.join("fine").exists());
+        assert!(!root.path.join("also_fine").exists());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn clone_structure_without_files() -> Result<()> {
+        let root = TempPath::new("clone_structure_without_files").await?;
+        root.multi_folder(vec!["src/a/b", "src/c", "src/d/e/f"])
+            .await?;
+        root.multi_file(vec!["src/top.txt", "src/a/b/deep.txt"])
+            .await?;
+        std::fs::write(root.path.join("src/c/data.bin"), "contents")?;
+        let src = root.path.join("src");
+
+        let sorted = |mut v: Vec<PathBuf>| {
+            v.sort();
+            v
+        };
+
+        let dst = root.path.join("skeleton");
+        clone_directory_structure(&src, &dst, false).await?;
+        assert_eq!(
+            sorted(list_nested_directories_relative(&dst).await?),
+            sorted(list_nested_directories_relative(&src).await?)
+        );
+        assert!(list_nested_files(&dst).await?.is_empty());
+
+        let dst = root.path.join("placeholders");
+        sync::clone_directory_structure(&src, &dst, true)?;
+        assert_eq!(
+            sorted(sync::list_nested_directories_relative(&dst)?),
+            sorted(sync::list_nested_directories_relative(&src)?)
+        );
+        assert_eq!(
+            sorted(sync::list_nested_files_relative(&dst)?),
+            sorted(sync::list_nested_files_relative(&src)?)
+        );
+        assert_eq!(std::fs::metadata(dst.join("c/data.bin"))?.len(), 0);
+
+        let missing = root.path.join("missing");
+        let never_made = root.path.join("never_made");
+        assert!(clone_directory_structure(&missing, &never_made, false)
+            .await
+            .is_err());
+        assert!(!never_made.exists());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn rename_files_with_closure() -> Result<()> {
+        let root = TempPath::new("rename_files_with_closure").await?;
+        root.multi_folder(vec!["Sub"]).await?;
+        root.multi_file(vec!["README.MD", "Main.RS", "Sub/Nested.TXT", "skip.txt"])
+            .await?;
+
+        let lowercase = |file: &Path| {
+            let name = file.file_name()?.to_str()?;
+            Some(PathBuf::from(name.to_lowercase()))
+        };
+        let renamed = rename_files(&root.path, true, None, lowercase).await?;
+        assert_eq!(renamed.len(), 3);
+        assert!(renamed.contains(&(root.path.join("Main.RS"), root.path.join("main.rs"))));
+
+        let mut files = list_nested_files_relative(&root.path).await?;
+        files.sort();
+        let expected: Vec<PathBuf> = ["Sub/nested.txt", "main.rs", "readme.md", "skip.txt"]
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+        assert_eq!(files, expected);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn rename_files_renumbered() -> Result<()> {
+        let root = TempPath::new("rename_files_renumbered").await?;
+        root.multi_file(vec!["b.jpg", "a.jpg", "c.jpg", "notes.txt"])
+            .await?;
+
+        let jpgs = FtFilter::Raw(".jpg".to_string());
+        let mut n = 0;
+        let renamed = sync::rename_files(&root.path, false, Some(jpgs), |_| {
+            n += 1;
+            Some(naming::generate_n_digit_name(n, 4, "jpg"))
+        })?;
+        assert_eq!(renamed.len(), 3);
+
+        let mut files = sync::list_files(&root.path)?;
+        files.sort();
+        let expected: Vec<PathBuf> = ["0001.jpg", "0002.jpg", "0003.jpg", "notes.txt"]
+            .iter()
+            .map(|f| root.path.join(f))
+            .collect();
+        assert_eq!(files, expected);
+
+        // Swapping names between files goes through temporary names
+        std::fs::write(root.path.join("0001.jpg"), "one")?;
+        std::fs::write(root.path.join("0002.jpg"), "two")?;
+        let swap = |file: &Path| match file.file_name()?.to_str()? {
+            "0001.jpg" => Some(PathBuf::from("0002.jpg")),
+            "0002.jpg" => Some(PathBuf::from("0001.jpg")),
+            _ => None,
+        };
+        rename_files(&root.path, false, None, swap).await?;
+        assert_eq!(std::fs::read_to_string(root.path.join("0001.jpg"))?, "two");
+        assert_eq!(std::fs::read_to_string(root.path.join("0002.jpg"))?, "one");
+        assert_eq!(sync::list_files(&root.path)?.len(), 4);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn rename_files_collisions() -> Result<()> {
+        let root = TempPath::new("rename_files_collisions").await?;
+        root.multi_file(vec!["a.txt", "b.txt", "keep.txt"]).await?;
+
+        // Two files to the same name
+        let same = |_: &Path| Some(PathBuf::from("same.txt"));
+        assert!(rename_files(&root.path, false, None, same).await.is_err());
+
+        // Over a file that isn't being renamed
+        let a_only =
+            |file: &Path| (file.file_name()? == "a.txt").then(|| PathBuf::from("keep.txt"));
+        assert!(sync::rename_files(&root.path, false, None, a_only).is_err());
+
+        // Nothing was touched
+        let mut files = list_files(&root.path).await?;
+        files.sort();
+        assert_eq!(
+            files,
+            vec![
+                root.path.join("a.txt"),
+                root.path.join("b.txt"),
+                root.path.join("keep.txt")
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn rename_files_rolls_back_on_failure() -> Result<()> {
+        let root = TempPath::new("rename_files_rollback").await?;
+        root.multi_file(vec!["a.txt", "b.txt", "c.txt"]).await?;
+        for name in ["a", "b", "c"] {
+            std::fs::write(root.path.join(format!("{name}.txt")), name)?;
+        }
+
+        // `a` and `b` swap so everything is staged, `c` can't go into a missing directory
+        let missing = root.path.join("missing/c.txt");
+        let swap = |file: &Path| match file.file_name()?.to_str()? {
+            "a.txt" => Some(PathBuf::from("b.txt")),
+            "b.txt" => Some(PathBuf::from("a.txt")),
+            _ => Some(missing.clone()),
+        };
+
+        let contents = || -> Result<Vec<(PathBuf, String)>> {
+            let mut files = sync::list_files(&root.path)?;
+            files.sort();
+            files
+                .into_iter()
+                .map(|f| Ok((f.clone(), std::fs::read_to_string(f)?)))
+                .collect()
+        };
+        let before = contents()?;
+
+        assert!(rename_files(&root.path, false, None, swap).await.is_err());
+        assert_eq!(contents()?, before);
+
+        assert!(sync::rename_files(&root.path, false, None, swap).is_err());
+        assert_eq!(contents()?, before);
+
+        // Without staging the renames done before the failure are undone too
+        let plain = |file: &Path| match file.file_name()?.to_str()? {
+            "c.txt" => Some(missing.clone()),
+            name => Some(PathBuf::from(format!("renamed_{name}"))),
+        };
+        assert!(rename_files(&root.path, false, None, plain).await.is_err());
+        assert_eq!(contents()?, before);
+        assert!(sync::rename_files(&root.path, false, None, plain).is_err());
+        assert_eq!(contents()?, before);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn move_matching_files() -> Result<()> {
+        let root = TempPath::new("move_matching_files").await?;
+        root.multi_folder(vec!["downloads/images"]).await?;
+        root.multi_file(vec![
+            "downloads/cat.jpg",
+            "downloads/dog.jpg",
+            "downloads/report.pdf",
+            "downloads/images/cat.jpg",
+        ])
+        .await?;
+        std::fs::write(root.path.join("downloads/cat.jpg"), "new cat")?;
+        let src = root.path.join("downloads");
+        let dst = src.join("images");
+
+        let jpgs = FtFilter::Regex(Regex::new(r"\.jpg$")?);
+        let mut moved = move_files_matching(&src, &dst, &jpgs).await?;
+        moved.sort();
+        assert_eq!(moved, vec![dst.join("cat (1).jpg"), dst.join("dog.jpg")]);
+
+        assert!(list_files_with_filter(&src, &jpgs).await?.is_empty());
+        assert_eq!(list_files(&src).await?, vec![src.join("report.pdf")]);
+        let mut images = list_files(&dst).await?;
+        images.sort();
+        assert_eq!(
+            images,
+            vec![
+                dst.join("cat (1).jpg"),
+                dst.join("cat.jpg"),
+                dst.join("dog.jpg")
+            ]
+        );
+        assert_eq!(std::fs::read_to_string(dst.join("cat (1).jpg"))?, "new cat");
+
+        // Sync version into a destination that doesn't exist yet
+        let pdfs = root.path.join("pdfs");
+        let moved = sync::move_files_matching(&src, &pdfs, FtFilter::Raw(".pdf".to_string()))?;
+        assert_eq!(moved, vec![pdfs.join("report.pdf")]);
+        assert!(sync::list_files(&src)?.is_empty());
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn free_paths_keep_non_utf8_names() -> Result<()> {
+        use std::os::unix::ffi::OsStrExt;
+
+        let root = TempPath::new("free_paths_non_utf8").await?;
+        let name = Path::new(OsStr::from_bytes(b"caf\xe9.txt"));
+        std::fs::File::create(root.path.join(name))?;
+
+        assert_eq!(
+            util::free_path(&root.path, name),
+            root.path.join(OsStr::from_bytes(b"caf\xe9 (1).txt"))
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn partition_files_round_robin() -> Result<()> {
+        let root = TempPath::new("partition_files_round_robin").await?;
+        let names: Vec<String> = (0..10).map(|i| format!("file_{i}.dat")).collect();
+        root.multi_file(names.clone()).await?;
+
+        let buckets =
+            partition_files_into_directories(&root.path, 3, FtPartition::RoundRobin).await?;
+        let sizes: Vec<usize> = buckets.iter().map(|b| b.len()).collect();
+        assert_eq!(sizes, vec![4, 3, 3]);
+        assert_eq!(buckets[1][0], root.path.join("0001/file_1.dat"));
+
+        // Every file is accounted for and nothing is left behind
+        assert!(list_files(&root.path).await?.is_empty());
+        let mut moved: Vec<String> = list_nested_files(&root.path)
+            .await?
+            .iter()
+            .map(|f| f.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        moved.sort();
+        let mut expected = names;
+        expected.sort();
+        assert_eq!(moved, expected);
+
+        assert!(
+            partition_files_into_directories(&root.path, 0, FtPartition::RoundRobin)
+                .await
+                .is_err()
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn partition_files_by_hash_is_stable() -> Result<()> {
+        let first = TempPath::new("partition_files_by_hash_first").await?;
+        let second = TempPath::new("partition_files_by_hash_second").await?;
+        first
+            .multi_file(vec!["a.txt", "b.txt", "c.txt", "d.txt"])
+            .await?;
+        second.multi_file(vec!["c.txt", "x.txt", "a.txt"]).await?;
+
+        let bucket_of = |buckets: &[Vec<PathBuf>], name: &str| {
+            buckets
+                .iter()
+                .position(|b| b.iter().any(|f| f.ends_with(name)))
+                .unwrap()
+        };
+
+        let one = sync::partition_files_into_directories(&first.path, 3, FtPartition::Hash)?;
+        let two = partition_files_into_directories(&second.path, 3, FtPartition::Hash).await?;
+        assert_eq!(one.iter().map(|b| b.len()).sum::<usize>(), 4);
+        assert_eq!(two.iter().map(|b| b.len()).sum::<usize>(), 3);
+        assert_eq!(bucket_of(&one, "a.txt"), bucket_of(&two, "a.txt"));
+        assert_eq!(bucket_of(&one, "c.txt"), bucket_of(&two, "c.txt"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn canonical_filter_matches_absolute_paths() -> Result<()> {
+        let root = TempPath::new("canonical_filter_matches_absolute").await?;
+        root.multi_folder(vec!["data/keep", "data/skip"]).await?;
+        root.multi_file(vec!["data/keep/a.txt", "data/skip/b.txt"])
+            .await?;
+
+        // Listing through `..` means the absolute prefix never appears in the results
+        let listing_root = root.path.join("data/skip/..");
+        let prefix = std::fs::canonicalize(&root.path)?.join("data/keep");
+        let plain = FtFilter::Path(prefix.clone());
+        assert!(list_nested_files_with_filter(&listing_root, &plain)
+            .await?
+            .is_empty());
+
+        let canonical = FtFilter::Canonical(Box::new(plain));
+        let result = list_nested_files_with_filter(&listing_root, &canonical).await?;
+        assert_eq!(result, vec![listing_root.join("keep/a.txt")]);
+        assert_eq!(
+            sync::list_nested_files_with_filter(&listing_root, &canonical)?,
+            result
+        );
+        let scanned = list_nested_files_with_filter_scanned(&listing_root, &canonical).await?;
+        assert_eq!((scanned.matched, scanned.total_scanned), (result, 2));
+
+        // Nesting resolves the already canonical path again
+        let nested = FtFilter::Canonical(Box::new(canonical.clone()));
+        assert_eq!(
+            list_nested_files_with_filter(&listing_root, &nested).await?,
+            sync::list_nested_files_with_filter(&listing_root, &nested)?
+        );
+
+        // Works through a symlinked root too
+        #[cfg(unix)]
+        {
+            let link = root.path.join("link");
+            std::os::unix::fs::symlink(root.path.join("data"), &link)?;
+            let result = list_nested_files_with_filter(&link, &canonical).await?;
+            assert_eq!(result, vec![link.join("keep/a.txt")]);
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn files_iter_is_lazy() -> Result<()> {
+        let root = TempPath::new("files_iter_is_lazy").await?;
+        root.multi_folder(vec!["a/b", "c"]).await?;
+        root.multi_file(vec!["1.txt", "2.log", "a/3.txt", "a/b/4.txt", "c/5.txt"])
+            .await?;
+
+        let mut all = sync::list_files_iter(&root.path, true, None)?.collect::<Result<Vec<_>>>()?;
+        let mut expected = list_nested_files(&root.path).await?;
+        all.sort();
+        expected.sort();
+        assert_eq!(all, expected);
+
+        let top = sync::list_files_iter(&root.path, false, None)?.collect::<Result<Vec<_>>>()?;
+        assert_eq!(top.len(), 2);
+
+        let filter = FtFilter::Raw(".log".to_string());
+        let logs =
+            sync::list_files_iter(&root.path, true, Some(filter))?.collect::<Result<Vec<_>>>()?;
+        assert_eq!(logs, vec![root.path.join("2.log")]);
+
+        // Taking a couple and dropping the rest is fine
+        let mut iter = sync::list_files_iter(&root.path, true, None)?;
+        assert!(iter.next().unwrap().is_ok());
+        assert!(iter.next().unwrap().is_ok());
+        drop(iter);
+
+        assert!(sync::list_files_iter(root.path.join("1.txt"), true, None).is_err());
+        assert!(sync::list_files_iter(root.path.join("missing"), true, None).is_err());
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    #[ignore = "permissions are not enforced for root, run with --ignored as an unprivileged user"]
+    async fn files_iter_continues_past_unreadable_directories() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = TempPath::new("files_iter_unreadable").await?;
+        root.multi_folder(vec!["open", "locked"]).await?;
+        root.multi_file(vec!["top.txt", "open/a.txt", "locked/b.txt"])
+            .await?;
+
+        let locked = root.path.join("locked");
+        std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o000))?;
+        assert!(
+            std::fs::read_dir(&locked).is_err(),
+            "permissions aren't enforced for this user"
+        );
+
+        let (ok, errors): (Vec<_>, Vec<_>) =
+            sync::list_files_iter(&root.path, true, None)?.partition(|r| r.is_ok());
+        assert_eq!(ok.len(), 2);
+        assert_eq!(errors.len(), 1);
+
+        std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o755))?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn directory_emptiness() -> Result<()> {
+        let root = TempPath::new("directory_emptiness").await?;
+        root.multi_folder(vec!["empty", "hidden", "nested/inner"])
+            .await?;
+        root.multi_file(vec!["hidden/.keep", "file.txt"]).await?;
+
+        assert!(is_directory_empty(root.path.join("empty")).await?);
+        assert!(sync::is_directory_empty(root.path.join("empty"))?);
+
+        assert!(!is_directory_empty(root.path.join("hidden")).await?);
+        assert!(!sync::is_directory_empty(root.path.join("hidden"))?);
+        assert!(!is_directory_empty(root.path.join("nested")).await?);
+
+        assert!(is_directory_empty(root.path.join("file.txt"))
+            .await
+            .is_err());
+        assert!(sync::is_directory_empty(root.path.join("file.txt")).is_err());
+        assert!(is_directory_empty(root.path.join("missing")).await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn numeric_directories_with_step_and_prefix() -> Result<()> {
+        let root = TempPath::new("numeric_directories_ext").await?;
+
+        create_numeric_directories_ext(&root.path, 0, 15, 5, 4, "batch_", "").await?;
+        let mut dirs = list_directories(&root.path).await?;
+        dirs.sort();
+        assert_eq!(
+            dirs,
+            vec![
+                root.path.join("batch_0000"),
+                root.path.join("batch_0005"),
+                root.path.join("batch_0010"),
+            ]
+        );
+
+        // ceil((end - start) / step) directories
+        for (start, end, step) in [(3, 20, 4), (0, 7, 7), (10, 11, 3), (5, 5, 2)] {
+            let sub = root.path.join(format!("count_{start}_{end}_{step}"));
+            sync::create_numeric_directories_ext(&sub, start, end, step, 2, "", "d")?;
+            let created = if sub.exists() {
+                sync::list_directories(&sub)?.len()
+            } else {
+                0
+            };
+            assert_eq!(created, (end - start).div_ceil(step));
+        }
+
+        let planned = create_numeric_directories_ext_dry_run(&root.path, 1, 4, 1, 3, "frame", "d")?;
+        assert_eq!(planned[2], root.path.join("frame003.d"));
+
+        // The dry run refuses the same ranges as the real thing instead of panicking
+        assert!(create_numeric_directories_ext_dry_run(&root.path, 0, 10, 0, 2, "", "").is_err());
+        assert!(
+            create_numeric_directories_ext_dry_run(&root.path, 0, usize::MAX, 2, 8, "", "")
+                .is_err()
+        );
+
+        assert!(
+            create_numeric_directories_ext(&root.path, 0, 10, 0, 2, "", "")
+                .await
+                .is_err()
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn numeric_directories_are_capped() -> Result<()> {
+        let root = TempPath::new("numeric_directories_are_capped").await?;
+
+        let err = create_numeric_directories(&root.path, 0, 10_000_000, 8)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("limit"));
+        assert!(
+            sync::create_numeric_directories(&root.path, 0, MAX_NUMERIC_DIRECTORIES + 1, 8)
+                .is_err()
+        );
+        assert!(
+            create_numeric_directories_ext(&root.path, 0, usize::MAX, 2, 8, "", "")
+                .await
+                .is_err()
+        );
+        assert!(list_directories(&root.path).await?.is_empty());
+
+        // Dry runs refuse the same ranges
+        assert!(create_numeric_directories_dry_run(&root.path, 0, 10_000_000, 8).is_err());
+        assert!(
+            create_numeric_directories_dry_run(&root.path, 0, MAX_NUMERIC_DIRECTORIES, 8).is_ok()
+        );
+
+        // Custom limits both ways
+        assert!(
+            create_numeric_directories_with_limit(&root.path, 0, 5, 2, 4)
+                .await
+                .is_err()
+        );
+        sync::create_numeric_directories_with_limit(&root.path, 0, 5, 2, 5)?;
+        assert_eq!(list_directories(&root.path).await?.len(), 5);
+
+        // Normal ranges still work
+        create_numeric_directories(&root.path, 5, 10, 2).await?;
+        assert_eq!(list_directories(&root.path).await?.len(), 10);
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn executable_filter() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = TempPath::new("executable_filter").await?;
+        root.multi_folder(vec!["bin"]).await?;
+        root.multi_file(vec!["bin/run.sh", "bin/notes.txt", "build"])
+            .await?;
+        for (file, mode) in [
+            ("bin/run.sh", 0o755),
+            ("build", 0o744),
+            ("bin/notes.txt", 0o644),
+        ] {
+            std::fs::set_permissions(root.path.join(file), std::fs::Permissions::from_mode(mode))?;
+        }
+
+        let mut result = list_nested_files_with_filter(&root.path, FtFilter::Executable).await?;
+        result.sort();
+        assert_eq!(
+            result,
+            vec![root.path.join("bin/run.sh"), root.path.join("build")]
+        );
+
+        // Directories have execute bits but are never executables
+        let dirs = sync::list_nested_directories_with_filter(&root.path, FtFilter::Executable)?;
+        assert!(dirs.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn empty_file_filters() -> Result<()> {
+        let root = TempPath::new("empty_file_filters").await?;
+        root.multi_folder(vec!["downloads/empty_dir"]).await?;
+        root.multi_file(vec!["downloads/failed.zip"]).await?;
+        std::fs::write(root.path.join("downloads/done.zip"), "data")?;
+
+        let empty = list_nested_files_with_filter(&root.path, FtFilter::Empty).await?;
+        assert_eq!(empty, vec![root.path.join("downloads/failed.zip")]);
+
+        let non_empty = sync::list_nested_files_with_filter(&root.path, FtFilter::NonEmpty)?;
+        assert_eq!(non_empty, vec![root.path.join("downloads/done.zip")]);
+
+        // Directories never match, however empty they are
+        for filter in [FtFilter::Empty, FtFilter::NonEmpty] {
+            let dirs = list_nested_directories_with_filter(&root.path, filter).await?;
+            assert!(dirs.is_empty());
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn recursive_flag_listing() -> Result<()> {
+        let root = TempPath::new("recursive_flag_listing").await?;
+        root.multi_folder(vec!["a/b/c", "d"]).await?;
+        root.multi_file(vec!["1.txt", "a/2.txt", "a/b/c/3.txt", "d/4.txt"])
+            .await?;
+
+        assert_eq!(
+            list_files_rec(&root.path, false).await?,
+            list_files(&root.path).await?
+        );
+        assert_eq!(
+            list_files_rec(&root.path, true).await?,
+            list_nested_files(&root.path).await?
+        );
+        assert_eq!(
+            list_directories_rec(&root.path, false).await?,
+            list_directories(&root.path).await?
+        );
+        assert_eq!(
+            list_directories_rec(&root.path, true).await?,
+            list_nested_directories(&root.path).await?
+        );
+        assert_eq!(list_files_rec(&root.path, true).await?.len(), 4);
+        assert_eq!(list_directories_rec(&root.path, false).await?.len(), 2);
+
+        assert_eq!(sync::list_files_rec(&root.path, false)?.len(), 1);
+        assert_eq!(sync::list_files_rec(&root.path, true)?.len(), 4);
+        assert_eq!(sync::list_directories_rec(&root.path, false)?.len(), 2);
+        assert_eq!(sync::list_directories_rec(&root.path, true)?.len(), 4);
+        assert!(sync::list_files_rec(root.path.join("1.txt"), true).is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn copy_directory_copies_everything() -> Result<()> {
+        let root = TempPath::new("copy_directory_copies_everything").await?;
+        root.multi_folder(vec!["src/a/b", "src/empty"]).await?;
+        std::fs::write(root.path.join("src/top.txt"), "top")?;
+        std::fs::write(root.path.join("src/a/b/deep.txt"), "deep")?;
+        let src = root.path.join("src");
+
+        let dst = root.path.join("copy");
+        copy_directory(&src, &dst).await?;
+        let sorted = |mut v: Vec<DirEntryKind>| {
+            v.sort();
+            v
+        };
+        let relative = |root: &Path| {
+            sync::list_nested_entries(root).map(|entries| {
+                sorted(
+                    entries
+                        .into_iter()
+                        .map(|e| match e {
+                            DirEntryKind::File(p) => {
+                                DirEntryKind::File(p.strip_prefix(root).unwrap().to_path_buf())
+                            }
+                            DirEntryKind::Dir(p) => {
+                                DirEntryKind::Dir(p.strip_prefix(root).unwrap().to_path_buf())
+                            }
+                        })
+                        .collect(),
+                )
+            })
+        };
+        assert_eq!(relative(&dst)?, relative(&src)?);
+        assert_eq!(std::fs::read_to_string(dst.join("a/b/deep.txt"))?, "deep");
+
+        // Overwrites into an existing destination
+        std::fs::write(root.path.join("src/top.txt"), "changed")?;
+        sync::copy_directory(&src, &dst)?;
+        assert_eq!(std::fs::read_to_string(dst.join("top.txt"))?, "changed");
+
+        assert!(copy_directory(&src, src.join("a/inside")).await.is_err());
+        assert!(!src.join("a/inside").exists());
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn copy_directory_preserves_metadata() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+        use std::time::{Duration, SystemTime};
+
+        let root = TempPath::new("copy_directory_preserves_metadata").await?;
+        root.multi_folder(vec!["src/private"]).await?;
+        let secret = root.path.join("src/private/secret.txt");
+        std::fs::write(&secret, "shh")?;
+        std::fs::set_permissions(&secret, std::fs::Permissions::from_mode(0o600))?;
+        let private = root.path.join("src/private");
+        std::fs::set_permissions(&private, std::fs::Permissions::from_mode(0o750))?;
+
+        let old = SystemTime::now() - Duration::from_secs(60 * 60 * 24 * 30);
+        std::fs::File::open(&secret)?.set_modified(old)?;
+        std::fs::File::open(&private)?.set_modified(old)?;
+
+        let close_to = |a: SystemTime, b: SystemTime| {
+            let diff = a
+                .duration_since(b)
+                .or_else(|_| b.duration_since(a))
+                .unwrap();
+            diff < Duration::from_secs(2)
+        };
+        let mode = |p: &Path| std::fs::metadata(p).map(|m| m.permissions().mode() & 0o777);
+
+        let options = FtCopyOptions {
+            preserve_metadata: true,
+            ..Default::default()
+        };
+        let dst = root.path.join("backup");
+        copy_directory_with_options(root.path.join("src"), &dst, options).await?;
+        let copy = dst.join("private/secret.txt");
+        assert_eq!(mode(&copy)?, 0o600);
+        assert!(close_to(std::fs::metadata(&copy)?.modified()?, old));
+        assert_eq!(mode(&dst.join("private"))?, 0o750);
+        assert!(close_to(
+            std::fs::metadata(dst.join("private"))?.modified()?,
+            old
+        ));
+
+        // Without the option the copy gets a fresh modification time
+        let plain = root.path.join("plain");
+        sync::copy_directory(root.path.join("src"), &plain)?;
+        let copy = plain.join("private/secret.txt");
+        assert!(!close_to(std::fs::metadata(&copy)?.modified()?, old));
+
+        let dst = root.path.join("sync_backup");
+        sync::copy_directory_with_options(root.path.join("src"), &dst, options)?;
+        assert!(close_to(
+            std::fs::metadata(dst.join("private/secret.txt"))?.modified()?,
+            old
+        ));
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn copy_directory_recreates_symlinks() -> Result<()> {
+        use std::os::unix::fs::symlink;
+
+        let root = TempPath::new("copy_directory_recreates_symlinks").await?;
+        root.multi_folder(vec!["src/data"]).await?;
+        std::fs::write(root.path.join("src/data/real.txt"), "real")?;
+        let src = root.path.join("src");
+        symlink("data/real.txt", src.join("file_link"))?;
+        symlink("data", src.join("dir_link"))?;
+        symlink("missing.txt", src.join("dangling"))?;
+
+        let dst = root.path.join("copy");
+        copy_directory(&src, &dst).await?;
+        for (link, target) in [
+            ("file_link", "data/real.txt"),
+            ("dir_link", "data"),
+            ("dangling", "missing.txt"),
+        ] {
+            let copied = dst.join(link);
+            assert!(copied.symlink_metadata()?.is_symlink());
+            assert_eq!(std::fs::read_link(&copied)?, PathBuf::from(target));
+        }
+        // The relative links resolve inside the copy, not back into the source
+        assert_eq!(
+            std::fs::canonicalize(dst.join("file_link"))?,
+            std::fs::canonicalize(dst.join("data/real.txt"))?
+        );
+
+        // Copying again replaces the links rather than failing on them
+        sync::copy_directory(&src, &dst)?;
+        assert!(dst.join("file_link").symlink_metadata()?.is_symlink());
+
+        let options = FtCopyOptions {
+            dereference_symlinks: true,
+            ..Default::default()
+        };
+        let deref = root.path.join("deref");
+        sync::copy_directory_with_options(&src, &deref, options)?;
+        assert!(deref.join("file_link").symlink_metadata()?.is_file());
+        assert_eq!(std::fs::read_to_string(deref.join("file_link"))?, "real");
+        assert!(deref.join("dir_link").symlink_metadata()?.is_dir());
+        assert!(deref.join("dir_link/real.txt").is_file());
+        assert!(deref.join("dangling").symlink_metadata().is_err());
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn copy_directory_dereferencing_stops_at_symlink_cycles() -> Result<()> {
+        let root = TempPath::new("copy_directory_symlink_cycles").await?;
+        root.multi_folder(vec!["src/nested"]).await?;
+        root.multi_file(vec!["src/nested/file.txt"]).await?;
+        let src = root.path.join("src");
+        std::os::unix::fs::symlink("..", src.join("nested/up"))?;
+        std::os::unix::fs::symlink(".", src.join("itself"))?;
+
+        let options = FtCopyOptions {
+            dereference_symlinks: true,
+            ..Default::default()
+        };
+        let err = copy_directory_with_options(&src, root.path.join("async"), options)
+            .await
+            .unwrap_err();
+        assert!(format!("{err:#}").contains("symlink cycle"));
+
+        let err =
+            sync::copy_directory_with_options(&src, root.path.join("sync"), options).unwrap_err();
+        assert!(format!("{err:#}").contains("symlink cycle"));
+
+        // Recreating the links never follows them
+        copy_directory(&src, root.path.join("links")).await?;
+        assert!(root
+            .path
+            .join("links/itself")
+            .symlink_metadata()?
+            .is_symlink());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn remove_directory_where_prunes_emptied_dirs() -> Result<()> {
+        let root = TempPath::new("remove_directory_where_prunes_emptied_dirs").await?;
+        root.multi_folder(vec!["cache/a/b", "src/scratch", "empty"])
+            .await?;
+        root.multi_file(vec![
+            "cache/a/b/one.tmp",
+            "cache/a/two.tmp",
+            "src/main.rs",
+            "src/scratch/three.tmp",
+            "src/keep.txt",
+        ])
+        .await?;
+        let is_tmp = |p: &Path| p.extension().is_some_and(|ext| ext == "tmp");
+
+        let mut removed = remove_directory_where(&root.path, is_tmp).await?;
+        removed.sort();
+        assert_eq!(
+            removed,
+            vec![
+                root.path.join("cache/a/b/one.tmp"),
+                root.path.join("cache/a/two.tmp"),
+                root.path.join("src/scratch/three.tmp"),
+            ]
+        );
+
+        // Directories left with nothing in them are gone, the rest survive untouched
+        assert!(!root.path.join("cache").exists());
+        assert!(!root.path.join("src/scratch").exists());
+        assert!(root.path.join("src/main.rs").is_file());
+        assert!(root.path.join("src/keep.txt").is_file());
+        assert!(root.path.join("empty").is_dir());
+        assert!(root.path.is_dir());
+
+        assert!(sync::remove_directory_where(&root.path, is_tmp)?.is_empty());
+        let removed = sync::remove_directory_where(&root.path, |p| p.ends_with("main.rs"))?;
+        assert_eq!(removed, vec![root.path.join("src/main.rs")]);
+        assert!(root.path.join("src/keep.txt").is_file());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn concurrent_listing_is_complete() -> Result<()> {
+        let root = TempPath::new("concurrent_listing_is_complete").await?;
+        let dirs: Vec<String> = (0..6)
+            .flat_map(|i| (0..3).map(move |j| format!("d{i}/sub{j}/leaf")))
+            .collect();
+        root.multi_folder(dirs.iter().map(String::as_str).collect())
+            .await?;
+        let files: Vec<String> = dirs
+            .iter()
+            .flat_map(|d| [format!("{d}/a.txt"), d.replace("/leaf", "/b.txt")])
+            .collect();
+        root.multi_file(files.iter().map(String::as_str).collect())
+            .await?;
+
+        let mut expected = list_nested_files(&root.path).await?;
+        expected.sort();
+        let mut expected_dirs = list_nested_directories(&root.path).await?;
+        expected_dirs.sort();
+
+        for max_concurrency in [1, 4] {
+            let mut found = list_nested_files_concurrent(&root.path, max_concurrency).await?;
+            found.sort();
+            assert_eq!(found, expected);
+
+            let mut found = list_nested_directories_concurrent(&root.path, max_concurrency).await?;
+            found.sort();
+            assert_eq!(found, expected_dirs);
+        }
+
+        assert!(list_nested_files_concurrent(&root.path, 0).await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn detailed_entries_report_metadata() -> Result<()> {
+        let root = TempPath::new("detailed_entries_report_metadata").await?;
+        root.multi_folder(vec!["photos/2024"]).await?;
+        std::fs::write(root.path.join("notes.txt"), "hello")?;
+        std::fs::write(root.path.join("photos/2024/cat.jpg"), vec![0u8; 1024])?;
+
+        let by_name = |mut items: Vec<FtFileInfo>| {
+            items.sort_by(|a, b| a.path.cmp(&b.path));
+            items
+        };
+
+        let items = by_name(list_entries_detailed(&root.path).await?);
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].path, root.path.join("notes.txt"));
+        assert_eq!(items[0].name, "notes.txt");
+        assert!(!items[0].is_dir);
+        assert_eq!(items[0].len, 5);
+        assert_eq!(
+            items[0].modified,
+            Some(std::fs::metadata(root.path.join("notes.txt"))?.modified()?)
+        );
+        assert_eq!(items[1].name, "photos");
+        assert!(items[1].is_dir);
+
+        let items = by_name(list_nested_entries_detailed(&root.path).await?);
+        let summary: Vec<(&str, bool, Option<u64>)> = items
+            .iter()
+            .map(|i| (i.name.as_str(), i.is_dir, (!i.is_dir).then_some(i.len)))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("notes.txt", false, Some(5)),
+                ("photos", true, None),
+                ("2024", true, None),
+                ("cat.jpg", false, Some(1024)),
+            ]
+        );
+        assert_eq!(items[3].path, root.path.join("photos/2024/cat.jpg"));
+
+        assert_eq!(
+            by_name(sync::list_nested_entries_detailed(&root.path)?),
+            items
+        );
+        assert_eq!(sync::list_entries_detailed(&root.path)?.len(), 2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn ensure_directory_rejects_files() -> Result<()> {
+        let root = TempPath::new("ensure_directory_rejects_files").await?;
+        root.multi_file(vec!["taken"]).await?;
+        let taken = root.path.join("taken");
+
+        assert!(ensure_directory(&taken).await.is_err());
+        assert!(ensure_directory_reported(&taken).await.is_err());
+        assert!(sync::ensure_directory(&taken).is_err());
+        assert!(sync::ensure_directory_reported(&taken).is_err());
+        assert!(taken.is_file());
+
+        // Existing directories are still fine
+        assert!(!ensure_directory_reported(&root.path).await?);
+        assert!(!sync::ensure_directory_reported(&root.path)?);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn or_empty_listings() -> Result<()> {
+        let root = TempPath::new("or_empty_listings").await?;
+        root.multi_folder(vec!["logs/old"]).await?;
+        root.multi_file(vec!["logs/app.log", "logs/old/app.1.log", "file.txt"])
+            .await?;
+
+        // Missing directories are just empty
+        let missing = root.path.join("missing");
+        assert!(list_files_or_empty(&missing).await?.is_empty());
+        assert!(list_nested_files_or_empty(&missing).await?.is_empty());
+        assert!(list_directories_or_empty(&missing).await?.is_empty());
+        assert!(list_nested_directories_or_empty(&missing).await?.is_empty());
+        assert!(sync::list_files_or_empty(&missing)?.is_empty());
+        assert!(sync::list_nested_directories_or_empty(&missing)?.is_empty());
+
+        // Files are still an error
+        let file = root.path.join("file.txt");
+        assert!(list_files_or_empty(&file).await.is_err());
+        assert!(list_nested_directories_or_empty(&file).await.is_err());
+        assert!(sync::list_nested_files_or_empty(&file).is_err());
+        assert!(sync::list_directories_or_empty(&file).is_err());
+
+        // Everything else lists as normal
+        let logs = root.path.join("logs");
+        assert_eq!(list_files_or_empty(&logs).await?, list_files(&logs).await?);
+        assert_eq!(list_nested_files_or_empty(&logs).await?.len(), 2);
+        assert_eq!(
+            sync::list_nested_directories_or_empty(&logs)?,
+            vec![logs.join("old")]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn groups_files_by_lowercased_extension() -> Result<()> {
+        let root = TempPath::new("groups_files_by_lowercased_extension").await?;
+        root.multi_folder(vec!["trip"]).await?;
+        root.multi_file(vec![
+            "a.JPG",
+            "b.jpg",
+            "c.Jpg",
+            "d.jpeg",
+            "README",
+            "trip/e.JPG",
+            "trip/f.png",
+        ])
+        .await?;
+
+        let groups = group_files_by_extension(&root.path, false).await?;
+        let mut keys: Vec<&str> = groups.keys().map(String::as_str).collect();
+        keys.sort();
+        assert_eq!(keys, vec!["", "jpeg", "jpg"]);
+        assert_eq!(
+            groups["jpg"],
+            vec![
+                root.path.join("a.JPG"),
+                root.path.join("b.jpg"),
+                root.path.join("c.Jpg")
+            ]
+        );
+        assert_eq!(groups[""], vec![root.path.join("README")]);
+
+        let groups = sync::group_files_by_extension(&root.path, true)?;
+        assert_eq!(groups["jpg"].len(), 4);
+        assert!(groups["jpg"].contains(&root.path.join("trip/e.JPG")));
+        assert_eq!(groups["png"], vec![root.path.join("trip/f.png")]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn largest_files_keeps_top_n() -> Result<()> {
+        let root = TempPath::new("largest_files_keeps_top_n").await?;
+        root.multi_folder(vec!["a/b", "c"]).await?;
+        let sizes = [
+            ("tiny.txt", 1),
+            ("a/big.bin", 4096),
+            ("a/b/huge.bin", 10_000),
+            ("a/b/small.txt", 20),
+            ("c/medium.dat", 500),
+        ];
+        for (file, len) in sizes {
+            std::fs::write(root.path.join(file), vec![0u8; len])?;
+        }
+
+        let largest = largest_files(&root.path, 3).await?;
+        assert_eq!(
+            largest,
+            vec![
+                (root.path.join("a/b/huge.bin"), 10_000),
+                (root.path.join("a/big.bin"), 4096),
+                (root.path.join("c/medium.dat"), 500),
+            ]
+        );
+
+        // Asking for more than there are returns everything
+        let all = sync::largest_files(&root.path, 20)?;
+        assert_eq!(all.len(), 5);
+        assert_eq!(all[4], (root.path.join("tiny.txt"), 1));
+
+        assert!(largest_files(&root.path, 0).await?.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn oldest_and_newest_files() -> Result<()> {
+        use std::time::Duration;
+
+        let root = TempPath::new("oldest_and_newest_files").await?;
+        root.multi_folder(vec!["archive"]).await?;
+        let now = SystemTime::now();
+        let day = Duration::from_secs(60 * 60 * 24);
+        let ages = [
+            ("today.log", 0),
+            ("yesterday.log", 1),
+            ("last_week.log", 7),
+            ("last_month.log", 30),
+            ("archive/last_year.log", 365),
+        ];
+        for (file, days) in ages {
+            let path = root.path.join(file);
+            std::fs::write(&path, "")?;
+            std::fs::File::options()
+                .write(true)
+                .open(&path)?
+                .set_modified(now - day * days)?;
+        }
+        let modified = |file: &str| now - day * ages.iter().find(|a| a.0 == file).unwrap().1;
+
+        let oldest = oldest_files(&root.path, 2, false).await?;
+        assert_eq!(
+            oldest,
+            vec![
+                (root.path.join("last_month.log"), modified("last_month.log")),
+                (root.path.join("last_week.log"), modified("last_week.log")),
+            ]
+        );
+
+        let oldest = sync::oldest_files(&root.path, 1, true)?;
+        assert_eq!(oldest[0].0, root.path.join("archive/last_year.log"));
+
+        let newest = newest_files(&root.path, 3, true).await?;
+        let names: Vec<PathBuf> = newest.into_iter().map(|(file, _)| file).collect();
+        assert_eq!(
+            names,
+            vec![
+                root.path.join("today.log"),
+                root.path.join("yesterday.log"),
+                root.path.join("last_week.log"),
+            ]
+        );
+        assert_eq!(sync::newest_files(&root.path, 10, false)?.len(), 4);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn limited_listing_stops_early() -> Result<()> {
+        let root = TempPath::new("limited_listing_stops_early").await?;
+        root.multi_folder(vec!["a", "b"]).await?;
+        root.multi_file(vec!["1.txt", "2.txt", "a/3.txt", "a/4.txt", "b/5.log"])
+            .await?;
+
+        let all = list_nested_files(&root.path).await?;
+        let limited = list_files_limited(&root.path, true, None, 3).await?;
+        assert_eq!(limited.len(), 3);
+        assert!(limited.iter().all(|f| all.contains(f)));
+        assert_eq!(
+            sync::list_files_limited(&root.path, true, None, 3)?.len(),
+            3
+        );
+
+        // Fewer files than the limit returns them all
+        assert_eq!(
+            list_files_limited(&root.path, true, None, 50).await?.len(),
+            5
+        );
+        assert_eq!(
+            list_files_limited(&root.path, false, None, 50).await?.len(),
+            2
+        );
+        let filter = FtFilter::Raw(".log".to_string());
+        assert_eq!(
+            sync::list_files_limited(&root.path, true, Some(filter), 50)?,
+            vec![root.path.join("b/5.log")]
+        );
+
+        assert!(list_files_limited(&root.path, true, None, 0)
+            .await?
+            .is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn concurrent_listing_on_explicit_handle() -> Result<()> {
+        let pool = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(2)
+            .enable_all()
+            .build()?;
+        let caller = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+
+        caller.block_on(async {
+            let root = TempPath::new("concurrent_listing_on_explicit_handle").await?;
+            root.multi_folder(vec!["a/b", "c"]).await?;
+            root.multi_file(vec!["1.txt", "a/2.txt", "a/b/3.txt", "c/4.txt"])
+                .await?;
+
+            let mut files = list_nested_files_concurrent_on(pool.handle(), &root.path, 2).await?;
+            files.sort();
+            let mut expected = list_nested_files(&root.path).await?;
+            expected.sort();
+            assert_eq!(files, expected);
+
+            let dirs = list_nested_directories_concurrent_on(pool.handle(), &root.path, 1).await?;
+            assert_eq!(dirs.len(), 3);
+
+            Ok(())
+        })
+    }
+
+    #[tokio::test]
+    async fn blocking_listing_matches_async() -> Result<()> {
+        let root = TempPath::new("blocking_listing_matches_async").await?;
+        root.multi_folder(vec!["a/b/c", "d"]).await?;
+        root.multi_file(vec![
+            "1.txt",
+            "a/2.txt",
+            "a/b/3.txt",
+            "a/b/c/4.txt",
+            "d/5.txt",
+        ])
+        .await?;
+
+        let sorted = |mut items: Vec<PathBuf>| {
+            items.sort();
+            items
+        };
+        assert_eq!(
+            sorted(list_nested_files_blocking(&root.path).await?),
+            sorted(list_nested_files(&root.path).await?)
+        );
+        assert_eq!(
+            sorted(list_nested_directories_blocking(&root.path).await?),
+            sorted(list_nested_directories(&root.path).await?)
+        );
+        assert!(list_nested_files_blocking(root.path.join("1.txt"))
+            .await
+            .is_err());
 
-        assert!(single_path.exists());
+        Ok(())
+    }
 
-        // Nested directories
-        let nested_path = tmp.join("create_dir/test/this/is/nested");
-        ensure_directory(&nested_path)
+    #[tokio::test]
+    async fn scanned_results_tell_empty_from_unmatched() -> Result<()> {
+        let root = TempPath::new("scanned_results_tell_empty_from_unmatched").await?;
+        root.multi_folder(vec!["docs/old", "empty"]).await?;
+        root.multi_file(vec!["docs/a.md", "docs/b.md", "docs/old/c.log"])
+            .await?;
+        let logs = FtFilter::Raw(".log".to_string());
+
+        // Files were there, none of them matched
+        let result = list_files_with_filter_scanned(root.path.join("docs"), &logs).await?;
+        assert_eq!(result.total_scanned, 2);
+        assert!(result.matched.is_empty());
+        assert!(!result.nothing_scanned());
+
+        let result = list_nested_files_with_filter_scanned(&root.path, &logs).await?;
+        assert_eq!(result.total_scanned, 3);
+        assert_eq!(result.matched, vec![root.path.join("docs/old/c.log")]);
+
+        // Nothing there to match in the first place
+        let result = sync::list_nested_files_with_filter_scanned(root.path.join("empty"), &logs)?;
+        assert!(result.nothing_scanned());
+        assert!(result.matched.is_empty());
+        assert_eq!(
+            sync::list_files_with_filter_scanned(root.path.join("docs"), &logs)?.total_scanned,
+            2
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn custom_filter_uses_closure() -> Result<()> {
+        let root = TempPath::new("lists_custom_filter").await?;
+        root.multi_folder(vec!["sub"]).await?;
+        root.multi_file(vec!["ab.txt", "abc.txt", "sub/abcd.txt", "sub/a.txt"])
+            .await?;
+
+        // Even byte length names only
+        let filter = FtFilter::custom(|path| path.file_name().is_some_and(|n| n.len() % 2 == 0));
+
+        let mut result = list_nested_files_with_filter(&root.path, &filter).await?;
+        result.sort();
+        assert_eq!(
+            result,
+            vec![root.path.join("ab.txt"), root.path.join("sub/abcd.txt")]
+        );
+
+        let mut result = sync::list_nested_files_with_filter(&root.path, &filter)?;
+        result.sort();
+        assert_eq!(result.len(), 2);
+
+        assert_eq!(filter, filter.clone());
+        assert_ne!(filter, FtFilter::custom(|_| true));
+        assert_eq!(format!("{filter:?}"), "Custom(..)");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn deeply_nested_async_walk_matches_sync() -> Result<()> {
+        let root = TempPath::new("walk_deeply_nested").await?;
+
+        // 50 levels, each with a file and a sibling directory
+        let mut level = PathBuf::new();
+        for depth in 0..50 {
+            level = level.join(format!("d{depth}"));
+            root.multi_folder(vec![level.clone(), level.join("side")])
+                .await?;
+            root.multi_file(vec![level.join(format!("f{depth}.txt"))])
+                .await?;
+        }
+
+        let files = list_nested_files(&root.path).await?;
+        assert_eq!(files.len(), 50);
+        assert_eq!(files, sync::list_nested_files(&root.path)?);
+
+        let dirs = list_nested_directories(&root.path).await?;
+        assert_eq!(dirs.len(), 100);
+        assert_eq!(dirs, sync::list_nested_directories(&root.path)?);
+
+        let entries = list_nested_entries(&root.path).await?;
+        assert_eq!(entries, sync::list_nested_entries(&root.path)?);
+        assert!(entries
+            .iter()
+            .any(|e| e.path().ends_with(level.join("f49.txt"))));
+
+        let filter = FtFilter::Name("side".to_string());
+        assert_eq!(
+            list_nested_directories_with_filter(&root.path, &filter).await?,
+            sync::list_nested_directories_with_filter(&root.path, &filter)?
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn multi_root_listings_dedup_overlapping_roots() -> Result<()> {
+        let root = TempPath::new("lists_multi_root").await?;
+        let other = TempPath::new("lists_multi_root_other").await?;
+        root.multi_folder(vec!["nested/deeper"]).await?;
+        root.multi_file(vec!["a.txt", "nested/b.txt", "nested/deeper/c.log"])
+            .await?;
+        other.multi_file(vec!["d.txt"]).await?;
+
+        let nested = root.path.join("nested");
+        let roots = [root.path.clone(), nested.clone(), other.path.clone()];
+
+        // Files under `nested` are listed twice without dedup
+        let all = list_nested_files_multi(&roots, false).await?;
+        assert_eq!(all.len(), 6);
+
+        let mut deduped = list_nested_files_multi(&roots, true).await?;
+        assert_eq!(deduped.len(), 4);
+        assert_eq!(deduped.last(), Some(&other.path.join("d.txt")));
+        deduped.sort();
+        let mut expected = vec![
+            root.path.join("a.txt"),
+            nested.join("b.txt"),
+            nested.join("deeper/c.log"),
+            other.path.join("d.txt"),
+        ];
+        expected.sort();
+        assert_eq!(deduped, expected);
+        assert_eq!(sync::list_nested_files_multi(&roots, true)?.len(), 4);
+        assert_eq!(sync::list_nested_files_multi(&roots, false)?, all);
+
+        let filter = FtFilter::Raw("txt".to_string());
+        let txt = list_nested_files_multi_with_filter(&roots, &filter, true).await?;
+        assert_eq!(txt.len(), 3);
+        assert_eq!(
+            sync::list_nested_files_multi_with_filter(&roots, &filter, true)?,
+            txt
+        );
+
+        let dirs = list_nested_directories_multi(&roots, true).await?;
+        assert_eq!(dirs, vec![nested.clone(), nested.join("deeper")]);
+        let dirs = sync::list_nested_directories_multi_with_filter(&roots, &filter, false)?;
+        assert!(dirs.is_empty());
+        let filter = FtFilter::Name("deeper".to_string());
+        let dirs = list_nested_directories_multi_with_filter(&roots, &filter, false).await?;
+        assert_eq!(dirs.len(), 2);
+
+        assert!(list_nested_files_multi(&[root.path.join("missing")], true)
             .await
-            .context("create directory nested")?;
+            .is_err());
+        assert!(sync::list_nested_files_multi(&[root.path.join("a.txt")], true).is_err());
 
-        assert!(nested_path.exists());
+        // The listings can run on another task
+        let spawned = tokio::spawn(async move {
+            let files = list_nested_files_multi(&roots, true).await?;
+            let dirs = list_nested_directories_multi_with_filter(&roots, &filter, false).await?;
+            anyhow::Ok((files.len(), dirs.len()))
+        });
+        assert_eq!(spawned.await??, (4, 2));
 
-        std::fs::remove_dir_all(single_path)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn listing_stats_count_the_traversal() -> Result<()> {
+        let root = TempPath::new("lists_with_stats").await?;
+        root.multi_folder(vec!["a/b", "c"]).await?;
+        root.multi_file(vec!["x.txt", "a/y.txt", "a/b/z.log", "c/w.txt"])
+            .await?;
+
+        let filter = FtFilter::Raw("txt".to_string());
+        let (files, stats) = list_nested_files_with_stats(&root.path, &filter).await?;
+        assert_eq!(stats.matched, files.len());
+        assert_eq!(files.len(), 3);
+        // The root, `a`, `a/b` and `c`
+        assert_eq!(stats.dirs_visited, 4);
+        assert_eq!(stats.entries_seen, 7);
+
+        let (sync_files, sync_stats) = sync::list_nested_files_with_stats(&root.path, &filter)?;
+        assert_eq!(sync_files, files);
+        assert_eq!(
+            (
+                sync_stats.dirs_visited,
+                sync_stats.entries_seen,
+                sync_stats.matched
+            ),
+            (4, 7, 3)
+        );
+
+        assert!(
+            list_nested_files_with_stats(root.path.join("x.txt"), &filter)
+                .await
+                .is_err()
+        );
 
         Ok(())
     }
 
     #[tokio::test]
-    async fn checks_if_a_directory_is_a_subdirectory() -> Result<()> {
-        let root = TempPath::new("is_subdir").await?;
-        let nested = root
-            .nest_folders(vec!["this", "is", "a", "nested", "tmp", "dir"])
+    async fn copies_only_matching_files() -> Result<()> {
+        let root = TempPath::new("copy_files_matching").await?;
+        root.multi_folder(vec!["src/a/b", "src/logs"]).await?;
+        root.multi_file(vec![
+            "src/top.txt",
+            "src/top.rs",
+            "src/a/mid.txt",
+            "src/a/b/deep.txt",
+            "src/a/b/deep.json",
+            "src/logs/out.log",
+        ])
+        .await?;
+        tokio::fs::write(root.path.join("src/a/mid.txt"), "middle").await?;
+
+        let (src, dst) = (root.path.join("src"), root.path.join("dst"));
+        let filter = FtFilter::Name(".txt".to_string());
+        let mut copied = copy_files_matching(&src, &dst, &filter).await?;
+        copied.sort();
+
+        let mut expected = vec![
+            dst.join("top.txt"),
+            dst.join("a/mid.txt"),
+            dst.join("a/b/deep.txt"),
+        ];
+        expected.sort();
+        assert_eq!(copied, expected);
+
+        let mut listed = list_nested_files(&dst).await?;
+        listed.sort();
+        assert_eq!(listed, expected);
+        assert_eq!(list_nested_directories(&dst).await?.len(), 2);
+        assert!(!dst.join("logs").exists());
+        assert_eq!(
+            tokio::fs::read_to_string(dst.join("a/mid.txt")).await?,
+            "middle"
+        );
+
+        let sync_dst = root.path.join("sync_dst");
+        let mut copied = sync::copy_files_matching(&src, &sync_dst, &filter)?;
+        copied.sort();
+        assert_eq!(copied, strip_and_rejoin(&expected, &dst, &sync_dst));
+
+        assert!(
+            copy_files_matching(root.path.join("missing"), &dst, &filter)
+                .await
+                .is_err()
+        );
+
+        Ok(())
+    }
+
+    fn strip_and_rejoin(items: &[PathBuf], from: &Path, to: &Path) -> Vec<PathBuf> {
+        items
+            .iter()
+            .map(|item| to.join(item.strip_prefix(from).unwrap()))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn depth_filters_are_inclusive() -> Result<()> {
+        let root = TempPath::new("lists_depth_filters").await?;
+        root.multi_folder(vec!["a/b/c"]).await?;
+        root.multi_file(vec!["d1.txt", "a/d2.txt", "a/b/d3.txt", "a/b/c/d4.txt"])
             .await?;
-        let mut result = is_subdir(&nested.path, "nested");
 
-        assert!(result);
+        let names = |items: Vec<PathBuf>| {
+            let mut names: Vec<String> = items
+                .iter()
+                .map(|i| i.file_name().unwrap().to_string_lossy().into_owned())
+                .collect();
+            names.sort();
+            names
+        };
 
-        result = is_subdir(&nested.path, "not_valid");
+        let deep = list_nested_files_with_filter(&root.path, FtFilter::MinDepth(3)).await?;
+        assert_eq!(names(deep), vec!["d3.txt", "d4.txt"]);
+
+        let shallow = list_nested_files_with_filter(&root.path, FtFilter::MaxDepth(2)).await?;
+        assert_eq!(names(shallow), vec!["d1.txt", "d2.txt"]);
+
+        let sync_deep = sync::list_nested_files_with_filter(&root.path, FtFilter::MinDepth(3))?;
+        assert_eq!(names(sync_deep), vec!["d3.txt", "d4.txt"]);
+
+        let sync_shallow = sync::list_nested_files_with_filter(&root.path, FtFilter::MaxDepth(1))?;
+        assert_eq!(names(sync_shallow), vec!["d1.txt"]);
+
+        // Depth is relative to the root, not the start of the path
+        let from_a =
+            list_nested_files_with_filter(root.path.join("a"), FtFilter::MaxDepth(1)).await?;
+        assert_eq!(names(from_a), vec!["d2.txt"]);
+
+        // Directories are counted the same way
+        let dirs = list_nested_directories_with_filter(&root.path, FtFilter::MinDepth(2)).await?;
+        assert_eq!(dirs.len(), 2);
+
+        let scanned =
+            list_nested_files_with_filter_scanned(&root.path, FtFilter::MinDepth(4)).await?;
+        assert_eq!(names(scanned.matched), vec!["d4.txt"]);
+        assert_eq!(scanned.total_scanned, 4);
+
+        let none = list_nested_files_with_filter(&root.path, FtFilter::MinDepth(5)).await?;
+        assert!(none.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn lists_directories_holding_files() -> Result<()> {
+        let root = TempPath::new("lists_dirs_with_files").await?;
+        root.multi_folder(vec![
+            "artist/album_a",
+            "artist/album_b/extras",
+            "empty",
+            "only_dirs/inner",
+        ])
+        .await?;
+        root.multi_file(vec![
+            "root.txt",
+            "artist/album_a/01.flac",
+            "artist/album_a/02.flac",
+            "artist/album_b/extras/cover.jpg",
+        ])
+        .await?;
+
+        let mut dirs = list_directories_containing_files(&root.path).await?;
+        dirs.sort();
+        assert_eq!(
+            dirs,
+            vec![
+                root.path.join("artist/album_a"),
+                root.path.join("artist/album_b/extras"),
+            ]
+        );
+
+        let mut sync_dirs = sync::list_directories_containing_files(&root.path)?;
+        sync_dirs.sort();
+        assert_eq!(sync_dirs, dirs);
+
+        assert!(
+            list_directories_containing_files(root.path.join("root.txt"))
+                .await
+                .is_err()
+        );
 
-        assert!(!result);
         Ok(())
     }
 
     #[test]
-    fn check_path_contains_subpath() {
-        // Basic str
-        let main = "I/am/a/path/hello/there";
-        assert!(path_contains(main, "a/path"));
-        assert!(!path_contains(main, "not"));
+    fn path_contains_components_aligns_to_separators() {
+        // Raw substring matching runs across component boundaries
+        assert!(path_contains("a/b/cd", "b/c"));
+        assert!(path_contains("xa/b/c", "a/b"));
+        assert!(!path_contains_components("a/b/cd", "b/c"));
+        assert!(!path_contains_components("xa/b/c", "a/b"));
 
-        // Check it works for paths
-        let main = Path::new(main);
-        assert!(path_contains(main, Path::new("a/path")));
-        assert!(!path_contains(main, Path::new("not")));
+        assert!(path_contains_components("a/b/c", "b/c"));
+        assert!(path_contains_components("a/b/c", "a"));
+        assert!(path_contains_components("/srv/data/logs", "/srv/data"));
+        assert!(path_contains_components("a//b/./c", "b/c"));
+        assert!(path_contains_components("a/b", ""));
 
-        // Pathbufs?
-        let main = PathBuf::from("I/am/a/path/hello/there");
-        assert!(path_contains(&main, PathBuf::from("a/path")));
-        assert!(!path_contains(main, PathBuf::from("not")));
+        // Components have to be consecutive and in order
+        assert!(!path_contains_components("a/b/c", "a/c"));
+        assert!(!path_contains_components("a/b/c", "c/b"));
+        assert!(!path_contains_components("a/bc/d", "b/c"));
+        assert!(!path_contains_components("a/b", "a/b/c"));
+        assert!(!path_contains_components("a/srv/data", "/srv/data"));
+    }
 
-        // What about strings?
-        assert!(path_contains(
-            String::from("I/am/a/path/hello/there"),
-            String::from("a/path")
+    #[tokio::test]
+    async fn finds_git_repositories_without_descending() -> Result<()> {
+        let root = TempPath::new("lists_git_repositories").await?;
+        root.multi_folder(vec![
+            ".git",
+            "apps/web/.git",
+            "apps/web/vendor/lib/.git",
+            "apps/cli/src",
+            "libs/core/.git/objects",
+            "docs/guide",
+        ])
+        .await?;
+        // Submodules have a `.git` file rather than a directory
+        root.multi_folder(vec!["libs/sub"]).await?;
+        root.multi_file(vec!["libs/sub/.git", "docs/guide/index.md"])
+            .await?;
+
+        let mut repos = list_git_repositories(&root.path).await?;
+        repos.sort();
+        assert_eq!(
+            repos,
+            vec![
+                root.path.join("apps/web"),
+                root.path.join("libs/core"),
+                root.path.join("libs/sub"),
+            ]
+        );
+
+        let mut sync_repos = sync::list_git_repositories(&root.path)?;
+        sync_repos.sort();
+        assert_eq!(sync_repos, repos);
+
+        // Searching inside a repository finds what's nested in it
+        let nested = list_git_repositories(root.path.join("apps/web")).await?;
+        assert_eq!(nested, vec![root.path.join("apps/web/vendor/lib")]);
+
+        assert!(list_git_repositories(root.path.join("libs/sub/.git"))
+            .await
+            .is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn set_listings_are_sorted_and_unique() -> Result<()> {
+        let root = TempPath::new("lists_sets").await?;
+        root.multi_folder(vec!["b/nested", "a", "c"]).await?;
+        root.multi_file(vec![
+            "z.txt",
+            "b/nested/y.txt",
+            "a/x.txt",
+            "c/w.log",
+            "a/a.txt",
+        ])
+        .await?;
+
+        let files = list_nested_files_set(&root.path, None).await?;
+        let expected: Vec<PathBuf> = ["a/a.txt", "a/x.txt", "b/nested/y.txt", "c/w.log", "z.txt"]
+            .iter()
+            .map(|f| root.path.join(f))
+            .collect();
+        assert_eq!(files.iter().cloned().collect::<Vec<_>>(), expected);
+
+        // Same items as the plain listing, once each
+        let listed = list_nested_files(&root.path).await?;
+        assert_eq!(files.len(), listed.len());
+        assert!(listed.iter().all(|f| files.contains(f)));
+
+        let filter = FtFilter::Raw("txt".to_string());
+        let txt = sync::list_nested_files_set(&root.path, Some(filter))?;
+        assert_eq!(txt.len(), 4);
+        assert!(txt.iter().is_sorted());
+
+        let dirs = list_nested_directories_set(&root.path, None).await?;
+        assert_eq!(
+            dirs.into_iter().collect::<Vec<_>>(),
+            vec![
+                root.path.join("a"),
+                root.path.join("b"),
+                root.path.join("b/nested"),
+                root.path.join("c"),
+            ]
+        );
+        assert_eq!(
+            sync::list_nested_directories_set(&root.path, None)?.len(),
+            4
+        );
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn filters_match_non_utf8_names() -> Result<()> {
+        use std::os::unix::ffi::OsStrExt;
+
+        let root = TempPath::new("filters_non_utf8").await?;
+        let dir = root.path.join(OsStr::from_bytes(b"caf\xe9_photos"));
+        std::fs::create_dir(&dir)?;
+        let invalid = dir.join(OsStr::from_bytes(b"r\xe9sum\xe9.pdf"));
+        std::fs::File::create(&invalid)?;
+        root.multi_file(vec!["plain.pdf", "notes.txt"]).await?;
+
+        let pdfs = |filter| async {
+            let mut result = list_nested_files_with_filter(&root.path, filter).await?;
+            result.sort();
+            anyhow::Ok(result)
+        };
+
+        assert_eq!(
+            pdfs(FtFilter::Raw(".pdf".to_string())).await?,
+            vec![invalid.clone(), root.path.join("plain.pdf")]
+        );
+        assert_eq!(
+            pdfs(FtFilter::Name("sum".to_string())).await?,
+            vec![invalid.clone()]
+        );
+        assert_eq!(
+            pdfs(FtFilter::ParentName("_photos".to_string())).await?,
+            vec![invalid.clone()]
+        );
+
+        // Patterns that are themselves not valid UTF-8 match byte for byte
+        let raw_dir = PathBuf::from(OsStr::from_bytes(b"caf\xe9_photos/r\xe9"));
+        assert_eq!(
+            pdfs(FtFilter::Path(raw_dir.clone())).await?,
+            vec![invalid.clone()]
+        );
+        assert!(path_contains(&invalid, &raw_dir));
+        assert!(path_contains_str(&invalid, "_photos/r"));
+        assert!(!path_contains(&invalid, OsStr::from_bytes(b"caf\xe8")));
+
+        let sync_result =
+            sync::list_nested_files_with_filter(&root.path, FtFilter::Name("sum".to_string()))?;
+        assert_eq!(sync_result, vec![invalid]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn listing_into_reuses_the_buffer() -> Result<()> {
+        let root = TempPath::new("lists_into_buffer").await?;
+        root.multi_folder(vec!["one/sub", "two"]).await?;
+        root.multi_file(vec!["one/a.txt", "one/b.txt", "one/sub/c.txt", "two/d.txt"])
+            .await?;
+
+        let mut buffer = Vec::new();
+        list_files_into(root.path.join("one"), &mut buffer).await?;
+        assert_eq!(buffer.len(), 2);
+        let capacity = buffer.capacity();
+
+        list_files_into(root.path.join("two"), &mut buffer).await?;
+        assert_eq!(buffer, vec![root.path.join("two/d.txt")]);
+        assert_eq!(buffer.capacity(), capacity);
+
+        list_nested_files_into(root.path.join("one"), &mut buffer).await?;
+        assert_eq!(buffer.len(), 3);
+        sync::list_files_into(root.path.join("two"), &mut buffer)?;
+        assert_eq!(buffer, vec![root.path.join("two/d.txt")]);
+
+        list_directories_into(&root.path, &mut buffer).await?;
+        assert_eq!(buffer.len(), 2);
+        sync::list_nested_directories_into(&root.path, &mut buffer)?;
+        assert_eq!(buffer.len(), 3);
+        list_nested_directories_into(root.path.join("two"), &mut buffer).await?;
+        assert!(buffer.is_empty());
+
+        assert!(sync::list_nested_files_into(root.path.join("missing"), &mut buffer).is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn maps_files_during_the_listing() -> Result<()> {
+        let root = TempPath::new("lists_map").await?;
+        root.multi_folder(vec!["sub"]).await?;
+        root.multi_file(vec!["a.txt", "b.rs", "c", "d.txt", "sub/e.md"])
+            .await?;
+
+        let extension = |file: &Path| file.extension().map(|e| e.to_string_lossy().into_owned());
+
+        let mut mapped = list_files_map(&root.path, None, extension).await?;
+        let mut two_step: Vec<_> = list_files(&root.path)
+            .await?
+            .iter()
+            .map(|f| extension(f))
+            .collect();
+        mapped.sort();
+        two_step.sort();
+        assert_eq!(mapped, two_step);
+        assert_eq!(
+            mapped,
+            vec![
+                None,
+                Some("rs".to_string()),
+                Some("txt".to_string()),
+                Some("txt".to_string()),
+            ]
+        );
+
+        let filter = FtFilter::Name(".txt".to_string());
+        let mut count = 0;
+        let txt = sync::list_files_map(&root.path, Some(filter), |_| {
+            count += 1;
+            count
+        })?;
+        assert_eq!(txt.len(), 2);
+        assert_eq!(count, 2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn filter_matches_every_variant() -> Result<()> {
+        let path = Path::new("projects/app/src/main.rs");
+
+        assert!(filter_matches(path, &FtFilter::Raw("app/src".to_string())));
+        assert!(!filter_matches(path, &FtFilter::Raw("lib".to_string())));
+        assert!(filter_matches(
+            path,
+            &FtFilter::Path(PathBuf::from("app/src"))
         ));
-        assert!(!path_contains(
-            String::from("I/am/a/path/hello/there"),
-            String::from("not")
+        assert!(!filter_matches(
+            path,
+            &FtFilter::Path(PathBuf::from("src/app"))
+        ));
+        assert!(filter_matches(path, &FtFilter::Name("main".to_string())));
+        assert!(!filter_matches(path, &FtFilter::Name("app".to_string())));
+        assert!(filter_matches(
+            path,
+            &FtFilter::ParentName("src".to_string())
+        ));
+        assert!(!filter_matches(
+            path,
+            &FtFilter::ParentName("app".to_string())
+        ));
+        assert!(filter_matches(
+            path,
+            &FtFilter::Regex(Regex::new(r"app/.*\.rs$")?)
         ));
+        assert!(!filter_matches(
+            path,
+            &FtFilter::Regex(Regex::new(r"^src")?)
+        ));
+        assert!(filter_matches(
+            path,
+            &FtFilter::RegexName(Regex::new(r"^main")?)
+        ));
+        assert!(!filter_matches(
+            path,
+            &FtFilter::RegexName(Regex::new(r"^src")?)
+        ));
+        assert!(filter_matches(path, &FtFilter::MinDepth(4)));
+        assert!(!filter_matches(path, &FtFilter::MinDepth(5)));
+        assert!(filter_matches(path, &FtFilter::MaxDepth(4)));
+        assert!(!filter_matches(path, &FtFilter::MaxDepth(3)));
+        assert!(filter_matches(
+            path,
+            &FtFilter::custom(|p| p.starts_with("projects"))
+        ));
+        assert!(!filter_matches(
+            path,
+            &FtFilter::custom(|p| p.is_absolute())
+        ));
+
+        // The filesystem backed filters never match a path that doesn't exist
+        for filter in [
+            FtFilter::Canonical(Box::new(FtFilter::Name("main".to_string()))),
+            FtFilter::Executable,
+            FtFilter::Empty,
+            FtFilter::NonEmpty,
+        ] {
+            assert!(!filter_matches(path, &filter));
+        }
+
+        let root = TempPath::new("filter_matches_fs").await?;
+        root.multi_file(vec!["empty.txt"]).await?;
+        let empty = root.path.join("empty.txt");
+        let name = FtFilter::Name("empty".to_string());
+        assert!(filter_matches(&empty, &FtFilter::Canonical(Box::new(name))));
+        assert!(filter_matches(&empty, &FtFilter::Empty));
+        assert!(!filter_matches(&empty, &FtFilter::NonEmpty));
+        assert!(!filter_matches(&empty, &FtFilter::Executable));
+
+        Ok(())
     }
 
+    #[cfg(unix)]
     #[tokio::test]
-    async fn check_list_files_works() -> Result<()> {
-        let root = TempPath::new("lf_test").await?;
-        root.multi_file(vec!["first.rs", "second.c", "third.js", "fourth.rb"])
-            .await?;
+    async fn same_file_follows_links() -> Result<()> {
+        let root = TempPath::new("same_file_links").await?;
+        root.multi_file(vec!["a.txt", "b.txt"]).await?;
+        let a = root.path.join("a.txt");
+        let b = root.path.join("b.txt");
+        let hardlink = root.path.join("hard.txt");
+        let symlink = root.path.join("sym.txt");
+        std::fs::hard_link(&a, &hardlink)?;
+        std::os::unix::fs::symlink(&a, &symlink)?;
+
+        assert!(same_file(&a, &hardlink).await?);
+        assert!(same_file(&a, &symlink).await?);
+        assert!(same_file(&a, root.path.join(".").join("a.txt")).await?);
+        assert!(!same_file(&a, &b).await?);
+        assert!(same_file(&a, root.path.join("missing.txt")).await.is_err());
+
+        assert!(sync::same_file(&hardlink, &symlink)?);
+        assert!(!sync::same_file(&b, &hardlink)?);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn same_file_detects_hardlinks() -> Result<()> {
+        let root = TempPath::new("same_file_hardlinks").await?;
+        root.multi_file(vec!["a.txt", "b.txt"]).await?;
+        let a = root.path.join("a.txt");
+        let hardlink = root.path.join("hard.txt");
+        std::fs::hard_link(&a, &hardlink)?;
+
+        // Same size and both empty, only the file ids tell them apart
+        assert!(same_file(&a, &hardlink).await?);
+        assert!(!same_file(&hardlink, root.path.join("b.txt")).await?);
+        assert!(sync::same_file(&hardlink, &a)?);
+
+        Ok(())
+    }
 
-        let res = list_files(root.path.clone()).await?;
-        assert_eq!(res.len(), 4);
+    #[tokio::test]
+    async fn copy_directory_refuses_to_copy_into_itself() -> Result<()> {
+        let root = TempPath::new("copy_into_itself").await?;
+        root.multi_folder(vec!["a"]).await?;
+        root.multi_file(vec!["a/file.txt"]).await?;
+        let src = root.path.join("a");
 
-        assert!(list_files("IDoNotExistAsADirectoryOrShouldntAtLeAst")
+        assert!(copy_directory(&src, src.join("b")).await.is_err());
+        assert!(!src.join("b").exists());
+        assert!(copy_directory(&src, &src).await.is_err());
+        assert!(copy_directory(&src, root.path.join("a/../a/c/d"))
             .await
             .is_err());
+        assert!(sync::copy_directory(&src, src.join("b")).is_err());
+        assert!(!src.join("b").exists());
+        assert_eq!(list_nested_files(&src).await?, vec![src.join("file.txt")]);
+
+        #[cfg(unix)]
+        {
+            let link = root.path.join("link");
+            std::os::unix::fs::symlink(&src, &link)?;
+            assert!(copy_directory(&src, link.join("b")).await.is_err());
+            assert!(sync::copy_directory(&src, &link).is_err());
+            assert!(!src.join("b").exists());
+        }
+
+        // Siblings are fine
+        copy_directory(&src, root.path.join("b")).await?;
+        assert!(root.path.join("b/file.txt").exists());
 
         Ok(())
     }
 
     #[tokio::test]
-    async fn check_list_nested_files_works() -> Result<()> {
-        let root = TempPath::new("lfr_test").await?;
-        let ffolder = root.new_folder("ffolder").await?;
-        let sfolder = root.new_folder("sfolder").await?;
-        let tfolder = root.new_folder("tfolder").await?;
+    async fn path_errors_carry_the_path() -> Result<()> {
+        let root = TempPath::new("path_errors").await?;
+        root.multi_file(vec!["file.txt"]).await?;
+        let missing = root.path.join("missing");
+        let file = root.path.join("file.txt");
 
-        root.new_file("initial.pdf").await?;
-        ffolder.new_file("first.rs").await?;
-        sfolder.multi_file(vec!["second.txt", "third.php"]).await?;
-        tfolder.new_file("fourth.cpp").await?;
+        let err = list_files(&missing).await.unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<FtError>(),
+            Some(&FtError::NotFound(missing.clone()))
+        );
+        assert!(err.to_string().contains(&missing.display().to_string()));
 
-        let res = list_nested_files(&root.path).await?;
-        assert_eq!(res.len(), 5);
+        let err = sync::list_nested_directories(&file).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<FtError>(),
+            Some(&FtError::NotADirectory(file.clone()))
+        );
+        assert_eq!(err.downcast_ref::<FtError>().map(|e| e.path()), Some(&file));
+        assert!(err.to_string().contains(&file.display().to_string()));
 
-        assert!(list_files("IDoNotExistAsADirectoryOrShouldntAtLeAst")
+        let err = list_nested_files_multi(&[root.path.clone(), missing.clone()], false)
             .await
-            .is_err());
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<FtError>(),
+            Some(&FtError::NotFound(missing))
+        );
 
         Ok(())
     }
 
     #[tokio::test]
-    async fn check_list_directories_works() -> Result<()> {
-        let root = TempPath::new("lfolder_test").await?;
-        root.multi_folder(vec!["folder1", "folder2", "folder3", "folder4"])
+    async fn excluded_directories_are_not_descended() -> Result<()> {
+        let root = TempPath::new("excluded_dirs").await?;
+        root.multi_folder(vec!["src/nested", "node_modules/dep/lib", "src/target"])
             .await?;
+        let mut files = vec!["src/main.rs", "src/nested/mod.rs", "src/target/out.o"];
+        let deps: Vec<String> = (0..50)
+            .map(|i| format!("node_modules/dep/lib/file_{i}.js"))
+            .collect();
+        files.extend(deps.iter().map(String::as_str));
+        root.multi_file(files).await?;
 
-        let res = list_directories(root.path.clone()).await?;
-        assert_eq!(res.len(), 4);
-
-        assert!(list_directories("non-existant_path").await.is_err());
+        let mut listed =
+            list_nested_files_excluding(&root.path, &["node_modules", "target"]).await?;
+        listed.sort();
+        assert_eq!(
+            listed,
+            vec![
+                root.path.join("src/main.rs"),
+                root.path.join("src/nested/mod.rs")
+            ]
+        );
 
-        Ok(())
-    }
+        let mut dirs = sync::list_nested_directories_excluding(&root.path, &["node_modules"])?;
+        dirs.sort();
+        assert_eq!(
+            dirs,
+            vec![
+                root.path.join("src"),
+                root.path.join("src/nested"),
+                root.path.join("src/target")
+            ]
+        );
 
-    #[tokio::test]
-    async fn check_list_nested_directories_works() -> Result<()> {
-        let root = TempPath::new("lfolderrec_test").await?;
-        root.multi_folder(vec!["folder1", "folder2"]).await?;
+        // The excluded directory is never opened
+        struct Opened<'a>(Vec<PathBuf>, util::ExcludedItems<'a>);
+        impl util::FtVisitor for Opened<'_> {
+            fn visit(&mut self, item: PathBuf) -> ControlFlow<()> {
+                self.1.visit(item)
+            }
 
-        let f1 = TempPath::new(root.join("folder1")).await?;
-        f1.multi_folder(vec!["sub1", "sub2", "sub3"]).await?;
+            fn entered_dir(&mut self, dir: &Path) {
+                self.0.push(dir.to_path_buf());
+            }
 
-        let s2 = TempPath::new(f1.join("sub2")).await?;
-        s2.multi_folder(vec!["deep1", "deep2"]).await?;
+            fn descend(&mut self, dir: &Path) -> bool {
+                self.1.descend(dir)
+            }
+        }
 
-        let res = list_nested_directories(root.path.clone()).await?;
-        assert_eq!(res.len(), 7);
+        let mut opened = Opened(vec![], util::ExcludedItems::new(&["node_modules"]));
+        walk(&root.path, FtIterItemState::RFile, None, &mut opened).await?;
+        assert_eq!(opened.0.len(), 4);
+        assert!(!opened.0.contains(&root.path.join("node_modules")));
 
-        assert!(list_nested_directories("not-a-valId_pathd").await.is_err());
+        let mut opened = Opened(vec![], util::ExcludedItems::new(&["node_modules"]));
+        util::walk_sync(&root.path, FtIterItemState::RFile, None, &mut opened)?;
+        assert_eq!(opened.0.len(), 4);
+        assert!(!opened.0.contains(&root.path.join("node_modules")));
 
         Ok(())
     }
 
+    #[cfg(unix)]
     #[tokio::test]
-    async fn numeric_directories() -> Result<()> {
-        let tmp = TempPath::new("numeric_directories").await?;
-        create_numeric_directories(&tmp.path, 0, 100, 4).await?;
-        let mut folders = list_directories(&tmp.path).await?;
-        folders.sort();
-        assert_eq!(folders.len(), 100);
+    async fn ensure_directory_with_mode_sets_permissions() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
 
-        for (i, folder) in folders.into_iter().enumerate() {
-            let test = &tmp.path.join(format!("{:0fill$}", i, fill = 4));
-            assert_eq!(&folder, test);
-        }
+        let root = TempPath::new("ensure_directory_mode").await?;
+        root.multi_file(vec!["file.txt"]).await?;
+        let mode_of = |path: PathBuf| -> Result<u32> {
+            Ok(std::fs::metadata(path)?.permissions().mode() & 0o777)
+        };
+
+        // Parents get the same permissions as any other new directory
+        ensure_directory(root.path.join("default")).await?;
+        let default_mode = mode_of(root.path.join("default"))?;
+
+        ensure_directory_with_mode(root.path.join("secrets/keys"), 0o700).await?;
+        assert_eq!(mode_of(root.path.join("secrets"))?, default_mode);
+        assert_eq!(mode_of(root.path.join("secrets/keys"))?, 0o700);
+
+        sync::ensure_directory_with_mode(root.path.join("private/nested"), 0o500)?;
+        assert_eq!(mode_of(root.path.join("private"))?, default_mode);
+        assert_eq!(mode_of(root.path.join("private/nested"))?, 0o500);
+
+        // Existing directories are left alone
+        ensure_directory_with_mode(root.path.join("secrets/keys"), 0o755).await?;
+        assert_eq!(mode_of(root.path.join("secrets/keys"))?, 0o700);
+
+        let err = ensure_directory_with_mode(root.path.join("file.txt"), 0o700)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<FtError>(),
+            Some(FtError::NotADirectory(path)) if path == &root.path.join("file.txt")
+        ));
+        let err = sync::ensure_directory_with_mode(root.path.join("file.txt"), 0o700).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<FtError>(),
+            Some(FtError::NotADirectory(_))
+        ));
 
         Ok(())
     }
 
+    #[cfg(unix)]
     #[tokio::test]
-    async fn multiple_directory_creation() -> Result<()> {
-        let tmp = TempPath::new("create_multiple_dirs").await?;
-        let dirs = ["config", "src", "tests"];
+    async fn lists_broken_symlinks() -> Result<()> {
+        use std::os::unix::fs::symlink;
 
-        create_multiple_directories(&tmp.path, &dirs).await?;
-        let folders = list_directories(&tmp.path).await?;
-        assert_eq!(folders.len(), 3);
+        let root = TempPath::new("broken_symlinks").await?;
+        root.multi_folder(vec!["links/nested", "real"]).await?;
+        root.multi_file(vec!["real/target.txt"]).await?;
 
-        for check in dirs {
-            let target = tmp.path.join(check);
-            assert!(folders.contains(&target));
-        }
+        symlink(
+            root.path.join("real/target.txt"),
+            root.path.join("links/valid"),
+        )?;
+        symlink(
+            root.path.join("real/gone.txt"),
+            root.path.join("links/dangling"),
+        )?;
+        symlink("../../nowhere", root.path.join("links/nested/relative"))?;
+        // A symlinked directory isn't followed, so its contents aren't reported twice
+        symlink(root.path.join("links"), root.path.join("real/loop"))?;
+
+        let mut broken = list_broken_symlinks(&root.path).await?;
+        broken.sort();
+        let expected = vec![
+            root.path.join("links/dangling"),
+            root.path.join("links/nested/relative"),
+        ];
+        assert_eq!(broken, expected);
+
+        let mut broken = sync::list_broken_symlinks(&root.path)?;
+        broken.sort();
+        assert_eq!(broken, expected);
 
         Ok(())
     }
 
     #[tokio::test]
-    async fn files_filter() -> Result<()> {
-        let root = TempPath::new("filter_files").await?;
-        root.multi_file(vec!["first.rs", "second.rs", "third.js", "fourth.rb"])
+    async fn pruned_directories_are_not_listed() -> Result<()> {
+        let root = TempPath::new("pruned_listing").await?;
+        root.multi_folder(vec!["a/skip/deeper", "skip", "b"])
             .await?;
+        root.multi_file(vec![
+            "top.txt",
+            "a/one.txt",
+            "a/skip/hidden.txt",
+            "a/skip/deeper/hidden.txt",
+            "skip/hidden.txt",
+            "b/two.rs",
+        ])
+        .await?;
 
-        // Raw string filter
-        let mut filter = FtFilter::Raw("fourth".to_string());
-        let mut result = list_files_with_filter(&root.path, filter).await?;
-        assert_eq!(result.len(), 1);
-        assert_eq!(result[0], root.path.join("fourth.rb"));
+        let not_skip = |dir: &Path| dir.file_name().is_some_and(|name| name != "skip");
 
-        // PathBuf filter
-        filter = FtFilter::Path(PathBuf::from("third.js"));
-        result = list_files_with_filter(&root.path, filter).await?;
-        assert_eq!(result.len(), 1);
-        assert_eq!(result[0], root.path.join("third.js"));
+        let mut files = list_nested_files_prune(&root.path, not_skip, None).await?;
+        files.sort();
+        assert_eq!(
+            files,
+            vec![
+                root.path.join("a/one.txt"),
+                root.path.join("b/two.rs"),
+                root.path.join("top.txt"),
+            ]
+        );
 
-        // Regex filter
-        filter = FtFilter::Regex(Regex::new(r"(.*)\.rs").unwrap());
-        result = list_files_with_filter(&root.path, filter).await?;
-        assert_eq!(result.len(), 2);
-        assert!(result.contains(&root.path.join("first.rs")));
-        assert!(result.contains(&root.path.join("second.rs")));
+        let mut seen = vec![];
+        let filter = FtFilter::Name(".txt".to_string());
+        let mut files = sync::list_nested_files_prune(
+            &root.path,
+            |dir| {
+                seen.push(dir.to_path_buf());
+                not_skip(dir)
+            },
+            Some(filter),
+        )?;
+        files.sort();
+        assert_eq!(
+            files,
+            vec![root.path.join("a/one.txt"), root.path.join("top.txt")]
+        );
+
+        // Consulted once per directory found, never for anything below a pruned one
+        seen.sort();
+        assert_eq!(
+            seen,
+            vec![
+                root.path.join("a"),
+                root.path.join("a/skip"),
+                root.path.join("b"),
+                root.path.join("skip"),
+            ]
+        );
 
         Ok(())
     }
 
-    #[tokio::test]
-    async fn files_filter_is_empty() -> Result<()> {
-        let root = TempPath::new("filter_files_empty").await?;
+    #[test]
+    fn computes_relative_paths() {
+        let rel = |from, to| relative_path(from, to);
 
-        // Raw string filter (normal + nested)
-        let mut filter = FtFilter::Raw("non-existant".to_string());
-        let mut result = list_files_with_filter(&root.path, filter).await?;
-        assert!(result.is_empty());
-        filter = FtFilter::Raw("non-existant".to_string());
-        result = list_nested_files_with_filter(&root.path, filter).await?;
-        assert!(result.is_empty());
+        // Siblings
+        assert_eq!(rel("a/b", "a/c"), Some(PathBuf::from("../c")));
+        assert_eq!(
+            rel("/srv/www", "/srv/data/x.txt"),
+            Some(PathBuf::from("../data/x.txt"))
+        );
 
-        // PathBuf Filter
-        filter = FtFilter::Path(PathBuf::from("another-missing"));
-        result = list_files_with_filter(&root.path, filter).await?;
-        assert!(result.is_empty());
-        filter = FtFilter::Path(PathBuf::from("another-missing"));
-        result = list_nested_files_with_filter(&root.path, filter).await?;
-        assert!(result.is_empty());
+        // Ancestors and descendants
+        assert_eq!(rel("a/b/c", "a"), Some(PathBuf::from("../..")));
+        assert_eq!(rel("a", "a/b/c"), Some(PathBuf::from("b/c")));
+        assert_eq!(rel("/", "/etc/hosts"), Some(PathBuf::from("etc/hosts")));
 
-        // Regex filter
-        filter = FtFilter::Regex(Regex::new(r"(.*)\.rs").unwrap());
-        result = list_files_with_filter(&root.path, filter).await?;
-        assert!(result.is_empty());
-        filter = FtFilter::Regex(Regex::new(r"(.*)\.rs").unwrap());
-        result = list_nested_files_with_filter(&root.path, filter).await?;
-        assert!(result.is_empty());
-        Ok(())
+        // Normalized first, the same location is `.`
+        assert_eq!(rel("a/./b/../b", "a//b"), Some(PathBuf::from(".")));
+        assert_eq!(rel(".", "a/../b"), Some(PathBuf::from("b")));
+
+        // Disjoint paths go through the shared root
+        assert_eq!(rel("a/b", "c/d"), Some(PathBuf::from("../../c/d")));
+        assert_eq!(rel("/x/y", "/z"), Some(PathBuf::from("../../z")));
+        assert_eq!(rel("a", "../b"), Some(PathBuf::from("../../b")));
+
+        // Nothing to go on without the filesystem
+        assert_eq!(rel("/a", "a"), None);
+        assert_eq!(rel("a", "/a"), None);
+        assert_eq!(rel("../a", "b"), None);
     }
 
     #[tokio::test]
-    async fn list_items_error() -> Result<()> {
-        let root = TempPath::new("filter_files_error").await?;
-        let test = root.new_file("test.js").await?;
+    async fn copies_report_files_and_bytes() -> Result<()> {
+        let root = TempPath::new("copy_report").await?;
+        root.multi_folder(vec!["src/nested", "src/empty"]).await?;
+        let contents = [
+            ("src/a.txt", "hello"),
+            ("src/b.log", "some longer contents"),
+            ("src/nested/c.txt", &"x".repeat(4096)),
+            ("src/nested/empty.txt", ""),
+        ];
+        for (name, data) in contents {
+            tokio::fs::write(root.path.join(name), data).await?;
+        }
+        let total: u64 = contents.iter().map(|(_, data)| data.len() as u64).sum();
+        let src = root.path.join("src");
 
-        assert!(list_files(&test.path).await.is_err());
-        assert!(list_nested_files(&test.path).await.is_err());
-        assert!(
-            list_files_with_filter(&test.path, FtFilter::Raw("filter".to_string()))
-                .await
-                .is_err()
-        );
-        assert!(
-            list_nested_files_with_filter(&test.path, FtFilter::Raw("filter".to_string()))
-                .await
-                .is_err()
-        );
-        assert!(list_directories(&test.path).await.is_err());
-        assert!(list_nested_directories(&test.path).await.is_err());
-        assert!(
-            list_directories_with_filter(&test.path, FtFilter::Raw("filter".to_string()))
-                .await
-                .is_err()
+        let report =
+            copy_directory_with_report(&src, root.path.join("copy"), FtCopyOptions::default())
+                .await?;
+        assert_eq!(
+            report,
+            FtCopyReport {
+                files: 4,
+                bytes: total
+            }
         );
-        assert!(list_nested_directories_with_filter(
-            &test.path,
-            FtFilter::Raw("filter".to_string())
-        )
-        .await
-        .is_err());
-        Ok(())
-    }
 
-    #[tokio::test]
-    async fn nested_files_filter() -> Result<()> {
-        let root = TempPath::new("nested_filter_files").await?;
-        let ffolder = root.new_folder("ffolder").await?;
-        let sfolder = root.new_folder("sfolder").await?;
-        let tfolder = root.new_folder("tfolder").await?;
+        let report = sync::copy_directory_with_report(
+            &src,
+            root.path.join("sync_copy"),
+            FtCopyOptions::default(),
+        )?;
+        assert_eq!(
+            report,
+            FtCopyReport {
+                files: 4,
+                bytes: total
+            }
+        );
 
-        root.new_file("initial.pdf").await?;
-        ffolder.new_file("first.rs").await?;
-        sfolder.multi_file(vec!["second.txt", "third.rs"]).await?;
-        tfolder.new_file("initial.cpp").await?;
+        let filter = FtFilter::Name(".txt".to_string());
+        let (copied, report) =
+            copy_files_matching_with_report(&src, root.path.join("txt"), &filter).await?;
+        assert_eq!(copied.len(), 3);
+        assert_eq!(
+            report,
+            FtCopyReport {
+                files: 3,
+                bytes: 5 + 4096
+            }
+        );
 
-        let mut filter = FtFilter::Raw("initial".to_string());
-        let mut result = list_nested_files_with_filter(&root.path, filter).await?;
-        assert_eq!(result.len(), 2);
-        assert!(result.contains(&root.path.join("tfolder/initial.cpp")));
-        assert!(result.contains(&root.path.join("initial.pdf")));
+        let (_, report) =
+            sync::copy_files_matching_with_report(&src, root.path.join("sync_txt"), filter)?;
+        assert_eq!(report.bytes, 5 + 4096);
 
-        filter = FtFilter::Path(PathBuf::from("second.txt"));
-        result = list_nested_files_with_filter(&root.path, filter).await?;
-        assert_eq!(result.len(), 1);
-        assert_eq!(result[0], root.path.join("sfolder/second.txt"));
         Ok(())
     }
 
+    #[cfg(unix)]
     #[tokio::test]
-    async fn directories_filter() -> Result<()> {
-        let root = TempPath::new("dir_filter").await?;
-        root.multi_folder(vec!["log_var", "store_var", "config", "etc"])
-            .await?;
+    async fn remove_directory_where_does_not_follow_symlinked_dirs() -> Result<()> {
+        let root = TempPath::new("remove_directory_where_symlinks").await?;
+        root.multi_folder(vec!["tree/sub", "outside"]).await?;
+        root.multi_file(vec![
+            "tree/sub/a.tmp",
+            "outside/keep.tmp",
+            "outside/real.tmp",
+        ])
+        .await?;
+        let tree = root.path.join("tree");
+        std::os::unix::fs::symlink(root.path.join("outside"), tree.join("linked"))?;
+        std::os::unix::fs::symlink(root.path.join("outside/real.tmp"), tree.join("file.tmp"))?;
 
-        // Raw string filter
-        let mut filter = FtFilter::Raw("config".to_string());
-        let mut result = list_directories_with_filter(&root.path, filter).await?;
-        assert_eq!(result.len(), 1);
-        assert_eq!(result[0], root.path.join("config"));
+        let is_tmp = |p: &Path| p.extension().is_some_and(|ext| ext == "tmp");
+        let mut removed = remove_directory_where(&tree, is_tmp).await?;
+        removed.sort();
+        assert_eq!(removed, vec![tree.join("file.tmp"), tree.join("sub/a.tmp")]);
 
-        // PathBuf filter
-        filter = FtFilter::Path(PathBuf::from("etc"));
-        result = list_directories_with_filter(&root.path, filter).await?;
-        assert_eq!(result.len(), 1);
-        assert_eq!(result[0], root.path.join("etc"));
+        // The targets survive, only the link to the file is gone
+        assert!(root.path.join("outside/keep.tmp").exists());
+        assert!(root.path.join("outside/real.tmp").exists());
+        assert!(tree.join("linked").exists());
+        assert!(!tree.join("sub").exists());
+
+        root.multi_file(vec!["tree/b.tmp"]).await?;
+        assert_eq!(
+            sync::remove_directory_where(&tree, is_tmp)?,
+            vec![tree.join("b.tmp")]
+        );
+        assert!(root.path.join("outside/keep.tmp").exists());
 
-        // Regex filter
-        filter = FtFilter::Regex(Regex::new(r"(.*)_var").unwrap());
-        result = list_directories_with_filter(&root.path, filter).await?;
-        assert_eq!(result.len(), 2);
-        assert!(result.contains(&root.path.join("log_var")));
-        assert!(result.contains(&root.path.join("store_var")));
         Ok(())
     }
 
     #[tokio::test]
-    async fn nested_directories_filter() -> Result<()> {
-        let root = TempPath::new("nested_dir_filter_test").await?;
-        root.multi_folder(vec!["folder1", "folder2"]).await?;
-
-        let f1 = TempPath::new(root.join("folder1")).await?;
-        f1.multi_folder(vec!["sub1", "sub_2", "sub3"]).await?;
-
-        let s2 = TempPath::new(f1.join("sub_2")).await?;
-        s2.multi_folder(vec!["deep_1", "deep2"]).await?;
+    async fn hashes_large_files_in_chunks() -> Result<()> {
+        // Records how the contents arrive
+        struct Chunks(Vec<usize>, Vec<u8>);
+        impl FtHasher for Chunks {
+            fn update(&mut self, chunk: &[u8]) {
+                self.0.push(chunk.len());
+                self.1.extend_from_slice(chunk);
+            }
 
-        // Raw filter
-        let mut filter = FtFilter::Raw("deep".to_string());
-        let mut result = list_nested_directories_with_filter(&root.path, filter).await?;
-        assert_eq!(result.len(), 2);
-        assert!(result.contains(&root.path.join("folder1/sub_2/deep_1")));
-        assert!(result.contains(&root.path.join("folder1/sub_2/deep2")));
+            fn finish(self) -> String {
+                format!(
+                    "{:?} {}",
+                    self.0,
+                    self.1.iter().map(|b| *b as u64).sum::<u64>()
+                )
+            }
+        }
 
-        // Path filter
-        filter = FtFilter::Path(PathBuf::from("folder1"));
-        result = list_nested_directories_with_filter(&root.path, filter).await?;
-        assert_eq!(result.len(), 6);
+        let root = TempPath::new("hash_in_chunks").await?;
+        let contents: Vec<u8> = (0..util::READ_CHUNK * 2 + 10).map(|i| i as u8).collect();
+        std::fs::write(root.path.join("big.bin"), &contents)?;
+        let sum: u64 = contents.iter().map(|b| *b as u64).sum();
+        let expected = format!("[{}, {}, 10] {sum}", util::READ_CHUNK, util::READ_CHUNK);
 
-        filter = FtFilter::Regex(Regex::new(r"(.*)_[0-9]{1}").unwrap());
-        result = list_nested_directories_with_filter(&root.path, filter).await?;
-        assert_eq!(result.len(), 3);
+        let new_hasher = || Chunks(vec![], vec![]);
+        let hashed = list_nested_files_with_hash(&root.path, new_hasher).await?;
+        assert_eq!(hashed, vec![(root.path.join("big.bin"), expected.clone())]);
+        let hashed = sync::list_nested_files_with_hash(&root.path, new_hasher)?;
+        assert_eq!(hashed, vec![(root.path.join("big.bin"), expected)]);
 
         Ok(())
     }
 
     #[tokio::test]
-    async fn list_dirs_empty() -> Result<()> {
-        let root = TempPath::new("list_dirs_empty").await?;
+    async fn clone_directory_structure_keeps_existing_files() -> Result<()> {
+        let root = TempPath::new("clone_structure_existing").await?;
+        root.multi_folder(vec!["src/sub", "dst/sub"]).await?;
+        root.multi_file(vec!["src/a.txt", "src/sub/b.txt"]).await?;
+        std::fs::write(root.path.join("dst/a.txt"), "precious")?;
+        std::fs::write(root.path.join("dst/sub/b.txt"), "also precious")?;
 
-        // Raw string filter (normal + nested)
-        let mut filter = FtFilter::Raw("non-existant".to_string());
-        let mut result = list_directories_with_filter(&root.path, filter).await?;
-        assert!(result.is_empty());
-        filter = FtFilter::Raw("non-existant".to_string());
-        result = list_nested_directories_with_filter(&root.path, filter).await?;
-        assert!(result.is_empty());
+        clone_directory_structure(root.path.join("src"), root.path.join("dst"), true).await?;
+        assert_eq!(
+            std::fs::read_to_string(root.path.join("dst/a.txt"))?,
+            "precious"
+        );
 
-        // PathBuf Filter
-        filter = FtFilter::Path(PathBuf::from("another-missing"));
-        result = list_directories_with_filter(&root.path, filter).await?;
-        assert!(result.is_empty());
-        filter = FtFilter::Path(PathBuf::from("another-missing"));
-        result = list_nested_directories_with_filter(&root.path, filter).await?;
-        assert!(result.is_empty());
+        std::fs::remove_file(root.path.join("dst/a.txt"))?;
+        sync::clone_directory_structure(root.path.join("src"), root.path.join("dst"), true)?;
+        assert_eq!(
+            std::fs::read_to_string(root.path.join("dst/sub/b.txt"))?,
+            "also precious"
+        );
+        assert_eq!(std::fs::read_to_string(root.path.join("dst/a.txt"))?, "");
 
-        // Regex filter
-        filter = FtFilter::Regex(Regex::new(r"(.*)\.rs").unwrap());
-        result = list_directories_with_filter(&root.path, filter).await?;
-        assert!(result.is_empty());
-        filter = FtFilter::Regex(Regex::new(r"(.*)\.rs").unwrap());
-        result = list_nested_directories_with_filter(&root.path, filter).await?;
-        assert!(result.is_empty());
         Ok(())
     }
 }
@@ -17,13 +17,37 @@
 //! // E.g. `00762527-012a-43c1-a673-cad9bc5eef64.pdf`
 //! let random_name = naming::generate_uuid4_name("pdf");
 //!
+//! // UUIDv7 names start with a timestamp so they sort in the order they were made
+//! // E.g. `01890a5d-ac96-774b-bcce-b302099a8057.pdf`
+//! let ordered_name = naming::generate_uuid7_name("pdf");
+//!
 //! // N-digit name is a number prefixed by X zeros (e.g. 0005.pdf)
 //! let n_digit_name = naming::generate_n_digit_name(5, 4, "pdf");
+//!
+//! // Several names can be made at once (e.g. 0001.png, 0002.png, 0003.png)
+//! let frames = naming::generate_n_digit_names(1, 3, 4, "png");
+//! let randoms = naming::generate_uuid4_names(3, "tmp");
+//!
+//! // Prefixed N-digit name joins a prefix and the number with a separator (e.g. frame-0005.png)
+//! let prefixed_name = naming::generate_prefixed_n_digit_name("frame", "-", 5, 4, "png");
+//!
+//! // Existing names can have their extension swapped (e.g. `photos/cat.webp`)
+//! let converted = naming::with_extension("photos/cat.png", "webp");
+//!
+//! // Counters hand out sequential names (e.g. `chunk_0.bin`, `chunk_1.bin`, ...)
+//! let mut counter = naming::FtCounter::new();
+//! let chunk_name = counter.next_name("chunk", "bin");
+//!
+//! // Names can be checked and fixed up before use (e.g. `report_Q1_.txt`)
+//! assert!(!naming::is_valid_filename("report:Q1?.txt"));
+//! let safe_name = naming::sanitize_filename("report:Q1?.txt");
 //! ```
 //!
 
+#[cfg(any(feature = "sha2", feature = "blake3"))]
+use anyhow::{Context, Result};
 use chrono::prelude::*;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use uuid::Uuid;
 
 /// Helper for makeing extensions
@@ -91,6 +115,55 @@ pub fn generate_uuid4_name(ext: &str) -> PathBuf {
     PathBuf::from(format!("{}{}", unique, make_extension(ext)))
 }
 
+/// Generates a time ordered UUIDv7 `PathBuf`
+///
+/// The UUID starts with the current Unix timestamp in milliseconds, so names generated later
+/// sort after earlier ones, including names generated within the same millisecond by the
+/// same process.
+///
+/// # Example
+///
+/// ```rust
+/// use filetools::naming::generate_uuid7_name;
+///
+/// // Will generate a UUIDv7 name (e.g. `01890a5d-ac96-774b-bcce-b302099a8057.log`)
+/// let first = generate_uuid7_name("log");
+/// let second = generate_uuid7_name("log");
+/// assert!(first < second);
+/// ```
+pub fn generate_uuid7_name(ext: &str) -> PathBuf {
+    let unique = Uuid::now_v7();
+
+    PathBuf::from(format!("{}{}", unique, make_extension(ext)))
+}
+
+/// UUID versions for [`generate_uuid_name`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FtUuidVersion {
+    /// Random, see [`generate_uuid4_name`]
+    V4,
+
+    /// Time ordered, see [`generate_uuid7_name`]
+    V7,
+}
+
+/// Generates a UUID `PathBuf` of the given version
+///
+/// # Example
+///
+/// ```rust
+/// use filetools::naming::{generate_uuid_name, FtUuidVersion};
+///
+/// // Will generate a UUIDv7 name (e.g. `01890a5d-ac96-774b-bcce-b302099a8057.json`)
+/// let name = generate_uuid_name(FtUuidVersion::V7, "json");
+/// ```
+pub fn generate_uuid_name(version: FtUuidVersion, ext: &str) -> PathBuf {
+    match version {
+        FtUuidVersion::V4 => generate_uuid4_name(ext),
+        FtUuidVersion::V7 => generate_uuid7_name(ext),
+    }
+}
+
 /// Generates a `PathBuf` from a `number` prefixed by `n_digits` zeros.
 ///
 /// If `ext` is empty, will just return the filled number.
@@ -115,6 +188,423 @@ pub fn generate_n_digit_name(number: usize, fill: usize, ext: &str) -> PathBuf {
     ))
 }
 
+/// Generates a `PathBuf` from a `prefix` and a `number` prefixed by `n_digits` zeros,
+/// joined by `sep`.
+///
+/// If `prefix` is empty the separator is left out too, giving the same name as
+/// [`generate_n_digit_name`]. If `ext` is empty, no extension is added.
+///
+/// # Example
+///
+/// ```rust
+/// use filetools::naming::generate_prefixed_n_digit_name;
+///
+/// // Will generate the name `frame-0001.png`
+/// let name = generate_prefixed_n_digit_name("frame", "-", 1, 4, "png");
+///
+/// // Will generate the name `batch0010`
+/// let another_name = generate_prefixed_n_digit_name("batch", "", 10, 4, "");
+/// ```
+pub fn generate_prefixed_n_digit_name(
+    prefix: &str,
+    sep: &str,
+    number: usize,
+    fill: usize,
+    ext: &str,
+) -> PathBuf {
+    let name = generate_n_digit_name(number, fill, ext);
+    if prefix.is_empty() {
+        return name;
+    }
+
+    PathBuf::from(format!("{}{}{}", prefix, sep, name.display()))
+}
+
+/// Generates `count` N-digit names numbered from `start`, see [`generate_n_digit_name`]
+///
+/// # Example
+///
+/// ```rust
+/// use filetools::naming::generate_n_digit_names;
+/// use std::path::PathBuf;
+///
+/// // Will generate `0008.log`, `0009.log` and `0010.log`
+/// let names = generate_n_digit_names(8, 3, 4, "log");
+/// assert_eq!(names[2], PathBuf::from("0010.log"));
+/// ```
+pub fn generate_n_digit_names(start: usize, count: usize, fill: usize, ext: &str) -> Vec<PathBuf> {
+    (start..start + count)
+        .map(|number| generate_n_digit_name(number, fill, ext))
+        .collect()
+}
+
+/// Generates `count` random UUIDv4 names, see [`generate_uuid4_name`]
+///
+/// # Example
+///
+/// ```rust
+/// use filetools::naming::generate_uuid4_names;
+///
+/// // E.g. `00762527-012a-43c1-a673-cad9bc5eef64.tmp`, ...
+/// let names = generate_uuid4_names(10, "tmp");
+/// assert_eq!(names.len(), 10);
+/// ```
+pub fn generate_uuid4_names(count: usize, ext: &str) -> Vec<PathBuf> {
+    (0..count).map(|_| generate_uuid4_name(ext)).collect()
+}
+
+/// Replaces the extension of `path` with `ext`, adding one if it has none.
+///
+/// A leading `.` on `ext` is ignored so `"webp"` and `".webp"` give the same name, and an
+/// empty `ext` removes the extension, matching how the rest of the naming functions treat it.
+///
+/// # Example
+///
+/// ```rust
+/// use filetools::naming::with_extension;
+/// use std::path::PathBuf;
+///
+/// assert_eq!(with_extension("photo.png", "webp"), PathBuf::from("photo.webp"));
+/// assert_eq!(with_extension("notes", ".txt"), PathBuf::from("notes.txt"));
+/// assert_eq!(with_extension("archive.tar.gz", ""), PathBuf::from("archive.tar"));
+/// ```
+pub fn with_extension(path: impl AsRef<Path>, ext: &str) -> PathBuf {
+    path.as_ref().with_extension(ext.trim_start_matches('.'))
+}
+
+/// Hands out sequential names like `prefix_0`, `prefix_1`, ... without the cost of a UUID.
+///
+/// Each counter keeps its own count, so two counters never affect each other.
+/// The number can be zero padded with [`FtCounter::with_fill`].
+///
+/// # Example
+///
+/// ```rust
+/// use filetools::naming::FtCounter;
+/// use std::path::PathBuf;
+///
+/// let mut counter = FtCounter::new().with_fill(3);
+/// assert_eq!(counter.next_name("part", "txt"), PathBuf::from("part_000.txt"));
+/// assert_eq!(counter.next_name("part", "txt"), PathBuf::from("part_001.txt"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FtCounter {
+    next: usize,
+    fill: usize,
+}
+
+impl FtCounter {
+    /// Creates a counter starting at 0 with no padding
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Zero pads the number to `fill` digits
+    pub fn with_fill(mut self, fill: usize) -> Self {
+        self.fill = fill;
+        self
+    }
+
+    /// Starts counting from `start` instead of 0
+    pub fn starting_at(mut self, start: usize) -> Self {
+        self.next = start;
+        self
+    }
+
+    /// Generates the next name, `prefix_N.ext`, and advances the counter.
+    ///
+    /// An empty `prefix` gives just the number and an empty `ext` leaves the extension off.
+    pub fn next_name(&mut self, prefix: &str, ext: &str) -> PathBuf {
+        let name = generate_prefixed_n_digit_name(prefix, "_", self.next, self.fill, ext);
+        self.next += 1;
+
+        name
+    }
+}
+
+/// Characters Windows doesn't allow anywhere in a file name
+const ILLEGAL_CHARS: &[char] = &['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+
+/// Device names Windows reserves, with or without an extension
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Longest file name most filesystems accept, in bytes
+const MAX_FILENAME_LEN: usize = 255;
+
+fn is_illegal_char(c: char) -> bool {
+    ILLEGAL_CHARS.contains(&c) || c.is_control()
+}
+
+/// Whether the part of `name` before the first `.` is a reserved device name like `CON`
+fn is_reserved_name(name: &str) -> bool {
+    let stem = name.split('.').next().unwrap_or_default();
+    RESERVED_NAMES
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(stem))
+}
+
+/// Checks if `name` can be used as a file name on every major OS.
+///
+/// Windows is the strictest so its rules are applied everywhere: no `< > : " / \ | ? *` or
+/// control characters, no trailing dots or spaces, no reserved device names like `CON` or
+/// `LPT1` (with or without an extension) and at most 255 bytes. `.`, `..` and empty names
+/// are never valid.
+///
+/// # Example
+///
+/// ```rust
+/// use filetools::naming::is_valid_filename;
+///
+/// assert!(is_valid_filename("report.txt"));
+/// assert!(!is_valid_filename("CON"));
+/// assert!(!is_valid_filename("a<b.txt"));
+/// assert!(!is_valid_filename("trailing."));
+/// ```
+pub fn is_valid_filename(name: &str) -> bool {
+    !name.is_empty()
+        && name != "."
+        && name != ".."
+        && name.len() <= MAX_FILENAME_LEN
+        && !name.contains(is_illegal_char)
+        && !name.ends_with(['.', ' '])
+        && !is_reserved_name(name)
+}
+
+/// Fixes up `name` so it passes [`is_valid_filename`].
+///
+/// Illegal characters are replaced with `_`, trailing dots and spaces are removed, reserved
+/// device names get a `_` after them (`CON.txt` becomes `CON_.txt`) and names that are too
+/// long are cut down to 255 bytes. Names left empty become `_`. Valid names are returned as is.
+///
+/// # Example
+///
+/// ```rust
+/// use filetools::naming::sanitize_filename;
+///
+/// assert_eq!(sanitize_filename("a<b.txt"), "a_b.txt");
+/// assert_eq!(sanitize_filename("CON"), "CON_");
+/// assert_eq!(sanitize_filename("trailing. "), "trailing");
+/// assert_eq!(sanitize_filename("report.txt"), "report.txt");
+/// ```
+pub fn sanitize_filename(name: &str) -> String {
+    let mut sanitized: String = name
+        .chars()
+        .map(|c| if is_illegal_char(c) { '_' } else { c })
+        .collect();
+
+    if sanitized.len() > MAX_FILENAME_LEN {
+        let mut end = MAX_FILENAME_LEN;
+        while !sanitized.is_char_boundary(end) {
+            end -= 1;
+        }
+        sanitized.truncate(end);
+    }
+
+    let trimmed = sanitized.trim_end_matches(['.', ' ']).len();
+    sanitized.truncate(trimmed);
+    if sanitized.is_empty() {
+        return "_".to_string();
+    }
+
+    if is_reserved_name(&sanitized) {
+        let stem_end = sanitized.find('.').unwrap_or(sanitized.len());
+        sanitized.insert(stem_end, '_');
+        // Keep within the length limit after the insert
+        if sanitized.len() > MAX_FILENAME_LEN {
+            sanitized.pop();
+        }
+    }
+
+    sanitized
+}
+
+/// Sharding levels used by [`content_addressed_name`], as in `ab/cd/abcdef...`
+#[cfg(any(feature = "sha2", feature = "blake3"))]
+pub const DEFAULT_SHARD_LEVELS: usize = 2;
+
+/// Hash used to name files by their contents, see [`content_addressed_name`]
+///
+/// Each variant is only available with its feature enabled. The algorithm is always picked
+/// explicitly so enabling another feature elsewhere in the dependency tree never changes
+/// the names of an existing store.
+#[cfg(any(feature = "sha2", feature = "blake3"))]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum FtHashAlgorithm {
+    /// SHA-256, needs the `sha2` feature
+    #[cfg(feature = "sha2")]
+    Sha256,
+
+    /// BLAKE3, needs the `blake3` feature
+    #[cfg(feature = "blake3")]
+    Blake3,
+}
+
+/// Incremental state of an [`FtHashAlgorithm`]
+#[cfg(any(feature = "sha2", feature = "blake3"))]
+enum ContentHasher {
+    #[cfg(feature = "sha2")]
+    Sha256(sha2::Sha256),
+
+    #[cfg(feature = "blake3")]
+    Blake3(Box<blake3::Hasher>),
+}
+
+#[cfg(any(feature = "sha2", feature = "blake3"))]
+impl ContentHasher {
+    fn new(algorithm: FtHashAlgorithm) -> Self {
+        match algorithm {
+            #[cfg(feature = "sha2")]
+            FtHashAlgorithm::Sha256 => Self::Sha256(<sha2::Sha256 as sha2::Digest>::new()),
+            #[cfg(feature = "blake3")]
+            FtHashAlgorithm::Blake3 => Self::Blake3(Box::default()),
+        }
+    }
+}
+
+#[cfg(any(feature = "sha2", feature = "blake3"))]
+impl crate::FtHasher for ContentHasher {
+    fn update(&mut self, chunk: &[u8]) {
+        match self {
+            #[cfg(feature = "sha2")]
+            Self::Sha256(hasher) => sha2::Digest::update(hasher, chunk),
+            #[cfg(feature = "blake3")]
+            Self::Blake3(hasher) => {
+                hasher.update(chunk);
+            }
+        }
+    }
+
+    fn finish(self) -> String {
+        match self {
+            #[cfg(feature = "sha2")]
+            Self::Sha256(hasher) => sha2::Digest::finalize(hasher)
+                .iter()
+                .map(|b| format!("{b:02x}"))
+                .collect(),
+            #[cfg(feature = "blake3")]
+            Self::Blake3(hasher) => hasher.finalize().to_hex().to_string(),
+        }
+    }
+}
+
+/// Splits the digest into `levels` two character directories followed by the full digest
+#[cfg(any(feature = "sha2", feature = "blake3"))]
+fn sharded_name(digest: &str, levels: usize, ext: &str) -> Result<PathBuf> {
+    anyhow::ensure!(
+        levels * 2 <= digest.len(),
+        "too many sharding levels for a {} character hash",
+        digest.len()
+    );
+
+    let mut name: PathBuf = (0..levels).map(|l| &digest[l * 2..l * 2 + 2]).collect();
+    name.push(format!("{}{}", digest, make_extension(ext)));
+
+    Ok(name)
+}
+
+/// Generates a content addressed `PathBuf` from the contents of the file at `path`.
+///
+/// The name is the hex hash of the file, sharded into [`DEFAULT_SHARD_LEVELS`] directories
+/// made from its leading characters (e.g. `ab/cd/abcdef....ext`), the same way git stores objects.
+/// Files with the same contents and `algorithm` always get the same name. The file is read
+/// in chunks on the blocking pool.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::content_addressed_name`]
+///
+/// # Errors
+///
+/// This function will return an error if the file cannot be read.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::naming::{content_addressed_name, FtHashAlgorithm};
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     // E.g. `9f/86/9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08.blob`
+///     # #[cfg(feature = "sha2")]
+///     let name = content_addressed_name("data/report.pdf", FtHashAlgorithm::Sha256, "blob").await?;
+///
+///     Ok(())
+/// }
+/// ```
+#[cfg(any(feature = "sha2", feature = "blake3"))]
+pub async fn content_addressed_name(
+    path: impl AsRef<Path>,
+    algorithm: FtHashAlgorithm,
+    ext: &str,
+) -> Result<PathBuf> {
+    content_addressed_name_with_levels(path, algorithm, ext, DEFAULT_SHARD_LEVELS).await
+}
+
+/// Generates a content addressed `PathBuf` with `levels` sharding directories.
+///
+/// Identical to [`content_addressed_name`] except the number of sharding directories is configurable,
+/// a `levels` of 0 gives just the hash with no directories.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::content_addressed_name_with_levels`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The file cannot be read
+/// * There are more `levels` than the hash has character pairs
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::naming::{content_addressed_name_with_levels, FtHashAlgorithm};
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     // E.g. `9f/9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08.blob`
+///     # #[cfg(feature = "sha2")]
+///     let name =
+///         content_addressed_name_with_levels("data/report.pdf", FtHashAlgorithm::Sha256, "blob", 1)
+///             .await?;
+///
+///     Ok(())
+/// }
+/// ```
+#[cfg(any(feature = "sha2", feature = "blake3"))]
+pub async fn content_addressed_name_with_levels(
+    path: impl AsRef<Path>,
+    algorithm: FtHashAlgorithm,
+    ext: &str,
+    levels: usize,
+) -> Result<PathBuf> {
+    let path = path.as_ref().to_path_buf();
+    let digest = tokio::task::spawn_blocking(move || {
+        crate::util::hashfile(&path, ContentHasher::new(algorithm))
+    })
+    .await
+    .context("file hashing task failed")??;
+
+    sharded_name(&digest, levels, ext)
+}
+
+/// Sync version of [`content_addressed_name_with_levels`], exposed through [`crate::sync`]
+#[cfg(any(feature = "sha2", feature = "blake3"))]
+pub(crate) fn content_addressed_name_sync(
+    path: impl AsRef<Path>,
+    algorithm: FtHashAlgorithm,
+    ext: &str,
+    levels: usize,
+) -> Result<PathBuf> {
+    let digest = crate::util::hashfile(path.as_ref(), ContentHasher::new(algorithm))?;
+    sharded_name(&digest, levels, ext)
+}
+
 #[cfg(test)]
 mod naming_tests {
     use super::*;
@@ -165,4 +655,272 @@ mod naming_tests {
         assert!(uuid_re.is_match(rn_name));
         assert!(rn_name.ends_with(".json"));
     }
+
+    #[test]
+    fn uuid7_names_sort_in_creation_order() {
+        let uuid7_re =
+            Regex::new(r"^[0-9a-f]{8}-[0-9a-f]{4}-7[0-9a-f]{3}-[0-9a-f]{4}-[0-9a-f]{12}\.json$")
+                .unwrap();
+
+        let names: Vec<PathBuf> = (0..100).map(|_| generate_uuid7_name("json")).collect();
+        assert!(names.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(names.iter().all(|n| uuid7_re.is_match(n.to_str().unwrap())));
+
+        let generic = generate_uuid_name(FtUuidVersion::V7, "json");
+        assert!(uuid7_re.is_match(generic.to_str().unwrap()));
+        assert!(names.last().unwrap() < &generic);
+
+        let v4 = generate_uuid_name(FtUuidVersion::V4, "");
+        assert_eq!(v4.to_str().unwrap().chars().nth(14), Some('4'));
+        assert!(v4.extension().is_none());
+    }
+
+    #[test]
+    fn generates_n_digit_names() {
+        assert_eq!(
+            generate_n_digit_name(5, 4, "json"),
+            PathBuf::from("0005.json")
+        );
+        assert_eq!(generate_n_digit_name(128, 2, ""), PathBuf::from("128"));
+    }
+
+    #[test]
+    fn generates_prefixed_n_digit_names() {
+        // Dash separator
+        assert_eq!(
+            generate_prefixed_n_digit_name("frame", "-", 1, 4, ""),
+            PathBuf::from("frame-0001")
+        );
+
+        // Underscore separator with an extension
+        assert_eq!(
+            generate_prefixed_n_digit_name("img", "_", 42, 3, "png"),
+            PathBuf::from("img_042.png")
+        );
+
+        // No separator
+        assert_eq!(
+            generate_prefixed_n_digit_name("batch", "", 10, 4, ""),
+            PathBuf::from("batch0010")
+        );
+
+        // Multi character separator
+        assert_eq!(
+            generate_prefixed_n_digit_name("part", " - ", 7, 2, "txt"),
+            PathBuf::from("part - 07.txt")
+        );
+
+        // No prefix means no separator either
+        assert_eq!(
+            generate_prefixed_n_digit_name("", "-", 3, 2, "log"),
+            generate_n_digit_name(3, 2, "log")
+        );
+    }
+
+    #[test]
+    fn validates_filenames() {
+        assert!(is_valid_filename("report.txt"));
+        assert!(is_valid_filename("CONSOLE.log"));
+        assert!(is_valid_filename(".gitignore"));
+
+        assert!(!is_valid_filename("CON"));
+        assert!(!is_valid_filename("con.txt"));
+        assert!(!is_valid_filename("Lpt1"));
+        assert!(!is_valid_filename("a<b.txt"));
+        assert!(!is_valid_filename("tab\there"));
+        assert!(!is_valid_filename("trailing."));
+        assert!(!is_valid_filename("trailing "));
+        assert!(!is_valid_filename(""));
+        assert!(!is_valid_filename(".."));
+        assert!(!is_valid_filename(&"a".repeat(256)));
+    }
+
+    #[test]
+    fn sanitizes_filenames() {
+        let cases = [
+            ("report.txt", "report.txt"),
+            ("CON", "CON_"),
+            ("con.tar.gz", "con_.tar.gz"),
+            ("a<b.txt", "a_b.txt"),
+            ("what?:*.md", "what___.md"),
+            ("trailing.", "trailing"),
+            ("trailing . . ", "trailing"),
+            ("..", "_"),
+            ("", "_"),
+        ];
+        for (name, expected) in cases {
+            assert_eq!(sanitize_filename(name), expected, "sanitizing {name:?}");
+            assert!(is_valid_filename(&sanitize_filename(name)));
+        }
+
+        let long = sanitize_filename(&"é".repeat(200));
+        assert!(long.len() <= 255);
+        assert!(is_valid_filename(&long));
+    }
+
+    #[test]
+    fn counters_are_sequential_and_independent() {
+        let mut counter = FtCounter::new();
+        let names: Vec<PathBuf> = (0..3).map(|_| counter.next_name("chunk", "bin")).collect();
+        assert_eq!(
+            names,
+            vec![
+                PathBuf::from("chunk_0.bin"),
+                PathBuf::from("chunk_1.bin"),
+                PathBuf::from("chunk_2.bin"),
+            ]
+        );
+
+        let mut other = FtCounter::new().with_fill(4).starting_at(10);
+        assert_eq!(other.next_name("img", "png"), PathBuf::from("img_0010.png"));
+        assert_eq!(
+            counter.next_name("chunk", "bin"),
+            PathBuf::from("chunk_3.bin")
+        );
+        assert_eq!(other.next_name("", ""), PathBuf::from("0011"));
+    }
+
+    #[cfg(any(feature = "sha2", feature = "blake3"))]
+    #[tokio::test]
+    async fn content_addressed_names_follow_contents() -> anyhow::Result<()> {
+        use crate::util::TempPath;
+
+        #[cfg(feature = "sha2")]
+        let algorithm = FtHashAlgorithm::Sha256;
+        #[cfg(not(feature = "sha2"))]
+        let algorithm = FtHashAlgorithm::Blake3;
+
+        let root = TempPath::new("naming_content_addressed").await?;
+        root.multi_file(vec!["a.txt", "b.txt", "c.txt"]).await?;
+        tokio::fs::write(root.path.join("a.txt"), "same").await?;
+        tokio::fs::write(root.path.join("b.txt"), "same").await?;
+        tokio::fs::write(root.path.join("c.txt"), "different").await?;
+
+        let a = content_addressed_name(root.path.join("a.txt"), algorithm, "blob").await?;
+        let b = content_addressed_name(root.path.join("b.txt"), algorithm, "blob").await?;
+        let c = content_addressed_name(root.path.join("c.txt"), algorithm, "blob").await?;
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+
+        // `ab/cd/abcd....blob`
+        let parts: Vec<String> = a
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(parts.len(), 3);
+        assert!(parts[2].starts_with(&format!("{}{}", parts[0], parts[1])));
+        assert!(parts[2].ends_with(".blob"));
+
+        let flat = content_addressed_name_sync(root.path.join("a.txt"), algorithm, "", 0)?;
+        assert_eq!(flat.components().count(), 1);
+        assert_eq!(a.file_name().unwrap(), &*format!("{}.blob", flat.display()));
+
+        assert!(content_addressed_name_sync(root.path.join("a.txt"), algorithm, "", 33).is_err());
+        assert!(
+            content_addressed_name(root.path.join("missing.txt"), algorithm, "")
+                .await
+                .is_err()
+        );
+
+        Ok(())
+    }
+
+    #[cfg(feature = "sha2")]
+    #[tokio::test]
+    async fn content_addressed_names_use_the_given_algorithm() -> anyhow::Result<()> {
+        use crate::util::{TempPath, READ_CHUNK};
+        use sha2::{Digest, Sha256};
+
+        let root = TempPath::new("naming_content_algorithm").await?;
+        tokio::fs::write(root.path.join("same.txt"), "same").await?;
+        let big: Vec<u8> = (0..READ_CHUNK * 3 + 1).map(|i| i as u8).collect();
+        tokio::fs::write(root.path.join("big.bin"), &big).await?;
+
+        let sha = content_addressed_name_with_levels(
+            root.path.join("same.txt"),
+            FtHashAlgorithm::Sha256,
+            "",
+            0,
+        )
+        .await?;
+        assert_eq!(
+            sha,
+            PathBuf::from("0967115f2813a3541eaef77de9d9d5773f1c0c04314b0bbfe4ff3b3b1c55b5d5")
+        );
+
+        // Read in chunks but hashed as a whole
+        let expected: String = Sha256::digest(&big)
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect();
+        let chunked =
+            content_addressed_name_sync(root.path.join("big.bin"), FtHashAlgorithm::Sha256, "", 0)?;
+        assert_eq!(chunked, PathBuf::from(expected));
+
+        #[cfg(feature = "blake3")]
+        {
+            let blake = content_addressed_name_with_levels(
+                root.path.join("big.bin"),
+                FtHashAlgorithm::Blake3,
+                "",
+                0,
+            )
+            .await?;
+            assert_eq!(blake, PathBuf::from(blake3::hash(&big).to_hex().as_str()));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn swaps_extensions() {
+        // Add
+        assert_eq!(with_extension("photo", "webp"), PathBuf::from("photo.webp"));
+        assert_eq!(
+            with_extension("dir.d/photo", ".webp"),
+            PathBuf::from("dir.d/photo.webp")
+        );
+
+        // Replace
+        assert_eq!(
+            with_extension("photos/cat.png", "webp"),
+            PathBuf::from("photos/cat.webp")
+        );
+        assert_eq!(
+            with_extension("cat.png", ".webp"),
+            PathBuf::from("cat.webp")
+        );
+        assert_eq!(
+            with_extension("backup.tar.gz", "zst"),
+            PathBuf::from("backup.tar.zst")
+        );
+
+        // Remove
+        assert_eq!(
+            with_extension("photos/cat.png", ""),
+            PathBuf::from("photos/cat")
+        );
+        assert_eq!(with_extension("cat", ""), PathBuf::from("cat"));
+        assert_eq!(with_extension("cat.png", "."), PathBuf::from("cat"));
+    }
+
+    #[test]
+    fn generates_names_in_batches() {
+        let names = generate_n_digit_names(98, 3, 3, "png");
+        assert_eq!(
+            names,
+            vec![
+                PathBuf::from("098.png"),
+                PathBuf::from("099.png"),
+                PathBuf::from("100.png"),
+            ]
+        );
+        assert!(generate_n_digit_names(5, 0, 3, "png").is_empty());
+
+        let names = generate_uuid4_names(50, "tmp");
+        assert_eq!(names.len(), 50);
+        let unique: std::collections::HashSet<_> = names.iter().collect();
+        assert_eq!(unique.len(), 50);
+        assert!(names.iter().all(|n| n.extension().unwrap() == "tmp"));
+    }
 }
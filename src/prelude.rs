@@ -0,0 +1,60 @@
+//! The most commonly used items, importable in one go
+//!
+//! Covers [`FtFilter`], the async listing functions, [`ensure_directory`] and the
+//! [`naming`](crate::naming) generators. The sync versions live in [`crate::sync`].
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use filetools::prelude::*;
+//!
+//! #[tokio::main]
+//! async fn main() -> anyhow::Result<()> {
+//!     ensure_directory("output/logs").await?;
+//!
+//!     let filter = FtFilter::Raw(".log".to_string());
+//!     let logs = list_nested_files_with_filter("/var/log", filter).await?;
+//!
+//!     let archive = generate_timestamped_name("logs", "tar.gz");
+//!
+//!     Ok(())
+//! }
+//! ```
+
+pub use crate::naming::{
+    generate_n_digit_name, generate_name, generate_prefixed_n_digit_name,
+    generate_timestamped_name, generate_uuid4_name,
+};
+pub use crate::{
+    ensure_directory, list_directories, list_directories_with_filter, list_files,
+    list_files_with_filter, list_nested_directories, list_nested_directories_with_filter,
+    list_nested_files, list_nested_files_with_filter, FtFilter,
+};
+
+#[cfg(test)]
+mod prelude_tests {
+    use crate::prelude::*;
+    use crate::util::TempPath;
+    use anyhow::Result;
+
+    #[tokio::test]
+    async fn glob_import_covers_common_usage() -> Result<()> {
+        let root = TempPath::new("prelude_glob_import").await?;
+        ensure_directory(root.path.join("out")).await?;
+
+        let name = generate_n_digit_name(7, 3, "txt");
+        tokio::fs::write(root.path.join("out").join(&name), "").await?;
+
+        let files = list_nested_files(&root.path).await?;
+        assert_eq!(files, vec![root.path.join("out/007.txt")]);
+
+        let filter = FtFilter::Raw("007".to_string());
+        assert_eq!(
+            list_nested_files_with_filter(&root.path, filter).await?,
+            files
+        );
+        assert_eq!(list_directories(&root.path).await?.len(), 1);
+
+        Ok(())
+    }
+}
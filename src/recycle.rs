@@ -0,0 +1,162 @@
+//! Sending files to the system trash instead of deleting them
+//!
+//! Requires the `trash` feature.
+
+use crate::{FtError, FtFilter};
+use anyhow::{Context, Result};
+use std::borrow::Borrow;
+use std::path::{Path, PathBuf};
+
+/// Moves a file or directory to the system trash / recycle bin.
+///
+/// Nothing is ever deleted permanently: if the platform has no trash to move it to, an error
+/// is returned and the path is left where it is.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::move_to_trash`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path does not exist
+/// * The platform has no trash or the path could not be moved to it
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::move_to_trash;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     // Gone from the project but still recoverable
+///     move_to_trash("my/project/old_notes.txt").await?;
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn move_to_trash(path: impl AsRef<Path> + Send) -> Result<()> {
+    // Trashing is blocking so keep it off the async threads
+    let path = path.as_ref().to_path_buf();
+    tokio::task::spawn_blocking(move || move_to_trash_sync(&path))
+        .await
+        .context("trash task failed")?
+}
+
+/// Sync version of [`move_to_trash`], exposed as [`crate::sync::move_to_trash`]
+pub(crate) fn move_to_trash_sync(path: &Path) -> Result<()> {
+    anyhow::ensure!(path.exists(), FtError::NotFound(path.to_path_buf()));
+
+    ::trash::delete(path).with_context(|| format!("unable to move {} to the trash", path.display()))
+}
+
+/// Moves all files directly inside a directory that match a filter to the system trash /
+/// recycle bin.
+///
+/// The trash counterpart of removing the files, nothing is ever deleted permanently: if the
+/// platform has no trash, an error is returned and the files are left where they are.
+/// Returns the paths of the trashed files.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::remove_files_matching_to_trash`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+/// * The platform has no trash or the files could not be moved to it
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::{remove_files_matching_to_trash, FtFilter};
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let logs = FtFilter::Raw(".log".to_string());
+///     let trashed = remove_files_matching_to_trash("my/app", logs).await?;
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn remove_files_matching_to_trash(
+    path: impl AsRef<Path> + Send,
+    filter: impl Borrow<FtFilter> + Send,
+) -> Result<Vec<PathBuf>> {
+    let files = crate::list_files_with_filter(path, filter).await?;
+
+    let trashing = files.clone();
+    tokio::task::spawn_blocking(move || trash_all(&trashing))
+        .await
+        .context("trash task failed")??;
+
+    Ok(files)
+}
+
+/// Sync version of [`remove_files_matching_to_trash`], exposed as
+/// [`crate::sync::remove_files_matching_to_trash`]
+pub(crate) fn remove_files_matching_to_trash_sync(
+    path: impl AsRef<Path>,
+    filter: impl Borrow<FtFilter>,
+) -> Result<Vec<PathBuf>> {
+    let files = crate::sync::list_files_with_filter(path, filter)?;
+    trash_all(&files)?;
+
+    Ok(files)
+}
+
+/// Trashes all `files` in one go, platforms batch these into a single operation
+fn trash_all(files: &[PathBuf]) -> Result<()> {
+    if files.is_empty() {
+        return Ok(());
+    }
+
+    ::trash::delete_all(files).context("unable to move files to the trash")
+}
+
+#[cfg(test)]
+mod recycle_tests {
+    use super::*;
+    use crate::util::TempPath;
+
+    #[tokio::test]
+    async fn missing_files_are_not_trashed() -> Result<()> {
+        let root = TempPath::new("recycle_missing_files").await?;
+        assert!(move_to_trash(root.path.join("missing.txt")).await.is_err());
+        assert!(move_to_trash_sync(&root.path.join("missing.txt")).is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[ignore = "moves files into the real trash of the user running the tests"]
+    async fn trashed_files_leave_their_directory() -> Result<()> {
+        let root = TempPath::new("recycle_trashed_files").await?;
+        root.multi_file(vec!["notes.txt", "a.log", "b.log", "keep.txt"])
+            .await?;
+
+        move_to_trash(root.path.join("notes.txt")).await?;
+        assert!(!root.path.join("notes.txt").exists());
+        assert!(move_to_trash(root.path.join("notes.txt")).await.is_err());
+
+        let mut trashed =
+            remove_files_matching_to_trash(&root.path, FtFilter::Raw(".log".to_string())).await?;
+        trashed.sort();
+        assert_eq!(
+            trashed,
+            vec![root.path.join("a.log"), root.path.join("b.log")]
+        );
+        assert!(!root.path.join("a.log").exists());
+        assert!(!root.path.join("b.log").exists());
+        assert!(root.path.join("keep.txt").is_file());
+
+        let filter = FtFilter::Raw(".log".to_string());
+        assert!(remove_files_matching_to_trash_sync(&root.path, filter)?.is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,153 @@
+//! Rendering directory trees as `tree` command style text
+
+use crate::tree::build_tree_sync;
+use crate::{build_tree, FtFilter, FtTreeNode};
+use anyhow::Result;
+use std::path::Path;
+
+/// Appends the lines for the children of `node`, each prefixed with `indent`
+fn render_children(node: &FtTreeNode, indent: &str, out: &mut String) {
+    let count = node.children.len();
+    for (i, child) in node.children.iter().enumerate() {
+        let last = i + 1 == count;
+        let (connector, nested) = if last {
+            ("└── ", "    ")
+        } else {
+            ("├── ", "│   ")
+        };
+
+        let name = child
+            .path
+            .file_name()
+            .map(|n| n.to_string_lossy())
+            .unwrap_or_else(|| child.path.to_string_lossy());
+
+        out.push_str(indent);
+        out.push_str(connector);
+        out.push_str(&name);
+        out.push('\n');
+
+        render_children(child, &format!("{indent}{nested}"), out);
+    }
+}
+
+/// Renders a tree with the root path on the first line and one line per item below it
+pub(crate) fn render(tree: &FtTreeNode) -> String {
+    let mut out = format!("{}\n", tree.path.display());
+    render_children(tree, "", &mut out);
+    out
+}
+
+/// Formats a directory as the familiar `tree` command output for logs and CLIs.
+///
+/// The first line is `path` as given, followed by one line per item drawn with `├──`, `└──`
+/// and `│` connectors. Items are sorted by name and every line ends with a newline.
+/// The filter and depth limit work as in [`build_tree`].
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::format_tree`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+/// * A directory could not be read
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::format_tree;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     // my/project
+///     // ├── Cargo.toml
+///     // └── src
+///     //     └── main.rs
+///     print!("{}", format_tree("my/project", None, None).await?);
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn format_tree(
+    path: impl AsRef<Path> + Send,
+    filter: Option<FtFilter>,
+    max_depth: Option<usize>,
+) -> Result<String> {
+    let tree = build_tree(path, filter, max_depth).await?;
+    Ok(render(&tree))
+}
+
+/// Sync version of [`format_tree`], exposed as [`crate::sync::format_tree`]
+pub(crate) fn format_tree_sync(
+    path: impl AsRef<Path>,
+    filter: Option<FtFilter>,
+    max_depth: Option<usize>,
+) -> Result<String> {
+    let tree = build_tree_sync(path, filter, max_depth)?;
+    Ok(render(&tree))
+}
+
+#[cfg(test)]
+mod render_tests {
+    use super::*;
+    use crate::util::TempPath;
+
+    #[tokio::test]
+    async fn draws_tree_connectors() -> Result<()> {
+        let root = TempPath::new("render_draws_tree_connectors").await?;
+        root.multi_folder(vec!["src/bin", "docs", "empty"]).await?;
+        root.multi_file(vec![
+            "Cargo.toml",
+            "src/lib.rs",
+            "src/bin/cli.rs",
+            "src/bin/tool.rs",
+            "docs/guide.md",
+        ])
+        .await?;
+
+        let expected = format!(
+            "{}
+├── Cargo.toml
+├── docs
+│   └── guide.md
+├── empty
+└── src
+    ├── bin
+    │   ├── cli.rs
+    │   └── tool.rs
+    └── lib.rs
+",
+            root.path.display()
+        );
+        assert_eq!(format_tree(&root.path, None, None).await?, expected);
+        assert_eq!(format_tree_sync(&root.path, None, None)?, expected);
+
+        let filter = FtFilter::Raw("cli".to_string());
+        let expected = format!(
+            "{}
+└── src
+    └── bin
+        └── cli.rs
+",
+            root.path.display()
+        );
+        assert_eq!(format_tree(&root.path, Some(filter), None).await?, expected);
+
+        let expected = format!(
+            "{}
+├── Cargo.toml
+├── docs
+├── empty
+└── src
+",
+            root.path.display()
+        );
+        assert_eq!(format_tree_sync(&root.path, None, Some(1))?, expected);
+
+        Ok(())
+    }
+}
@@ -1,20 +1,56 @@
 //! Sync variations of the main [`crate`] functions
 //!
 //! All operations are identical to those defined in the `async` version.
-use crate::util::FtIterItemState;
-use crate::{naming::generate_n_digit_name, util::iteritems_sync, FtFilter};
+use crate::util::{
+    brokenlinks_sync, check_numeric_range, copy_metadata, copy_tree_sync, countitems_sync,
+    detaileditems_sync, dirs_holding_files, emptied_dirs, ensure_dir_root, firstitem_sync,
+    free_path, gitrepos_sync, group_by_extension, iteritems_into_sync, iteritems_sync,
+    limiteditems_sync, modified_key, move_file_sync, multiitems_sync, oldest_key, partition_plan,
+    progressitems_sync, rename_plan, strip_root, tree_targets, walk_sync, CancellableItems,
+    EntryItems, ExcludedItems, FileIter, FtIterItemState, PartialItems, PrunedItems, ScannedItems,
+    StatsItems, TopItems,
+};
+use crate::{
+    DirEntryKind, FtCopyOptions, FtCopyReport, FtError, FtFileInfo, FtFilter, FtFilterResult,
+    FtHasher, FtIterTarget, FtPage, FtPager, FtPartialListing, FtPartition, FtTraversalStats,
+    FtTreeNode, MAX_NUMERIC_DIRECTORIES, PARTITION_FILL,
+};
 use anyhow::{Context, Result};
+use std::borrow::Borrow;
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, HashMap};
 use std::fs;
+use std::ops::ControlFlow;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::time::{Instant, SystemTime};
+
+// Dry runs never touch the filesystem so they are shared with the async API
+pub use crate::{
+    create_multiple_directories_dry_run, create_numeric_directories_dry_run,
+    create_numeric_directories_ext_dry_run,
+};
+
+// Watching runs on its own thread so the same function works for sync code
+#[cfg(feature = "notify")]
+pub use crate::watch_directory;
 
 /// Creates a directory at the given path.
 ///
-/// If the directory already exists, nothing is done
+/// If the directory already exists, nothing is done. A file at the path is an error
+/// rather than being mistaken for the directory.
 ///
 /// ## Async
 ///
 /// For the `async` version, see: [`crate::ensure_directory`]
 ///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The path already exists but is a file, not a directory
+/// * The directory could not be created
+///
 /// # Example
 ///
 /// ```rust,no_run
@@ -25,13 +61,135 @@ use std::path::{Path, PathBuf};
 ///
 /// ```
 pub fn ensure_directory(dir: impl AsRef<Path>) -> Result<()> {
-    if !dir.as_ref().exists() {
-        fs::create_dir_all(dir).context("unable to create directory")?;
-    }
+    ensure_directory_reported(dir)?;
 
     Ok(())
 }
 
+/// Creates a directory at the given path, reporting whether it was created.
+///
+/// Returns `true` if the directory was created and `false` if it already existed.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::ensure_directory_reported`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The path already exists but is a file, not a directory
+/// * The directory could not be created
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::ensure_directory_reported;
+///
+/// let target_path = "directory/to/create";
+/// if ensure_directory_reported(target_path).expect("unable to create directory") {
+///     println!("created {target_path}");
+/// }
+/// ```
+pub fn ensure_directory_reported(dir: impl AsRef<Path>) -> Result<bool> {
+    if dir.as_ref().exists() {
+        anyhow::ensure!(
+            dir.as_ref().is_dir(),
+            FtError::NotADirectory(dir.as_ref().to_path_buf())
+        );
+        return Ok(false);
+    }
+
+    fs::create_dir_all(dir).context("unable to create directory")?;
+
+    Ok(true)
+}
+
+/// Creates a directory at the given path with the given Unix permission bits.
+///
+/// Like [`ensure_directory`], missing parents are created and an existing directory is left
+/// as it is, permissions included. Parents get the default permissions, only the directory
+/// itself gets `mode`, which is still masked by the process umask like `mkdir(2)`, so bits
+/// the umask clears are not set.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::ensure_directory_with_mode`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The path already exists but is a file, not a directory
+/// * The directory could not be created
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::ensure_directory_with_mode;
+///
+/// // Only readable by the current user
+/// ensure_directory_with_mode("secrets/keys", 0o700).expect("unable to create directory");
+/// ```
+#[cfg(unix)]
+pub fn ensure_directory_with_mode(dir: impl AsRef<Path>, mode: u32) -> Result<()> {
+    use std::os::unix::fs::DirBuilderExt;
+
+    if dir.as_ref().exists() {
+        anyhow::ensure!(
+            dir.as_ref().is_dir(),
+            FtError::NotADirectory(dir.as_ref().to_path_buf())
+        );
+        return Ok(());
+    }
+
+    let dir = dir.as_ref();
+    if let Some(parent) = dir.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).context("unable to create directory")?;
+    }
+
+    match fs::DirBuilder::new().mode(mode).create(dir) {
+        // Created in the meantime, left as it is like an existing directory
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists && dir.is_dir() => Ok(()),
+        created => created.context("unable to create directory"),
+    }
+}
+
+/// Creates an empty file at the given path, or updates its modification time if it
+/// already exists (like `touch`).
+///
+/// Any missing parent directories are created first.
+/// Returns `true` if the file was created and `false` if it already existed.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::ensure_file`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * A missing parent directory could not be created
+/// * The file could not be created
+/// * The existing file could not be opened to update its modification time
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::ensure_file;
+///
+/// // Creates `logs/app` if needed, then `logs/app/output.log`
+/// let created = ensure_file("logs/app/output.log").expect("unable to create file");
+/// ```
+pub fn ensure_file(path: impl AsRef<Path>) -> Result<bool> {
+    let path = path.as_ref();
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        ensure_directory(parent)?;
+    }
+
+    crate::util::touch(path)
+}
+
 /// Creates a range of numeric folders in the given path
 ///
 /// Directories can be padded with X zeros using the `fill` parameter.
@@ -40,6 +198,14 @@ pub fn ensure_directory(dir: impl AsRef<Path>) -> Result<()> {
 ///
 /// For the `async` version, see: [`crate::create_numeric_directories`]
 ///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The range covers more than [`crate::MAX_NUMERIC_DIRECTORIES`] directories, checked
+///   before anything is created. Use [`create_numeric_directories_with_limit`] to raise the limit
+/// * A directory could not be created
+///
 /// # Example
 ///
 /// ```rust,no_run
@@ -59,8 +225,86 @@ pub fn create_numeric_directories(
     end: usize,
     fill: usize,
 ) -> Result<()> {
-    for i in start..end {
-        let name = path.as_ref().join(generate_n_digit_name(i, fill, ""));
+    create_numeric_directories_with_limit(path, start, end, fill, MAX_NUMERIC_DIRECTORIES)
+}
+
+/// Creates a range of numeric folders in the given path, refusing to create more than `limit`.
+///
+/// Identical to [`create_numeric_directories`] except for the limit, which guards against
+/// a mistyped range creating millions of directories.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::create_numeric_directories_with_limit`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The range covers more than `limit` directories, checked before anything is created
+/// * A directory could not be created
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::create_numeric_directories_with_limit;
+///
+/// // Really do want a million directories
+/// create_numeric_directories_with_limit("frames", 0, 1_000_000, 7, 1_000_000)
+///     .expect("unable to create numeric directories");
+/// ```
+pub fn create_numeric_directories_with_limit(
+    path: impl AsRef<Path>,
+    start: usize,
+    end: usize,
+    fill: usize,
+    limit: usize,
+) -> Result<()> {
+    check_numeric_range(end.saturating_sub(start), limit)?;
+    for name in crate::util::numeric_dirs(path.as_ref(), start, end, fill) {
+        ensure_directory(name).context("creating numeric directories")?;
+    }
+
+    Ok(())
+}
+
+/// Creates numeric folders in the given path with a custom step, prefix and extension.
+///
+/// Every `step`th number from `start` up to (but not including) `end` is padded with zeros
+/// to `fill` digits and placed between `prefix` and `ext`, so a step of 5 with the prefix
+/// `batch_` gives `batch_0000`, `batch_0005`, `batch_0010`, ...
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::create_numeric_directories_ext`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * `step` is zero
+/// * The range covers more than [`crate::MAX_NUMERIC_DIRECTORIES`] directories
+/// * A directory could not be created
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::create_numeric_directories_ext;
+///
+/// // Creates "batches/batch_0000", "batches/batch_0005" and "batches/batch_0010"
+/// create_numeric_directories_ext("batches", 0, 15, 5, 4, "batch_", "")
+///     .expect("unable to create numeric directories");
+/// ```
+pub fn create_numeric_directories_ext(
+    path: impl AsRef<Path>,
+    start: usize,
+    end: usize,
+    step: usize,
+    fill: usize,
+    prefix: &str,
+    ext: &str,
+) -> Result<()> {
+    for name in create_numeric_directories_ext_dry_run(path, start, end, step, fill, prefix, ext)? {
         ensure_directory(name).context("creating numeric directories")?;
     }
 
@@ -91,14 +335,117 @@ pub fn create_multiple_directories(
     path: impl AsRef<Path>,
     directories: &[impl AsRef<Path>],
 ) -> Result<()> {
-    for dir in directories {
-        let target = path.as_ref().join(dir);
+    for target in create_multiple_directories_dry_run(path, directories) {
+        ensure_directory(target)?;
+    }
+
+    Ok(())
+}
+
+/// Creates multiple directories inside the target path, returning the ones that were
+/// newly created.
+///
+/// Directories that already existed are not included in the result.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::create_multiple_directories_reported`]
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::create_multiple_directories_reported;
+///
+/// let root = "dir/to/populate";
+/// let to_create = ["dir1", "dir2", "dir3"];
+///
+/// // Only contains the directories that did not exist beforehand
+/// let created = create_multiple_directories_reported(root, &to_create)
+///     .expect("unable to create multiple directories");
+/// ```
+pub fn create_multiple_directories_reported(
+    path: impl AsRef<Path>,
+    directories: &[impl AsRef<Path>],
+) -> Result<Vec<PathBuf>> {
+    let mut created = vec![];
+    for target in create_multiple_directories_dry_run(path, directories) {
+        if ensure_directory_reported(&target)? {
+            created.push(target);
+        }
+    }
+
+    Ok(created)
+}
+
+/// Creates a whole directory tree inside `root` from a list of relative paths.
+///
+/// Each entry is a path relative to `root` like `src/bin` or `docs/assets`, any missing
+/// intermediate directories are created along the way. Entries that already exist are left alone.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::create_directory_tree`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * An entry is absolute or contains `..`, checked before anything is created
+/// * A directory could not be created
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::create_directory_tree;
+///
+/// // Creates `project/src`, `project/src/bin`, `project/tests`, `project/docs`
+/// // and `project/docs/assets`
+/// create_directory_tree("project", &["src/bin", "tests", "docs/assets"])
+///     .expect("unable to create directory tree");
+/// ```
+pub fn create_directory_tree(root: impl AsRef<Path>, spec: &[impl AsRef<Path>]) -> Result<()> {
+    for target in tree_targets(root.as_ref(), spec)? {
         ensure_directory(target)?;
     }
 
     Ok(())
 }
 
+/// Atomically writes `contents` to the file at `path`.
+///
+/// The contents are written to a temporary sibling file which is synced to disk and then
+/// renamed over `path`, so readers only ever see the old file or the complete new one.
+/// On Unix the parent directory is synced afterwards so the rename survives a crash.
+/// If anything fails the temporary file is removed and the original is left untouched.
+///
+/// A replaced file keeps its permissions. If `path` is a symlink, the file it points to is
+/// replaced and the link is kept.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::write_atomic`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path has no file name
+/// * The given path is a symlink whose target can't be resolved
+/// * The temporary file could not be written or synced
+/// * The temporary file could not be renamed over the target
+/// * The parent directory could not be synced (Unix only)
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::write_atomic;
+///
+/// write_atomic("config/settings.json", r#"{ "verbose": true }"#).expect("unable to write file");
+/// ```
+pub fn write_atomic(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> Result<()> {
+    crate::util::write_atomic(path.as_ref(), contents.as_ref())
+}
+
 /// Lists all files in the given directory (not including subdirectories).
 ///
 /// ## Async
@@ -122,16 +469,59 @@ pub fn create_multiple_directories(
 /// // Will return a Vec containing paths to all files in the directory
 /// let files = list_files(target_dir).expect("unable to list files");
 /// ```
-pub fn list_files<P: AsRef<Path>>(path: P) -> Result<Vec<impl AsRef<Path>>> {
-    anyhow::ensure!(path.as_ref().exists(), "path does not exist");
-    anyhow::ensure!(
-        path.as_ref().is_dir(),
-        "path should be a directory, not a file"
-    );
+pub fn list_files<P: AsRef<Path>>(path: P) -> Result<Vec<PathBuf>> {
+    ensure_dir_root(&path)?;
 
     iteritems_sync(path, FtIterItemState::File, None)
 }
 
+/// Lists files in a folder (not including subdirectories), optionally matching a filter
+/// pattern, and maps each one with `f` as it is found.
+///
+/// Saves a second pass over the listing when only something derived from each file is needed.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::list_files_map`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::list_files_map;
+///
+/// let sizes = list_files_map("documents", None, |file| {
+///     std::fs::metadata(file).map(|m| m.len()).unwrap_or(0)
+/// })
+/// .expect("unable to list files");
+/// ```
+pub fn list_files_map<P, T, F>(path: P, filter: Option<FtFilter>, mut f: F) -> Result<Vec<T>>
+where
+    P: AsRef<Path>,
+    F: FnMut(&Path) -> T,
+{
+    ensure_dir_root(&path)?;
+
+    let mut mapped = vec![];
+    walk_sync(
+        path,
+        FtIterItemState::File,
+        filter.as_ref(),
+        &mut |item: PathBuf| {
+            mapped.push(f(&item));
+            ControlFlow::Continue(())
+        },
+    )?;
+
+    Ok(mapped)
+}
+
 /// Lists all files in a directory including ALL subdirectories
 ///
 /// Use responsibly.
@@ -158,11 +548,7 @@ pub fn list_files<P: AsRef<Path>>(path: P) -> Result<Vec<impl AsRef<Path>>> {
 /// let files = list_nested_files(target_dir).expect("unable to list files recursively");
 /// ```
 pub fn list_nested_files<P: AsRef<Path>>(path: P) -> Result<Vec<PathBuf>> {
-    anyhow::ensure!(path.as_ref().exists(), "path does not exist");
-    anyhow::ensure!(
-        path.as_ref().is_dir(),
-        "path should be a directory, not a file"
-    );
+    ensure_dir_root(&path)?;
 
     iteritems_sync(path, FtIterItemState::RFile, None)
 }
@@ -199,14 +585,13 @@ pub fn list_nested_files<P: AsRef<Path>>(path: P) -> Result<Vec<PathBuf>> {
 ///
 ///
 /// ```
-pub fn list_files_with_filter<P: AsRef<Path>>(path: P, filter: FtFilter) -> Result<Vec<PathBuf>> {
-    anyhow::ensure!(path.as_ref().exists(), "path does not exist");
-    anyhow::ensure!(
-        path.as_ref().is_dir(),
-        "path should be a directory, not a file"
-    );
+pub fn list_files_with_filter<P: AsRef<Path>>(
+    path: P,
+    filter: impl Borrow<FtFilter>,
+) -> Result<Vec<PathBuf>> {
+    ensure_dir_root(&path)?;
 
-    iteritems_sync(path, FtIterItemState::File, Some(&filter))
+    iteritems_sync(path, FtIterItemState::File, Some(filter.borrow()))
 }
 
 /// Lists files in a folder (including ALL subdirectories) matching a filter pattern.
@@ -243,56 +628,54 @@ pub fn list_files_with_filter<P: AsRef<Path>>(path: P, filter: FtFilter) -> Resu
 /// ```
 pub fn list_nested_files_with_filter<P: AsRef<Path>>(
     path: P,
-    filter: FtFilter,
+    filter: impl Borrow<FtFilter>,
 ) -> Result<Vec<PathBuf>> {
-    anyhow::ensure!(path.as_ref().exists(), "path does not exist");
-    anyhow::ensure!(
-        path.as_ref().is_dir(),
-        "path should be a directory, not a file"
-    );
+    ensure_dir_root(&path)?;
 
-    iteritems_sync(path, FtIterItemState::RFile, Some(&filter))
+    iteritems_sync(path, FtIterItemState::RFile, Some(filter.borrow()))
 }
 
-/// Lists all directories in the given directory (not including subdirectories).
+/// Lists all files in a folder (including ALL subdirectories) as a sorted set, optionally
+/// matching a filter pattern.
+///
+/// The `BTreeSet` orders the paths lexicographically by component and holds each one once.
 ///
 /// ## Async
 ///
-/// For the `async` version, see: [`crate::list_directories`]
+/// For the `async` version, see: [`crate::list_nested_files_set`]
 ///
 /// # Errors
 ///
 /// This function will return an error in the following situations:
 ///
-/// * The path given is a file and not a directory
+/// * The given path is a file and not a directory
 /// * The given path does not exist
 ///
 /// # Example
 ///
 /// ```rust,no_run
-/// use filetools::sync::list_directories;
+/// use filetools::sync::list_nested_files_set;
 ///
-/// let target_dir = "some/dir/containing/files";
-///
-/// // Will return a Vec containing paths to all directories in the directory
-/// let dirs = list_directories(target_dir).expect("unable to list directories");
+/// let files = list_nested_files_set("my/project", None).expect("unable to list files");
 /// ```
-pub fn list_directories<P: AsRef<Path>>(path: P) -> Result<Vec<impl AsRef<Path>>> {
-    anyhow::ensure!(path.as_ref().exists(), "path does not exist");
-    anyhow::ensure!(
-        path.as_ref().is_dir(),
-        "path should be a directory, not a file"
-    );
-    iteritems_sync(path, FtIterItemState::Dir, None)
+pub fn list_nested_files_set<P: AsRef<Path>>(
+    path: P,
+    filter: Option<FtFilter>,
+) -> Result<BTreeSet<PathBuf>> {
+    ensure_dir_root(&path)?;
+
+    let items = iteritems_sync(path, FtIterItemState::RFile, filter.as_ref())?;
+    Ok(items.into_iter().collect())
 }
 
-/// Lists all directories in a directory including ALL subdirectories
+/// Lists all directories in a folder (including ALL subdirectories) as a sorted set, optionally
+/// matching a filter pattern.
 ///
-/// Use responsibly.
+/// The `BTreeSet` orders the paths lexicographically by component and holds each one once.
 ///
 /// ## Async
 ///
-/// For the `async` version, see: [`crate::list_nested_directories`]
+/// For the `async` version, see: [`crate::list_nested_directories_set`]
 ///
 /// # Errors
 ///
@@ -304,38 +687,354 @@ pub fn list_directories<P: AsRef<Path>>(path: P) -> Result<Vec<impl AsRef<Path>>
 /// # Example
 ///
 /// ```rust,no_run
-/// use filetools::sync::list_nested_directories;
-///
-/// let target_dir = "some/dir/containing/nested/files";
+/// use filetools::sync::list_nested_directories_set;
 ///
-/// // Will return a Vec containing all directories in the directory (including all subdirectories)
-/// let dirs = list_nested_directories(target_dir).expect("unable to list directories recursively");
+/// let directories = list_nested_directories_set("my/project", None).expect("unable to list directories");
 /// ```
-pub fn list_nested_directories<P: AsRef<Path> + Send>(path: P) -> Result<Vec<PathBuf>> {
-    anyhow::ensure!(path.as_ref().exists(), "path does not exist");
-    anyhow::ensure!(
-        path.as_ref().is_dir(),
-        "path should be a directory, not a file"
-    );
-    iteritems_sync(path, FtIterItemState::RDir, None)
+pub fn list_nested_directories_set<P: AsRef<Path>>(
+    path: P,
+    filter: Option<FtFilter>,
+) -> Result<BTreeSet<PathBuf>> {
+    ensure_dir_root(&path)?;
+
+    let items = iteritems_sync(path, FtIterItemState::RDir, filter.as_ref())?;
+    Ok(items.into_iter().collect())
 }
 
-/// Lists directories in a given directory (not including subdirectories) matching a filter pattern.
+/// Lists all files in several folders (including ALL subdirectories).
 ///
-/// This pattern can be a `String`, `PathBuf`, or a [`regex::Regex`] pattern.
+/// The roots are walked one after another and the results are joined in the order the roots
+/// were given. If `dedup` is set, paths found under more than one root are only kept the first
+/// time they are seen.
 ///
 /// ## Async
 ///
-/// For the `async` version, see: [`crate::list_directories_with_filter`]
+/// For the `async` version, see: [`crate::list_nested_files_multi`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * Any of the given paths is a file and not a directory
+/// * Any of the given paths does not exist
 ///
 /// # Example
 ///
 /// ```rust,no_run
-/// use regex::Regex;
-/// use std::path::PathBuf;
-/// use filetools::{sync::list_directories_with_filter, FtFilter};
-///
-/// let root = "some/path/containing/dirs";
+/// use filetools::{sync::list_nested_files_multi};
+///
+/// let roots = ["/etc/nginx", "/etc/nginx/sites-enabled", "/opt/nginx"];
+/// let files = list_nested_files_multi(&roots, true).expect("unable to list files");
+/// ```
+pub fn list_nested_files_multi<P: AsRef<Path>>(roots: &[P], dedup: bool) -> Result<Vec<PathBuf>> {
+    multiitems_sync(roots, FtIterItemState::RFile, None, dedup)
+}
+
+/// Lists all files in several folders (including ALL subdirectories) matching a filter pattern.
+///
+/// The roots are walked one after another and the results are joined in the order the roots
+/// were given. If `dedup` is set, paths found under more than one root are only kept the first
+/// time they are seen.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::list_nested_files_multi_with_filter`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * Any of the given paths is a file and not a directory
+/// * Any of the given paths does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::{sync::list_nested_files_multi_with_filter, FtFilter};
+///
+/// let roots = ["/etc/nginx", "/opt/nginx"];
+/// let filter = FtFilter::Raw("conf".to_string());
+/// let files = list_nested_files_multi_with_filter(&roots, filter, true).expect("unable to list files");
+/// ```
+pub fn list_nested_files_multi_with_filter<P: AsRef<Path>>(
+    roots: &[P],
+    filter: impl Borrow<FtFilter>,
+    dedup: bool,
+) -> Result<Vec<PathBuf>> {
+    multiitems_sync(roots, FtIterItemState::RFile, Some(filter.borrow()), dedup)
+}
+
+/// Lists all directories in several folders (including ALL subdirectories).
+///
+/// The roots are walked one after another and the results are joined in the order the roots
+/// were given. If `dedup` is set, paths found under more than one root are only kept the first
+/// time they are seen.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::list_nested_directories_multi`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * Any of the given paths is a file and not a directory
+/// * Any of the given paths does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::{sync::list_nested_directories_multi};
+///
+/// let roots = ["/etc/nginx", "/etc/nginx/sites-enabled", "/opt/nginx"];
+/// let directories = list_nested_directories_multi(&roots, true).expect("unable to list directories");
+/// ```
+pub fn list_nested_directories_multi<P: AsRef<Path>>(
+    roots: &[P],
+    dedup: bool,
+) -> Result<Vec<PathBuf>> {
+    multiitems_sync(roots, FtIterItemState::RDir, None, dedup)
+}
+
+/// Lists all directories in several folders (including ALL subdirectories) matching a filter pattern.
+///
+/// The roots are walked one after another and the results are joined in the order the roots
+/// were given. If `dedup` is set, paths found under more than one root are only kept the first
+/// time they are seen.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::list_nested_directories_multi_with_filter`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * Any of the given paths is a file and not a directory
+/// * Any of the given paths does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::{sync::list_nested_directories_multi_with_filter, FtFilter};
+///
+/// let roots = ["/etc/nginx", "/opt/nginx"];
+/// let filter = FtFilter::Raw("conf".to_string());
+/// let directories = list_nested_directories_multi_with_filter(&roots, filter, true).expect("unable to list directories");
+/// ```
+pub fn list_nested_directories_multi_with_filter<P: AsRef<Path>>(
+    roots: &[P],
+    filter: impl Borrow<FtFilter>,
+    dedup: bool,
+) -> Result<Vec<PathBuf>> {
+    multiitems_sync(roots, FtIterItemState::RDir, Some(filter.borrow()), dedup)
+}
+
+/// Lists all directories in the given directory (not including subdirectories).
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::list_directories`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The path given is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::list_directories;
+///
+/// let target_dir = "some/dir/containing/files";
+///
+/// // Will return a Vec containing paths to all directories in the directory
+/// let dirs = list_directories(target_dir).expect("unable to list directories");
+/// ```
+pub fn list_directories<P: AsRef<Path>>(path: P) -> Result<Vec<PathBuf>> {
+    ensure_dir_root(&path)?;
+    iteritems_sync(path, FtIterItemState::Dir, None)
+}
+
+/// Lists all directories in a directory including ALL subdirectories
+///
+/// Use responsibly.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::list_nested_directories`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::list_nested_directories;
+///
+/// let target_dir = "some/dir/containing/nested/files";
+///
+/// // Will return a Vec containing all directories in the directory (including all subdirectories)
+/// let dirs = list_nested_directories(target_dir).expect("unable to list directories recursively");
+/// ```
+pub fn list_nested_directories<P: AsRef<Path> + Send>(path: P) -> Result<Vec<PathBuf>> {
+    ensure_dir_root(&path)?;
+    iteritems_sync(path, FtIterItemState::RDir, None)
+}
+
+/// Lists files in a folder (not including subdirectories) into a caller provided buffer.
+///
+/// Same as [`list_files`] except `items` is cleared and filled with the results instead of a new
+/// `Vec` being returned. If the listing fails `items` holds whatever was found before the error.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::list_files_into`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::list_files_into;
+///
+/// let mut files = Vec::new();
+/// for dir in ["data/one", "data/two"] {
+///     list_files_into(dir, &mut files).expect("unable to list files");
+/// }
+/// ```
+pub fn list_files_into<P: AsRef<Path>>(path: P, items: &mut Vec<PathBuf>) -> Result<()> {
+    ensure_dir_root(&path)?;
+
+    iteritems_into_sync(path, FtIterItemState::File, None, items)
+}
+
+/// Lists files in a folder (including ALL subdirectories) into a caller provided buffer.
+///
+/// Same as [`list_nested_files`] except `items` is cleared and filled with the results instead of a new
+/// `Vec` being returned. If the listing fails `items` holds whatever was found before the error.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::list_nested_files_into`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::list_nested_files_into;
+///
+/// let mut files = Vec::new();
+/// for dir in ["data/one", "data/two"] {
+///     list_nested_files_into(dir, &mut files).expect("unable to list files");
+/// }
+/// ```
+pub fn list_nested_files_into<P: AsRef<Path>>(path: P, items: &mut Vec<PathBuf>) -> Result<()> {
+    ensure_dir_root(&path)?;
+
+    iteritems_into_sync(path, FtIterItemState::RFile, None, items)
+}
+
+/// Lists directories in a folder (not including subdirectories) into a caller provided buffer.
+///
+/// Same as [`list_directories`] except `items` is cleared and filled with the results instead of a new
+/// `Vec` being returned. If the listing fails `items` holds whatever was found before the error.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::list_directories_into`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::list_directories_into;
+///
+/// let mut directories = Vec::new();
+/// for dir in ["data/one", "data/two"] {
+///     list_directories_into(dir, &mut directories).expect("unable to list directories");
+/// }
+/// ```
+pub fn list_directories_into<P: AsRef<Path>>(path: P, items: &mut Vec<PathBuf>) -> Result<()> {
+    ensure_dir_root(&path)?;
+
+    iteritems_into_sync(path, FtIterItemState::Dir, None, items)
+}
+
+/// Lists directories in a folder (including ALL subdirectories) into a caller provided buffer.
+///
+/// Same as [`list_nested_directories`] except `items` is cleared and filled with the results instead of a new
+/// `Vec` being returned. If the listing fails `items` holds whatever was found before the error.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::list_nested_directories_into`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::list_nested_directories_into;
+///
+/// let mut directories = Vec::new();
+/// for dir in ["data/one", "data/two"] {
+///     list_nested_directories_into(dir, &mut directories).expect("unable to list directories");
+/// }
+/// ```
+pub fn list_nested_directories_into<P: AsRef<Path>>(
+    path: P,
+    items: &mut Vec<PathBuf>,
+) -> Result<()> {
+    ensure_dir_root(&path)?;
+
+    iteritems_into_sync(path, FtIterItemState::RDir, None, items)
+}
+
+/// Lists directories in a given directory (not including subdirectories) matching a filter pattern.
+///
+/// This pattern can be a `String`, `PathBuf`, or a [`regex::Regex`] pattern.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::list_directories_with_filter`]
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use regex::Regex;
+/// use std::path::PathBuf;
+/// use filetools::{sync::list_directories_with_filter, FtFilter};
+///
+/// let root = "some/path/containing/dirs";
 ///
 /// // List all dirs containing the phrase `log`
 /// let mut filter = FtFilter::Raw("log".to_string());
@@ -352,14 +1051,10 @@ pub fn list_nested_directories<P: AsRef<Path> + Send>(path: P) -> Result<Vec<Pat
 /// ```
 pub fn list_directories_with_filter<P: AsRef<Path>>(
     path: P,
-    filter: FtFilter,
+    filter: impl Borrow<FtFilter>,
 ) -> Result<Vec<PathBuf>> {
-    anyhow::ensure!(path.as_ref().exists(), "path does not exist");
-    anyhow::ensure!(
-        path.as_ref().is_dir(),
-        "path should be a directory, not a file"
-    );
-    iteritems_sync(path, FtIterItemState::Dir, Some(&filter))
+    ensure_dir_root(&path)?;
+    iteritems_sync(path, FtIterItemState::Dir, Some(filter.borrow()))
 }
 
 /// Lists directories in a given directory (including ALL subdirectories) matching a filter pattern.
@@ -396,14 +1091,2571 @@ pub fn list_directories_with_filter<P: AsRef<Path>>(
 /// ```
 pub fn list_nested_directories_with_filter<P: AsRef<Path>>(
     path: P,
-    filter: FtFilter,
+    filter: impl Borrow<FtFilter>,
 ) -> Result<Vec<PathBuf>> {
-    anyhow::ensure!(path.as_ref().exists(), "path does not exist");
-    anyhow::ensure!(
-        path.as_ref().is_dir(),
-        "path should be a directory, not a file"
-    );
-    iteritems_sync(path, FtIterItemState::RDir, Some(&filter))
+    ensure_dir_root(&path)?;
+    iteritems_sync(path, FtIterItemState::RDir, Some(filter.borrow()))
+}
+
+/// Counts the files in a directory matching an optional filter without collecting them.
+///
+/// If `recursive` is `true`, files in ALL subdirectories are counted too.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::count_files`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::{sync::count_files, FtFilter};
+///
+/// let root = "some/path/containing/nested/files";
+///
+/// // Count every log file in the tree
+/// let filter = FtFilter::Raw(".log".to_string());
+/// let total = count_files(root, true, Some(filter)).expect("unable to count files");
+/// ```
+pub fn count_files<P: AsRef<Path>>(
+    path: P,
+    recursive: bool,
+    filter: Option<FtFilter>,
+) -> Result<usize> {
+    ensure_dir_root(&path)?;
+
+    let iterstate = if recursive {
+        FtIterItemState::RFile
+    } else {
+        FtIterItemState::File
+    };
+
+    countitems_sync(path, iterstate, filter.as_ref())
+}
+
+/// Counts the directories in a directory matching an optional filter without collecting them.
+///
+/// If `recursive` is `true`, directories in ALL subdirectories are counted too.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::count_directories`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::count_directories;
+///
+/// let root = "some/path/containing/dirs";
+///
+/// // Count every directory in the tree
+/// let total = count_directories(root, true, None).expect("unable to count directories");
+/// ```
+pub fn count_directories<P: AsRef<Path>>(
+    path: P,
+    recursive: bool,
+    filter: Option<FtFilter>,
+) -> Result<usize> {
+    ensure_dir_root(&path)?;
+
+    let iterstate = if recursive {
+        FtIterItemState::RDir
+    } else {
+        FtIterItemState::Dir
+    };
+
+    countitems_sync(path, iterstate, filter.as_ref())
+}
+
+/// Finds the first file in a directory (including ALL subdirectories) matching a filter pattern.
+///
+/// Traversal stops as soon as a match is found, so this is much cheaper than listing
+/// everything when you only need to know if a match exists.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::find_first_file`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::{sync::find_first_file, FtFilter};
+///
+/// let root = "some/path/containing/nested/files";
+///
+/// // Check if there are any log files in the tree
+/// let filter = FtFilter::Raw(".log".to_string());
+/// let first_log = find_first_file(root, filter).expect("unable to search for file");
+/// ```
+pub fn find_first_file<P: AsRef<Path>>(
+    path: P,
+    filter: impl Borrow<FtFilter>,
+) -> Result<Option<PathBuf>> {
+    ensure_dir_root(&path)?;
+
+    firstitem_sync(path, FtIterItemState::RFile, Some(filter.borrow()))
+}
+
+/// Finds all files and directories matching a glob pattern.
+///
+/// The literal part of the pattern before the first wildcard is used as the root of the
+/// search, the remainder is matched against paths relative to that root. `*` and `?` never
+/// match a path separator, `**` matches across any number of directories and character
+/// classes like `[abc]` are supported. The results are sorted.
+///
+/// If the pattern has no wildcards, the path itself is returned if it exists.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::glob`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The wildcard part of the pattern is not a valid glob
+/// * The literal root of the pattern exists but cannot be read
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::glob;
+///
+/// // Compressed logs anywhere under `/var/log`
+/// let logs = glob("/var/log/**/*.gz").expect("unable to glob");
+/// ```
+#[cfg(feature = "globset")]
+pub fn glob(pattern: &str) -> Result<Vec<PathBuf>> {
+    crate::glob::glob_sync(pattern)
+}
+
+/// Lists files in a folder (including ALL subdirectories) matching any glob in a precompiled `GlobSet`.
+///
+/// Each file's path relative to `path` is matched against the set, so a set built once can be
+/// reused across many directories without compiling the patterns again.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::list_nested_files_globset`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::list_nested_files_globset;
+/// use globset::{Glob, GlobSetBuilder};
+///
+/// let mut builder = GlobSetBuilder::new();
+/// builder.add(Glob::new("**/*.rs").expect("invalid glob"));
+/// let set = builder.build().expect("unable to build glob set");
+///
+/// let files = list_nested_files_globset("my/project", &set).expect("unable to list files");
+/// ```
+#[cfg(feature = "globset")]
+pub fn list_nested_files_globset(
+    path: impl AsRef<Path>,
+    set: &globset::GlobSet,
+) -> Result<Vec<PathBuf>> {
+    crate::glob::list_nested_files_globset_sync(path, set)
+}
+
+/// Generates a content addressed `PathBuf` from the contents of the file at `path`.
+///
+/// The name is the hex hash of the file, sharded into [`crate::naming::DEFAULT_SHARD_LEVELS`]
+/// directories made from its leading characters (e.g. `ab/cd/abcdef....ext`).
+/// Files with the same contents and `algorithm` always get the same name. The file is read
+/// in chunks.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::naming::content_addressed_name`]
+///
+/// # Errors
+///
+/// This function will return an error if the file cannot be read.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::{naming::FtHashAlgorithm, sync::content_addressed_name};
+///
+/// # #[cfg(feature = "sha2")]
+/// let name = content_addressed_name("data/report.pdf", FtHashAlgorithm::Sha256, "blob")
+///     .expect("unable to hash file");
+/// ```
+#[cfg(any(feature = "sha2", feature = "blake3"))]
+pub fn content_addressed_name(
+    path: impl AsRef<Path>,
+    algorithm: crate::naming::FtHashAlgorithm,
+    ext: &str,
+) -> Result<PathBuf> {
+    crate::naming::content_addressed_name_sync(
+        path,
+        algorithm,
+        ext,
+        crate::naming::DEFAULT_SHARD_LEVELS,
+    )
+}
+
+/// Generates a content addressed `PathBuf` with `levels` sharding directories.
+///
+/// Identical to [`content_addressed_name`] except the number of sharding directories is configurable.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::naming::content_addressed_name_with_levels`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The file cannot be read
+/// * There are more `levels` than the hash has character pairs
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::{naming::FtHashAlgorithm, sync::content_addressed_name_with_levels};
+///
+/// # #[cfg(feature = "sha2")]
+/// let name = content_addressed_name_with_levels("data/report.pdf", FtHashAlgorithm::Sha256, "blob", 1)
+///     .expect("unable to hash file");
+/// ```
+#[cfg(any(feature = "sha2", feature = "blake3"))]
+pub fn content_addressed_name_with_levels(
+    path: impl AsRef<Path>,
+    algorithm: crate::naming::FtHashAlgorithm,
+    ext: &str,
+    levels: usize,
+) -> Result<PathBuf> {
+    crate::naming::content_addressed_name_sync(path, algorithm, ext, levels)
+}
+
+/// Lists files in a directory (including ALL subdirectories), relative to the given path.
+///
+/// Identical to [`crate::sync::list_nested_files`] except the `path` prefix is stripped from each result.
+/// Filters are still applied to the full path.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::list_nested_files_relative`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::list_nested_files_relative;
+///
+/// let root = "some/path/containing/nested/files";
+///
+/// // Paths like `sub/item` instead of `some/path/containing/nested/files/sub/item`
+/// let relative = list_nested_files_relative(root).expect("unable to list relative files");
+/// ```
+pub fn list_nested_files_relative<P: AsRef<Path>>(path: P) -> Result<Vec<PathBuf>> {
+    ensure_dir_root(&path)?;
+
+    let items = iteritems_sync(path.as_ref(), FtIterItemState::RFile, None)?;
+    Ok(strip_root(path, items))
+}
+
+/// Lists files in a directory (including ALL subdirectories) matching a filter pattern, relative to the given path.
+///
+/// Identical to [`crate::sync::list_nested_files_with_filter`] except the `path` prefix is stripped from each result.
+/// Filters are still applied to the full path.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::list_nested_files_with_filter_relative`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::{sync::list_nested_files_with_filter_relative, FtFilter};
+///
+/// let root = "some/path/containing/nested/files";
+///
+/// // Paths like `sub/item` instead of `some/path/containing/nested/files/sub/item`
+/// let relative = list_nested_files_with_filter_relative(root, FtFilter::Raw("log".to_string())).expect("unable to list relative files");
+/// ```
+pub fn list_nested_files_with_filter_relative<P: AsRef<Path>>(
+    path: P,
+    filter: impl Borrow<FtFilter>,
+) -> Result<Vec<PathBuf>> {
+    ensure_dir_root(&path)?;
+
+    let items = iteritems_sync(path.as_ref(), FtIterItemState::RFile, Some(filter.borrow()))?;
+    Ok(strip_root(path, items))
+}
+
+/// Lists directories in a directory (including ALL subdirectories), relative to the given path.
+///
+/// Identical to [`crate::sync::list_nested_directories`] except the `path` prefix is stripped from each result.
+/// Filters are still applied to the full path.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::list_nested_directories_relative`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::list_nested_directories_relative;
+///
+/// let root = "some/path/containing/nested/directories";
+///
+/// // Paths like `sub/item` instead of `some/path/containing/nested/directories/sub/item`
+/// let relative = list_nested_directories_relative(root).expect("unable to list relative directories");
+/// ```
+pub fn list_nested_directories_relative<P: AsRef<Path>>(path: P) -> Result<Vec<PathBuf>> {
+    ensure_dir_root(&path)?;
+
+    let items = iteritems_sync(path.as_ref(), FtIterItemState::RDir, None)?;
+    Ok(strip_root(path, items))
+}
+
+/// Lists directories in a directory (including ALL subdirectories) matching a filter pattern, relative to the given path.
+///
+/// Identical to [`crate::sync::list_nested_directories_with_filter`] except the `path` prefix is stripped from each result.
+/// Filters are still applied to the full path.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::list_nested_directories_with_filter_relative`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::{sync::list_nested_directories_with_filter_relative, FtFilter};
+///
+/// let root = "some/path/containing/nested/directories";
+///
+/// // Paths like `sub/item` instead of `some/path/containing/nested/directories/sub/item`
+/// let relative = list_nested_directories_with_filter_relative(root, FtFilter::Raw("log".to_string())).expect("unable to list relative directories");
+/// ```
+pub fn list_nested_directories_with_filter_relative<P: AsRef<Path>>(
+    path: P,
+    filter: impl Borrow<FtFilter>,
+) -> Result<Vec<PathBuf>> {
+    ensure_dir_root(&path)?;
+
+    let items = iteritems_sync(path.as_ref(), FtIterItemState::RDir, Some(filter.borrow()))?;
+    Ok(strip_root(path, items))
+}
+
+/// Lists all files in a directory including ALL subdirectories, carrying on past any
+/// directories or entries that can't be read.
+///
+/// Returns the files that could be listed along with each path that failed and its error,
+/// e.g. subdirectories the current user doesn't have permission to read.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::list_nested_files_partial`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::list_nested_files_partial;
+///
+/// let (files, errors) = list_nested_files_partial("/home").expect("unable to list files");
+/// for (path, err) in errors {
+///     eprintln!("skipped {}: {err}", path.display());
+/// }
+/// ```
+pub fn list_nested_files_partial<P: AsRef<Path>>(path: P) -> Result<FtPartialListing> {
+    ensure_dir_root(&path)?;
+
+    let mut partial = PartialItems::default();
+    walk_sync(path, FtIterItemState::RFile, None, &mut partial)?;
+
+    Ok((partial.items, partial.errors))
+}
+
+/// Lists all directories in a directory including ALL subdirectories, carrying on past any
+/// directories or entries that can't be read.
+///
+/// Returns the directories that could be listed along with each path that failed and its
+/// error. Unreadable directories are still listed themselves, only their contents are missing.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::list_nested_directories_partial`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::list_nested_directories_partial;
+///
+/// let (dirs, errors) = list_nested_directories_partial("/home").expect("unable to list dirs");
+/// ```
+pub fn list_nested_directories_partial<P: AsRef<Path>>(path: P) -> Result<FtPartialListing> {
+    ensure_dir_root(&path)?;
+
+    let mut partial = PartialItems::default();
+    walk_sync(path, FtIterItemState::RDir, None, &mut partial)?;
+
+    Ok((partial.items, partial.errors))
+}
+
+/// Lists all files in a directory including ALL subdirectories, resolving the root first.
+///
+/// The root is canonicalized before traversal so if it is (or passes through) a symlink,
+/// the results are returned under the real target path instead of the symlink.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::list_nested_files_canonical`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::list_nested_files_canonical;
+///
+/// // If `current` links to `releases/v2`, returns paths under `releases/v2`
+/// let files = list_nested_files_canonical("current").expect("unable to list files");
+/// ```
+pub fn list_nested_files_canonical<P: AsRef<Path>>(path: P) -> Result<Vec<PathBuf>> {
+    ensure_dir_root(&path)?;
+
+    let root = fs::canonicalize(path).context("unable to resolve root path")?;
+    iteritems_sync(root, FtIterItemState::RFile, None)
+}
+
+/// Lists all directories in a directory including ALL subdirectories, resolving the root first.
+///
+/// The root is canonicalized before traversal so if it is (or passes through) a symlink,
+/// the results are returned under the real target path instead of the symlink.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::list_nested_directories_canonical`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::list_nested_directories_canonical;
+///
+/// // If `current` links to `releases/v2`, returns paths under `releases/v2`
+/// let directories = list_nested_directories_canonical("current").expect("unable to list directories");
+/// ```
+pub fn list_nested_directories_canonical<P: AsRef<Path>>(path: P) -> Result<Vec<PathBuf>> {
+    ensure_dir_root(&path)?;
+
+    let root = fs::canonicalize(path).context("unable to resolve root path")?;
+    iteritems_sync(root, FtIterItemState::RDir, None)
+}
+
+/// Lists the items in a directory, optionally recursing into ALL subdirectories and
+/// applying a filter pattern.
+///
+/// This is the general form of the other listing functions. `target` picks whether files,
+/// directories or both are returned, and with [`FtIterTarget::Both`] everything is gathered
+/// in a single traversal. Directories are listed before their contents.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::list_items`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::{sync::list_items, FtIterTarget};
+///
+/// let root = "some/path/containing/nested/items";
+///
+/// // Every file and directory in the tree, in one pass
+/// let everything = list_items(root, FtIterTarget::Both, true, None).expect("unable to list items");
+/// ```
+pub fn list_items<P: AsRef<Path>>(
+    path: P,
+    target: FtIterTarget,
+    recursive: bool,
+    filter: Option<FtFilter>,
+) -> Result<Vec<PathBuf>> {
+    ensure_dir_root(&path)?;
+
+    let iterstate = FtIterItemState::new(target, recursive);
+    iteritems_sync(path, iterstate, filter.as_ref())
+}
+
+/// Lists all files and directories in a directory including ALL subdirectories
+/// in a single traversal.
+///
+/// Each item is tagged as a [`DirEntryKind::File`] or [`DirEntryKind::Dir`] so there is no need
+/// to walk the tree twice or check the item type again. Directories are listed before their contents.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::list_nested_entries`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::list_nested_entries;
+///
+/// let entries = list_nested_entries("some/project").expect("unable to list entries");
+/// let files = entries.iter().filter(|e| e.is_file()).count();
+/// ```
+pub fn list_nested_entries<P: AsRef<Path>>(path: P) -> Result<Vec<DirEntryKind>> {
+    ensure_dir_root(&path)?;
+
+    let mut entries = EntryItems::default();
+    walk_sync(path, FtIterItemState::RBoth, None, &mut entries)?;
+
+    Ok(entries.entries)
+}
+
+/// Lists the directories (including ALL subdirectories) that directly contain at least one file.
+///
+/// Directories that only hold other directories, or nothing at all, are left out, as is
+/// `path` itself.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::list_directories_containing_files`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::list_directories_containing_files;
+///
+/// let albums = list_directories_containing_files("/home/user/Music").expect("unable to list directories");
+/// ```
+pub fn list_directories_containing_files<P: AsRef<Path>>(path: P) -> Result<Vec<PathBuf>> {
+    let entries = list_nested_entries(path)?;
+    Ok(dirs_holding_files(entries))
+}
+
+/// Lists the git repositories below a directory, including ALL subdirectories.
+///
+/// A directory is a repository if it directly contains a `.git` entry, either a directory or
+/// the `.git` file of a submodule or worktree. Once a repository is found its contents are not
+/// searched. `path` itself is never listed but is always searched.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::list_git_repositories`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+/// * A directory could not be read
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::list_git_repositories;
+///
+/// let repos = list_git_repositories("/home/user/code").expect("unable to find repositories");
+/// ```
+pub fn list_git_repositories(path: impl AsRef<Path>) -> Result<Vec<PathBuf>> {
+    let path = path.as_ref();
+    ensure_dir_root(path)?;
+
+    gitrepos_sync(path)
+}
+
+/// Lists the broken symlinks below a directory, including ALL subdirectories.
+///
+/// A symlink is broken when its target can't be resolved, usually because it was moved or
+/// deleted. Unlike the listing functions, symlinks are not followed: symlinked directories
+/// aren't searched, which also keeps link cycles from trapping the walk.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::list_broken_symlinks`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+/// * A directory could not be read
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::list_broken_symlinks;
+///
+/// for link in list_broken_symlinks("/home/user/.config").expect("unable to search") {
+///     println!("dangling: {}", link.display());
+/// }
+/// ```
+pub fn list_broken_symlinks(path: impl AsRef<Path>) -> Result<Vec<PathBuf>> {
+    let path = path.as_ref();
+    ensure_dir_root(path)?;
+
+    brokenlinks_sync(path)
+}
+
+/// Lists all files and directories in a directory including ALL subdirectories matching a filter pattern
+/// in a single traversal.
+///
+/// Each item is tagged as a [`DirEntryKind::File`] or [`DirEntryKind::Dir`] so there is no need
+/// to walk the tree twice or check the item type again. Directories are listed before their contents.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::list_nested_entries_with_filter`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::{sync::list_nested_entries_with_filter, FtFilter};
+///
+/// let entries = list_nested_entries_with_filter("some/project", FtFilter::Raw("src".to_string())).expect("unable to list entries");
+/// let files = entries.iter().filter(|e| e.is_file()).count();
+/// ```
+pub fn list_nested_entries_with_filter<P: AsRef<Path>>(
+    path: P,
+    filter: impl Borrow<FtFilter>,
+) -> Result<Vec<DirEntryKind>> {
+    ensure_dir_root(&path)?;
+
+    let mut entries = EntryItems::default();
+    walk_sync(
+        path,
+        FtIterItemState::RBoth,
+        Some(filter.borrow()),
+        &mut entries,
+    )?;
+
+    Ok(entries.entries)
+}
+
+/// Lists files in a directory once, returning a [`FtPager`] to page through them.
+///
+/// The directory is only walked when this is called, requesting pages afterwards is cheap.
+/// Set `recursive` to include files in ALL subdirectories.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::list_files_pager`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::list_files_pager;
+///
+/// let pager = list_files_pager("some/big/folder", true).expect("unable to list files");
+/// let first = pager.page(0, 50);
+/// let second = pager.page(50, 50);
+/// ```
+pub fn list_files_pager<P: AsRef<Path>>(path: P, recursive: bool) -> Result<FtPager> {
+    let items = list_items(path, FtIterTarget::Files, recursive, None)?;
+    Ok(FtPager::new(items))
+}
+
+/// Lists a single page of at most `limit` files in a directory, starting at `offset`.
+///
+/// Files are sorted before paging. This walks the directory on every call, use
+/// [`list_files_pager`] if more than one page is needed.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::list_files_paged`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::list_files_paged;
+///
+/// // The third page of 20 files directly inside the folder
+/// let page = list_files_paged("some/folder", false, 40, 20).expect("unable to list files");
+/// ```
+pub fn list_files_paged<P: AsRef<Path>>(
+    path: P,
+    recursive: bool,
+    offset: usize,
+    limit: usize,
+) -> Result<FtPage> {
+    Ok(list_files_pager(path, recursive)?.page(offset, limit))
+}
+
+/// Lists all files in a directory including ALL subdirectories, calling `progress` with
+/// each one as soon as it is found.
+///
+/// Useful for showing progress during long traversals. `progress` is called once for
+/// every item in the returned `Vec`, in the same order.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::list_nested_files_with_progress`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::list_nested_files_with_progress;
+///
+/// let mut found = 0;
+/// let files = list_nested_files_with_progress("some/huge/folder", |_| found += 1)
+///     .expect("unable to list files");
+/// ```
+pub fn list_nested_files_with_progress<P: AsRef<Path>>(
+    path: P,
+    progress: impl FnMut(&Path),
+) -> Result<Vec<PathBuf>> {
+    ensure_dir_root(&path)?;
+
+    progressitems_sync(path, FtIterItemState::RFile, None, progress)
+}
+
+/// Lists all directories in a directory including ALL subdirectories, calling `progress` with
+/// each one as soon as it is found.
+///
+/// Useful for showing progress during long traversals. `progress` is called once for
+/// every item in the returned `Vec`, in the same order.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::list_nested_directories_with_progress`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::list_nested_directories_with_progress;
+///
+/// let mut found = 0;
+/// let directories = list_nested_directories_with_progress("some/huge/folder", |_| found += 1)
+///     .expect("unable to list directories");
+/// ```
+pub fn list_nested_directories_with_progress<P: AsRef<Path>>(
+    path: P,
+    progress: impl FnMut(&Path),
+) -> Result<Vec<PathBuf>> {
+    ensure_dir_root(&path)?;
+
+    progressitems_sync(path, FtIterItemState::RDir, None, progress)
+}
+
+/// Lists all files in a directory including ALL subdirectories, stopping early once
+/// `cancel` is set.
+///
+/// The flag is checked before every directory read and every entry, so setting it stops
+/// the traversal promptly even on deep trees. Whatever was found is returned along with whether
+/// the traversal was cancelled before it finished, a listing that wasn't cancelled is complete.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::list_nested_files_cancellable`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::list_nested_files_cancellable;
+/// use std::sync::atomic::{AtomicBool, Ordering};
+/// use std::sync::Arc;
+///
+/// let cancel = Arc::new(AtomicBool::new(false));
+///
+/// let flag = Arc::clone(&cancel);
+/// let listing = std::thread::spawn(move || list_nested_files_cancellable("/", &flag));
+///
+/// // The user navigated away
+/// cancel.store(true, Ordering::Relaxed);
+/// let (found, cancelled) = listing.join().unwrap().expect("unable to list files");
+/// if cancelled {
+///     println!("stopped after {} files", found.len());
+/// }
+/// ```
+pub fn list_nested_files_cancellable<P: AsRef<Path>>(
+    path: P,
+    cancel: &AtomicBool,
+) -> Result<(Vec<PathBuf>, bool)> {
+    ensure_dir_root(&path)?;
+
+    let mut visitor = CancellableItems::new(cancel);
+    walk_sync(path, FtIterItemState::RFile, None, &mut visitor)?;
+
+    Ok(visitor.finish())
+}
+
+/// Lists all directories in a directory including ALL subdirectories, stopping early once
+/// `cancel` is set.
+///
+/// The flag is checked before every directory read and every entry, so setting it stops
+/// the traversal promptly even on deep trees. Whatever was found is returned along with whether
+/// the traversal was cancelled before it finished, a listing that wasn't cancelled is complete.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::list_nested_directories_cancellable`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::list_nested_directories_cancellable;
+/// use std::sync::atomic::{AtomicBool, Ordering};
+/// use std::sync::Arc;
+///
+/// let cancel = Arc::new(AtomicBool::new(false));
+///
+/// let flag = Arc::clone(&cancel);
+/// let listing = std::thread::spawn(move || list_nested_directories_cancellable("/", &flag));
+///
+/// // The user navigated away
+/// cancel.store(true, Ordering::Relaxed);
+/// let (found, cancelled) = listing.join().unwrap().expect("unable to list directories");
+/// if cancelled {
+///     println!("stopped after {} directories", found.len());
+/// }
+/// ```
+pub fn list_nested_directories_cancellable<P: AsRef<Path>>(
+    path: P,
+    cancel: &AtomicBool,
+) -> Result<(Vec<PathBuf>, bool)> {
+    ensure_dir_root(&path)?;
+
+    let mut visitor = CancellableItems::new(cancel);
+    walk_sync(path, FtIterItemState::RDir, None, &mut visitor)?;
+
+    Ok(visitor.finish())
+}
+
+/// Lists all files in a directory including ALL subdirectories along with a digest of
+/// each file's contents.
+///
+/// `new_hasher` makes an [`FtHasher`] for each file, so any hashing crate can be plugged in.
+/// Files are read in chunks one at a time.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::list_nested_files_with_hash`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+/// * One of the files can't be read
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::list_nested_files_with_hash;
+/// use std::collections::hash_map::DefaultHasher;
+///
+/// // Good enough to spot changes within one run, use a digest crate for anything stored
+/// let hashed =
+///     list_nested_files_with_hash("some/folder", DefaultHasher::new).expect("unable to hash files");
+/// ```
+pub fn list_nested_files_with_hash<P, F, H>(
+    path: P,
+    new_hasher: F,
+) -> Result<Vec<(PathBuf, String)>>
+where
+    P: AsRef<Path>,
+    F: Fn() -> H,
+    H: FtHasher,
+{
+    ensure_dir_root(&path)?;
+
+    iteritems_sync(path, FtIterItemState::RFile, None)?
+        .into_iter()
+        .map(|file| {
+            let digest = crate::util::hashfile(&file, new_hasher())?;
+            Ok((file, digest))
+        })
+        .collect()
+}
+
+/// Finds groups of files with identical contents in a directory including ALL subdirectories.
+///
+/// Each group contains at least two files, files without a duplicate are left out.
+/// Files are compared by size first and only files of the same size are read and hashed.
+/// Groups and the files in them are sorted.
+///
+/// Symlinks are skipped, and hardlinks to the same file are only listed once under their first
+/// path in sorted order.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::find_duplicates`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+/// * One of the files can't be read
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::find_duplicates;
+///
+/// for group in find_duplicates("some/photos").expect("unable to find duplicates") {
+///     println!("{} copies of {}", group.len(), group[0].display());
+/// }
+/// ```
+pub fn find_duplicates<P: AsRef<Path>>(path: P) -> Result<Vec<Vec<PathBuf>>> {
+    crate::dedup::find_duplicates_sync(path)
+}
+
+/// Builds the hierarchy of a directory as a tree of [`FtTreeNode`]s in a single pass.
+///
+/// Children are sorted by name. With a filter, files are kept if they match it and
+/// directories are kept if they match it or still contain something that does, so branches
+/// without any matches are pruned. `max_depth` limits how far down the tree goes: `Some(1)`
+/// only holds the immediate children of `path`, directories at the limit have no children.
+/// Symlinks are followed like in the listing functions, except a symlink back to one of its own
+/// ancestors which is kept as a directory without children instead of being followed forever.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::build_tree`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+/// * A directory could not be read
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::{sync::build_tree, FtFilter};
+///
+/// // Only the branches leading to Lua files
+/// let filter = FtFilter::Raw(".lua".to_string());
+/// let tree = build_tree("/home/user/.config/nvim", Some(filter), None)
+///     .expect("unable to build tree");
+/// ```
+pub fn build_tree(
+    path: impl AsRef<Path>,
+    filter: Option<FtFilter>,
+    max_depth: Option<usize>,
+) -> Result<FtTreeNode> {
+    crate::tree::build_tree_sync(path, filter, max_depth)
+}
+
+/// Formats a directory as the familiar `tree` command output for logs and CLIs.
+///
+/// The first line is `path` as given, followed by one line per item drawn with `├──`, `└──`
+/// and `│` connectors. Items are sorted by name and every line ends with a newline.
+/// The filter and depth limit work as in [`build_tree`].
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::format_tree`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+/// * A directory could not be read
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::format_tree;
+///
+/// // my/project
+/// // ├── Cargo.toml
+/// // └── src
+/// //     └── main.rs
+/// print!("{}", format_tree("my/project", None, None).expect("unable to format tree"));
+/// ```
+pub fn format_tree(
+    path: impl AsRef<Path>,
+    filter: Option<FtFilter>,
+    max_depth: Option<usize>,
+) -> Result<String> {
+    crate::render::format_tree_sync(path, filter, max_depth)
+}
+
+/// Recreates the directory structure of `src` inside `dst` without copying any file contents.
+///
+/// Every directory under `src` is created at the same relative location under `dst`.
+/// If `placeholders` is set, an empty file is also created for every file in `src`.
+/// `dst` is created if it doesn't exist. Anything already in `dst` is left untouched, a
+/// placeholder is not created where a file already exists.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::clone_directory_structure`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * `src` is a file and not a directory
+/// * `src` does not exist
+/// * A directory or placeholder file could not be created
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::clone_directory_structure;
+///
+/// // Just the folders of a real project as a test fixture
+/// clone_directory_structure("real/project", "fixtures/skeleton", false)
+///     .expect("unable to clone directory structure");
+/// ```
+pub fn clone_directory_structure(
+    src: impl AsRef<Path>,
+    dst: impl AsRef<Path>,
+    placeholders: bool,
+) -> Result<()> {
+    let (src, dst) = (src.as_ref(), dst.as_ref());
+    let dirs = list_nested_directories_relative(src)?;
+    ensure_directory(dst)?;
+    for dir in dirs {
+        ensure_directory(dst.join(dir))?;
+    }
+
+    if placeholders {
+        for file in list_nested_files_relative(src)? {
+            let created = fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(dst.join(file));
+
+            match created {
+                Err(e) if e.kind() != std::io::ErrorKind::AlreadyExists => {
+                    return Err(e).context("unable to create placeholder file");
+                }
+                _ => {}
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Renames files in a directory based on a closure.
+///
+/// `rename` is called with each file's path and returns its new name, which is joined onto
+/// the file's directory (so an absolute path moves it elsewhere). Files it returns `None` for
+/// are left alone. Set `recursive` to include files in ALL subdirectories and pass a `filter`
+/// to only rename matching files.
+///
+/// Every new name is worked out before anything is renamed, so a collision leaves all files
+/// untouched. Renames that swap or chain names between files are handled. If a rename fails
+/// part way through, the files already renamed are put back where they were.
+/// Returns the old and new path of each renamed file.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::rename_files`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+/// * Two files would be renamed to the same path
+/// * A file would be renamed over an existing file that isn't being renamed itself
+/// * A file could not be renamed
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::rename_files;
+///
+/// // Lowercase every file name
+/// rename_files("photos", true, None, |file| {
+///     let name = file.file_name()?.to_str()?;
+///     Some(name.to_lowercase().into())
+/// })
+/// .expect("unable to rename files");
+/// ```
+pub fn rename_files<P: AsRef<Path>>(
+    path: P,
+    recursive: bool,
+    filter: Option<FtFilter>,
+    rename: impl FnMut(&Path) -> Option<PathBuf>,
+) -> Result<Vec<(PathBuf, PathBuf)>> {
+    let files = list_items(path, FtIterTarget::Files, recursive, filter)?;
+    let plan = rename_plan(files, rename)?;
+    crate::util::apply_rename_plan_sync(&plan)?;
+
+    Ok(plan.moves)
+}
+
+/// Moves all files directly inside `src` that match a filter into the `dst` directory.
+///
+/// `dst` is created if it doesn't exist. If a file with the same name is already in `dst`
+/// the moved file gets a numbered name like `photo (1).jpg` instead of overwriting it.
+/// Files are moved across filesystems by copying and removing them.
+/// Returns the new path of each moved file.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::move_files_matching`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * `src` is a file and not a directory
+/// * `src` does not exist
+/// * `dst` could not be created
+/// * A file could not be moved
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::{sync::move_files_matching, FtFilter};
+///
+/// // Sort the downloads folder
+/// let images = FtFilter::Raw(".jpg".to_string());
+/// move_files_matching("Downloads", "Downloads/images", images).expect("unable to move images");
+/// ```
+pub fn move_files_matching(
+    src: impl AsRef<Path>,
+    dst: impl AsRef<Path>,
+    filter: impl Borrow<FtFilter>,
+) -> Result<Vec<PathBuf>> {
+    let files = list_files_with_filter(src, filter)?;
+    ensure_directory(&dst)?;
+
+    let mut moved = vec![];
+    for file in files {
+        let Some(name) = file.file_name() else {
+            continue;
+        };
+
+        let target = free_path(dst.as_ref(), Path::new(name));
+        move_file_sync(&file, &target)?;
+        moved.push(target);
+    }
+
+    Ok(moved)
+}
+
+/// Splits the files directly inside a directory into `buckets` numbered subdirectories
+/// (`0000`, `0001`, ...).
+///
+/// The subdirectories are created with [`create_numeric_directories`] and each file is moved
+/// into one of them as decided by `strategy`, see [`FtPartition`].
+/// Returns the new paths of the files in each bucket, in bucket order.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::partition_files_into_directories`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * `buckets` is zero
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+/// * A bucket directory could not be created or a file could not be moved
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::{sync::partition_files_into_directories, FtPartition};
+///
+/// // Shard a dataset into `dataset/0000` to `dataset/0007`
+/// let shards = partition_files_into_directories("dataset", 8, FtPartition::RoundRobin)
+///     .expect("unable to partition files");
+/// ```
+pub fn partition_files_into_directories(
+    path: impl AsRef<Path>,
+    buckets: usize,
+    strategy: FtPartition,
+) -> Result<Vec<Vec<PathBuf>>> {
+    anyhow::ensure!(buckets > 0, "at least one bucket is needed");
+    let files = list_files(&path)?;
+
+    create_numeric_directories(&path, 0, buckets, PARTITION_FILL)?;
+    let dirs = crate::util::numeric_dirs(path.as_ref(), 0, buckets, PARTITION_FILL);
+
+    let mut partitioned = vec![vec![]; buckets];
+    for (file, bucket) in partition_plan(files, buckets, strategy) {
+        let Some(name) = file.file_name() else {
+            continue;
+        };
+
+        let target = free_path(&dirs[bucket], Path::new(name));
+        move_file_sync(&file, &target)?;
+        partitioned[bucket].push(target);
+    }
+
+    Ok(partitioned)
+}
+
+/// Lists all files in a directory including ALL subdirectories, skipping anything ignored
+/// by `.gitignore`, `.ignore` or the global git excludes.
+///
+/// A drop-in alternative to [`list_nested_files`] for source trees, so `target/`,
+/// `node_modules/` and the like are never walked into. Ignore files are honoured even outside
+/// of a git repository. Hidden files are still listed but the `.git` directory is skipped.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::list_nested_files_gitignore`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+/// * A directory could not be read
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::list_nested_files_gitignore;
+///
+/// // Every source file in the project without anything from `target/`
+/// let sources = list_nested_files_gitignore("my/rust/project").expect("unable to list files");
+/// ```
+#[cfg(feature = "ignore")]
+pub fn list_nested_files_gitignore<P: AsRef<Path>>(path: P) -> Result<Vec<PathBuf>> {
+    crate::gitignore::list_nested_files_gitignore_sync(path)
+}
+
+/// Lazily lists the files in a directory, yielding each one as soon as it is found.
+///
+/// Unlike the other listing functions nothing is collected up front, so results can be shown
+/// as they stream in and stopping early (e.g. by dropping the iterator) skips the rest of
+/// the walk. Set `recursive` to include files in ALL subdirectories and pass a `filter` to
+/// only yield matching files.
+///
+/// A subdirectory that can't be read becomes an `Err` item and the walk carries on with
+/// the next one rather than ending the iterator.
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+/// * The given path can't be read
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::list_files_iter;
+///
+/// // Print files as they are found, stopping after the first 100
+/// for file in list_files_iter("some/huge/folder", true, None)
+///     .expect("unable to read folder")
+///     .take(100)
+/// {
+///     match file {
+///         Ok(file) => println!("{}", file.display()),
+///         Err(e) => eprintln!("skipped: {e:#}"),
+///     }
+/// }
+/// ```
+pub fn list_files_iter<P: AsRef<Path>>(
+    path: P,
+    recursive: bool,
+    filter: Option<FtFilter>,
+) -> Result<impl Iterator<Item = Result<PathBuf>>> {
+    ensure_dir_root(&path)?;
+
+    FileIter::new(path.as_ref(), recursive, filter)
+}
+
+/// Checks if a directory is empty.
+///
+/// Stops at the first entry found rather than listing the whole directory.
+/// Hidden files count as entries.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::is_directory_empty`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+/// * The directory can't be read
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::is_directory_empty;
+///
+/// if is_directory_empty("old/builds").expect("unable to check directory") {
+///     std::fs::remove_dir("old/builds").expect("unable to remove directory");
+/// }
+/// ```
+pub fn is_directory_empty<P: AsRef<Path>>(path: P) -> Result<bool> {
+    ensure_dir_root(&path)?;
+
+    let mut entries = fs::read_dir(path.as_ref()).context("unable to read directory")?;
+    match entries.next() {
+        Some(entry) => entry
+            .map(|_| false)
+            .context("unable to read directory entry"),
+        None => Ok(true),
+    }
+}
+
+/// Determines if two paths refer to the same underlying file.
+///
+/// Symlinks are followed and hardlinks count as the same file, so this is a better check
+/// than comparing the paths before copying or moving a file over itself.
+/// On Unix the device and inode numbers are compared, elsewhere the canonicalized paths are.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::same_file`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * Either path does not exist
+/// * The metadata of either path can't be read
+/// * Either item can't be opened to read its file id (not on Unix)
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::same_file;
+///
+/// if !same_file("report.pdf", "backup/report.pdf").expect("unable to compare files") {
+///     std::fs::copy("report.pdf", "backup/report.pdf").expect("unable to copy file");
+/// }
+/// ```
+pub fn same_file(a: impl AsRef<Path>, b: impl AsRef<Path>) -> Result<bool> {
+    let (a, b) = (a.as_ref(), b.as_ref());
+    let a_meta = fs::metadata(a).context("unable to read metadata")?;
+    let b_meta = fs::metadata(b).context("unable to read metadata")?;
+
+    crate::util::same_file_identity(a, &a_meta, b, &b_meta)
+}
+
+/// Lists all files in the given directory, including ALL subdirectories if `recursive` is set.
+///
+/// Same as calling [`list_files`] or [`list_nested_files`] depending on the flag.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::list_files_rec`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::list_files_rec;
+///
+/// let recursive = std::env::args().any(|arg| arg == "-r");
+/// let files = list_files_rec("some/folder", recursive).expect("unable to list files");
+/// ```
+pub fn list_files_rec<P: AsRef<Path>>(path: P, recursive: bool) -> Result<Vec<PathBuf>> {
+    list_items(path, FtIterTarget::Files, recursive, None)
+}
+
+/// Lists all directories in the given directory, including ALL subdirectories if `recursive` is set.
+///
+/// Same as calling [`list_directories`] or [`list_nested_directories`] depending on the flag.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::list_directories_rec`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::list_directories_rec;
+///
+/// let recursive = std::env::args().any(|arg| arg == "-r");
+/// let directories = list_directories_rec("some/folder", recursive).expect("unable to list directories");
+/// ```
+pub fn list_directories_rec<P: AsRef<Path>>(path: P, recursive: bool) -> Result<Vec<PathBuf>> {
+    list_items(path, FtIterTarget::Dirs, recursive, None)
+}
+
+/// Recursively copies the contents of the `src` directory into `dst`.
+///
+/// `dst` and any missing directories inside it are created, existing files are overwritten.
+/// Symlinks are recreated as symlinks pointing at the same target, see
+/// [`FtCopyOptions::dereference_symlinks`] to copy what they point to instead.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::copy_directory`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * `src` is a file and not a directory
+/// * `src` does not exist
+/// * `dst` is `src` or inside it, also checked through symlinks
+/// * A file, directory or symlink could not be copied
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::copy_directory;
+///
+/// copy_directory("my/project", "backups/project").expect("unable to copy directory");
+/// ```
+pub fn copy_directory(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> Result<()> {
+    copy_directory_with_options(src, dst, FtCopyOptions::default())
+}
+
+/// Recursively copies the contents of the `src` directory into `dst` with the given options.
+///
+/// Same as [`copy_directory`] but lets [`FtCopyOptions`] pick what else to carry over.
+/// Directory metadata is applied last, deepest first, as copying files into a directory
+/// changes its modification time.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::copy_directory_with_options`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * `src` is a file and not a directory
+/// * `src` does not exist
+/// * `dst` is `src` or inside it, also checked through symlinks
+/// * A file, directory or symlink could not be copied
+/// * `dereference_symlinks` is set and a symlink points back at a directory it is inside
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::{sync::copy_directory_with_options, FtCopyOptions};
+///
+/// // A faithful backup keeping modification times and permissions
+/// let options = FtCopyOptions {
+///     preserve_metadata: true,
+///     ..Default::default()
+/// };
+/// copy_directory_with_options("my/project", "backups/project", options)
+///     .expect("unable to copy directory");
+/// ```
+pub fn copy_directory_with_options(
+    src: impl AsRef<Path>,
+    dst: impl AsRef<Path>,
+    options: FtCopyOptions,
+) -> Result<()> {
+    copy_directory_with_report(src, dst, options)?;
+
+    Ok(())
+}
+
+/// Recursively copies the contents of the `src` directory into `dst` with the given options,
+/// reporting how many files and bytes were copied.
+///
+/// Same as [`copy_directory_with_options`], the totals come from the copies themselves so
+/// no extra pass over the tree is needed.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::copy_directory_with_report`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * `src` is a file and not a directory
+/// * `src` does not exist
+/// * `dst` is `src` or inside it, also checked through symlinks
+/// * A file, directory or symlink could not be copied
+/// * `dereference_symlinks` is set and a symlink points back at a directory it is inside
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::{sync::copy_directory_with_report, FtCopyOptions};
+///
+/// let report = copy_directory_with_report("my/project", "backups/project", FtCopyOptions::default())
+///     .expect("unable to copy directory");
+/// println!("copied {} files ({} bytes)", report.files, report.bytes);
+/// ```
+pub fn copy_directory_with_report(
+    src: impl AsRef<Path>,
+    dst: impl AsRef<Path>,
+    options: FtCopyOptions,
+) -> Result<FtCopyReport> {
+    let (src, dst) = (src.as_ref(), dst.as_ref());
+    ensure_dir_root(src)?;
+    anyhow::ensure!(
+        !crate::util::copies_into_itself(src, dst)?,
+        "can't copy a directory into itself"
+    );
+
+    ensure_directory(dst)?;
+    let mut dirs = vec![(src.to_path_buf(), dst.to_path_buf())];
+    let mut report = FtCopyReport::default();
+    copy_tree_sync(src, dst, options, &mut dirs, &mut report, &mut vec![])?;
+
+    if options.preserve_metadata {
+        for (dir, target) in dirs.iter().rev() {
+            copy_metadata(dir, target)?;
+        }
+    }
+
+    Ok(report)
+}
+
+/// Copies the files under `src` (including ALL subdirectories) matching a filter pattern
+/// into `dst`, keeping their directory structure relative to `src`.
+///
+/// The filter is applied the same way as [`list_nested_files_with_filter`]. Only the directories
+/// needed to hold the copied files are created under `dst`. Returns the paths of the copies.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::copy_files_matching`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * `src` is a file and not a directory
+/// * `src` does not exist
+/// * A file could not be copied or its directory could not be created
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::{sync::copy_files_matching, FtFilter};
+///
+/// let filter = FtFilter::Name(".conf".to_string());
+/// let copied = copy_files_matching("/etc/nginx", "backups/nginx", filter).expect("unable to copy files");
+/// ```
+pub fn copy_files_matching(
+    src: impl AsRef<Path>,
+    dst: impl AsRef<Path>,
+    pattern: impl Borrow<FtFilter>,
+) -> Result<Vec<PathBuf>> {
+    let (copied, _) = copy_files_matching_with_report(src, dst, pattern)?;
+
+    Ok(copied)
+}
+
+/// Copies the files under `src` (including ALL subdirectories) matching a filter pattern
+/// into `dst`, reporting how many files and bytes were copied.
+///
+/// Same as [`copy_files_matching`], returning the paths of the copies along with the totals.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::copy_files_matching_with_report`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * `src` is a file and not a directory
+/// * `src` does not exist
+/// * A file could not be copied or its directory could not be created
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::{sync::copy_files_matching_with_report, FtFilter};
+///
+/// let filter = FtFilter::Name(".conf".to_string());
+/// let (copied, report) = copy_files_matching_with_report("/etc/nginx", "backups/nginx", filter)
+///     .expect("unable to copy files");
+/// println!("backed up {} bytes", report.bytes);
+/// ```
+pub fn copy_files_matching_with_report(
+    src: impl AsRef<Path>,
+    dst: impl AsRef<Path>,
+    pattern: impl Borrow<FtFilter>,
+) -> Result<(Vec<PathBuf>, FtCopyReport)> {
+    let (src, dst) = (src.as_ref(), dst.as_ref());
+    let files = list_nested_files_with_filter(src, pattern)?;
+
+    let mut copied = Vec::with_capacity(files.len());
+    let mut report = FtCopyReport::default();
+    for relative in strip_root(src, files) {
+        let target = dst.join(&relative);
+        if let Some(parent) = target.parent() {
+            ensure_directory(parent)?;
+        }
+
+        let bytes = fs::copy(src.join(&relative), &target)
+            .with_context(|| format!("unable to copy {}", src.join(&relative).display()))?;
+        report.add(bytes);
+        copied.push(target);
+    }
+
+    Ok((copied, report))
+}
+
+/// Removes every file under `path` that `predicate` approves, then prunes the directories
+/// that removal left empty.
+///
+/// A guarded alternative to a blunt recursive delete: only files the predicate returns `true`
+/// for are removed and a directory is only removed when it held one of those files and is now
+/// empty. `path` itself and directories that were already empty are kept. Returns the removed
+/// files.
+///
+/// Symlinked directories are not followed, so nothing outside of `path` is ever removed.
+/// A symlink to a file is handed to `predicate` like a file and removing it only removes the link.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::remove_directory_where`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+/// * A file or emptied directory could not be removed
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::remove_directory_where;
+///
+/// // Clear out temporary files along with any directories holding nothing else
+/// let removed = remove_directory_where("build", |file| {
+///     file.extension().is_some_and(|ext| ext == "tmp")
+/// })
+/// .expect("unable to clean build directory");
+/// ```
+pub fn remove_directory_where(
+    path: impl AsRef<Path>,
+    mut predicate: impl FnMut(&Path) -> bool,
+) -> Result<Vec<PathBuf>> {
+    let path = path.as_ref();
+    ensure_dir_root(path)?;
+
+    let mut removed = vec![];
+    for file in crate::util::physicalfiles_sync(path)? {
+        if predicate(&file) {
+            fs::remove_file(&file)
+                .with_context(|| format!("unable to remove {}", file.display()))?;
+            removed.push(file);
+        }
+    }
+
+    for dir in emptied_dirs(path, &removed) {
+        if is_directory_empty(&dir)? {
+            fs::remove_dir(&dir).with_context(|| format!("unable to remove {}", dir.display()))?;
+        }
+    }
+
+    Ok(removed)
+}
+
+/// Moves a file or directory to the system trash / recycle bin.
+///
+/// Nothing is ever deleted permanently: if the platform has no trash to move it to, an error
+/// is returned and the path is left where it is.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::move_to_trash`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path does not exist
+/// * The platform has no trash or the path could not be moved to it
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::move_to_trash;
+///
+/// // Gone from the project but still recoverable
+/// move_to_trash("my/project/old_notes.txt").expect("unable to trash file");
+/// ```
+#[cfg(feature = "trash")]
+pub fn move_to_trash(path: impl AsRef<Path>) -> Result<()> {
+    crate::recycle::move_to_trash_sync(path.as_ref())
+}
+
+/// Moves all files directly inside a directory that match a filter to the system trash /
+/// recycle bin.
+///
+/// The trash counterpart of removing the files, nothing is ever deleted permanently: if the
+/// platform has no trash, an error is returned and the files are left where they are.
+/// Returns the paths of the trashed files.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::remove_files_matching_to_trash`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+/// * The platform has no trash or the files could not be moved to it
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::{sync::remove_files_matching_to_trash, FtFilter};
+///
+/// let logs = FtFilter::Raw(".log".to_string());
+/// let trashed = remove_files_matching_to_trash("my/app", logs).expect("unable to trash logs");
+/// ```
+#[cfg(feature = "trash")]
+pub fn remove_files_matching_to_trash(
+    path: impl AsRef<Path>,
+    filter: impl Borrow<FtFilter>,
+) -> Result<Vec<PathBuf>> {
+    crate::recycle::remove_files_matching_to_trash_sync(path, filter)
+}
+
+/// Lists all files and directories in a directory with their name, type, size
+/// and modification time.
+///
+/// The details come from a single stat per item during the traversal so callers don't need
+/// to stat every path again. Symlinks are followed and report what they point to.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::list_entries_detailed`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+/// * A directory could not be read
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::list_entries_detailed;
+///
+/// for info in list_entries_detailed("Downloads").expect("unable to list entries") {
+///     println!("{}\t{} bytes", info.name, info.len);
+/// }
+/// ```
+pub fn list_entries_detailed(path: impl AsRef<Path>) -> Result<Vec<FtFileInfo>> {
+    let path = path.as_ref();
+    ensure_dir_root(path)?;
+
+    let mut items = vec![];
+    detaileditems_sync(path, false, &mut items)?;
+
+    Ok(items)
+}
+
+/// Lists all files and directories in a directory including ALL subdirectories with their name, type, size
+/// and modification time.
+///
+/// The details come from a single stat per item during the traversal so callers don't need
+/// to stat every path again. Symlinks are followed and report what they point to.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::list_nested_entries_detailed`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+/// * A directory could not be read
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::list_nested_entries_detailed;
+///
+/// for info in list_nested_entries_detailed("Downloads").expect("unable to list entries") {
+///     println!("{}\t{} bytes", info.name, info.len);
+/// }
+/// ```
+pub fn list_nested_entries_detailed(path: impl AsRef<Path>) -> Result<Vec<FtFileInfo>> {
+    let path = path.as_ref();
+    ensure_dir_root(path)?;
+
+    let mut items = vec![];
+    detaileditems_sync(path, true, &mut items)?;
+
+    Ok(items)
+}
+
+/// Lists all files in the given directory (not including subdirectories), returning an empty list if the
+/// directory doesn't exist.
+///
+/// Same as [`list_files`] for when a missing directory just means there's nothing to list.
+/// A file at the path is still an error.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::list_files_or_empty`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The path given is a file and not a directory
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::list_files_or_empty;
+///
+/// // Nothing to do if the app never created the directory
+/// let logs = list_files_or_empty("/var/log/myapp").expect("unable to list files");
+/// ```
+pub fn list_files_or_empty<P: AsRef<Path>>(path: P) -> Result<Vec<PathBuf>> {
+    if !path.as_ref().exists() {
+        return Ok(vec![]);
+    }
+
+    list_files(path.as_ref())
+}
+
+/// Lists all files in a directory including ALL subdirectories, returning an empty list if the
+/// directory doesn't exist.
+///
+/// Same as [`list_nested_files`] for when a missing directory just means there's nothing to list.
+/// A file at the path is still an error.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::list_nested_files_or_empty`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The path given is a file and not a directory
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::list_nested_files_or_empty;
+///
+/// // Nothing to do if the app never created the directory
+/// let logs = list_nested_files_or_empty("/var/log/myapp").expect("unable to list files");
+/// ```
+pub fn list_nested_files_or_empty<P: AsRef<Path>>(path: P) -> Result<Vec<PathBuf>> {
+    if !path.as_ref().exists() {
+        return Ok(vec![]);
+    }
+
+    list_nested_files(path.as_ref())
+}
+
+/// Lists all directories in the given directory (not including subdirectories), returning an empty list if the
+/// directory doesn't exist.
+///
+/// Same as [`list_directories`] for when a missing directory just means there's nothing to list.
+/// A file at the path is still an error.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::list_directories_or_empty`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The path given is a file and not a directory
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::list_directories_or_empty;
+///
+/// // Nothing to do if the app never created the directory
+/// let plugins = list_directories_or_empty(".myapp/plugins").expect("unable to list directories");
+/// ```
+pub fn list_directories_or_empty<P: AsRef<Path>>(path: P) -> Result<Vec<PathBuf>> {
+    if !path.as_ref().exists() {
+        return Ok(vec![]);
+    }
+
+    list_directories(path.as_ref())
+}
+
+/// Lists all directories in a directory including ALL subdirectories, returning an empty list if the
+/// directory doesn't exist.
+///
+/// Same as [`list_nested_directories`] for when a missing directory just means there's nothing to list.
+/// A file at the path is still an error.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::list_nested_directories_or_empty`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The path given is a file and not a directory
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::list_nested_directories_or_empty;
+///
+/// // Nothing to do if the app never created the directory
+/// let plugins = list_nested_directories_or_empty(".myapp/plugins").expect("unable to list directories");
+/// ```
+pub fn list_nested_directories_or_empty<P: AsRef<Path>>(path: P) -> Result<Vec<PathBuf>> {
+    if !path.as_ref().exists() {
+        return Ok(vec![]);
+    }
+
+    list_nested_directories(path.as_ref())
+}
+
+/// Groups the files in a directory by their lowercased extension, descending into
+/// subdirectories if `recursive` is set.
+///
+/// `photo.JPG` and `photo.jpg` both land under `"jpg"`, only the case is ignored so `.jpeg`
+/// files get their own `"jpeg"` group. Files without an extension are grouped under `""`.
+/// The files in each group are sorted.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::group_files_by_extension`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The path given is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::group_files_by_extension;
+///
+/// let groups = group_files_by_extension("Pictures", true).expect("unable to group files");
+/// let photos = groups.get("jpg").map_or(0, Vec::len);
+/// ```
+pub fn group_files_by_extension(
+    path: impl AsRef<Path>,
+    recursive: bool,
+) -> Result<HashMap<String, Vec<PathBuf>>> {
+    let files = list_files_rec(path, recursive)?;
+
+    Ok(group_by_extension(files))
+}
+
+/// Finds the `n` largest files in a directory including ALL subdirectories.
+///
+/// Returns each file with its size in bytes, largest first. Only the `n` largest sizes seen
+/// so far are kept during the traversal, so memory doesn't grow with the size of the tree.
+/// Files of the same size are ordered by path, last first.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::largest_files`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The path given is a file and not a directory
+/// * The given path does not exist
+/// * A directory could not be read
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::largest_files;
+///
+/// for (file, len) in largest_files("/home/user", 20).expect("unable to find largest files") {
+///     println!("{len:>12} {}", file.display());
+/// }
+/// ```
+pub fn largest_files(path: impl AsRef<Path>, n: usize) -> Result<Vec<(PathBuf, u64)>> {
+    let path = path.as_ref();
+    ensure_dir_root(path)?;
+
+    let mut visitor = TopItems::new(n, |meta| Some(meta.len()));
+    walk_sync(path, FtIterItemState::RFile, None, &mut visitor)?;
+
+    Ok(visitor.into_sorted())
+}
+
+/// Finds the `n` oldest files in a directory by modification time, descending into
+/// subdirectories if `recursive` is set.
+///
+/// Returns each file with its modification time, oldest first. Only the `n` oldest times seen
+/// so far are kept during the traversal. Files without a modification time, on platforms
+/// that don't record it, are left out.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::oldest_files`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The path given is a file and not a directory
+/// * The given path does not exist
+/// * A directory could not be read
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::oldest_files;
+///
+/// // Rotate out the oldest logs
+/// for (log, _) in oldest_files("/var/log/myapp", 10, false).expect("unable to list files") {
+///     std::fs::remove_file(log).expect("unable to remove log");
+/// }
+/// ```
+pub fn oldest_files(
+    path: impl AsRef<Path>,
+    n: usize,
+    recursive: bool,
+) -> Result<Vec<(PathBuf, SystemTime)>> {
+    let path = path.as_ref();
+    ensure_dir_root(path)?;
+
+    let iterstate = if recursive {
+        FtIterItemState::RFile
+    } else {
+        FtIterItemState::File
+    };
+
+    let mut visitor = TopItems::new(n, oldest_key);
+    walk_sync(path, iterstate, None, &mut visitor)?;
+
+    Ok(visitor
+        .into_sorted()
+        .into_iter()
+        .map(|(file, Reverse(modified))| (file, modified))
+        .collect())
+}
+
+/// Finds the `n` newest files in a directory by modification time, descending into
+/// subdirectories if `recursive` is set.
+///
+/// Returns each file with its modification time, newest first. Only the `n` newest times seen
+/// so far are kept during the traversal. Files without a modification time, on platforms
+/// that don't record it, are left out.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::newest_files`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The path given is a file and not a directory
+/// * The given path does not exist
+/// * A directory could not be read
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::newest_files;
+///
+/// // Show what changed most recently
+/// for (file, modified) in newest_files("my/project", 10, true).expect("unable to list files") {
+///     println!("{} {modified:?}", file.display());
+/// }
+/// ```
+pub fn newest_files(
+    path: impl AsRef<Path>,
+    n: usize,
+    recursive: bool,
+) -> Result<Vec<(PathBuf, SystemTime)>> {
+    let path = path.as_ref();
+    ensure_dir_root(path)?;
+
+    let iterstate = if recursive {
+        FtIterItemState::RFile
+    } else {
+        FtIterItemState::File
+    };
+
+    let mut visitor = TopItems::new(n, modified_key);
+    walk_sync(path, iterstate, None, &mut visitor)?;
+
+    Ok(visitor.into_sorted())
+}
+
+/// Lists at most `limit` files in a directory, descending into subdirectories if `recursive`
+/// is set.
+///
+/// The traversal stops as soon as `limit` files have been found, so an accidental listing of
+/// `/` doesn't walk the whole filesystem. Which files make the cut depends on the order the OS
+/// returns directory entries in.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::list_files_limited`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The path given is a file and not a directory
+/// * The given path does not exist
+/// * A directory could not be read
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::{sync::list_files_limited, FtFilter};
+///
+/// // A quick preview rather than the whole tree
+/// let filter = FtFilter::Raw(".rs".to_string());
+/// let preview = list_files_limited("/", true, Some(filter), 100).expect("unable to list files");
+/// ```
+pub fn list_files_limited(
+    path: impl AsRef<Path>,
+    recursive: bool,
+    filter: Option<FtFilter>,
+    limit: usize,
+) -> Result<Vec<PathBuf>> {
+    let path = path.as_ref();
+    ensure_dir_root(path)?;
+
+    let iterstate = if recursive {
+        FtIterItemState::RFile
+    } else {
+        FtIterItemState::File
+    };
+
+    limiteditems_sync(path, iterstate, filter.as_ref(), limit)
+}
+
+/// Lists the files in the given directory (not including subdirectories) matching a filter, along
+/// with how many files were checked.
+///
+/// Same as [`list_files_with_filter`] but an empty result can be told apart: `total_scanned` is 0 when there
+/// were no files at all and more than 0 when there were files but none matched.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::list_files_with_filter_scanned`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::{sync::list_files_with_filter_scanned, FtFilter};
+///
+/// let filter = FtFilter::Raw(".log".to_string());
+/// let result = list_files_with_filter_scanned("my/app", filter).expect("unable to list files");
+/// if result.nothing_scanned() {
+///     println!("the directory is empty");
+/// } else if result.matched.is_empty() {
+///     println!("no logs among {} files", result.total_scanned);
+/// }
+/// ```
+pub fn list_files_with_filter_scanned(
+    path: impl AsRef<Path>,
+    pattern: impl Borrow<FtFilter>,
+) -> Result<FtFilterResult> {
+    let path = path.as_ref();
+    ensure_dir_root(path)?;
+
+    let mut visitor = ScannedItems::new(path, pattern.borrow());
+    walk_sync(path, FtIterItemState::File, None, &mut visitor)?;
+
+    Ok(visitor.result)
+}
+
+/// Lists the files in a directory including ALL subdirectories matching a filter, along
+/// with how many files were checked.
+///
+/// Same as [`list_nested_files_with_filter`] but an empty result can be told apart: `total_scanned` is 0 when there
+/// were no files at all and more than 0 when there were files but none matched.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::list_nested_files_with_filter_scanned`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::{sync::list_nested_files_with_filter_scanned, FtFilter};
+///
+/// let filter = FtFilter::Raw(".log".to_string());
+/// let result = list_nested_files_with_filter_scanned("my/app", filter).expect("unable to list files");
+/// if result.nothing_scanned() {
+///     println!("the directory is empty");
+/// } else if result.matched.is_empty() {
+///     println!("no logs among {} files", result.total_scanned);
+/// }
+/// ```
+pub fn list_nested_files_with_filter_scanned(
+    path: impl AsRef<Path>,
+    pattern: impl Borrow<FtFilter>,
+) -> Result<FtFilterResult> {
+    let path = path.as_ref();
+    ensure_dir_root(path)?;
+
+    let mut visitor = ScannedItems::new(path, pattern.borrow());
+    walk_sync(path, FtIterItemState::RFile, None, &mut visitor)?;
+
+    Ok(visitor.result)
+}
+
+/// Lists files in a folder (including ALL subdirectories) matching a filter pattern, along
+/// with statistics about the traversal.
+///
+/// Same as [`list_nested_files_with_filter`] but also reports how many directories were opened,
+/// how many entries were read from them and how long it took.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::list_nested_files_with_stats`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::{sync::list_nested_files_with_stats, FtFilter};
+///
+/// let filter = FtFilter::Raw(".log".to_string());
+/// let (logs, stats) = list_nested_files_with_stats("/var/log", filter).expect("unable to list files");
+/// println!("{} directories visited in {:?}", stats.dirs_visited, stats.elapsed);
+/// ```
+pub fn list_nested_files_with_stats(
+    path: impl AsRef<Path>,
+    pattern: impl Borrow<FtFilter>,
+) -> Result<(Vec<PathBuf>, FtTraversalStats)> {
+    let path = path.as_ref();
+    ensure_dir_root(path)?;
+
+    let start = Instant::now();
+    let mut visitor = StatsItems::default();
+    walk_sync(
+        path,
+        FtIterItemState::RFile,
+        Some(pattern.borrow()),
+        &mut visitor,
+    )?;
+    visitor.stats.elapsed = start.elapsed();
+
+    Ok((visitor.items, visitor.stats))
+}
+
+/// Lists all files in the given directory, including ALL subdirectories apart from those
+/// named in `exclude_dirs`.
+///
+/// Directories are matched on their name alone, e.g. `node_modules` or `.git`, at any depth.
+/// Excluded directories aren't read at all which is much cheaper than filtering the results
+/// afterwards for large trees like build outputs.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::list_nested_files_excluding`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::list_nested_files_excluding;
+///
+/// let sources = list_nested_files_excluding("my/project", &["node_modules", "target", ".git"])
+///     .expect("unable to list files");
+/// ```
+pub fn list_nested_files_excluding(
+    path: impl AsRef<Path>,
+    exclude_dirs: &[impl AsRef<Path>],
+) -> Result<Vec<PathBuf>> {
+    let path = path.as_ref();
+    ensure_dir_root(path)?;
+
+    let mut visitor = ExcludedItems::new(exclude_dirs);
+    walk_sync(path, FtIterItemState::RFile, None, &mut visitor)?;
+
+    Ok(visitor.items)
+}
+
+/// Lists all directories in the given directory, including ALL subdirectories apart from those
+/// named in `exclude_dirs`.
+///
+/// Directories are matched on their name alone, e.g. `node_modules` or `.git`, at any depth.
+/// Excluded directories aren't read at all which is much cheaper than filtering the results
+/// afterwards for large trees like build outputs. The excluded directories are not listed either.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::list_nested_directories_excluding`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::list_nested_directories_excluding;
+///
+/// let sources = list_nested_directories_excluding("my/project", &["node_modules", "target", ".git"])
+///     .expect("unable to list directories");
+/// ```
+pub fn list_nested_directories_excluding(
+    path: impl AsRef<Path>,
+    exclude_dirs: &[impl AsRef<Path>],
+) -> Result<Vec<PathBuf>> {
+    let path = path.as_ref();
+    ensure_dir_root(path)?;
+
+    let mut visitor = ExcludedItems::new(exclude_dirs);
+    walk_sync(path, FtIterItemState::RDir, None, &mut visitor)?;
+
+    Ok(visitor.items)
+}
+
+/// Lists all files in the given directory, including the subdirectories `should_descend`
+/// approves, optionally matching a filter pattern.
+///
+/// `should_descend` is called with each directory found before it is read, returning `false`
+/// skips everything below it. The more general form of [`list_nested_files_excluding`] for
+/// pruning on anything known from the path, like its depth or an ignore file inside it.
+///
+/// ## Async
+///
+/// For the `async` version, see: [`crate::list_nested_files_prune`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::sync::list_nested_files_prune;
+///
+/// // Skip any directory marked as excluded from backups
+/// let files = list_nested_files_prune("/home/user", |dir| !dir.join(".nobackup").exists(), None)
+///     .expect("unable to list files");
+/// ```
+pub fn list_nested_files_prune<F>(
+    path: impl AsRef<Path>,
+    should_descend: F,
+    filter: Option<FtFilter>,
+) -> Result<Vec<PathBuf>>
+where
+    F: FnMut(&Path) -> bool,
+{
+    let path = path.as_ref();
+    ensure_dir_root(path)?;
+
+    let mut visitor = PrunedItems::new(should_descend);
+    walk_sync(path, FtIterItemState::RFile, filter.as_ref(), &mut visitor)?;
+
+    Ok(visitor.items)
 }
 
 // No tests needed cause these are tested in the main crate
@@ -0,0 +1,466 @@
+//! Directory trees as a nested structure rather than a flat listing
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use filetools::build_tree;
+//!
+//! #[tokio::main]
+//! async fn main() -> anyhow::Result<()> {
+//!     let tree = build_tree("my/project", None, Some(2)).await?;
+//!     for child in &tree.children {
+//!         println!("{} ({} items)", child.path.display(), child.children.len());
+//!     }
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use crate::util::{ensure_dir_root, matches_filter_at};
+use crate::FtFilter;
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+/// A file or directory in a tree built by [`build_tree`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FtTreeNode {
+    /// Path of the item, built by joining names onto the root like the listing functions
+    pub path: PathBuf,
+
+    /// Items inside the directory sorted by name, always empty for files
+    pub children: Vec<FtTreeNode>,
+
+    /// Whether the item is a directory
+    pub is_dir: bool,
+}
+
+impl FtTreeNode {
+    fn file(path: PathBuf) -> Self {
+        Self {
+            path,
+            children: vec![],
+            is_dir: false,
+        }
+    }
+
+    fn dir(path: PathBuf, children: Vec<FtTreeNode>) -> Self {
+        Self {
+            path,
+            children,
+            is_dir: true,
+        }
+    }
+
+    /// Items are kept if they match the filter or still have children that do
+    fn keep(&self, filter: Option<&FtFilter>, depth: usize) -> bool {
+        match filter {
+            Some(f) => !self.children.is_empty() || matches_filter_at(&self.path, f, depth),
+            None => true,
+        }
+    }
+}
+
+/// Whether the items at `depth` are beyond the depth limit
+fn too_deep(depth: usize, max_depth: Option<usize>) -> bool {
+    max_depth.is_some_and(|max| depth >= max)
+}
+
+/// Keeps the nodes at `depth` below the root the filter allows in a stable, name sorted order
+fn finish(mut nodes: Vec<FtTreeNode>, filter: Option<&FtFilter>, depth: usize) -> Vec<FtTreeNode> {
+    nodes.retain(|node| node.keep(filter, depth));
+    nodes.sort_by(|a, b| a.path.file_name().cmp(&b.path.file_name()));
+    nodes
+}
+
+/// A directory being read while building a tree, with the nodes found in it so far
+struct Frame {
+    path: PathBuf,
+
+    /// Canonical path of the directory, to spot symlinks leading back to it
+    canonical: PathBuf,
+
+    /// How far below the root the directory sits
+    depth: usize,
+
+    /// Entries not looked at yet, in reverse so the next one can be popped
+    pending: Vec<PathBuf>,
+    nodes: Vec<FtTreeNode>,
+}
+
+/// Where an entry of the directory on top of the stack goes
+enum Step {
+    /// A file, or a directory that isn't descended into, added to the parent as it is
+    Node(FtTreeNode),
+
+    /// A directory whose entries have to be read next
+    Descend(PathBuf),
+
+    /// Neither a file nor a directory (e.g. a broken symlink)
+    Skip,
+}
+
+impl Frame {
+    fn new(path: PathBuf, canonical: PathBuf, depth: usize, mut pending: Vec<PathBuf>) -> Self {
+        pending.reverse();
+        Self {
+            path,
+            canonical,
+            depth,
+            pending,
+            nodes: vec![],
+        }
+    }
+}
+
+/// Decides what to do with `path`, an entry of the directory on top of `stack`.
+///
+/// `canonical` is the canonical path of `path` if it is a directory. Directories at the
+/// depth limit, and symlinks back to one of their own ancestors, are kept without children.
+fn step(
+    stack: &[Frame],
+    path: PathBuf,
+    canonical: Option<&Path>,
+    max_depth: Option<usize>,
+) -> Step {
+    let depth = stack.last().map_or(0, |frame| frame.depth) + 1;
+    match canonical {
+        Some(canonical) => {
+            let cycle = stack.iter().any(|frame| frame.canonical == canonical);
+            if cycle || too_deep(depth, max_depth) {
+                Step::Node(FtTreeNode::dir(path, vec![]))
+            } else {
+                Step::Descend(path)
+            }
+        }
+        None if path.is_file() => Step::Node(FtTreeNode::file(path)),
+        None => Step::Skip,
+    }
+}
+
+/// Pops the finished directory on top of `stack`, adding it to its parent. Returns the
+/// children of the root once the root itself is finished.
+fn close(stack: &mut Vec<Frame>, filter: Option<&FtFilter>) -> Option<Vec<FtTreeNode>> {
+    let frame = stack.pop()?;
+    let nodes = finish(frame.nodes, filter, frame.depth + 1);
+    match stack.last_mut() {
+        Some(parent) => {
+            parent.nodes.push(FtTreeNode::dir(frame.path, nodes));
+            None
+        }
+        None => Some(nodes),
+    }
+}
+
+/// Reads the entry paths of `dir`
+async fn read_entries(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut entries = fs::read_dir(dir)
+        .await
+        .with_context(|| format!("unable to read {}", dir.display()))?;
+
+    let mut paths = vec![];
+    while let Some(entry) = entries
+        .next_entry()
+        .await
+        .with_context(|| format!("unable to read {}", dir.display()))?
+    {
+        paths.push(entry.path());
+    }
+
+    Ok(paths)
+}
+
+/// Sync version of [`read_entries`]
+fn read_entries_sync(dir: &Path) -> Result<Vec<PathBuf>> {
+    std::fs::read_dir(dir)
+        .with_context(|| format!("unable to read {}", dir.display()))?
+        .map(|entry| {
+            entry
+                .map(|e| e.path())
+                .with_context(|| format!("unable to read {}", dir.display()))
+        })
+        .collect()
+}
+
+/// Canonical path of `path` if it is a directory, following symlinks like the listings
+async fn canonical_dir(path: &Path) -> Result<Option<PathBuf>> {
+    if !fs::metadata(path).await.is_ok_and(|meta| meta.is_dir()) {
+        return Ok(None);
+    }
+
+    let canonical = fs::canonicalize(path)
+        .await
+        .with_context(|| format!("unable to canonicalize {}", path.display()))?;
+    Ok(Some(canonical))
+}
+
+/// Sync version of [`canonical_dir`]
+fn canonical_dir_sync(path: &Path) -> Result<Option<PathBuf>> {
+    if !path.is_dir() {
+        return Ok(None);
+    }
+
+    let canonical = std::fs::canonicalize(path)
+        .with_context(|| format!("unable to canonicalize {}", path.display()))?;
+    Ok(Some(canonical))
+}
+
+/// Reads the children of the `root` directory.
+///
+/// Rather than recursing, the directories being read are kept on an explicit stack like
+/// the listing traversal, which also makes it easy to spot symlinks back to an ancestor.
+async fn read_children(
+    root: &Path,
+    filter: Option<&FtFilter>,
+    max_depth: Option<usize>,
+) -> Result<Vec<FtTreeNode>> {
+    if too_deep(0, max_depth) {
+        return Ok(vec![]);
+    }
+
+    let canonical = canonical_dir(root)
+        .await?
+        .unwrap_or_else(|| root.to_path_buf());
+    let entries = read_entries(root).await?;
+    let mut stack = vec![Frame::new(root.to_path_buf(), canonical, 0, entries)];
+
+    loop {
+        let Some(path) = stack.last_mut().and_then(|frame| frame.pending.pop()) else {
+            match close(&mut stack, filter) {
+                Some(children) => return Ok(children),
+                None => continue,
+            }
+        };
+
+        let canonical = canonical_dir(&path).await?;
+        match step(&stack, path, canonical.as_deref(), max_depth) {
+            Step::Node(node) => stack
+                .last_mut()
+                .expect("stack is not empty")
+                .nodes
+                .push(node),
+            Step::Descend(path) => {
+                let entries = read_entries(&path).await?;
+                let depth = stack.len();
+                let canonical = canonical.expect("only directories are descended into");
+                stack.push(Frame::new(path, canonical, depth, entries));
+            }
+            Step::Skip => {}
+        }
+    }
+}
+
+/// Sync version of [`read_children`]
+fn read_children_sync(
+    root: &Path,
+    filter: Option<&FtFilter>,
+    max_depth: Option<usize>,
+) -> Result<Vec<FtTreeNode>> {
+    if too_deep(0, max_depth) {
+        return Ok(vec![]);
+    }
+
+    let canonical = canonical_dir_sync(root)?.unwrap_or_else(|| root.to_path_buf());
+    let entries = read_entries_sync(root)?;
+    let mut stack = vec![Frame::new(root.to_path_buf(), canonical, 0, entries)];
+
+    loop {
+        let Some(path) = stack.last_mut().and_then(|frame| frame.pending.pop()) else {
+            match close(&mut stack, filter) {
+                Some(children) => return Ok(children),
+                None => continue,
+            }
+        };
+
+        let canonical = canonical_dir_sync(&path)?;
+        match step(&stack, path, canonical.as_deref(), max_depth) {
+            Step::Node(node) => stack
+                .last_mut()
+                .expect("stack is not empty")
+                .nodes
+                .push(node),
+            Step::Descend(path) => {
+                let entries = read_entries_sync(&path)?;
+                let depth = stack.len();
+                let canonical = canonical.expect("only directories are descended into");
+                stack.push(Frame::new(path, canonical, depth, entries));
+            }
+            Step::Skip => {}
+        }
+    }
+}
+
+/// Builds the hierarchy of a directory as a tree of [`FtTreeNode`]s in a single pass.
+///
+/// Children are sorted by name. With a filter, files are kept if they match it and
+/// directories are kept if they match it or still contain something that does, so branches
+/// without any matches are pruned. `max_depth` limits how far down the tree goes: `Some(1)`
+/// only holds the immediate children of `path`, directories at the limit have no children.
+/// Symlinks are followed like in the listing functions, except a symlink back to one of its own
+/// ancestors which is kept as a directory without children instead of being followed forever.
+///
+/// ## Sync
+///
+/// For the `sync` version, see [`crate::sync::build_tree`]
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+/// * A directory could not be read
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::{build_tree, FtFilter};
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     // Only the branches leading to Lua files
+///     let filter = FtFilter::Raw(".lua".to_string());
+///     let tree = build_tree("/home/user/.config/nvim", Some(filter), None).await?;
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn build_tree(
+    path: impl AsRef<Path> + Send,
+    filter: Option<FtFilter>,
+    max_depth: Option<usize>,
+) -> Result<FtTreeNode> {
+    let path = path.as_ref();
+    ensure_dir_root(path)?;
+
+    let children = read_children(path, filter.as_ref(), max_depth).await?;
+    Ok(FtTreeNode::dir(path.to_path_buf(), children))
+}
+
+/// Sync version of [`build_tree`], exposed as [`crate::sync::build_tree`]
+pub(crate) fn build_tree_sync(
+    path: impl AsRef<Path>,
+    filter: Option<FtFilter>,
+    max_depth: Option<usize>,
+) -> Result<FtTreeNode> {
+    let path = path.as_ref();
+    ensure_dir_root(path)?;
+
+    let children = read_children_sync(path, filter.as_ref(), max_depth)?;
+    Ok(FtTreeNode::dir(path.to_path_buf(), children))
+}
+
+#[cfg(test)]
+mod tree_tests {
+    use super::*;
+    use crate::util::TempPath;
+
+    async fn fixture(name: &str) -> Result<TempPath> {
+        let root = TempPath::new(name).await?;
+        root.multi_folder(vec!["src/bin", "docs", "empty"]).await?;
+        root.multi_file(vec![
+            "Cargo.toml",
+            "src/lib.rs",
+            "src/bin/cli.rs",
+            "docs/guide.md",
+        ])
+        .await?;
+
+        Ok(root)
+    }
+
+    fn leaf_files(node: &FtTreeNode, files: &mut Vec<PathBuf>) {
+        for child in &node.children {
+            assert_eq!(child.path.parent(), Some(node.path.as_path()));
+            assert!(node.is_dir);
+            if child.is_dir {
+                leaf_files(child, files);
+            } else {
+                assert!(child.children.is_empty());
+                files.push(child.path.clone());
+            }
+        }
+    }
+
+    fn names(node: &FtTreeNode) -> Vec<String> {
+        node.children
+            .iter()
+            .map(|c| c.path.file_name().unwrap().to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn tree_matches_nested_listing() -> Result<()> {
+        let root = fixture("tree_matches_nested_listing").await?;
+
+        let tree = build_tree(&root.path, None, None).await?;
+        assert_eq!(tree.path, root.path);
+        assert_eq!(names(&tree), vec!["Cargo.toml", "docs", "empty", "src"]);
+
+        let mut files = vec![];
+        leaf_files(&tree, &mut files);
+        files.sort();
+        let mut expected = crate::list_nested_files(&root.path).await?;
+        expected.sort();
+        assert_eq!(files, expected);
+
+        let src = &tree.children[3];
+        assert_eq!(names(src), vec!["bin", "lib.rs"]);
+        assert_eq!(
+            src.children[0].children[0].path,
+            root.path.join("src/bin/cli.rs")
+        );
+
+        assert_eq!(build_tree_sync(&root.path, None, None)?, tree);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn tree_filter_and_depth() -> Result<()> {
+        let root = fixture("tree_filter_and_depth").await?;
+
+        // Branches without a Rust file are pruned
+        let filter = FtFilter::Raw(".rs".to_string());
+        let tree = build_tree(&root.path, Some(filter.clone()), None).await?;
+        assert_eq!(names(&tree), vec!["src"]);
+        assert_eq!(names(&tree.children[0]), vec!["bin", "lib.rs"]);
+        assert_eq!(build_tree_sync(&root.path, Some(filter), None)?, tree);
+
+        let tree = build_tree(&root.path, None, Some(1)).await?;
+        assert_eq!(names(&tree), vec!["Cargo.toml", "docs", "empty", "src"]);
+        assert!(tree.children.iter().all(|c| c.children.is_empty()));
+
+        let tree = build_tree_sync(&root.path, None, Some(0))?;
+        assert!(tree.children.is_empty());
+
+        assert!(build_tree(root.path.join("Cargo.toml"), None, None)
+            .await
+            .is_err());
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn tree_stops_at_symlink_cycles() -> Result<()> {
+        let root = fixture("tree_stops_at_symlink_cycles").await?;
+        std::os::unix::fs::symlink(&root.path, root.path.join("src/bin/root"))?;
+        std::os::unix::fs::symlink("../docs", root.path.join("src/docs"))?;
+
+        let tree = build_tree(&root.path, None, None).await?;
+        assert_eq!(build_tree_sync(&root.path, None, None)?, tree);
+
+        // The link back to the root is kept but not followed
+        let bin = &tree.children[3].children[0];
+        assert_eq!(names(bin), vec!["cli.rs", "root"]);
+        assert!(bin.children[1].is_dir);
+        assert!(bin.children[1].children.is_empty());
+
+        // A link to a directory that isn't an ancestor is followed as usual
+        let linked_docs = &tree.children[3].children[1];
+        assert_eq!(names(linked_docs), vec!["guide.md"]);
+
+        Ok(())
+    }
+}
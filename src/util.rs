@@ -1,9 +1,22 @@
 //! Internal helper utilities and types
 
-use crate::{ensure_directory, path_contains, FtFilter};
+use crate::vfs::{FtFileSystem, FtReadDir, FtRealFs};
+use crate::{
+    ensure_directory, naming, path_contains_str, DirEntryKind, FtCopyOptions, FtCopyReport,
+    FtError, FtFileInfo, FtFilter, FtFilterResult, FtHasher, FtIterTarget, FtPartition,
+    FtTraversalStats,
+};
 use anyhow::{Context, Result};
 use async_recursion::async_recursion;
-use std::path::{Path, PathBuf};
+use std::cell::Cell;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::ffi::{OsStr, OsString};
+use std::io::{Read, Write};
+use std::ops::ControlFlow;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::SystemTime;
 use tokio::fs;
 
 /// Determines the type of iteration performed by the `list_directories` and `list_files` functions
@@ -20,141 +33,2399 @@ pub(crate) enum FtIterItemState {
 
     /// Iterate directories with recursion
     RDir,
+
+    /// Iterate files and directories with no recursion
+    Both,
+
+    /// Iterate files and directories with recursion
+    RBoth,
+}
+
+impl FtIterItemState {
+    /// Picks the iteration state for the public [`FtIterTarget`] and recursion flag
+    pub(crate) fn new(target: FtIterTarget, recursive: bool) -> Self {
+        match (target, recursive) {
+            (FtIterTarget::Files, false) => Self::File,
+            (FtIterTarget::Files, true) => Self::RFile,
+            (FtIterTarget::Dirs, false) => Self::Dir,
+            (FtIterTarget::Dirs, true) => Self::RDir,
+            (FtIterTarget::Both, false) => Self::Both,
+            (FtIterTarget::Both, true) => Self::RBoth,
+        }
+    }
+}
+
+/// (De)serializes a [`Regex`] as its pattern string, recompiling it on the way back in
+#[cfg(feature = "serde")]
+pub(crate) mod serde_regex {
+    use regex::Regex;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(re: &Regex, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(re.as_str())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Regex, D::Error> {
+        let pattern = String::deserialize(deserializer)?;
+        Regex::new(&pattern).map_err(D::Error::custom)
+    }
+}
+
+/// Checks if the items at `a` and `b` are the same file from their metadata, see [`crate::same_file`]
+///
+/// Unix compares the device and inode numbers straight from the metadata.
+#[cfg(unix)]
+pub(crate) fn same_file_identity(
+    _a: &Path,
+    a_meta: &std::fs::Metadata,
+    _b: &Path,
+    b_meta: &std::fs::Metadata,
+) -> Result<bool> {
+    use std::os::unix::fs::MetadataExt;
+
+    Ok(a_meta.dev() == b_meta.dev() && a_meta.ino() == b_meta.ino())
+}
+
+/// Checks if the items at `a` and `b` are the same file from their metadata, see [`crate::same_file`]
+///
+/// std doesn't expose a stable file id here, so both items are opened to compare their ids
+/// through `same-file` (the volume serial number and file index on Windows). The metadata
+/// rules out most different files without opening them.
+#[cfg(not(unix))]
+pub(crate) fn same_file_identity(
+    a: &Path,
+    a_meta: &std::fs::Metadata,
+    b: &Path,
+    b_meta: &std::fs::Metadata,
+) -> Result<bool> {
+    if a_meta.is_dir() != b_meta.is_dir() || a_meta.len() != b_meta.len() {
+        return Ok(false);
+    }
+
+    let a = same_file::Handle::from_path(a).context("unable to open file")?;
+    let b = same_file::Handle::from_path(b).context("unable to open file")?;
+    Ok(a == b)
+}
+
+/// Checks if copying the `src` directory to `dst` would copy it into itself
+///
+/// Either `dst` is lexically inside `src`, it is the same directory as `src`, or it resolves
+/// inside `src` once symlinks are followed. `dst` doesn't need to exist yet, its nearest existing
+/// ancestor is resolved instead.
+pub(crate) fn copies_into_itself(src: &Path, dst: &Path) -> Result<bool> {
+    if crate::is_descendant_of(dst, src) {
+        return Ok(true);
+    }
+
+    if let (Ok(src_meta), Ok(dst_meta)) = (std::fs::metadata(src), std::fs::metadata(dst)) {
+        if same_file_identity(src, &src_meta, dst, &dst_meta)? {
+            return Ok(true);
+        }
+    }
+
+    let src = std::fs::canonicalize(src).context("unable to canonicalize path")?;
+    let mut existing = dst;
+    let mut missing = vec![];
+    while !existing.exists() {
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                missing.push(name);
+                existing = parent;
+            }
+            // Nothing left to resolve, the lexical check is all there is
+            _ => return Ok(false),
+        }
+    }
+
+    let base = if existing.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        existing
+    };
+    let mut resolved = std::fs::canonicalize(base).context("unable to canonicalize path")?;
+    resolved.extend(missing.iter().rev());
+
+    Ok(resolved == src || crate::is_descendant_of(&resolved, &src))
+}
+
+/// Checks if `path` is an executable file, see [`FtFilter::Executable`]
+fn is_executable(path: &Path) -> bool {
+    std::fs::metadata(path).is_ok_and(|meta| executable_meta(path, &meta))
+}
+
+/// Async version of [`is_executable`]
+async fn is_executable_async(path: &Path) -> bool {
+    fs::metadata(path)
+        .await
+        .is_ok_and(|meta| executable_meta(path, &meta))
+}
+
+/// Checks if the item at `path` with the metadata `meta` is an executable file
+fn executable_meta(path: &Path, meta: &std::fs::Metadata) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let _ = path;
+        meta.is_file() && meta.permissions().mode() & 0o111 != 0
+    }
+
+    #[cfg(windows)]
+    {
+        let executable_ext = path.extension().is_some_and(|ext| {
+            ["exe", "bat", "cmd"]
+                .iter()
+                .any(|e| ext.eq_ignore_ascii_case(e))
+        });
+
+        executable_ext && meta.is_file()
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    {
+        let _ = (path, meta);
+        false
+    }
+}
+
+/// Whether `needle` appears in `haystack`.
+///
+/// Compared on the encoded bytes rather than as strings so names that aren't valid UTF-8
+/// are matched as they are instead of being dropped or mangled by a lossy conversion.
+pub(crate) fn os_contains(haystack: &OsStr, needle: &OsStr) -> bool {
+    contains_bytes(haystack.as_encoded_bytes(), needle.as_encoded_bytes())
+}
+
+/// Same as [`os_contains`] but treating `/` and `\` as the same separator on Windows.
+///
+/// Everywhere else `\` is an ordinary file name character so it is matched literally.
+#[cfg(windows)]
+pub(crate) fn os_contains_any_separator(haystack: &OsStr, needle: &OsStr) -> bool {
+    // `\` is ASCII so it never appears inside a multi byte sequence
+    let unify = |s: &OsStr| -> Vec<u8> {
+        s.as_encoded_bytes()
+            .iter()
+            .map(|&b| if b == b'\\' { b'/' } else { b })
+            .collect()
+    };
+
+    contains_bytes(&unify(haystack), &unify(needle))
+}
+
+/// Same as [`os_contains`], outside Windows `\` is an ordinary file name character
+/// and not a separator so there is nothing to unify
+#[cfg(not(windows))]
+pub(crate) fn os_contains_any_separator(haystack: &OsStr, needle: &OsStr) -> bool {
+    os_contains(haystack, needle)
+}
+
+fn contains_bytes(haystack: &[u8], needle: &[u8]) -> bool {
+    needle.is_empty() || haystack.windows(needle.len()).any(|w| w == needle)
+}
+
+/// Number of components of `item` below `root`, 1 for the direct children of `root`
+pub(crate) fn depth_under(root: &Path, item: &Path) -> usize {
+    match item.strip_prefix(root) {
+        Ok(relative) => relative.components().count(),
+        Err(_) => item.components().count(),
+    }
+}
+
+/// Helper function to determine if an path item is valid based on the supplied filter
+///
+/// `depth` is how many components `item` is below the traversal root, see [`depth_under`].
+pub(crate) fn matches_filter_at(item: impl AsRef<Path>, filter: &FtFilter, depth: usize) -> bool {
+    match filter {
+        // I know these are the same for Raw and Path
+        // but it complains when you try and use the | with match
+        // for this
+        FtFilter::Raw(raw) => {
+            if path_contains_str(&item, raw) {
+                return true;
+            }
+        }
+        FtFilter::Path(filter_path) => {
+            if os_contains_any_separator(item.as_ref().as_os_str(), filter_path.as_os_str()) {
+                return true;
+            }
+        }
+        FtFilter::Name(name) => {
+            let item_name = item.as_ref().file_name();
+            if item_name.is_some_and(|n| os_contains(n, OsStr::new(name))) {
+                return true;
+            }
+        }
+        FtFilter::ParentName(name) => {
+            let parent_name = item.as_ref().parent().and_then(|p| p.file_name());
+            if parent_name.is_some_and(|n| os_contains(n, OsStr::new(name))) {
+                return true;
+            }
+        }
+        FtFilter::Canonical(inner) => {
+            if let Ok(canonical) = std::fs::canonicalize(&item) {
+                return matches_filter_at(canonical, inner, depth);
+            }
+        }
+        FtFilter::Executable => {
+            if is_executable(item.as_ref()) {
+                return true;
+            }
+        }
+        FtFilter::Empty | FtFilter::NonEmpty => {
+            let want_empty = matches!(filter, FtFilter::Empty);
+            if let Ok(meta) = std::fs::metadata(item.as_ref()) {
+                return meta.is_file() && (meta.len() == 0) == want_empty;
+            }
+        }
+        FtFilter::Regex(re) => {
+            // Non UTF-8 paths are matched against their lossy representation
+            // rather than panicking
+            if re.is_match(&item.as_ref().to_string_lossy()) {
+                return true;
+            }
+        }
+        FtFilter::RegexName(re) => {
+            let item_name = item.as_ref().file_name().map(|n| n.to_string_lossy());
+            if item_name.is_some_and(|n| re.is_match(&n)) {
+                return true;
+            }
+        }
+        FtFilter::Custom(predicate) => {
+            if predicate(item.as_ref()) {
+                return true;
+            }
+        }
+        FtFilter::MinDepth(min) => return depth >= *min,
+        FtFilter::MaxDepth(max) => return depth <= *max,
+    }
+
+    false
+}
+
+/// Same as [`matches_filter_at`] but for the async traversal, the filters that have to look
+/// at the item on disk go through `tokio::fs` rather than blocking the runtime
+pub(crate) async fn matches_filter_at_async(item: &Path, filter: &FtFilter, depth: usize) -> bool {
+    let mut item = item.to_path_buf();
+    let mut filter = filter;
+
+    // Unwrapped in a loop rather than recursing, which an async fn can't do without boxing
+    while let FtFilter::Canonical(inner) = filter {
+        match fs::canonicalize(&item).await {
+            Ok(canonical) => (item, filter) = (canonical, inner),
+            Err(_) => return false,
+        }
+    }
+
+    match filter {
+        FtFilter::Executable => is_executable_async(&item).await,
+        FtFilter::Empty | FtFilter::NonEmpty => {
+            let want_empty = matches!(filter, FtFilter::Empty);
+            fs::metadata(&item)
+                .await
+                .is_ok_and(|meta| meta.is_file() && (meta.len() == 0) == want_empty)
+        }
+        _ => matches_filter_at(item, filter, depth),
+    }
+}
+
+/// Helper function to iterate through a directory to find all Files / Directories
+/// depending on the `FilterState` passed.
+pub(crate) async fn iteritems<P: AsRef<Path> + Send>(
+    path: P,
+    iterstate: FtIterItemState,
+    filter: Option<&FtFilter>,
+) -> Result<Vec<PathBuf>> {
+    let mut items = vec![];
+    walk(path, iterstate, filter, &mut |item| {
+        items.push(item);
+        ControlFlow::Continue(())
+    })
+    .await?;
+
+    Ok(items)
+}
+
+/// Lists the items matching `iterstate` under `root` and checks each against `filter`,
+/// counting how many were checked. The async counterpart of [`ScannedItems`], the filter
+/// is applied after the walk so it can use [`matches_filter_at_async`].
+pub(crate) async fn scanneditems(
+    root: &Path,
+    iterstate: FtIterItemState,
+    filter: &FtFilter,
+) -> Result<FtFilterResult> {
+    let items = iteritems(root, iterstate, None).await?;
+
+    let mut result = FtFilterResult {
+        total_scanned: items.len(),
+        ..Default::default()
+    };
+    for item in items {
+        if matches_filter_at_async(&item, filter, depth_under(root, &item)).await {
+            result.matched.push(item);
+        }
+    }
+
+    Ok(result)
+}
+
+/// Receives the items found while walking through a directory with [`walk`] / [`walk_sync`]
+///
+/// Any `FnMut(PathBuf) -> ControlFlow<()>` closure is a visitor that propagates errors.
+pub(crate) trait FtVisitor {
+    /// Called for every item that matches the `FtIterItemState` and filter.
+    ///
+    /// Returning `ControlFlow::Break` stops the traversal.
+    fn visit(&mut self, item: PathBuf) -> ControlFlow<()>;
+
+    /// Called by the traversal with the kind of item found, forwarding to [`Self::visit`]
+    /// unless the visitor cares about the kind.
+    fn visit_entry(&mut self, entry: DirEntryKind) -> ControlFlow<()> {
+        self.visit(entry.into_path())
+    }
+
+    /// Called when a directory or one of its entries can't be read.
+    ///
+    /// Returning an error aborts the traversal, returning `Ok` skips the rest of the
+    /// directory and carries on with the next one.
+    fn error(&mut self, path: &Path, err: std::io::Error) -> Result<()> {
+        Err(err).with_context(|| format!("unable to read directory {}", path.display()))
+    }
+
+    /// Checked before each directory read and each entry, returning `true` stops
+    /// the traversal as if [`Self::visit`] returned `ControlFlow::Break`.
+    fn cancelled(&self) -> bool {
+        false
+    }
+
+    /// Called for every directory opened by the traversal, including the root
+    fn entered_dir(&mut self, _dir: &Path) {}
+
+    /// Called for every entry read, before it is checked against the `FtIterItemState` and filter
+    fn saw_entry(&mut self, _item: &Path) {}
+
+    /// Checked before a recursive traversal goes into `dir`, returning `false` skips
+    /// everything below it
+    fn descend(&mut self, _dir: &Path) -> bool {
+        true
+    }
+}
+
+impl<F: FnMut(PathBuf) -> ControlFlow<()>> FtVisitor for F {
+    fn visit(&mut self, item: PathBuf) -> ControlFlow<()> {
+        self(item)
+    }
+}
+
+/// Visitor that collects items and any read errors instead of aborting
+#[derive(Default)]
+pub(crate) struct PartialItems {
+    pub items: Vec<PathBuf>,
+    pub errors: Vec<(PathBuf, std::io::Error)>,
+}
+
+impl FtVisitor for PartialItems {
+    fn visit(&mut self, item: PathBuf) -> ControlFlow<()> {
+        self.items.push(item);
+        ControlFlow::Continue(())
+    }
+
+    fn error(&mut self, path: &Path, err: std::io::Error) -> Result<()> {
+        self.errors.push((path.to_path_buf(), err));
+        Ok(())
+    }
+}
+
+/// Visitor that collects items along with whether they are files or directories
+#[derive(Default)]
+pub(crate) struct EntryItems {
+    pub entries: Vec<DirEntryKind>,
+}
+
+impl FtVisitor for EntryItems {
+    fn visit(&mut self, item: PathBuf) -> ControlFlow<()> {
+        // Only reached if the traversal doesn't know the kind, which never happens
+        let entry = if item.is_dir() {
+            DirEntryKind::Dir(item)
+        } else {
+            DirEntryKind::File(item)
+        };
+
+        self.visit_entry(entry)
+    }
+
+    fn visit_entry(&mut self, entry: DirEntryKind) -> ControlFlow<()> {
+        self.entries.push(entry);
+        ControlFlow::Continue(())
+    }
+}
+
+/// Visitor that keeps the `n` files with the largest keys in a min-heap, so at most `n`
+/// keys are held however many files are visited.
+///
+/// Keys come from each file's metadata, files without one are skipped.
+pub(crate) struct TopItems<K: Ord> {
+    n: usize,
+    key: fn(&std::fs::Metadata) -> Option<K>,
+    heap: BinaryHeap<Reverse<(K, PathBuf)>>,
+}
+
+impl<K: Ord> TopItems<K> {
+    pub fn new(n: usize, key: fn(&std::fs::Metadata) -> Option<K>) -> Self {
+        Self {
+            n,
+            key,
+            heap: BinaryHeap::with_capacity(n.saturating_add(1).min(1024)),
+        }
+    }
+
+    /// The kept files, largest key first
+    pub fn into_sorted(self) -> Vec<(PathBuf, K)> {
+        self.heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Reverse((key, path))| (path, key))
+            .collect()
+    }
+}
+
+impl<K: Ord> FtVisitor for TopItems<K> {
+    fn visit(&mut self, item: PathBuf) -> ControlFlow<()> {
+        // Files removed mid traversal are skipped
+        let Some(key) = std::fs::metadata(&item).ok().and_then(|m| (self.key)(&m)) else {
+            return ControlFlow::Continue(());
+        };
+
+        self.heap.push(Reverse((key, item)));
+        if self.heap.len() > self.n {
+            self.heap.pop();
+        }
+
+        ControlFlow::Continue(())
+    }
+}
+
+/// Modification time as a [`TopItems`] key, `None` where the platform doesn't record it
+pub(crate) fn modified_key(meta: &std::fs::Metadata) -> Option<SystemTime> {
+    meta.modified().ok()
+}
+
+/// Reversed modification time as a [`TopItems`] key so the oldest files are kept
+pub(crate) fn oldest_key(meta: &std::fs::Metadata) -> Option<Reverse<SystemTime>> {
+    meta.modified().ok().map(Reverse)
+}
+
+/// Visitor that applies `filter` itself so it can count every item it was checked against
+pub(crate) struct ScannedItems<'a> {
+    root: &'a Path,
+    filter: &'a FtFilter,
+    pub result: FtFilterResult,
+}
+
+impl<'a> ScannedItems<'a> {
+    pub fn new(root: &'a Path, filter: &'a FtFilter) -> Self {
+        Self {
+            root,
+            filter,
+            result: FtFilterResult::default(),
+        }
+    }
+}
+
+impl FtVisitor for ScannedItems<'_> {
+    fn visit(&mut self, item: PathBuf) -> ControlFlow<()> {
+        self.result.total_scanned += 1;
+        if matches_filter_at(&item, self.filter, depth_under(self.root, &item)) {
+            self.result.matched.push(item);
+        }
+
+        ControlFlow::Continue(())
+    }
+}
+
+/// Visitor that collects items while counting the directories and entries the traversal went through
+#[derive(Default)]
+pub(crate) struct StatsItems {
+    pub items: Vec<PathBuf>,
+    pub stats: FtTraversalStats,
+}
+
+impl FtVisitor for StatsItems {
+    fn visit(&mut self, item: PathBuf) -> ControlFlow<()> {
+        self.items.push(item);
+        self.stats.matched += 1;
+        ControlFlow::Continue(())
+    }
+
+    fn entered_dir(&mut self, _dir: &Path) {
+        self.stats.dirs_visited += 1;
+    }
+
+    fn saw_entry(&mut self, _item: &Path) {
+        self.stats.entries_seen += 1;
+    }
+}
+
+/// Visitor that collects items without listing or descending into directories with an excluded name
+pub(crate) struct ExcludedItems<'a> {
+    pub items: Vec<PathBuf>,
+    exclude: Vec<&'a OsStr>,
+}
+
+impl<'a> ExcludedItems<'a> {
+    pub fn new(exclude: &'a [impl AsRef<Path>]) -> Self {
+        Self {
+            items: vec![],
+            exclude: exclude.iter().map(|dir| dir.as_ref().as_os_str()).collect(),
+        }
+    }
+
+    fn excluded(&self, dir: &Path) -> bool {
+        dir.file_name()
+            .is_some_and(|name| self.exclude.contains(&name))
+    }
+}
+
+impl FtVisitor for ExcludedItems<'_> {
+    fn visit(&mut self, item: PathBuf) -> ControlFlow<()> {
+        self.items.push(item);
+        ControlFlow::Continue(())
+    }
+
+    fn visit_entry(&mut self, entry: DirEntryKind) -> ControlFlow<()> {
+        match entry {
+            DirEntryKind::Dir(dir) if self.excluded(&dir) => ControlFlow::Continue(()),
+            entry => self.visit(entry.into_path()),
+        }
+    }
+
+    fn descend(&mut self, dir: &Path) -> bool {
+        !self.excluded(dir)
+    }
+}
+
+/// Visitor that collects items, only descending into the directories `should_descend` approves
+pub(crate) struct PrunedItems<F> {
+    pub items: Vec<PathBuf>,
+    should_descend: F,
+}
+
+impl<F: FnMut(&Path) -> bool> PrunedItems<F> {
+    pub fn new(should_descend: F) -> Self {
+        Self {
+            items: vec![],
+            should_descend,
+        }
+    }
+}
+
+impl<F: FnMut(&Path) -> bool> FtVisitor for PrunedItems<F> {
+    fn visit(&mut self, item: PathBuf) -> ControlFlow<()> {
+        self.items.push(item);
+        ControlFlow::Continue(())
+    }
+
+    fn descend(&mut self, dir: &Path) -> bool {
+        (self.should_descend)(dir)
+    }
+}
+
+/// Visitor that collects items until `cancel` is set, remembering if the traversal saw it
+pub(crate) struct CancellableItems<'a> {
+    pub items: Vec<PathBuf>,
+    cancel: &'a AtomicBool,
+    stopped: Cell<bool>,
+}
+
+impl<'a> CancellableItems<'a> {
+    pub fn new(cancel: &'a AtomicBool) -> Self {
+        Self {
+            items: vec![],
+            cancel,
+            stopped: Cell::new(false),
+        }
+    }
+
+    /// The items found and whether the traversal was cancelled before it finished
+    pub fn finish(self) -> (Vec<PathBuf>, bool) {
+        (self.items, self.stopped.get())
+    }
+}
+
+impl FtVisitor for CancellableItems<'_> {
+    fn visit(&mut self, item: PathBuf) -> ControlFlow<()> {
+        self.items.push(item);
+        ControlFlow::Continue(())
+    }
+
+    fn cancelled(&self) -> bool {
+        let cancelled = self.cancel.load(Ordering::Relaxed);
+        self.stopped.set(self.stopped.get() || cancelled);
+        cancelled
+    }
+}
+
+/// Walks through a directory handing each item that matches the `FtIterItemState`
+/// and filter to `visitor`.
+///
+/// Traversal stops as soon as the visitor returns `ControlFlow::Break`.
+pub(crate) async fn walk<P: AsRef<Path> + Send>(
+    path: P,
+    iterstate: FtIterItemState,
+    filter: Option<&FtFilter>,
+    visitor: &mut (dyn FtVisitor + Send),
+) -> Result<()> {
+    walk_in(&FtRealFs, path.as_ref(), iterstate, filter, visitor).await
+}
+
+/// Same as [`walk`] but on any [`FtFileSystem`].
+///
+/// Rather than recursing (and boxing a future per level), the directories being read are kept
+/// on an explicit stack. A subdirectory is pushed as soon as it is found so items come out in
+/// the same depth first order as [`walk_sync`].
+pub(crate) async fn walk_in<F: FtFileSystem>(
+    fs: &F,
+    path: &Path,
+    iterstate: FtIterItemState,
+    filter: Option<&FtFilter>,
+    visitor: &mut (dyn FtVisitor + Send),
+) -> Result<()> {
+    let recursive = matches!(
+        iterstate,
+        FtIterItemState::RFile | FtIterItemState::RDir | FtIterItemState::RBoth
+    );
+
+    let mut stack = vec![];
+    if let Some(entries) = open_dir(fs, path, visitor).await? {
+        stack.push((path.to_path_buf(), entries));
+    }
+
+    loop {
+        // Entries of the directory on top of the stack are this many levels below the root
+        let depth = stack.len();
+        let Some((dir, entries)) = stack.last_mut() else {
+            break;
+        };
+
+        if visitor.cancelled() {
+            return Ok(());
+        }
+
+        // A failed entry is reported rather than mistaken for the end of the directory,
+        // the rest of it is skipped as the reader may keep failing
+        let e_path = match entries.next_entry().await {
+            Some(Ok(e_path)) => e_path,
+            Some(Err(e)) => {
+                visitor.error(dir, e)?;
+                stack.pop();
+                continue;
+            }
+            None => {
+                stack.pop();
+                continue;
+            }
+        };
+        visitor.saw_entry(&e_path);
+
+        // If a filter is present, set the value to the result of the filter
+        // check, else default to true so always adds the value
+        let filter_pass = match filter.as_ref() {
+            Some(f) => matches_filter_at_async(&e_path, f, depth).await,
+            None => true,
+        };
+
+        // Items that can't be inspected (e.g. broken symlinks) are neither files nor directories
+        let (is_dir, is_file) = match fs.metadata(&e_path).await {
+            Ok(meta) => (meta.is_dir, meta.is_file),
+            Err(_) => (false, false),
+        };
+        let wants_file = !matches!(iterstate, FtIterItemState::Dir | FtIterItemState::RDir);
+        let wants_dir = !matches!(iterstate, FtIterItemState::File | FtIterItemState::RFile);
+
+        let flow = if is_dir && wants_dir && filter_pass {
+            visitor.visit_entry(DirEntryKind::Dir(e_path.clone()))
+        } else if is_file && wants_file && filter_pass {
+            visitor.visit_entry(DirEntryKind::File(e_path.clone()))
+        } else {
+            ControlFlow::Continue(())
+        };
+
+        if flow.is_break() {
+            return Ok(());
+        }
+
+        if is_dir && recursive && visitor.descend(&e_path) {
+            if visitor.cancelled() {
+                return Ok(());
+            }
+
+            if let Some(entries) = open_dir(fs, &e_path, visitor).await? {
+                stack.push((e_path, entries));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads `dir` for [`walk_in`], handing any error to `visitor` and returning `None` if it
+/// chose to carry on without it
+async fn open_dir<F: FtFileSystem>(
+    fs: &F,
+    dir: &Path,
+    visitor: &mut (dyn FtVisitor + Send),
+) -> Result<Option<F::ReadDir>> {
+    match fs.read_dir(dir).await {
+        Ok(entries) => {
+            visitor.entered_dir(dir);
+            Ok(Some(entries))
+        }
+        Err(e) => {
+            visitor.error(dir, e)?;
+            Ok(None)
+        }
+    }
+}
+
+pub(crate) fn iteritems_sync<P: AsRef<Path>>(
+    path: P,
+    iterstate: FtIterItemState,
+    filter: Option<&FtFilter>,
+) -> Result<Vec<PathBuf>> {
+    let mut items = vec![];
+    walk_sync(path, iterstate, filter, &mut |item| {
+        items.push(item);
+        ControlFlow::Continue(())
+    })?;
+
+    Ok(items)
+}
+
+/// Same as [`iteritems`] but clears `items` and fills it rather than allocating a new `Vec`
+pub(crate) async fn iteritems_into<P: AsRef<Path> + Send>(
+    path: P,
+    iterstate: FtIterItemState,
+    filter: Option<&FtFilter>,
+    items: &mut Vec<PathBuf>,
+) -> Result<()> {
+    items.clear();
+    walk(path, iterstate, filter, &mut |item| {
+        items.push(item);
+        ControlFlow::Continue(())
+    })
+    .await
+}
+
+/// Sync version of [`iteritems_into`]
+pub(crate) fn iteritems_into_sync<P: AsRef<Path>>(
+    path: P,
+    iterstate: FtIterItemState,
+    filter: Option<&FtFilter>,
+    items: &mut Vec<PathBuf>,
+) -> Result<()> {
+    items.clear();
+    walk_sync(path, iterstate, filter, &mut |item| {
+        items.push(item);
+        ControlFlow::Continue(())
+    })
+}
+
+/// Errors with [`FtError::NotFound`] or [`FtError::NotADirectory`] unless `path` is an
+/// existing directory, the guard in front of every traversal
+pub(crate) fn ensure_dir_root(path: impl AsRef<Path>) -> Result<()> {
+    let path = path.as_ref();
+    anyhow::ensure!(path.exists(), FtError::NotFound(path.to_path_buf()));
+    anyhow::ensure!(path.is_dir(), FtError::NotADirectory(path.to_path_buf()));
+
+    Ok(())
+}
+
+/// Checks every root of a multi root listing exists and is a directory
+pub(crate) fn check_roots(roots: &[impl AsRef<Path>]) -> Result<()> {
+    for root in roots {
+        ensure_dir_root(root)?;
+    }
+
+    Ok(())
+}
+
+/// Joins the listings of several roots in the order the roots were given, keeping only
+/// the first occurrence of each path if `dedup` is set
+fn merge_listings(listings: Vec<Vec<PathBuf>>, dedup: bool) -> Vec<PathBuf> {
+    let items = listings.into_iter().flatten();
+    if !dedup {
+        return items.collect();
+    }
+
+    let mut seen = HashSet::new();
+    items.filter(|item| seen.insert(item.clone())).collect()
+}
+
+/// Lists the items matching `iterstate` and `filter` under each of `roots`, walking
+/// each root in its own task on the current runtime.
+pub(crate) async fn multiitems(
+    roots: &[impl AsRef<Path> + Sync],
+    iterstate: FtIterItemState,
+    filter: Option<&FtFilter>,
+    dedup: bool,
+) -> Result<Vec<PathBuf>> {
+    check_roots(roots)?;
+
+    let mut tasks = tokio::task::JoinSet::new();
+    for (idx, root) in roots.iter().enumerate() {
+        let (root, filter) = (root.as_ref().to_path_buf(), filter.cloned());
+        tasks.spawn(async move {
+            let items = iteritems(&root, iterstate, filter.as_ref()).await?;
+            anyhow::Ok((idx, items))
+        });
+    }
+
+    let mut listings = vec![vec![]; roots.len()];
+    while let Some(joined) = tasks.join_next().await {
+        let (idx, items) = joined.context("listing task failed")??;
+        listings[idx] = items;
+    }
+
+    Ok(merge_listings(listings, dedup))
+}
+
+/// Sync version of [`multiitems`], walking the roots one after another
+pub(crate) fn multiitems_sync(
+    roots: &[impl AsRef<Path>],
+    iterstate: FtIterItemState,
+    filter: Option<&FtFilter>,
+    dedup: bool,
+) -> Result<Vec<PathBuf>> {
+    check_roots(roots)?;
+
+    let listings = roots
+        .iter()
+        .map(|root| iteritems_sync(root, iterstate, filter))
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(merge_listings(listings, dedup))
+}
+
+/// Sync version of [`walk`]
+pub(crate) fn walk_sync<P: AsRef<Path>>(
+    path: P,
+    iterstate: FtIterItemState,
+    filter: Option<&FtFilter>,
+    visitor: &mut dyn FtVisitor,
+) -> Result<()> {
+    walk_sync_in(path, iterstate, filter, visitor, &read_dir_sync)
+}
+
+/// Reads the entry paths of `dir` from the real filesystem for [`walk_sync`]
+fn read_dir_sync(dir: &Path) -> std::io::Result<impl Iterator<Item = std::io::Result<PathBuf>>> {
+    Ok(std::fs::read_dir(dir)?.map(|entry| entry.map(|e| e.path())))
+}
+
+/// Same as [`walk_sync`] but listing each directory through `read_dir`, the entries
+/// themselves are still inspected on the real filesystem.
+pub(crate) fn walk_sync_in<P, R, I>(
+    path: P,
+    iterstate: FtIterItemState,
+    filter: Option<&FtFilter>,
+    visitor: &mut dyn FtVisitor,
+    read_dir: &R,
+) -> Result<()>
+where
+    P: AsRef<Path>,
+    R: Fn(&Path) -> std::io::Result<I>,
+    I: Iterator<Item = std::io::Result<PathBuf>>,
+{
+    // Whether the walk finished or was stopped early is the visitor's business
+    let _ = walk_dir_sync(path, iterstate, filter, 1, visitor, read_dir)?;
+
+    Ok(())
+}
+
+/// Recursive part of [`walk_sync_in`], passing any `ControlFlow::Break` back up the chain.
+///
+/// The entries of `path` are `depth` levels below the root.
+fn walk_dir_sync<P, R, I>(
+    path: P,
+    iterstate: FtIterItemState,
+    filter: Option<&FtFilter>,
+    depth: usize,
+    visitor: &mut dyn FtVisitor,
+    read_dir: &R,
+) -> Result<ControlFlow<()>>
+where
+    P: AsRef<Path>,
+    R: Fn(&Path) -> std::io::Result<I>,
+    I: Iterator<Item = std::io::Result<PathBuf>>,
+{
+    if visitor.cancelled() {
+        return Ok(ControlFlow::Break(()));
+    }
+
+    let entries = match read_dir(path.as_ref()) {
+        Ok(entries) => entries,
+        Err(e) => {
+            visitor.error(path.as_ref(), e)?;
+            return Ok(ControlFlow::Continue(()));
+        }
+    };
+    visitor.entered_dir(path.as_ref());
+
+    for entry in entries {
+        if visitor.cancelled() {
+            return Ok(ControlFlow::Break(()));
+        }
+
+        // A failed entry is reported rather than mistaken for the end of the directory,
+        // the rest of it is skipped as the iterator may keep failing
+        let e_path = match entry {
+            Ok(e_path) => e_path,
+            Err(e) => {
+                visitor.error(path.as_ref(), e)?;
+                break;
+            }
+        };
+        visitor.saw_entry(&e_path);
+
+        // If a filter is present, set the value to the result of the filter
+        // check, else default to true so always adds the value
+        let filter_pass = match filter.as_ref() {
+            Some(f) => matches_filter_at(&e_path, f, depth),
+            None => true,
+        };
+
+        let flow = match iterstate {
+            FtIterItemState::File => {
+                if e_path.is_file() && filter_pass {
+                    visitor.visit_entry(DirEntryKind::File(e_path))
+                } else {
+                    ControlFlow::Continue(())
+                }
+            }
+            FtIterItemState::RFile => {
+                if e_path.is_file() && filter_pass {
+                    visitor.visit_entry(DirEntryKind::File(e_path))
+                } else if e_path.is_dir() && visitor.descend(&e_path) {
+                    walk_dir_sync(e_path, iterstate, filter, depth + 1, visitor, read_dir)?
+                } else {
+                    ControlFlow::Continue(())
+                }
+            }
+            FtIterItemState::Dir => {
+                if e_path.is_dir() && filter_pass {
+                    visitor.visit_entry(DirEntryKind::Dir(e_path))
+                } else {
+                    ControlFlow::Continue(())
+                }
+            }
+            FtIterItemState::RDir => {
+                if e_path.is_dir() {
+                    if filter_pass
+                        && visitor
+                            .visit_entry(DirEntryKind::Dir(e_path.clone()))
+                            .is_break()
+                    {
+                        return Ok(ControlFlow::Break(()));
+                    }
+
+                    if visitor.descend(&e_path) {
+                        walk_dir_sync(e_path, iterstate, filter, depth + 1, visitor, read_dir)?
+                    } else {
+                        ControlFlow::Continue(())
+                    }
+                } else {
+                    ControlFlow::Continue(())
+                }
+            }
+            FtIterItemState::Both => {
+                if e_path.is_file() && filter_pass {
+                    visitor.visit_entry(DirEntryKind::File(e_path))
+                } else if e_path.is_dir() && filter_pass {
+                    visitor.visit_entry(DirEntryKind::Dir(e_path))
+                } else {
+                    ControlFlow::Continue(())
+                }
+            }
+            FtIterItemState::RBoth => {
+                if e_path.is_dir() {
+                    if filter_pass
+                        && visitor
+                            .visit_entry(DirEntryKind::Dir(e_path.clone()))
+                            .is_break()
+                    {
+                        return Ok(ControlFlow::Break(()));
+                    }
+
+                    if visitor.descend(&e_path) {
+                        walk_dir_sync(e_path, iterstate, filter, depth + 1, visitor, read_dir)?
+                    } else {
+                        ControlFlow::Continue(())
+                    }
+                } else if e_path.is_file() && filter_pass {
+                    visitor.visit_entry(DirEntryKind::File(e_path))
+                } else {
+                    ControlFlow::Continue(())
+                }
+            }
+        };
+
+        if flow.is_break() {
+            return Ok(flow);
+        }
+    }
+
+    Ok(ControlFlow::Continue(()))
+}
+
+/// Counts the items in a directory without collecting them
+pub(crate) async fn countitems<P: AsRef<Path> + Send>(
+    path: P,
+    iterstate: FtIterItemState,
+    filter: Option<&FtFilter>,
+) -> Result<usize> {
+    let mut count = 0;
+    walk(path, iterstate, filter, &mut |_| {
+        count += 1;
+        ControlFlow::Continue(())
+    })
+    .await?;
+
+    Ok(count)
+}
+
+/// Sync version of [`countitems`]
+pub(crate) fn countitems_sync<P: AsRef<Path>>(
+    path: P,
+    iterstate: FtIterItemState,
+    filter: Option<&FtFilter>,
+) -> Result<usize> {
+    let mut count = 0;
+    walk_sync(path, iterstate, filter, &mut |_| {
+        count += 1;
+        ControlFlow::Continue(())
+    })?;
+
+    Ok(count)
+}
+
+/// Collects the items in a directory, calling `progress` with each one as it is found
+pub(crate) async fn progressitems<P: AsRef<Path> + Send>(
+    path: P,
+    iterstate: FtIterItemState,
+    filter: Option<&FtFilter>,
+    mut progress: impl FnMut(&Path) + Send,
+) -> Result<Vec<PathBuf>> {
+    let mut items = vec![];
+    walk(path, iterstate, filter, &mut |item: PathBuf| {
+        progress(&item);
+        items.push(item);
+        ControlFlow::Continue(())
+    })
+    .await?;
+
+    Ok(items)
+}
+
+/// Sync version of [`progressitems`]
+pub(crate) fn progressitems_sync<P: AsRef<Path>>(
+    path: P,
+    iterstate: FtIterItemState,
+    filter: Option<&FtFilter>,
+    mut progress: impl FnMut(&Path),
+) -> Result<Vec<PathBuf>> {
+    let mut items = vec![];
+    walk_sync(path, iterstate, filter, &mut |item: PathBuf| {
+        progress(&item);
+        items.push(item);
+        ControlFlow::Continue(())
+    })?;
+
+    Ok(items)
+}
+
+/// Maximum number of files read at once by [`hashitems`]
+const HASH_CONCURRENCY: usize = 8;
+
+/// Size of the chunks files are read in when hashing or comparing them
+pub(crate) const READ_CHUNK: usize = 64 * 1024;
+
+/// Fills as much of `buf` as `reader` has left, returning how much was read
+pub(crate) fn read_chunk(reader: &mut impl Read, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(filled)
+}
+
+/// Feeds `file` to `hasher` in [`READ_CHUNK`] sized pieces, returning the digest
+pub(crate) fn hashfile<H: FtHasher>(file: &Path, mut hasher: H) -> Result<String> {
+    let context = || format!("unable to read file {}", file.display());
+    let mut reader = std::fs::File::open(file).with_context(context)?;
+    let mut buf = vec![0; READ_CHUNK];
+
+    loop {
+        let read = read_chunk(&mut reader, &mut buf).with_context(context)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buf[..read]);
+    }
+
+    Ok(hasher.finish())
+}
+
+/// Hashes each file on the blocking pool with at most [`HASH_CONCURRENCY`] files in flight,
+/// keeping the order of `files`
+pub(crate) async fn hashitems<F, H>(
+    files: Vec<PathBuf>,
+    new_hasher: F,
+) -> Result<Vec<(PathBuf, String)>>
+where
+    F: Fn() -> H + Send + Sync + 'static,
+    H: FtHasher,
+{
+    let new_hasher = std::sync::Arc::new(new_hasher);
+    let mut hashed = Vec::with_capacity(files.len());
+    let mut pending = files.into_iter().enumerate();
+    let mut tasks = tokio::task::JoinSet::new();
+
+    loop {
+        while tasks.len() < HASH_CONCURRENCY {
+            let Some((idx, file)) = pending.next() else {
+                break;
+            };
+
+            let new_hasher = std::sync::Arc::clone(&new_hasher);
+            tasks.spawn_blocking(move || {
+                let digest = hashfile(&file, new_hasher())?;
+                anyhow::Ok((idx, file, digest))
+            });
+        }
+
+        match tasks.join_next().await {
+            Some(task) => hashed.push(task.context("file hashing task failed")??),
+            None => break,
+        }
+    }
+
+    hashed.sort_by_key(|(idx, ..)| *idx);
+    Ok(hashed
+        .into_iter()
+        .map(|(_, file, digest)| (file, digest))
+        .collect())
+}
+
+/// Lists the files and directories in `dir` with their metadata, descending into
+/// subdirectories if `recursive` is set.
+///
+/// Each item is stat'ed once (following symlinks), items that are neither files nor
+/// directories are skipped.
+#[async_recursion]
+pub(crate) async fn detaileditems(
+    dir: &Path,
+    recursive: bool,
+    items: &mut Vec<FtFileInfo>,
+) -> Result<()> {
+    let mut entries = fs::read_dir(dir)
+        .await
+        .with_context(|| format!("unable to read directory {}", dir.display()))?;
+
+    while let Some(entry) = entries
+        .next_entry()
+        .await
+        .with_context(|| format!("unable to read directory {}", dir.display()))?
+    {
+        let path = entry.path();
+        let Ok(meta) = fs::metadata(&path).await else {
+            continue;
+        };
+
+        if meta.is_dir() {
+            items.push(FtFileInfo::new(path.clone(), &meta));
+            if recursive {
+                detaileditems(&path, recursive, items).await?;
+            }
+        } else if meta.is_file() {
+            items.push(FtFileInfo::new(path, &meta));
+        }
+    }
+
+    Ok(())
+}
+
+/// Sync version of [`detaileditems`]
+pub(crate) fn detaileditems_sync(
+    dir: &Path,
+    recursive: bool,
+    items: &mut Vec<FtFileInfo>,
+) -> Result<()> {
+    let entries = std::fs::read_dir(dir)
+        .with_context(|| format!("unable to read directory {}", dir.display()))?;
+
+    for entry in entries {
+        let path = entry
+            .with_context(|| format!("unable to read directory {}", dir.display()))?
+            .path();
+        let Ok(meta) = std::fs::metadata(&path) else {
+            continue;
+        };
+
+        if meta.is_dir() {
+            items.push(FtFileInfo::new(path.clone(), &meta));
+            if recursive {
+                detaileditems_sync(&path, recursive, items)?;
+            }
+        } else if meta.is_file() {
+            items.push(FtFileInfo::new(path, &meta));
+        }
+    }
+
+    Ok(())
+}
+
+/// Recursively lists the items in `root` that match `iterstate`, reading up to
+/// `max_concurrency` directories at once in tasks spawned on `handle`.
+///
+/// Only the recursive states make sense here, the order of the results is not stable.
+pub(crate) async fn concurrentitems(
+    root: &Path,
+    iterstate: FtIterItemState,
+    max_concurrency: usize,
+    handle: &tokio::runtime::Handle,
+) -> Result<Vec<PathBuf>> {
+    anyhow::ensure!(max_concurrency > 0, "max_concurrency must be at least 1");
+    let (want_files, want_dirs) = match iterstate {
+        FtIterItemState::RFile => (true, false),
+        FtIterItemState::RDir => (false, true),
+        FtIterItemState::RBoth => (true, true),
+        _ => anyhow::bail!("concurrent listing is always recursive"),
+    };
+
+    let permits = std::sync::Arc::new(tokio::sync::Semaphore::new(max_concurrency));
+    let mut tasks = tokio::task::JoinSet::new();
+    let mut items = vec![];
+    let mut pending = vec![root.to_path_buf()];
+
+    loop {
+        for dir in pending.drain(..) {
+            let permits = std::sync::Arc::clone(&permits);
+            tasks.spawn_on(
+                async move {
+                    // Held until the whole directory has been read
+                    let _permit = permits.acquire_owned().await?;
+                    let mut entries = fs::read_dir(&dir)
+                        .await
+                        .with_context(|| format!("unable to read directory {}", dir.display()))?;
+
+                    let (mut files, mut dirs) = (vec![], vec![]);
+                    while let Some(entry) = entries
+                        .next_entry()
+                        .await
+                        .with_context(|| format!("unable to read directory {}", dir.display()))?
+                    {
+                        // Follows symlinks like the serial traversal
+                        let path = entry.path();
+                        match fs::metadata(&path).await {
+                            Ok(meta) if meta.is_dir() => dirs.push(path),
+                            Ok(meta) if meta.is_file() => files.push(path),
+                            _ => {}
+                        }
+                    }
+
+                    anyhow::Ok((files, dirs))
+                },
+                handle,
+            );
+        }
+
+        let Some(task) = tasks.join_next().await else {
+            break;
+        };
+
+        let (files, dirs) = task.context("directory read task failed")??;
+        if want_files {
+            items.extend(files);
+        }
+        if want_dirs {
+            items.extend(dirs.iter().cloned());
+        }
+        pending.extend(dirs);
+    }
+
+    Ok(items)
+}
+
+/// Joins each relative path in `spec` onto `root`, rejecting any that could end up outside it
+pub(crate) fn tree_targets(root: &Path, spec: &[impl AsRef<Path>]) -> Result<Vec<PathBuf>> {
+    spec.iter()
+        .map(|entry| {
+            let entry = entry.as_ref();
+            let escapes = entry
+                .components()
+                .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir));
+            anyhow::ensure!(
+                !escapes,
+                "directory tree entry {} must be relative to the root without `..`",
+                entry.display()
+            );
+
+            Ok(root.join(entry))
+        })
+        .collect()
+}
+
+/// Where each file goes in a batch rename, see [`rename_plan`]
+pub(crate) struct RenamePlan {
+    /// Source and target of each rename
+    pub moves: Vec<(PathBuf, PathBuf)>,
+
+    /// Whether some targets are also sources, so everything has to be moved
+    /// out of the way first
+    pub staged: bool,
+}
+
+/// Works out the target of each file in a batch rename, erroring on any collision
+/// before anything is touched.
+///
+/// Names returned by `rename` are joined onto the file's directory.
+pub(crate) fn rename_plan(
+    files: Vec<PathBuf>,
+    mut rename: impl FnMut(&Path) -> Option<PathBuf>,
+) -> Result<RenamePlan> {
+    let mut targets: HashMap<PathBuf, PathBuf> = HashMap::new();
+    let mut moves = vec![];
+    for file in files {
+        let Some(name) = rename(&file) else {
+            continue;
+        };
+
+        let target = match file.parent() {
+            Some(parent) => parent.join(name),
+            None => name,
+        };
+        if target == file {
+            continue;
+        }
+
+        if let Some(other) = targets.insert(target.clone(), file.clone()) {
+            anyhow::bail!(
+                "{} and {} would both be renamed to {}",
+                other.display(),
+                file.display(),
+                target.display()
+            );
+        }
+
+        moves.push((file, target));
+    }
+
+    let moving: HashSet<&PathBuf> = moves.iter().map(|(from, _)| from).collect();
+    let mut staged = false;
+    for (from, to) in &moves {
+        if moving.contains(to) {
+            staged = true;
+        } else {
+            anyhow::ensure!(
+                !to.exists(),
+                "renaming {} would overwrite {}",
+                from.display(),
+                to.display()
+            );
+        }
+    }
+
+    Ok(RenamePlan { moves, staged })
+}
+
+/// Carries out the renames of `plan`, putting every file back where it was if one fails
+pub(crate) async fn apply_rename_plan(plan: &RenamePlan) -> Result<()> {
+    let mut done = vec![];
+    match apply_renames(plan, &mut done).await {
+        Ok(()) => Ok(()),
+        Err(e) => {
+            let mut stuck = vec![];
+            for (from, to) in done.into_iter().rev() {
+                if fs::rename(&to, &from).await.is_err() {
+                    stuck.push((from, to));
+                }
+            }
+
+            Err(rollback_error(e, stuck))
+        }
+    }
+}
+
+/// Renames of [`apply_rename_plan`], logging each completed one as `(from, to)` in `done`
+async fn apply_renames(plan: &RenamePlan, done: &mut Vec<(PathBuf, PathBuf)>) -> Result<()> {
+    let mut staging = vec![];
+    for (from, to) in &plan.moves {
+        let src = if plan.staged {
+            let tmp = atomic_temp_path(from)?;
+            fs::rename(from, &tmp)
+                .await
+                .with_context(|| format!("unable to rename {}", from.display()))?;
+            done.push((from.clone(), tmp.clone()));
+            tmp
+        } else {
+            from.clone()
+        };
+        staging.push((src, to));
+    }
+
+    for (src, to) in staging {
+        fs::rename(&src, to)
+            .await
+            .with_context(|| format!("unable to rename {}", src.display()))?;
+        done.push((src, to.clone()));
+    }
+
+    Ok(())
+}
+
+/// Sync version of [`apply_rename_plan`]
+pub(crate) fn apply_rename_plan_sync(plan: &RenamePlan) -> Result<()> {
+    let mut done = vec![];
+    apply_renames_sync(plan, &mut done).map_err(|e| {
+        let stuck = done
+            .into_iter()
+            .rev()
+            .filter(|(from, to)| std::fs::rename(to, from).is_err())
+            .collect();
+
+        rollback_error(e, stuck)
+    })
+}
+
+/// Sync version of [`apply_renames`]
+fn apply_renames_sync(plan: &RenamePlan, done: &mut Vec<(PathBuf, PathBuf)>) -> Result<()> {
+    let mut staging = vec![];
+    for (from, to) in &plan.moves {
+        let src = if plan.staged {
+            let tmp = atomic_temp_path(from)?;
+            std::fs::rename(from, &tmp)
+                .with_context(|| format!("unable to rename {}", from.display()))?;
+            done.push((from.clone(), tmp.clone()));
+            tmp
+        } else {
+            from.clone()
+        };
+        staging.push((src, to));
+    }
+
+    for (src, to) in staging {
+        std::fs::rename(&src, to).with_context(|| format!("unable to rename {}", src.display()))?;
+        done.push((src, to.clone()));
+    }
+
+    Ok(())
+}
+
+/// Adds the renames that couldn't be rolled back after `err` to it
+fn rollback_error(err: anyhow::Error, stuck: Vec<(PathBuf, PathBuf)>) -> anyhow::Error {
+    if stuck.is_empty() {
+        return err;
+    }
+
+    let stuck: Vec<String> = stuck
+        .iter()
+        .map(|(from, to)| format!("{} (left at {})", from.display(), to.display()))
+        .collect();
+    err.context(format!("unable to roll back renaming {}", stuck.join(", ")))
+}
+
+/// Picks a path for `name` inside `dir` that doesn't exist yet, adding ` (1)`, ` (2)`, ...
+/// before the extension if it is taken.
+///
+/// The name is built as an `OsString` so names that aren't valid UTF-8 are kept as they are.
+pub(crate) fn free_path(dir: &Path, name: &Path) -> PathBuf {
+    let mut candidate = dir.join(name);
+    let stem = name.file_stem().unwrap_or(name.as_os_str());
+    let ext = name.extension();
+
+    let mut n = 1;
+    while candidate.exists() {
+        let mut numbered = stem.to_os_string();
+        numbered.push(format!(" ({n})"));
+        if let Some(ext) = ext {
+            numbered.push(".");
+            numbered.push(ext);
+        }
+        candidate = dir.join(numbered);
+        n += 1;
+    }
+
+    candidate
+}
+
+/// Whether `err` is a rename failing because the source and destination are on
+/// different filesystems.
+///
+/// Checked on the raw OS error as `ErrorKind::CrossesDevices` needs Rust 1.85.
+fn crosses_devices(err: &std::io::Error) -> bool {
+    // EXDEV on every Unix, ERROR_NOT_SAME_DEVICE on Windows
+    #[cfg(unix)]
+    const CROSSES_DEVICES: i32 = 18;
+    #[cfg(windows)]
+    const CROSSES_DEVICES: i32 = 17;
+    #[cfg(not(any(unix, windows)))]
+    const CROSSES_DEVICES: i32 = -1;
+
+    err.raw_os_error() == Some(CROSSES_DEVICES)
+}
+
+/// Moves a file, falling back to copying and removing it when `from` and `to` are on
+/// different filesystems
+pub(crate) async fn move_file(from: &Path, to: &Path) -> Result<()> {
+    match fs::rename(from, to).await {
+        Err(e) if crosses_devices(&e) => {
+            fs::copy(from, to)
+                .await
+                .with_context(|| format!("unable to copy {}", from.display()))?;
+            fs::remove_file(from)
+                .await
+                .with_context(|| format!("unable to remove {}", from.display()))
+        }
+        result => result.with_context(|| format!("unable to move {}", from.display())),
+    }
+}
+
+/// Sync version of [`move_file`]
+pub(crate) fn move_file_sync(from: &Path, to: &Path) -> Result<()> {
+    match std::fs::rename(from, to) {
+        Err(e) if crosses_devices(&e) => {
+            std::fs::copy(from, to)
+                .with_context(|| format!("unable to copy {}", from.display()))?;
+            std::fs::remove_file(from)
+                .with_context(|| format!("unable to remove {}", from.display()))
+        }
+        result => result.with_context(|| format!("unable to move {}", from.display())),
+    }
+}
+
+/// Picks the bucket for each file in `files` according to `strategy`
+pub(crate) fn partition_plan(
+    mut files: Vec<PathBuf>,
+    buckets: usize,
+    strategy: FtPartition,
+) -> Vec<(PathBuf, usize)> {
+    files.sort();
+    files
+        .into_iter()
+        .enumerate()
+        .map(|(idx, file)| {
+            let bucket = match strategy {
+                FtPartition::RoundRobin => idx % buckets,
+                FtPartition::Hash => {
+                    // FNV-1a so buckets stay the same across Rust versions
+                    let name = file.file_name().unwrap_or_default().as_encoded_bytes();
+                    let hash = name.iter().fold(0xcbf29ce484222325u64, |hash, byte| {
+                        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+                    });
+                    (hash % buckets as u64) as usize
+                }
+            };
+
+            (file, bucket)
+        })
+        .collect()
 }
 
-/// Helper function to determine if an path item is valid based on the supplied filter
-fn matches_filter(item: impl AsRef<Path>, filter: &FtFilter) -> bool {
-    match filter {
-        // I know these are the same for Raw and Path
-        // but it complains when you try and use the | with match
-        // for this
-        FtFilter::Raw(raw) => {
-            if path_contains(&item, raw) {
-                return true;
+/// Lazily walks a directory for files using an explicit stack of open directories
+/// instead of recursion, see [`crate::sync::list_files_iter`]
+pub(crate) struct FileIter {
+    stack: Vec<(PathBuf, std::fs::ReadDir)>,
+    recursive: bool,
+    filter: Option<FtFilter>,
+}
+
+impl FileIter {
+    pub fn new(root: &Path, recursive: bool, filter: Option<FtFilter>) -> Result<Self> {
+        let entries = std::fs::read_dir(root)
+            .with_context(|| format!("unable to read directory {}", root.display()))?;
+
+        Ok(Self {
+            stack: vec![(root.to_path_buf(), entries)],
+            recursive,
+            filter,
+        })
+    }
+}
+
+impl Iterator for FileIter {
+    type Item = Result<PathBuf>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let depth = self.stack.len();
+            let (dir, entries) = self.stack.last_mut()?;
+            let entry = match entries.next() {
+                Some(Ok(entry)) => entry,
+                Some(Err(e)) => {
+                    // Same as the other walkers, the rest of the directory is skipped
+                    let err = Err(e)
+                        .with_context(|| format!("unable to read directory {}", dir.display()));
+                    self.stack.pop();
+                    return Some(err);
+                }
+                None => {
+                    self.stack.pop();
+                    continue;
+                }
+            };
+
+            let e_path = entry.path();
+            if e_path.is_dir() {
+                if self.recursive {
+                    match std::fs::read_dir(&e_path) {
+                        Ok(entries) => self.stack.push((e_path, entries)),
+                        Err(e) => {
+                            return Some(Err(e).with_context(|| {
+                                format!("unable to read directory {}", e_path.display())
+                            }))
+                        }
+                    }
+                }
+
+                continue;
+            }
+
+            let filter_pass = match self.filter.as_ref() {
+                Some(f) => matches_filter_at(&e_path, f, depth),
+                None => true,
+            };
+
+            if e_path.is_file() && filter_pass {
+                return Some(Ok(e_path));
             }
         }
-        FtFilter::Path(filter_path) => {
-            if path_contains(&item, filter_path) {
-                return true;
+    }
+}
+
+/// Errors if creating `count` numeric directories would go over `limit`
+pub(crate) fn check_numeric_range(count: usize, limit: usize) -> Result<()> {
+    anyhow::ensure!(
+        count <= limit,
+        "refusing to create {count} numeric directories, the limit is {limit}"
+    );
+
+    Ok(())
+}
+
+/// The numeric directories from `start` up to `end` in `path`, padded to `fill` digits,
+/// without checking the range against any limit
+pub(crate) fn numeric_dirs(path: &Path, start: usize, end: usize, fill: usize) -> Vec<PathBuf> {
+    (start..end)
+        .map(|i| path.join(naming::generate_n_digit_name(i, fill, "")))
+        .collect()
+}
+
+/// Copies everything inside `src` into `dst`, recording each copied directory in `dirs` so
+/// their metadata can be applied once they've been filled
+///
+/// When dereferencing symlinks, `ancestors` holds the canonical paths of the directories being
+/// copied above `src` so a link back to one of them fails instead of recursing forever.
+#[async_recursion]
+pub(crate) async fn copy_tree(
+    src: &Path,
+    dst: &Path,
+    options: FtCopyOptions,
+    dirs: &mut Vec<(PathBuf, PathBuf)>,
+    report: &mut FtCopyReport,
+    ancestors: &mut Vec<PathBuf>,
+) -> Result<()> {
+    if options.dereference_symlinks {
+        let canonical = fs::canonicalize(src)
+            .await
+            .with_context(|| format!("unable to canonicalize {}", src.display()))?;
+        check_symlink_cycle(src, &canonical, ancestors)?;
+        ancestors.push(canonical);
+    }
+
+    let mut entries = fs::read_dir(src)
+        .await
+        .with_context(|| format!("unable to read {}", src.display()))?;
+
+    while let Some(entry) = entries
+        .next_entry()
+        .await
+        .with_context(|| format!("unable to read {}", src.display()))?
+    {
+        let (from, to) = (entry.path(), dst.join(entry.file_name()));
+        let file_type = entry
+            .file_type()
+            .await
+            .with_context(|| format!("unable to read {}", from.display()))?;
+
+        if file_type.is_symlink() && !options.dereference_symlinks {
+            off_runtime(&from, &to, copy_symlink).await?;
+        } else if from.is_dir() {
+            fs::create_dir_all(&to)
+                .await
+                .with_context(|| format!("unable to create {}", to.display()))?;
+            dirs.push((from.clone(), to.clone()));
+            copy_tree(&from, &to, options, dirs, report, ancestors).await?;
+        } else if from.is_file() {
+            let bytes = fs::copy(&from, &to)
+                .await
+                .with_context(|| format!("unable to copy {}", from.display()))?;
+            report.add(bytes);
+            if options.preserve_metadata {
+                off_runtime(&from, &to, copy_metadata).await?;
             }
         }
-        FtFilter::Regex(re) => {
-            if re.is_match(item.as_ref().to_str().unwrap()) {
-                return true;
+    }
+
+    ancestors.pop();
+    Ok(())
+}
+
+/// Runs the blocking `copy` step of [`copy_tree`] from `src` to `dst` on the blocking pool
+async fn off_runtime(src: &Path, dst: &Path, copy: fn(&Path, &Path) -> Result<()>) -> Result<()> {
+    let (src, dst) = (src.to_path_buf(), dst.to_path_buf());
+    tokio::task::spawn_blocking(move || copy(&src, &dst))
+        .await
+        .context("copying task failed")?
+}
+
+/// Sync version of [`copy_tree`]
+pub(crate) fn copy_tree_sync(
+    src: &Path,
+    dst: &Path,
+    options: FtCopyOptions,
+    dirs: &mut Vec<(PathBuf, PathBuf)>,
+    report: &mut FtCopyReport,
+    ancestors: &mut Vec<PathBuf>,
+) -> Result<()> {
+    if options.dereference_symlinks {
+        let canonical = std::fs::canonicalize(src)
+            .with_context(|| format!("unable to canonicalize {}", src.display()))?;
+        check_symlink_cycle(src, &canonical, ancestors)?;
+        ancestors.push(canonical);
+    }
+
+    let entries =
+        std::fs::read_dir(src).with_context(|| format!("unable to read {}", src.display()))?;
+
+    for entry in entries {
+        let entry = entry.with_context(|| format!("unable to read {}", src.display()))?;
+        let (from, to) = (entry.path(), dst.join(entry.file_name()));
+        let file_type = entry
+            .file_type()
+            .with_context(|| format!("unable to read {}", from.display()))?;
+
+        if file_type.is_symlink() && !options.dereference_symlinks {
+            copy_symlink(&from, &to)?;
+        } else if from.is_dir() {
+            std::fs::create_dir_all(&to)
+                .with_context(|| format!("unable to create {}", to.display()))?;
+            dirs.push((from.clone(), to.clone()));
+            copy_tree_sync(&from, &to, options, dirs, report, ancestors)?;
+        } else if from.is_file() {
+            let bytes = std::fs::copy(&from, &to)
+                .with_context(|| format!("unable to copy {}", from.display()))?;
+            report.add(bytes);
+            if options.preserve_metadata {
+                copy_metadata(&from, &to)?;
             }
         }
     }
 
-    false
+    ancestors.pop();
+    Ok(())
 }
 
-/// Helper function to iterate through a directory to find all Files / Directories
-/// depending on the `FilterState` passed.
-#[async_recursion]
-pub(crate) async fn iteritems<P: AsRef<Path> + Send>(
-    path: P,
-    iterstate: FtIterItemState,
-    filter: Option<&'async_recursion FtFilter>,
-) -> Result<Vec<PathBuf>> {
-    let mut items = vec![];
+/// Errors if the directory `dir`, resolved to `canonical`, is one of the `ancestors` being copied
+fn check_symlink_cycle(dir: &Path, canonical: &Path, ancestors: &[PathBuf]) -> Result<()> {
+    anyhow::ensure!(
+        !ancestors.iter().any(|a| a == canonical),
+        "symlink cycle: {} points back at {}",
+        dir.display(),
+        canonical.display()
+    );
 
-    let mut entries = fs::read_dir(path.as_ref())
-        .await
-        .context("list items inner call")?;
+    Ok(())
+}
 
-    while let Some(entry) = entries.next_entry().await? {
-        let e_path = entry.path();
+/// Recreates the symlink `src` at `dst` with the same, possibly relative, target
+fn copy_symlink(src: &Path, dst: &Path) -> Result<()> {
+    let target =
+        std::fs::read_link(src).with_context(|| format!("unable to read {}", src.display()))?;
 
-        // If a filter is present, set the value to the result of the filter
-        // check, else default to true so always adds the value
-        let filter_pass = match filter.as_ref() {
-            Some(f) => matches_filter(&e_path, f),
-            None => true,
-        };
+    // Replace an existing file or link like copying over it would
+    if dst.symlink_metadata().is_ok_and(|m| !m.is_dir()) {
+        std::fs::remove_file(dst)
+            .with_context(|| format!("unable to replace {}", dst.display()))?;
+    }
 
-        match iterstate {
-            FtIterItemState::File => {
-                if e_path.is_file() && filter_pass {
-                    items.push(e_path);
-                }
+    #[cfg(unix)]
+    let linked = std::os::unix::fs::symlink(&target, dst);
+
+    // Windows needs to know what kind of link it is, dangling ones are treated as files
+    #[cfg(windows)]
+    let linked = if src.is_dir() {
+        std::os::windows::fs::symlink_dir(&target, dst)
+    } else {
+        std::os::windows::fs::symlink_file(&target, dst)
+    };
+
+    #[cfg(not(any(unix, windows)))]
+    let linked: std::io::Result<()> = Err(std::io::ErrorKind::Unsupported.into());
+
+    linked.with_context(|| format!("unable to create symlink {}", dst.display()))
+}
+
+/// Creates the empty file at `path`, or updates its modification time if it already exists.
+///
+/// Returns `true` if the file was created. An existing file is never truncated, even one
+/// created between the check and the write.
+pub(crate) fn touch(path: &Path) -> Result<bool> {
+    match std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+    {
+        Ok(_) => return Ok(true),
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {}
+        Err(e) => return Err(e).context("unable to create file"),
+    }
+
+    // Same as `copy_metadata`, a read only handle is enough for the owner on Unix
+    #[cfg(unix)]
+    let file = std::fs::File::open(path);
+    #[cfg(not(unix))]
+    let file = std::fs::OpenOptions::new().write(true).open(path);
+
+    file.context("unable to open existing file")?
+        .set_modified(SystemTime::now())
+        .context("unable to update file modification time")?;
+
+    Ok(false)
+}
+
+/// Gives `dst` the same modification / access times and permissions as `src`
+pub(crate) fn copy_metadata(src: &Path, dst: &Path) -> Result<()> {
+    let meta = std::fs::metadata(src)
+        .with_context(|| format!("unable to read metadata for {}", src.display()))?;
+
+    let mut times = std::fs::FileTimes::new();
+    if let Ok(modified) = meta.modified() {
+        times = times.set_modified(modified);
+    }
+    if let Ok(accessed) = meta.accessed() {
+        times = times.set_accessed(accessed);
+    }
+
+    // Owners can set times through a read only handle on Unix, elsewhere it takes
+    // write access which directories can't be opened with
+    #[cfg(unix)]
+    let target = Some(std::fs::File::open(dst));
+    #[cfg(not(unix))]
+    let target = meta
+        .is_file()
+        .then(|| std::fs::OpenOptions::new().write(true).open(dst));
+
+    if let Some(target) = target {
+        target
+            .and_then(|f| f.set_times(times))
+            .with_context(|| format!("unable to set times for {}", dst.display()))?;
+    }
+
+    std::fs::set_permissions(dst, meta.permissions())
+        .with_context(|| format!("unable to set permissions for {}", dst.display()))
+}
+
+/// Whether `dir` is the top of a git repository (or submodule, where `.git` is a file)
+fn is_git_repository(dir: &Path) -> bool {
+    std::fs::symlink_metadata(dir.join(".git")).is_ok()
+}
+
+/// Finds the git repositories below `root`, not descending into a repository once found.
+///
+/// Directories are walked with an explicit stack, children are pushed in reverse so
+/// repositories come out in the same depth first order as the listing functions.
+pub(crate) async fn gitrepos(root: &Path) -> Result<Vec<PathBuf>> {
+    let mut repos = vec![];
+    let mut pending = vec![root.to_path_buf()];
+
+    while let Some(dir) = pending.pop() {
+        if dir != root && is_git_repository(&dir) {
+            repos.push(dir);
+            continue;
+        }
+
+        let mut entries = fs::read_dir(&dir)
+            .await
+            .with_context(|| format!("unable to read directory {}", dir.display()))?;
+
+        let mut subdirs = vec![];
+        while let Some(entry) = entries
+            .next_entry()
+            .await
+            .with_context(|| format!("unable to read directory {}", dir.display()))?
+        {
+            let path = entry.path();
+            if path.is_dir() && entry.file_name() != ".git" {
+                subdirs.push(path);
             }
-            FtIterItemState::RFile => {
-                if e_path.is_file() && filter_pass {
-                    items.push(e_path)
-                } else if e_path.is_dir() {
-                    items.extend(iteritems(e_path, iterstate, filter).await?);
-                }
+        }
+
+        pending.extend(subdirs.into_iter().rev());
+    }
+
+    Ok(repos)
+}
+
+/// Sync version of [`gitrepos`]
+pub(crate) fn gitrepos_sync(root: &Path) -> Result<Vec<PathBuf>> {
+    let mut repos = vec![];
+    let mut pending = vec![root.to_path_buf()];
+
+    while let Some(dir) = pending.pop() {
+        if dir != root && is_git_repository(&dir) {
+            repos.push(dir);
+            continue;
+        }
+
+        let entries = std::fs::read_dir(&dir)
+            .with_context(|| format!("unable to read directory {}", dir.display()))?;
+
+        let mut subdirs = vec![];
+        for entry in entries {
+            let entry =
+                entry.with_context(|| format!("unable to read directory {}", dir.display()))?;
+            let path = entry.path();
+            if path.is_dir() && entry.file_name() != ".git" {
+                subdirs.push(path);
             }
-            FtIterItemState::Dir => {
-                if e_path.is_dir() && filter_pass {
-                    items.push(e_path);
+        }
+
+        pending.extend(subdirs.into_iter().rev());
+    }
+
+    Ok(repos)
+}
+
+/// Finds the symlinks below `root` whose target can't be resolved.
+///
+/// Entries are inspected without following symlinks, so linked directories are not descended
+/// into and link cycles can't trap the walk. Walked in the same order as [`gitrepos`].
+pub(crate) async fn brokenlinks(root: &Path) -> Result<Vec<PathBuf>> {
+    let mut broken = vec![];
+    let mut pending = vec![root.to_path_buf()];
+
+    while let Some(dir) = pending.pop() {
+        let mut entries = fs::read_dir(&dir)
+            .await
+            .with_context(|| format!("unable to read directory {}", dir.display()))?;
+
+        let mut subdirs = vec![];
+        while let Some(entry) = entries
+            .next_entry()
+            .await
+            .with_context(|| format!("unable to read directory {}", dir.display()))?
+        {
+            let path = entry.path();
+            let meta = fs::symlink_metadata(&path)
+                .await
+                .with_context(|| format!("unable to read metadata of {}", path.display()))?;
+
+            if meta.is_symlink() {
+                if fs::metadata(&path).await.is_err() {
+                    broken.push(path);
                 }
+            } else if meta.is_dir() {
+                subdirs.push(path);
             }
-            FtIterItemState::RDir => {
-                if e_path.is_dir() {
-                    if filter_pass {
-                        items.push(e_path.clone());
-                    }
+        }
+
+        pending.extend(subdirs.into_iter().rev());
+    }
+
+    Ok(broken)
+}
+
+/// Sync version of [`brokenlinks`]
+pub(crate) fn brokenlinks_sync(root: &Path) -> Result<Vec<PathBuf>> {
+    let mut broken = vec![];
+    let mut pending = vec![root.to_path_buf()];
 
-                    items.extend(iteritems(e_path, iterstate, filter).await?);
+    while let Some(dir) = pending.pop() {
+        let entries = std::fs::read_dir(&dir)
+            .with_context(|| format!("unable to read directory {}", dir.display()))?;
+
+        let mut subdirs = vec![];
+        for entry in entries {
+            let entry =
+                entry.with_context(|| format!("unable to read directory {}", dir.display()))?;
+            let path = entry.path();
+            let meta = std::fs::symlink_metadata(&path)
+                .with_context(|| format!("unable to read metadata of {}", path.display()))?;
+
+            if meta.is_symlink() {
+                if std::fs::metadata(&path).is_err() {
+                    broken.push(path);
                 }
+            } else if meta.is_dir() {
+                subdirs.push(path);
+            }
+        }
+
+        pending.extend(subdirs.into_iter().rev());
+    }
+
+    Ok(broken)
+}
+
+/// Finds the files below `root` without following symlinked directories, for removals.
+///
+/// Entries are inspected with `symlink_metadata` so a symlinked directory is neither descended
+/// into nor listed, anything it points to outside the tree is left alone. A symlink to a file
+/// is listed as itself, removing it only removes the link. Walked in the same order as [`gitrepos`].
+pub(crate) async fn physicalfiles(root: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    let mut pending = vec![root.to_path_buf()];
+
+    while let Some(dir) = pending.pop() {
+        let mut entries = fs::read_dir(&dir)
+            .await
+            .with_context(|| format!("unable to read directory {}", dir.display()))?;
+
+        let mut subdirs = vec![];
+        while let Some(entry) = entries
+            .next_entry()
+            .await
+            .with_context(|| format!("unable to read directory {}", dir.display()))?
+        {
+            let path = entry.path();
+            let meta = fs::symlink_metadata(&path)
+                .await
+                .with_context(|| format!("unable to read metadata of {}", path.display()))?;
+
+            // Symlinks to files are the only links that count
+            let linked_file =
+                meta.is_symlink() && fs::metadata(&path).await.is_ok_and(|m| m.is_file());
+            if meta.is_dir() {
+                subdirs.push(path);
+            } else if meta.is_file() || linked_file {
+                files.push(path);
+            }
+        }
+
+        pending.extend(subdirs.into_iter().rev());
+    }
+
+    Ok(files)
+}
+
+/// Sync version of [`physicalfiles`]
+pub(crate) fn physicalfiles_sync(root: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    let mut pending = vec![root.to_path_buf()];
+
+    while let Some(dir) = pending.pop() {
+        let entries = std::fs::read_dir(&dir)
+            .with_context(|| format!("unable to read directory {}", dir.display()))?;
+
+        let mut subdirs = vec![];
+        for entry in entries {
+            let entry =
+                entry.with_context(|| format!("unable to read directory {}", dir.display()))?;
+            let path = entry.path();
+            let meta = std::fs::symlink_metadata(&path)
+                .with_context(|| format!("unable to read metadata of {}", path.display()))?;
+
+            // Symlinks to files are the only links that count
+            let linked_file =
+                meta.is_symlink() && std::fs::metadata(&path).is_ok_and(|m| m.is_file());
+            if meta.is_dir() {
+                subdirs.push(path);
+            } else if meta.is_file() || linked_file {
+                files.push(path);
             }
         }
+
+        pending.extend(subdirs.into_iter().rev());
     }
 
+    Ok(files)
+}
+
+/// Directories between `root` and the `removed` files that may have been left empty,
+/// deepest first so children are pruned before their parents
+pub(crate) fn emptied_dirs(root: &Path, removed: &[PathBuf]) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = removed
+        .iter()
+        .flat_map(|file| file.ancestors().skip(1))
+        .filter(|dir| dir.starts_with(root) && *dir != root)
+        .map(Path::to_path_buf)
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+
+    dirs.sort_by_key(|dir| std::cmp::Reverse(dir.components().count()));
+    dirs
+}
+
+/// Buckets `files` by lowercased extension, files without one go under `""`.
+/// Each bucket is sorted.
+pub(crate) fn group_by_extension(files: Vec<PathBuf>) -> HashMap<String, Vec<PathBuf>> {
+    let mut groups: HashMap<String, Vec<PathBuf>> = HashMap::new();
+    for file in files {
+        let ext = file
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        groups.entry(ext).or_default().push(file);
+    }
+
+    groups.values_mut().for_each(|group| group.sort());
+    groups
+}
+
+/// Keeps the directories from a combined listing that directly hold one of its files,
+/// in the order they were listed
+pub(crate) fn dirs_holding_files(entries: Vec<DirEntryKind>) -> Vec<PathBuf> {
+    let parents: HashSet<PathBuf> = entries
+        .iter()
+        .filter(|entry| entry.is_file())
+        .filter_map(|entry| entry.path().parent().map(Path::to_path_buf))
+        .collect();
+
+    entries
+        .into_iter()
+        .filter(|entry| entry.is_dir() && parents.contains(entry.path()))
+        .map(DirEntryKind::into_path)
+        .collect()
+}
+
+/// Strips the traversal `root` from each item so they are relative to it
+///
+/// Every item is built by joining entries onto `root` so the prefix always matches exactly.
+pub(crate) fn strip_root(root: impl AsRef<Path>, items: Vec<PathBuf>) -> Vec<PathBuf> {
+    items
+        .into_iter()
+        .map(|item| match item.strip_prefix(root.as_ref()) {
+            Ok(relative) => relative.to_path_buf(),
+            Err(_) => item,
+        })
+        .collect()
+}
+
+/// Finds the first item in a directory, stopping the traversal as soon as it is found
+pub(crate) async fn firstitem<P: AsRef<Path> + Send>(
+    path: P,
+    iterstate: FtIterItemState,
+    filter: Option<&FtFilter>,
+) -> Result<Option<PathBuf>> {
+    let mut first = None;
+    walk(path, iterstate, filter, &mut |item| {
+        first = Some(item);
+        ControlFlow::Break(())
+    })
+    .await?;
+
+    Ok(first)
+}
+
+/// Collects at most `limit` items, stopping the traversal as soon as the limit is reached
+pub(crate) async fn limiteditems<P: AsRef<Path> + Send>(
+    path: P,
+    iterstate: FtIterItemState,
+    filter: Option<&FtFilter>,
+    limit: usize,
+) -> Result<Vec<PathBuf>> {
+    let mut items = vec![];
+    if limit == 0 {
+        return Ok(items);
+    }
+
+    walk(path, iterstate, filter, &mut |item| {
+        items.push(item);
+        if items.len() < limit {
+            ControlFlow::Continue(())
+        } else {
+            ControlFlow::Break(())
+        }
+    })
+    .await?;
+
     Ok(items)
 }
 
-pub(crate) fn iteritems_sync<P: AsRef<Path>>(
+/// Sync version of [`limiteditems`]
+pub(crate) fn limiteditems_sync<P: AsRef<Path>>(
     path: P,
     iterstate: FtIterItemState,
     filter: Option<&FtFilter>,
+    limit: usize,
 ) -> Result<Vec<PathBuf>> {
     let mut items = vec![];
+    if limit == 0 {
+        return Ok(items);
+    }
 
-    let mut entries = std::fs::read_dir(path.as_ref()).context("sync iteritems entry call")?;
+    walk_sync(path, iterstate, filter, &mut |item| {
+        items.push(item);
+        if items.len() < limit {
+            ControlFlow::Continue(())
+        } else {
+            ControlFlow::Break(())
+        }
+    })?;
 
-    while let Some(Ok(entry)) = entries.next() {
-        let e_path = entry.path();
+    Ok(items)
+}
 
-        // If a filter is present, set the value to the result of the filter
-        // check, else default to true so always adds the value
-        let filter_pass = match filter.as_ref() {
-            Some(f) => matches_filter(&e_path, f),
-            None => true,
-        };
-        match iterstate {
-            FtIterItemState::File => {
-                if e_path.is_file() && filter_pass {
-                    items.push(e_path);
-                }
-            }
-            FtIterItemState::RFile => {
-                if e_path.is_file() && filter_pass {
-                    items.push(e_path)
-                } else if e_path.is_dir() {
-                    items.extend(iteritems_sync(e_path, iterstate, filter)?);
-                }
-            }
-            FtIterItemState::Dir => {
-                if e_path.is_dir() && filter_pass {
-                    items.push(e_path);
-                }
-            }
-            FtIterItemState::RDir => {
-                if e_path.is_dir() {
-                    if filter_pass {
-                        items.push(e_path.clone());
-                    }
+/// Sync version of [`firstitem`]
+pub(crate) fn firstitem_sync<P: AsRef<Path>>(
+    path: P,
+    iterstate: FtIterItemState,
+    filter: Option<&FtFilter>,
+) -> Result<Option<PathBuf>> {
+    let mut first = None;
+    walk_sync(path, iterstate, filter, &mut |item| {
+        first = Some(item);
+        ControlFlow::Break(())
+    })?;
 
-                    items.extend(iteritems_sync(e_path, iterstate, filter)?);
-                }
+    Ok(first)
+}
+
+/// Resolves `input` like [`crate::resolve_path`], reading variables through `lookup` instead
+/// of the process environment
+pub(crate) fn resolve_path_with(
+    input: &str,
+    lookup: &impl Fn(&str) -> Option<OsString>,
+) -> PathBuf {
+    let expanded = expand_env_vars(input, lookup);
+    match (expanded.strip_prefix('~'), home_dir(lookup)) {
+        (Some(""), Some(home)) => home,
+        (Some(rest), Some(home)) if rest.starts_with(['/', std::path::MAIN_SEPARATOR]) => {
+            home.join(&rest[1..])
+        }
+        _ => PathBuf::from(expanded),
+    }
+}
+
+/// Gets the current user's home directory from the variables `lookup` gives
+pub(crate) fn home_dir(lookup: &impl Fn(&str) -> Option<OsString>) -> Option<PathBuf> {
+    lookup("HOME")
+        .or_else(|| lookup("USERPROFILE"))
+        .filter(|home| !home.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Substitutes `$VAR` and `${VAR}` occurrences with their values from `lookup`
+///
+/// Anything that is not a set variable, or not valid unicode, is left untouched.
+pub(crate) fn expand_env_vars(input: &str, lookup: &impl Fn(&str) -> Option<OsString>) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut rest = input;
+
+    while let Some(idx) = rest.find('$') {
+        output.push_str(&rest[..idx]);
+        let after = &rest[idx + 1..];
+
+        let (name, consumed) = if let Some(braced) = after.strip_prefix('{') {
+            match braced.find('}') {
+                Some(end) => (&braced[..end], end + 2),
+                None => ("", 0),
             }
+        } else {
+            let end = after
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(after.len());
+            (&after[..end], end)
+        };
+
+        let value = lookup(name).and_then(|value| value.into_string().ok());
+        match value {
+            Some(value) if !name.is_empty() => output.push_str(&value),
+            _ => output.push_str(&rest[idx..idx + 1 + consumed]),
         }
+
+        rest = &after[consumed..];
     }
 
-    Ok(items)
+    output.push_str(rest);
+    output
+}
+
+/// Builds a hidden temporary path alongside `path` for atomic writes
+///
+/// The temp file lives in the same directory so the final rename stays on one filesystem.
+pub(crate) fn atomic_temp_path(path: &Path) -> Result<PathBuf> {
+    let name = path
+        .file_name()
+        .context("atomic write target has no file name")?;
+    let parent = match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+
+    let mut tmp_name = std::ffi::OsString::from(".");
+    tmp_name.push(name);
+    tmp_name.push(".");
+    tmp_name.push(naming::generate_uuid4_name("tmp"));
+
+    Ok(parent.join(tmp_name))
+}
+
+/// Writes `contents` to `path` through a synced temporary file renamed into place, see
+/// [`crate::write_atomic`]
+pub(crate) fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    // Replace the file a symlink points to rather than the link itself
+    let path = match std::fs::symlink_metadata(path) {
+        Ok(meta) if meta.is_symlink() => std::fs::canonicalize(path)
+            .with_context(|| format!("unable to resolve symlink {}", path.display()))?,
+        _ => path.to_path_buf(),
+    };
+    let tmp = atomic_temp_path(&path)?;
+
+    let result = (|| {
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&tmp)
+            .context("unable to create temporary file")?;
+        file.write_all(contents)
+            .context("unable to write temporary file")?;
+
+        // Keep the permissions of the file being replaced
+        if let Some(meta) = std::fs::metadata(&path).ok().filter(|m| m.is_file()) {
+            file.set_permissions(meta.permissions())
+                .context("unable to copy permissions to temporary file")?;
+        }
+
+        file.sync_all().context("unable to sync temporary file")?;
+        std::fs::rename(&tmp, &path).context("unable to move temporary file into place")
+    })();
+
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp);
+    }
+    result?;
+
+    // The rename itself is only durable once the directory is synced too
+    #[cfg(unix)]
+    {
+        let parent = match path.parent() {
+            Some(p) if !p.as_os_str().is_empty() => p,
+            _ => Path::new("."),
+        };
+        std::fs::File::open(parent)
+            .and_then(|dir| dir.sync_all())
+            .context("unable to sync parent directory")?;
+    }
+
+    Ok(())
 }
+
 /// Helper for creating temp directories
 ///
 /// Tempfile _would_ work but I want nested dirs and easy ways to create
@@ -184,7 +2455,7 @@ impl TempPath {
         let p = self.path.join(name);
         tokio::fs::File::create(&p).await?;
 
-        Self::new(p).await
+        Ok(Self { path: p })
     }
 
     pub async fn multi_file(&self, names: Vec<impl AsRef<Path>>) -> Result<()> {
@@ -0,0 +1,564 @@
+//! File system abstraction the async listing traversal runs on
+//!
+//! The listing functions in the crate root walk the real disk through [`FtRealFs`]. Anything
+//! implementing [`FtFileSystem`] can be walked the same way with [`list_items_in`], such as
+//! the in-memory [`FtMemFs`] which makes listing logic testable without touching the disk.
+//!
+//! Filters that inspect the items themselves ([`FtFilter::Executable`], [`FtFilter::Empty`],
+//! [`FtFilter::NonEmpty`] and [`FtFilter::Canonical`]) always check the real disk.
+//!
+//! # Example
+//!
+//! ```rust
+//! use filetools::{vfs::{self, FtMemFs}, FtIterTarget};
+//! use std::path::PathBuf;
+//!
+//! #[tokio::main]
+//! async fn main() -> anyhow::Result<()> {
+//!     let fs = FtMemFs::new();
+//!     fs.create_dir_all("project/src")?;
+//!     fs.write("project/src/main.rs", "fn main() {}")?;
+//!
+//!     let files = vfs::list_items_in(&fs, "project", FtIterTarget::Files, true, None).await?;
+//!     assert_eq!(files, vec![PathBuf::from("project/src/main.rs")]);
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use crate::util::{walk_in, FtIterItemState};
+use crate::{FtError, FtFilter, FtIterTarget};
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::io;
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// The parts of an item's metadata the traversal needs
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct FtFsMetadata {
+    /// Whether the item is a directory
+    pub is_dir: bool,
+
+    /// Whether the item is a regular file
+    pub is_file: bool,
+
+    /// Size of the item in bytes
+    pub len: u64,
+}
+
+/// The open entries of a directory, returned by [`FtFileSystem::read_dir`]
+///
+/// Entries are pulled one at a time so a traversal that stops early never reads the rest of
+/// a large directory. A `Vec<PathBuf>` iterator works for file systems listing in one go.
+pub trait FtReadDir: Send {
+    /// Gets the path of the next entry, `None` once the directory is exhausted.
+    ///
+    /// An entry that can't be read is returned as an error, the traversal reports it and
+    /// skips the rest of the directory.
+    fn next_entry(&mut self) -> impl Future<Output = Option<io::Result<PathBuf>>> + Send;
+}
+
+impl FtReadDir for std::vec::IntoIter<PathBuf> {
+    async fn next_entry(&mut self) -> Option<io::Result<PathBuf>> {
+        self.next().map(Ok)
+    }
+}
+
+/// A file system that can be listed with [`list_items_in`]
+///
+/// Paths are used exactly as given, entries returned by [`FtFileSystem::read_dir`] are the
+/// directory path joined with each entry name.
+pub trait FtFileSystem: Send + Sync {
+    /// The entries of an open directory
+    type ReadDir: FtReadDir;
+
+    /// Opens `path` to read the entries directly inside it
+    fn read_dir(&self, path: &Path) -> impl Future<Output = io::Result<Self::ReadDir>> + Send;
+
+    /// Gets the metadata of the item at `path`, following symlinks
+    fn metadata(&self, path: &Path) -> impl Future<Output = io::Result<FtFsMetadata>> + Send;
+
+    /// Creates `path` and any missing parent directories
+    fn create_dir_all(&self, path: &Path) -> impl Future<Output = io::Result<()>> + Send;
+
+    /// Writes `contents` to the file at `path`, replacing it if it exists
+    fn write(&self, path: &Path, contents: &[u8]) -> impl Future<Output = io::Result<()>> + Send;
+
+    /// Reads the whole file at `path`
+    fn read(&self, path: &Path) -> impl Future<Output = io::Result<Vec<u8>>> + Send;
+}
+
+/// The real file system, used by all the listing functions in the crate root
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct FtRealFs;
+
+/// The entries of a directory on disk, streamed from [`tokio::fs::ReadDir`]
+#[derive(Debug)]
+pub struct FtRealReadDir(tokio::fs::ReadDir);
+
+impl FtReadDir for FtRealReadDir {
+    async fn next_entry(&mut self) -> Option<io::Result<PathBuf>> {
+        self.0
+            .next_entry()
+            .await
+            .transpose()
+            .map(|e| e.map(|e| e.path()))
+    }
+}
+
+impl FtFileSystem for FtRealFs {
+    type ReadDir = FtRealReadDir;
+
+    async fn read_dir(&self, path: &Path) -> io::Result<FtRealReadDir> {
+        tokio::fs::read_dir(path).await.map(FtRealReadDir)
+    }
+
+    async fn metadata(&self, path: &Path) -> io::Result<FtFsMetadata> {
+        let meta = tokio::fs::metadata(path).await?;
+
+        Ok(FtFsMetadata {
+            is_dir: meta.is_dir(),
+            is_file: meta.is_file(),
+            len: meta.len(),
+        })
+    }
+
+    async fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        tokio::fs::create_dir_all(path).await
+    }
+
+    async fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        tokio::fs::write(path, contents).await
+    }
+
+    async fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        tokio::fs::read(path).await
+    }
+}
+
+/// An item in an [`FtMemFs`]
+#[derive(Debug, Clone)]
+enum MemNode {
+    Dir,
+    File(Vec<u8>),
+}
+
+/// An in-memory file system for testing code built on [`list_items_in`] without disk I/O.
+///
+/// Paths are stored as given without being normalized, so `a/b` and `./a/b` are different items.
+/// Directories are listed in name order. The sync helpers ([`FtMemFs::create_dir_all`] and
+/// [`FtMemFs::write`]) make it easy to set up a tree before the async traversal runs.
+#[derive(Debug, Default)]
+pub struct FtMemFs {
+    nodes: Mutex<BTreeMap<PathBuf, MemNode>>,
+}
+
+impl FtMemFs {
+    /// Creates an empty file system
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates the directory at `path` and any missing parents
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `path` or one of its parents is a file.
+    pub fn create_dir_all(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut nodes = self.nodes.lock().expect("memory file system poisoned");
+
+        let ancestors: Vec<&Path> = path
+            .as_ref()
+            .ancestors()
+            .filter(|a| !a.as_os_str().is_empty())
+            .collect();
+
+        for dir in ancestors.into_iter().rev() {
+            match nodes.get(dir) {
+                Some(MemNode::File(_)) => return Err(not_a_directory(dir)),
+                Some(MemNode::Dir) => {}
+                None => {
+                    nodes.insert(dir.to_path_buf(), MemNode::Dir);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes `contents` to the file at `path`, replacing it if it exists
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the parent directory does not exist or `path` is a directory.
+    pub fn write(&self, path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut nodes = self.nodes.lock().expect("memory file system poisoned");
+
+        let parent = path.parent().filter(|p| !p.as_os_str().is_empty());
+        if let Some(parent) = parent {
+            match nodes.get(parent) {
+                Some(MemNode::Dir) => {}
+                Some(MemNode::File(_)) => return Err(not_a_directory(parent)),
+                None => return Err(not_found(parent)),
+            }
+        }
+
+        if let Some(MemNode::Dir) = nodes.get(path) {
+            return Err(io::Error::other(format!(
+                "{} is a directory",
+                path.display()
+            )));
+        }
+
+        nodes.insert(
+            path.to_path_buf(),
+            MemNode::File(contents.as_ref().to_vec()),
+        );
+        Ok(())
+    }
+}
+
+impl FtFileSystem for FtMemFs {
+    type ReadDir = std::vec::IntoIter<PathBuf>;
+
+    async fn read_dir(&self, path: &Path) -> io::Result<Self::ReadDir> {
+        let nodes = self.nodes.lock().expect("memory file system poisoned");
+        match nodes.get(path) {
+            Some(MemNode::Dir) => {}
+            Some(MemNode::File(_)) => return Err(not_a_directory(path)),
+            None => return Err(not_found(path)),
+        }
+
+        Ok(nodes
+            .range(path.to_path_buf()..)
+            .skip(1)
+            .map(|(p, _)| p)
+            .take_while(|p| p.starts_with(path))
+            .filter(|p| p.parent() == Some(path))
+            .cloned()
+            .collect::<Vec<_>>()
+            .into_iter())
+    }
+
+    async fn metadata(&self, path: &Path) -> io::Result<FtFsMetadata> {
+        let nodes = self.nodes.lock().expect("memory file system poisoned");
+        match nodes.get(path) {
+            Some(MemNode::Dir) => Ok(FtFsMetadata {
+                is_dir: true,
+                is_file: false,
+                len: 0,
+            }),
+            Some(MemNode::File(contents)) => Ok(FtFsMetadata {
+                is_dir: false,
+                is_file: true,
+                len: contents.len() as u64,
+            }),
+            None => Err(not_found(path)),
+        }
+    }
+
+    async fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        FtMemFs::create_dir_all(self, path)
+    }
+
+    async fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        FtMemFs::write(self, path, contents)
+    }
+
+    async fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        let nodes = self.nodes.lock().expect("memory file system poisoned");
+        match nodes.get(path) {
+            Some(MemNode::File(contents)) => Ok(contents.clone()),
+            Some(MemNode::Dir) => Err(io::Error::other(format!(
+                "{} is a directory",
+                path.display()
+            ))),
+            None => Err(not_found(path)),
+        }
+    }
+}
+
+fn not_found(path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("{} does not exist", path.display()),
+    )
+}
+
+fn not_a_directory(path: &Path) -> io::Error {
+    io::Error::other(format!("{} is not a directory", path.display()))
+}
+
+/// Lists the items in a directory of any [`FtFileSystem`], optionally recursing into ALL
+/// subdirectories and applying a filter pattern.
+///
+/// The same as [`crate::list_items`] (which is this function on [`FtRealFs`]) but for any
+/// file system. There is no `sync` version as the file system is accessed asynchronously.
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+/// * A directory could not be read
+///
+/// # Example
+///
+/// ```rust
+/// use filetools::{vfs::{self, FtMemFs}, FtFilter, FtIterTarget};
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let fs = FtMemFs::new();
+///     fs.create_dir_all("logs/old")?;
+///     fs.write("logs/today.log", "")?;
+///     fs.write("logs/old/monday.log", "")?;
+///
+///     let filter = FtFilter::Raw("old".to_string());
+///     let old = vfs::list_items_in(&fs, "logs", FtIterTarget::Both, true, Some(filter)).await?;
+///     assert_eq!(old.len(), 2);
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn list_items_in<F: FtFileSystem>(
+    fs: &F,
+    path: impl AsRef<Path> + Send,
+    target: FtIterTarget,
+    recursive: bool,
+    filter: Option<FtFilter>,
+) -> Result<Vec<PathBuf>> {
+    let path = path.as_ref();
+    let meta = fs.metadata(path).await.ok();
+    anyhow::ensure!(meta.is_some(), FtError::NotFound(path.to_path_buf()));
+    anyhow::ensure!(
+        meta.is_some_and(|m| m.is_dir),
+        FtError::NotADirectory(path.to_path_buf())
+    );
+
+    let mut items = vec![];
+    let iterstate = FtIterItemState::new(target, recursive);
+    walk_in(fs, path, iterstate, filter.as_ref(), &mut |item| {
+        items.push(item);
+        ControlFlow::Continue(())
+    })
+    .await?;
+
+    Ok(items)
+}
+
+#[cfg(test)]
+mod vfs_tests {
+    use super::*;
+    use crate::util::TempPath;
+
+    fn fixture() -> io::Result<FtMemFs> {
+        let fs = FtMemFs::new();
+        fs.create_dir_all("root/a/b")?;
+        fs.create_dir_all("root/c")?;
+        fs.write("root/top.txt", "top")?;
+        fs.write("root/a/mid.txt", "middle")?;
+        fs.write("root/a/b/deep.rs", "")?;
+        fs.write("root/c/other.txt", "other")?;
+        fs.write("rootless.txt", "")?;
+
+        Ok(fs)
+    }
+
+    #[tokio::test]
+    async fn lists_an_in_memory_tree() -> Result<()> {
+        let fs = fixture()?;
+
+        let files = list_items_in(&fs, "root", FtIterTarget::Files, true, None).await?;
+        assert_eq!(
+            files,
+            vec![
+                PathBuf::from("root/a/b/deep.rs"),
+                PathBuf::from("root/a/mid.txt"),
+                PathBuf::from("root/c/other.txt"),
+                PathBuf::from("root/top.txt"),
+            ]
+        );
+
+        let top = list_items_in(&fs, "root", FtIterTarget::Files, false, None).await?;
+        assert_eq!(top, vec![PathBuf::from("root/top.txt")]);
+
+        let dirs = list_items_in(&fs, "root", FtIterTarget::Dirs, true, None).await?;
+        assert_eq!(
+            dirs,
+            vec![
+                PathBuf::from("root/a"),
+                PathBuf::from("root/a/b"),
+                PathBuf::from("root/c"),
+            ]
+        );
+
+        let filter = FtFilter::Name(".txt".to_string());
+        let txt = list_items_in(&fs, "root", FtIterTarget::Both, true, Some(filter)).await?;
+        assert_eq!(txt.len(), 3);
+
+        let deep = list_items_in(
+            &fs,
+            "root",
+            FtIterTarget::Files,
+            true,
+            Some(FtFilter::MinDepth(3)),
+        )
+        .await?;
+        assert_eq!(deep, vec![PathBuf::from("root/a/b/deep.rs")]);
+
+        assert!(
+            list_items_in(&fs, "missing", FtIterTarget::Files, true, None)
+                .await
+                .is_err()
+        );
+        assert!(
+            list_items_in(&fs, "root/top.txt", FtIterTarget::Files, true, None)
+                .await
+                .is_err()
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn memory_fs_behaves_like_a_file_system() -> Result<()> {
+        let fs = fixture()?;
+
+        assert_eq!(fs.read(Path::new("root/a/mid.txt")).await?, b"middle");
+        assert_eq!(fs.metadata(Path::new("root/c/other.txt")).await?.len, 5);
+        assert!(fs.metadata(Path::new("root/a")).await?.is_dir);
+
+        // Parents have to exist and files can't hold anything
+        assert!(fs.write("root/missing/file.txt", "").is_err());
+        assert!(fs.create_dir_all("root/top.txt/sub").is_err());
+        assert!(fs.read_dir(Path::new("root/top.txt")).await.is_err());
+        assert!(fs.write("root/a", "").is_err());
+
+        // `root` and `rootless.txt` share a prefix but only one is inside the other
+        assert_eq!(fs.read_dir(Path::new("root/c")).await?.len(), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn real_fs_matches_the_crate_listings() -> Result<()> {
+        let root = TempPath::new("vfs_real_fs").await?;
+        root.multi_folder(vec!["a/b"]).await?;
+        root.multi_file(vec!["a/one.txt", "a/b/two.txt"]).await?;
+
+        let listed = list_items_in(&FtRealFs, &root.path, FtIterTarget::Both, true, None).await?;
+        assert_eq!(
+            listed,
+            crate::list_items(&root.path, FtIterTarget::Both, true, None).await?
+        );
+
+        Ok(())
+    }
+
+    /// [`FtMemFs`] where reading `root/c` fails after its entries, counting the entries pulled
+    #[derive(Default)]
+    struct FaultyFs {
+        inner: FtMemFs,
+        pulled: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    struct FaultyReadDir {
+        entries: std::vec::IntoIter<PathBuf>,
+        fails: bool,
+        pulled: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    impl FtReadDir for FaultyReadDir {
+        async fn next_entry(&mut self) -> Option<io::Result<PathBuf>> {
+            self.pulled
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            match self.entries.next() {
+                None if self.fails => Some(Err(io::Error::other("entry went missing"))),
+                entry => entry.map(Ok),
+            }
+        }
+    }
+
+    impl FtFileSystem for FaultyFs {
+        type ReadDir = FaultyReadDir;
+
+        async fn read_dir(&self, path: &Path) -> io::Result<FaultyReadDir> {
+            Ok(FaultyReadDir {
+                entries: self.inner.read_dir(path).await?,
+                fails: path == Path::new("root/c"),
+                pulled: self.pulled.clone(),
+            })
+        }
+
+        async fn metadata(&self, path: &Path) -> io::Result<FtFsMetadata> {
+            self.inner.metadata(path).await
+        }
+
+        async fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+            self.inner.create_dir_all(path)
+        }
+
+        async fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+            self.inner.write(path, contents)
+        }
+
+        async fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+            self.inner.read(path).await
+        }
+    }
+
+    #[tokio::test]
+    async fn entries_are_streamed_and_errors_reported_per_directory() -> Result<()> {
+        use crate::util::PartialItems;
+        use std::sync::atomic::Ordering;
+
+        let fs = FaultyFs {
+            inner: fixture()?,
+            ..Default::default()
+        };
+
+        // Stopping at the first item leaves the rest of the directory unread
+        let mut first = vec![];
+        walk_in(
+            &fs,
+            Path::new("root"),
+            FtIterItemState::RBoth,
+            None,
+            &mut |item| {
+                first.push(item);
+                ControlFlow::Break(())
+            },
+        )
+        .await?;
+        assert_eq!(first, vec![PathBuf::from("root/a")]);
+        assert_eq!(fs.pulled.load(Ordering::Relaxed), 1);
+
+        // The failing entry aborts by default, naming the directory
+        let err = list_items_in(&fs, "root", FtIterTarget::Files, true, None)
+            .await
+            .unwrap_err();
+        assert!(format!("{err:#}").contains("root/c"));
+
+        // Carrying on keeps everything read before and after the failure
+        let mut partial = PartialItems::default();
+        walk_in(
+            &fs,
+            Path::new("root"),
+            FtIterItemState::RFile,
+            None,
+            &mut partial,
+        )
+        .await?;
+        assert_eq!(partial.items.len(), 4);
+        assert_eq!(partial.errors.len(), 1);
+        assert_eq!(partial.errors[0].0, PathBuf::from("root/c"));
+
+        Ok(())
+    }
+}
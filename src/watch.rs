@@ -0,0 +1,199 @@
+//! Watching a directory for changes
+//!
+//! Requires the `notify` feature.
+
+use crate::util::{depth_under, ensure_dir_root, matches_filter_at};
+use crate::FtFilter;
+use anyhow::{Context, Result};
+use notify::event::{ModifyKind, RenameMode};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+
+/// A change to an item in a watched directory, see [`watch_directory`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FtWatchEvent {
+    /// A file or directory was created, or renamed into this path
+    Created(PathBuf),
+
+    /// A file or directory's contents or metadata changed
+    Modified(PathBuf),
+
+    /// A file or directory was removed, or renamed away from this path
+    Removed(PathBuf),
+}
+
+impl FtWatchEvent {
+    /// The path the event happened to
+    pub fn path(&self) -> &Path {
+        match self {
+            Self::Created(path) | Self::Modified(path) | Self::Removed(path) => path,
+        }
+    }
+}
+
+/// Handle for an active watch started by [`watch_directory`], watching stops when it is dropped
+pub struct FtWatcher {
+    _watcher: RecommendedWatcher,
+}
+
+impl std::fmt::Debug for FtWatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FtWatcher").finish_non_exhaustive()
+    }
+}
+
+/// Converts a raw `notify` event into the events we report
+fn convert(event: Event) -> Vec<FtWatchEvent> {
+    let Event { kind, paths, .. } = event;
+    match kind {
+        EventKind::Create(_) => paths.into_iter().map(FtWatchEvent::Created).collect(),
+        EventKind::Remove(_) => paths.into_iter().map(FtWatchEvent::Removed).collect(),
+        EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+            paths.into_iter().map(FtWatchEvent::Removed).collect()
+        }
+        EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+            paths.into_iter().map(FtWatchEvent::Created).collect()
+        }
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if paths.len() == 2 => {
+            let mut paths = paths.into_iter();
+            let (from, to) = (paths.next().unwrap(), paths.next().unwrap());
+            vec![FtWatchEvent::Removed(from), FtWatchEvent::Created(to)]
+        }
+        EventKind::Modify(_) => paths.into_iter().map(FtWatchEvent::Modified).collect(),
+        _ => vec![],
+    }
+}
+
+/// Watches a directory for created, modified and removed items, calling `callback` for each change.
+///
+/// Set `recursive` to include changes in ALL subdirectories. If a `filter` is given,
+/// only changes to paths matching it are reported. Renames are reported as a removal of
+/// the old path and a creation of the new one.
+///
+/// The callback runs on a background thread owned by the watcher, so this works the same
+/// from sync and async code. Watching stops when the returned [`FtWatcher`] is dropped.
+/// Errors from the underlying OS watcher are not reported.
+///
+/// # Errors
+///
+/// This function will return an error in the following situations:
+///
+/// * The given path is a file and not a directory
+/// * The given path does not exist
+/// * The OS watcher could not be created or could not watch the path
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use filetools::{watch_directory, FtFilter, FtWatchEvent};
+///
+/// let rust_files = FtFilter::Raw(".rs".to_string());
+/// let watcher = watch_directory("src", true, Some(rust_files), |event| match event {
+///     FtWatchEvent::Created(path) => println!("new file {}", path.display()),
+///     FtWatchEvent::Modified(path) => println!("rebuilding for {}", path.display()),
+///     FtWatchEvent::Removed(path) => println!("{} is gone", path.display()),
+/// })
+/// .expect("unable to watch directory");
+///
+/// // ... keep `watcher` alive for as long as changes should be reported
+/// drop(watcher);
+/// ```
+pub fn watch_directory<P, F>(
+    path: P,
+    recursive: bool,
+    filter: Option<FtFilter>,
+    mut callback: F,
+) -> Result<FtWatcher>
+where
+    P: AsRef<Path>,
+    F: FnMut(FtWatchEvent) + Send + 'static,
+{
+    ensure_dir_root(&path)?;
+
+    let root = path.as_ref().to_path_buf();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+        let Ok(event) = event else {
+            return;
+        };
+
+        for change in convert(event) {
+            let pass = match filter.as_ref() {
+                Some(f) => matches_filter_at(change.path(), f, depth_under(&root, change.path())),
+                None => true,
+            };
+
+            if pass {
+                callback(change);
+            }
+        }
+    })
+    .context("unable to create directory watcher")?;
+
+    let mode = if recursive {
+        RecursiveMode::Recursive
+    } else {
+        RecursiveMode::NonRecursive
+    };
+    watcher
+        .watch(path.as_ref(), mode)
+        .with_context(|| format!("unable to watch {}", path.as_ref().display()))?;
+
+    Ok(FtWatcher { _watcher: watcher })
+}
+
+#[cfg(test)]
+mod watch_tests {
+    use super::*;
+    use crate::util::TempPath;
+    use std::sync::mpsc;
+    use std::time::{Duration, Instant};
+
+    /// Waits for an event matching `pred`, ignoring any others
+    fn wait_for(
+        rx: &mpsc::Receiver<FtWatchEvent>,
+        pred: impl Fn(&FtWatchEvent) -> bool,
+    ) -> Option<FtWatchEvent> {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while let Some(left) = deadline.checked_duration_since(Instant::now()) {
+            match rx.recv_timeout(left) {
+                Ok(event) if pred(&event) => return Some(event),
+                Ok(_) => continue,
+                Err(_) => return None,
+            }
+        }
+
+        None
+    }
+
+    #[tokio::test]
+    async fn reports_created_files() -> Result<()> {
+        let root = TempPath::new("watch_reports_created").await?;
+        let (tx, rx) = mpsc::channel();
+        let filter = FtFilter::Raw(".log".to_string());
+        let watcher = watch_directory(&root.path, true, Some(filter), move |event| {
+            let _ = tx.send(event);
+        })?;
+
+        std::fs::write(root.path.join("ignored.txt"), "not a log")?;
+        let log = root.path.join("app.log");
+        std::fs::write(&log, "hello")?;
+
+        let event = wait_for(&rx, |e| matches!(e, FtWatchEvent::Created(_)));
+        assert_eq!(event, Some(FtWatchEvent::Created(log.clone())));
+
+        std::fs::remove_file(&log)?;
+        let event = wait_for(&rx, |e| matches!(e, FtWatchEvent::Removed(_)));
+        assert_eq!(event, Some(FtWatchEvent::Removed(log)));
+
+        // Nothing for the filtered out file
+        drop(watcher);
+        assert!(rx.iter().all(|e| e.path().extension().unwrap() == "log"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn watching_requires_a_directory() {
+        assert!(watch_directory("does/not/exist", false, None, |_| {}).is_err());
+    }
+}